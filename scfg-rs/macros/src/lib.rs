@@ -0,0 +1,395 @@
+//! Procedural macros for the [scfg] crate. These are re-exported by `scfg` when
+//! its `macros` feature is enabled, and should be used from there.
+//!
+//! [scfg]: https://docs.rs/scfg
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::path::PathBuf;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, Attribute, Data, DataStruct, DeriveInput, Expr,
+    ExprLit, Fields, GenericArgument, Lit, LitStr, Meta, MetaNameValue, PathArguments, Token, Type,
+};
+
+/// Parses an scfg document at compile time, expanding to an expression
+/// building the equivalent `scfg::Scfg`.
+///
+/// A document that fails to parse is reported as a compile error with the line
+/// number of the problem.
+#[proc_macro]
+pub fn scfg_literal(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    match parse::document(&lit.value()) {
+        Ok(block) => expand(&block, None).into(),
+        Err(err) => syn::Error::new(lit.span(), err).to_compile_error().into(),
+    }
+}
+
+/// Reads and parses an scfg file at compile time, expanding to an expression
+/// building the equivalent `scfg::Scfg`.
+///
+/// The path is relative to the directory containing the manifest of the crate
+/// being compiled. A document that fails to parse is reported as a compile
+/// error with the path and line number of the problem.
+#[proc_macro]
+pub fn include_scfg(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    let mut path = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    path.push(lit.value());
+
+    let src = match std::fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(err) => {
+            let msg = format!("couldn't read {}: {}", path.display(), err);
+            return syn::Error::new(lit.span(), msg).to_compile_error().into();
+        }
+    };
+    match parse::document(&src) {
+        Ok(block) => expand(&block, Some(&path.to_string_lossy())).into(),
+        Err(err) => {
+            let msg = format!("{}: {}", path.display(), err);
+            syn::Error::new(lit.span(), msg).to_compile_error().into()
+        }
+    }
+}
+
+/// Derives `scfg::Documented` for a struct with named fields, from the doc
+/// comments of its fields. See the documentation of the trait for the
+/// attributes it takes.
+#[proc_macro_derive(Documented, attributes(scfg))]
+pub fn derive_documented(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    documented(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn documented(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        let msg = "generic types are not supported";
+        return Err(syn::Error::new_spanned(&input.generics, msg));
+    }
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            let msg = "only structs with named fields are supported";
+            return Err(syn::Error::new_spanned(&input.ident, msg));
+        }
+    };
+    let rename_all = serde_attrs(&input.attrs)?.rename_all;
+    let mut docs = Vec::new();
+    for field in fields {
+        let serde = serde_attrs(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+        let name = match serde.rename {
+            Some(name) => name,
+            None => rename(ident, rename_all.as_deref()).ok_or_else(|| {
+                let rule = rename_all.as_deref().unwrap_or_default();
+                let msg = format!("unsupported rename_all rule {:?}", rule);
+                syn::Error::new_spanned(&input.ident, msg)
+            })?,
+        };
+        let doc = doc_comment(&field.attrs);
+        let optional = held_type(&field.ty, "Option").is_some();
+        let (block, example) = scfg_attrs(&field.attrs)?;
+        let block = if block {
+            let ty = held_type(&field.ty, "Option").unwrap_or(&field.ty);
+            let ty = held_type(ty, "Vec").unwrap_or(ty);
+            quote!(Some(<#ty as ::scfg::Documented>::fields))
+        } else {
+            quote!(None)
+        };
+        let example = match example {
+            Some(example) => quote!(Some(#example)),
+            None => quote!(None),
+        };
+        docs.push(quote! {
+            ::scfg::FieldDoc {
+                name: #name,
+                doc: #doc,
+                optional: #optional,
+                example: #example,
+                block: #block,
+            }
+        });
+    }
+    let ident = &input.ident;
+    Ok(quote! {
+        impl ::scfg::Documented for #ident {
+            fn fields() -> &'static [::scfg::FieldDoc] {
+                static FIELDS: &[::scfg::FieldDoc] = &[#(#docs),*];
+                FIELDS
+            }
+        }
+    })
+}
+
+/// The serde attributes which change the directives of fields.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+}
+
+fn serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut serde = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                serde.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("rename_all") && meta.input.peek(Token![=]) {
+                serde.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                serde.skip = true;
+            } else {
+                skip_meta(&meta)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(serde)
+}
+
+/// Skips the value of an attribute, like `= "..."` or `(...)`.
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Lit>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested| skip_meta(&nested))?;
+    }
+    Ok(())
+}
+
+/// Returns the `block` flag and the `example` of the `scfg` attributes.
+fn scfg_attrs(attrs: &[Attribute]) -> syn::Result<(bool, Option<String>)> {
+    let mut block = false;
+    let mut example = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("scfg")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("block") {
+                block = true;
+            } else if meta.path.is_ident("example") {
+                example = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                return Err(meta.error("unknown scfg attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok((block, example))
+}
+
+/// Renames `field` following a serde `rename_all` rule, or returns `None` if
+/// the rule is unknown.
+fn rename(field: &str, rule: Option<&str>) -> Option<String> {
+    let renamed = match rule {
+        None | Some("snake_case") => field.to_owned(),
+        Some("lowercase") => field.to_lowercase(),
+        Some("UPPERCASE") | Some("SCREAMING_SNAKE_CASE") => field.to_uppercase(),
+        Some("kebab-case") => field.replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => field.to_uppercase().replace('_', "-"),
+        Some("PascalCase") | Some("camelCase") => {
+            let mut renamed = String::new();
+            let mut upper = rule == Some("PascalCase");
+            for c in field.chars() {
+                if c == '_' {
+                    upper = true;
+                } else if upper {
+                    renamed.extend(c.to_uppercase());
+                    upper = false;
+                } else {
+                    renamed.push(c);
+                }
+            }
+            renamed
+        }
+        Some(_) => return None,
+    };
+    Some(renamed)
+}
+
+/// Returns the doc comment of an item, without the comment markers.
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let doc: Vec<_> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(MetaNameValue {
+                value:
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(lit), ..
+                    }),
+                ..
+            }) => Some(lit.value()),
+            _ => None,
+        })
+        .collect();
+    let doc = doc.join("\n");
+    let lines: Vec<_> = doc
+        .lines()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+        .collect();
+    lines.join("\n").trim_matches('\n').to_owned()
+}
+
+/// Returns `T` if `ty` is `wrapper<T>`, like `Option<T>`.
+fn held_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Expands to a block expression building `block`. If the document was read
+/// from `path`, the file is included so that changes to it trigger a rebuild.
+fn expand(block: &parse::Block, path: Option<&str>) -> TokenStream2 {
+    let track = path.map(|path| quote! { const _: &str = include_str!(#path); });
+    let adds = expand_block(block, &quote!(doc));
+    quote! {{
+        #track
+        #[allow(unused_mut)]
+        let mut doc = ::scfg::Scfg::new();
+        #adds
+        doc
+    }}
+}
+
+fn expand_block(block: &parse::Block, target: &TokenStream2) -> TokenStream2 {
+    block
+        .iter()
+        .map(|directive| {
+            let name = &directive.name;
+            let params = &directive.params;
+            let child = directive.child.as_ref().map(|child| {
+                let adds = expand_block(child, &quote!(child));
+                quote! {
+                    let child = directive.get_or_create_child();
+                    #adds
+                }
+            });
+            quote! {{
+                #[allow(unused_variables)]
+                let directive = #target.add(#name);
+                #(directive.append_param(#params);)*
+                #child
+            }}
+        })
+        .collect()
+}
+
+/// A minimal version of the scfg parser, which cannot be shared with the scfg
+/// crate without a dependency cycle. It must accept and reject the same
+/// documents, with the same error messages.
+mod parse {
+    pub type Block = Vec<Directive>;
+
+    pub struct Directive {
+        pub name: String,
+        pub params: Vec<String>,
+        pub child: Option<Block>,
+    }
+
+    pub fn document(src: &str) -> Result<Block, String> {
+        let mut lines = src.lines();
+        let mut lineno = 0;
+        let (block, closing_brace) = read_block(&mut lines, &mut lineno)?;
+        if closing_brace {
+            return Err(error(lineno, "unexpected '}'"));
+        }
+        Ok(block)
+    }
+
+    fn error(lineno: usize, msg: impl std::fmt::Display) -> String {
+        format!("parsing error at line {}: {}", lineno, msg)
+    }
+
+    fn read_block<'a>(
+        lines: &mut impl Iterator<Item = &'a str>,
+        lineno: &mut usize,
+    ) -> Result<(Block, bool), String> {
+        let mut block = Block::new();
+
+        loop {
+            *lineno += 1;
+            let line = match lines.next() {
+                Some(line) => line,
+                None => return Ok((block, false)),
+            };
+            if line.contains('\0') {
+                return Err(error(*lineno, format_args!("invalid character {:?}", '\0')));
+            }
+            let line = line.trim();
+
+            let mut words = shell_words::split(line).map_err(|err| error(*lineno, err))?;
+            if words.is_empty() {
+                continue;
+            }
+
+            let last_byte = *line.as_bytes().last().unwrap();
+            if words.len() == 1 && last_byte == b'}' {
+                return Ok((block, true));
+            }
+
+            if line.starts_with('{') && line != "{" {
+                return Err(error(
+                    *lineno,
+                    "unexpected '{', a block must follow a directive name",
+                ));
+            }
+
+            let has_child = words.last().unwrap() == "{" && last_byte == b'{';
+            let has_empty_child = words.len() > 1
+                && words.last().unwrap() == "{}"
+                && matches!(line.strip_suffix("{}"), Some(rest) if rest.ends_with(char::is_whitespace));
+            if has_child || has_empty_child {
+                words.pop();
+                let name = if words.is_empty() {
+                    String::new()
+                } else {
+                    words.remove(0)
+                };
+                let child = if has_empty_child {
+                    Block::new()
+                } else {
+                    let (child, closing_brace) = read_block(lines, lineno)?;
+                    if !closing_brace {
+                        return Err(error(*lineno, "io: unexpected end of file"));
+                    }
+                    child
+                };
+                block.push(Directive {
+                    name,
+                    params: words,
+                    child: Some(child),
+                });
+            } else {
+                let name = words.remove(0);
+                block.push(Directive {
+                    name,
+                    params: words,
+                    child: None,
+                });
+            }
+        }
+    }
+}
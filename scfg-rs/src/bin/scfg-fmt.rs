@@ -0,0 +1,235 @@
+//! Formats scfg files, keeping their comments. See [`scfg::format`].
+//!
+//! ```text
+//! usage: scfg-fmt [--check | --write] [FILE]...
+//! ```
+//!
+//! Without files, or for a file named `-`, standard input is read. By default
+//! the formatted documents are written to standard output. With `--write`,
+//! files are rewritten in place when they change. With `--check`, nothing is
+//! written, and a unified diff is printed for every file that is not formatted.
+//!
+//! The exit status is 0 on success, 1 if `--check` found a file that is not
+//! formatted, and 2 if a file could not be read, parsed or written, or if the
+//! arguments are invalid.
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process,
+};
+
+const USAGE: &str = "usage: scfg-fmt [--check | --write] [FILE]...";
+
+/// The number of unchanged lines shown around changes in diffs.
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Print,
+    Write,
+    Check,
+}
+
+fn main() {
+    let mut mode = Mode::Print;
+    let mut paths = Vec::new();
+    let mut options_done = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            _ if options_done => paths.push(arg),
+            "--" => options_done = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--write" | "--check" if mode != Mode::Print => usage_error(),
+            "--write" => mode = Mode::Write,
+            "--check" => mode = Mode::Check,
+            _ if arg.starts_with('-') && arg != "-" => usage_error(),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("-".to_owned());
+    }
+    if mode == Mode::Write && paths.iter().any(|path| path == "-") {
+        eprintln!("scfg-fmt: --write cannot be used with standard input");
+        process::exit(2);
+    }
+
+    let mut status = 0;
+    for path in &paths {
+        match run(mode, path) {
+            Ok(true) => {}
+            Ok(false) => status = status.max(1),
+            Err(err) => {
+                eprintln!("scfg-fmt: {}", err);
+                status = 2;
+            }
+        }
+    }
+    process::exit(status);
+}
+
+fn usage_error() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Formats the file at `path`. Returns false if `--check` found it is not
+/// formatted.
+fn run(mode: Mode, path: &str) -> Result<bool, String> {
+    let name = if path == "-" { "<stdin>" } else { path };
+    let src = read(path).map_err(|err| format!("{}: {}", name, err))?;
+    let formatted = scfg::format(&src).map_err(|err| format!("{}: {}", name, err))?;
+    match mode {
+        Mode::Print => io::stdout()
+            .write_all(formatted.as_bytes())
+            .map_err(|err| format!("<stdout>: {}", err))?,
+        Mode::Write if formatted != src => {
+            fs::write(path, &formatted).map_err(|err| format!("{}: {}", name, err))?
+        }
+        Mode::Write => {}
+        Mode::Check if formatted != src => {
+            print!("{}", diff(name, &src, &formatted));
+            return Ok(false);
+        }
+        Mode::Check => {}
+    }
+    Ok(true)
+}
+
+fn read(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src)?;
+        Ok(src)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op<'a> {
+    Keep(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Returns the shortest edit script turning `old` into `new`, computed from
+/// their longest common subsequence.
+///
+/// The common prefix and suffix are kept without entering the LCS table, so
+/// that its size is quadratic only in the changed region, which formatting
+/// usually keeps small.
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old_rest[..old_rest.len() - suffix];
+    let new_mid = &new_rest[..new_rest.len() - suffix];
+
+    // lcs[i][j] is the length of the LCS of old_mid[i..] and new_mid[j..]
+    let mut lcs = vec![vec![0; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops: Vec<_> = old[..prefix].iter().map(|line| Op::Keep(line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            ops.push(Op::Keep(old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_mid.len() && (j == new_mid.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Delete(old_mid[i]));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(new_mid[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old_rest[old_mid.len()..].iter().map(|line| Op::Keep(line)));
+    ops
+}
+
+/// Formats the changes from `old` to `new` as a unified diff.
+fn diff(name: &str, old: &str, new: &str) -> String {
+    // lines keep their terminator, so that a missing final newline is a change
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = new.split_inclusive('\n').collect();
+    let ops = edit_script(&old_lines, &new_lines);
+    let changes: Vec<_> = (0..ops.len())
+        .filter(|&k| !matches!(ops[k], Op::Keep(_)))
+        .collect();
+
+    let mut out = format!("--- {}\n+++ {}\n", name, name);
+    let mut k = 0;
+    while k < changes.len() {
+        // a hunk covers the changes closer than twice the context
+        let first = changes[k];
+        while k + 1 < changes.len() && changes[k + 1] - changes[k] <= 2 * CONTEXT {
+            k += 1;
+        }
+        let last = changes[k];
+        k += 1;
+
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + 1 + CONTEXT).min(ops.len());
+        let old_start = ops[..start]
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(_)))
+            .count();
+        let new_start = ops[..start]
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(_)))
+            .count();
+        let hunk = &ops[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(_)))
+            .count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        for op in hunk {
+            let (prefix, line) = match op {
+                Op::Keep(line) => (' ', line),
+                Op::Delete(line) => ('-', line),
+                Op::Insert(line) => ('+', line),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Formats a hunk range starting after `start` lines, in unified diff style.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
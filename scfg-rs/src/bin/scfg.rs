@@ -0,0 +1,249 @@
+//! Queries and converts scfg files from the command line.
+//!
+//! ```text
+//! usage: scfg get [--all] [--json] [--raw] FILE PATH
+//!        scfg convert (--to json [--pretty] | --from json) [FILE]
+//!        scfg validate [--schema SCHEMA] [--max-errors N] [--quiet] [FILE]
+//! ```
+//!
+//! `scfg get` prints the parameters of the directive found at `PATH`, a list
+//! of directive names separated by `.` such as `train.model.max-speed`, in the
+//! document read from `FILE`. The first matching directive is printed, or
+//! every one with `--all`, each on its own line. Parameters are quoted like in
+//! scfg documents unless `--raw` is given, in which case they are separated by
+//! single spaces. With `--json`, a JSON array holding an object with the `line`
+//! and `params` of every printed directive is written instead. The exit status
+//! is 1 if no directive matched.
+//!
+//! `scfg convert` converts a document to or from JSON, with the mapping of
+//! [`Scfg::to_json_value`] and [`Scfg::from_json_value`], and writes it to
+//! standard output. JSON is written on a single line unless `--pretty` is
+//! given. JSON values that do not map back to a document are rejected, naming
+//! the offending value with a JSON pointer.
+//!
+//! `scfg validate` checks the syntax of a document, and prints the warnings
+//! found while parsing it, such as control characters in words. With
+//! `--schema`, the document is also validated against the schema described by
+//! `SCHEMA` (see [`Schema::from_scfg`]), and every violation is printed, sorted
+//! by line, as `FILE:LINE: PATH: RULE`. At most `N` violations are printed with
+//! `--max-errors`, followed by the number of violations left out. Nothing is
+//! printed with `--quiet`. The exit status is 1 if the schema was violated.
+//!
+//! All commands read standard input if `FILE` is `-`, or omitted where
+//! allowed. The exit status is 2 if the input could not be read, parsed or
+//! converted, or if the arguments are invalid.
+use scfg::{Directive, Scfg, Schema};
+use std::{
+    env, fs,
+    io::{self, Read},
+    process,
+};
+
+const USAGE: &str = "usage: scfg get [--all] [--json] [--raw] FILE PATH
+       scfg convert (--to json [--pretty] | --from json) [FILE]
+       scfg validate [--schema SCHEMA] [--max-errors N] [--quiet] [FILE]";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("get") => get(args),
+        Some("convert") => convert(args),
+        Some("validate") => validate(args),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => usage_error(),
+    };
+    match result {
+        Ok(status) => process::exit(status),
+        Err(err) => {
+            eprintln!("scfg: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+fn usage_error() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Runs `scfg get`, returning the exit status.
+fn get(args: impl Iterator<Item = String>) -> Result<i32, String> {
+    let (mut all, mut json, mut raw) = (false, false, false);
+    let mut operands = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--json" => json = true,
+            "--raw" => raw = true,
+            _ if arg.starts_with("--") => usage_error(),
+            _ => operands.push(arg),
+        }
+    }
+    if operands.len() != 2 {
+        usage_error();
+    }
+    let doc = parse(&operands[0])?;
+
+    let path: Vec<_> = operands[1].split('.').collect();
+    let mut matches = doc.get_all_path(&path);
+    if !all {
+        matches.truncate(1);
+    }
+
+    if json {
+        let matches: Vec<_> = matches
+            .iter()
+            .map(|directive| {
+                serde_json::json!({
+                    "line": directive.line(),
+                    "params": directive.params(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::from(matches));
+    } else {
+        for directive in &matches {
+            println!("{}", format_params(directive, raw));
+        }
+    }
+    Ok(if matches.is_empty() { 1 } else { 0 })
+}
+
+fn format_params(directive: &Directive, raw: bool) -> String {
+    if raw {
+        directive.params().join(" ")
+    } else {
+        directive.to_string()
+    }
+}
+
+/// Runs `scfg convert`, returning the exit status.
+fn convert(mut args: impl Iterator<Item = String>) -> Result<i32, String> {
+    let (mut to, mut from, mut pretty) = (None, None, false);
+    let mut operands = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => to = Some(args.next().unwrap_or_else(|| usage_error())),
+            "--from" => from = Some(args.next().unwrap_or_else(|| usage_error())),
+            "--pretty" => pretty = true,
+            _ if arg.starts_with("--") => usage_error(),
+            _ => operands.push(arg),
+        }
+    }
+    let file = match operands.as_slice() {
+        [] => "-",
+        [file] => file,
+        _ => usage_error(),
+    };
+    match (to.as_deref(), from.as_deref()) {
+        (Some("json"), None) => {
+            let value = parse(file)?.into_json_value();
+            let json = if pretty {
+                serde_json::to_string_pretty(&value)
+            } else {
+                serde_json::to_string(&value)
+            };
+            println!("{}", json.map_err(|err| err.to_string())?);
+        }
+        (None, Some("json")) if !pretty => {
+            let name = display_name(file);
+            let value: serde_json::Value = serde_json::from_str(&read(file)?)
+                .map_err(|err| format!("{}: invalid JSON: {}", name, err))?;
+            let doc = Scfg::from_json_value(&value)
+                .map_err(|err| format!("{}: cannot convert {}", name, err))?;
+            doc.write(&mut io::stdout())
+                .map_err(|err| format!("<stdout>: {}", err))?;
+        }
+        (Some(format), None) | (None, Some(format)) if format != "json" => {
+            return Err(format!("unsupported format {:?}", format));
+        }
+        _ => usage_error(),
+    }
+    Ok(0)
+}
+
+/// Runs `scfg validate`, returning the exit status.
+fn validate(mut args: impl Iterator<Item = String>) -> Result<i32, String> {
+    let (mut schema, mut max_errors, mut quiet) = (None, None, false);
+    let mut operands = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => schema = Some(args.next().unwrap_or_else(|| usage_error())),
+            "--max-errors" => {
+                let max = args.next().and_then(|max| max.parse::<usize>().ok());
+                max_errors = Some(max.unwrap_or_else(|| usage_error()));
+            }
+            "--quiet" => quiet = true,
+            _ if arg.starts_with("--") => usage_error(),
+            _ => operands.push(arg),
+        }
+    }
+    let file = match operands.as_slice() {
+        [] => "-",
+        [file] => file,
+        _ => usage_error(),
+    };
+    let name = display_name(file);
+
+    let schema = match schema {
+        Some(schema) => {
+            let doc = parse(&schema)?;
+            Some(Schema::from_scfg(&doc).map_err(|err| format!("{}: {}", schema, err))?)
+        }
+        None => None,
+    };
+    let (doc, warnings) = Scfg::parse_with_warnings(read(file)?.as_bytes())
+        .map_err(|err| format!("{}: {}", name, err))?;
+    if !quiet {
+        for warning in &warnings {
+            println!("{}:{}: warning: {}", name, warning.line(), warning.kind());
+        }
+    }
+
+    let mut errors = match schema.map(|schema| schema.validate(&doc)) {
+        Some(Err(errors)) => errors,
+        _ => return Ok(0),
+    };
+    if !quiet {
+        errors.sort_by_key(|err| err.line);
+        let shown = max_errors.unwrap_or(errors.len()).min(errors.len());
+        for err in &errors[..shown] {
+            match err.line {
+                Some(line) => print!("{}:{}: ", name, line),
+                None => print!("{}: ", name),
+            }
+            println!("{}: {}", err.path.join("."), err.rule);
+        }
+        if shown < errors.len() {
+            println!("{}: {} more errors", name, errors.len() - shown);
+        }
+    }
+    Ok(1)
+}
+
+fn display_name(file: &str) -> &str {
+    if file == "-" {
+        "<stdin>"
+    } else {
+        file
+    }
+}
+
+fn read(file: &str) -> Result<String, String> {
+    let src = if file == "-" {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src).map(|_| src)
+    } else {
+        fs::read_to_string(file)
+    };
+    src.map_err(|err| format!("{}: {}", display_name(file), err))
+}
+
+fn parse(file: &str) -> Result<Scfg, String> {
+    read(file)?
+        .parse()
+        .map_err(|err| format!("{}: {}", display_name(file), err))
+}
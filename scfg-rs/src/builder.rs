@@ -0,0 +1,139 @@
+//! A closure based builder for nested documents.
+use crate::{Directive, Scfg};
+use std::ops::{Deref, DerefMut};
+
+/// Builds the directives of a block, see [`Scfg::build`].
+///
+/// A builder dereferences to the [`Scfg`] block it fills, so the rest of the
+/// API, such as [`Scfg::add`], can be used along with it.
+#[derive(Debug)]
+pub struct Builder<'a> {
+    block: &'a mut Scfg,
+}
+
+impl Builder<'_> {
+    /// Adds a directive with the supplied parameters and no child block.
+    /// Returns the new directive.
+    ///
+    /// # Note
+    /// This does not validate that `name` and `params` are legal scfg words.
+    pub fn directive<I>(&mut self, name: impl Into<String>, params: I) -> &mut Directive
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let directive = Directive {
+            params: params.into_iter().map(Into::into).collect(),
+            child: None,
+            line: None,
+        };
+        self.block.add_directive(name, directive)
+    }
+
+    /// Adds a directive with the supplied parameters and a child block, which
+    /// is populated by `body`. Returns the new directive.
+    ///
+    /// # Note
+    /// This does not validate that `name` and `params` are legal scfg words.
+    pub fn block<I, F>(&mut self, name: impl Into<String>, params: I, body: F) -> &mut Directive
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+        F: FnOnce(&mut Builder<'_>),
+    {
+        self.block
+            .add_block(name, params, |child| body(&mut Builder { block: child }))
+    }
+}
+
+impl Deref for Builder<'_> {
+    type Target = Scfg;
+
+    fn deref(&self) -> &Scfg {
+        self.block
+    }
+}
+
+impl DerefMut for Builder<'_> {
+    fn deref_mut(&mut self) -> &mut Scfg {
+        self.block
+    }
+}
+
+impl Scfg {
+    /// Builds a document with nested closures, one per block.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc = Scfg::build(|b| {
+    ///     b.block("train", ["Shinkansen"], |b| {
+    ///         b.block("model", ["E5"], |b| {
+    ///             b.directive("max-speed", ["320km/h"]);
+    ///             b.directive("weight", ["453.5t"]);
+    ///             b.directive("lines-served", ["Tōhoku", "Hokkaido"]);
+    ///         });
+    ///         b.block("model", ["E7"], |b| {
+    ///             b.directive("max-speed", ["275km/h"]);
+    ///             b.directive("weight", ["540t"]);
+    ///             // the builder can be mixed with the rest of the API
+    ///             b.add("lines-served")
+    ///                 .append_param("Hokuriku")
+    ///                 .append_param("Jōetsu");
+    ///         });
+    ///     });
+    /// });
+    ///
+    /// let parsed: Scfg = r#"train "Shinkansen" {
+    ///     model "E5" {
+    ///         max-speed 320km/h
+    ///         weight 453.5t
+    ///
+    ///         lines-served "Tōhoku" "Hokkaido"
+    ///     }
+    ///
+    ///     model "E7" {
+    ///         max-speed 275km/h
+    ///         weight 540t
+    ///
+    ///         lines-served "Hokuriku" "Jōetsu"
+    ///     }
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    /// assert_eq!(doc, parsed);
+    /// ```
+    pub fn build<F>(body: F) -> Scfg
+    where
+        F: FnOnce(&mut Builder<'_>),
+    {
+        let mut doc = Scfg::new();
+        body(&mut Builder { block: &mut doc });
+        doc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let cert = String::from("cert.pem");
+        let doc = Scfg::build(|b| {
+            b.directive("domain", ["example.com"]);
+            b.block("listen", ["0.0.0.0:6697"], |b| {
+                b.directive("certificate", [&cert]);
+                b.block("empty", Vec::<String>::new(), |_| {});
+            });
+            b.directive("motd", Vec::<String>::new())
+                .get_or_create_child()
+                .add("line")
+                .append_param("hello");
+        });
+        let exp: Scfg = "domain example.com\nlisten 0.0.0.0:6697 {\n\tcertificate cert.pem\n\tempty {\n\t}\n}\nmotd {\n\tline hello\n}\n"
+            .parse()
+            .unwrap();
+        assert_eq!(doc, exp);
+        assert_eq!(Scfg::build(|_| {}), Scfg::new());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{ParseError, Scfg};
+use sha2::{Digest, Sha256};
+use std::{fmt, io};
+
+const PREFIX: &str = "# checksum: sha256:";
+
+/// An error returned by [`Scfg::verify_checksum`].
+#[derive(Debug)]
+pub enum ChecksumError {
+    /// The last line of the source is not a checksum comment.
+    Missing,
+    /// The checksum does not match the content.
+    Mismatch {
+        /// The hex encoded checksum found in the source.
+        expected: String,
+        /// The hex encoded checksum of the content.
+        actual: String,
+    },
+    /// The content is not a valid scfg document.
+    Parse(ParseError),
+}
+
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumError::Missing => write!(f, "missing checksum"),
+            ChecksumError::Mismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected sha256:{}, found sha256:{}",
+                expected, actual
+            ),
+            ChecksumError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ChecksumError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChecksumError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+impl Scfg {
+    /// Writes the document to the specified writer like [`write`], followed by
+    /// a `# checksum: sha256:<hex>` comment line holding the SHA-256 of
+    /// everything written before it.
+    ///
+    /// The output can be checked with [`verify_checksum`].
+    ///
+    /// [`write`]: Scfg::write
+    /// [`verify_checksum`]: Scfg::verify_checksum
+    pub fn write_with_checksum<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let mut buf = Vec::new();
+        self.write(&mut buf)?;
+        writer.write_all(&buf)?;
+        writeln!(writer, "{}{}", PREFIX, sha256_hex(&buf))
+    }
+
+    /// Verifies a document written by [`write_with_checksum`].
+    ///
+    /// The last line of `src` must be the checksum comment, and must match
+    /// the SHA-256 of everything before it, which must be a valid document.
+    ///
+    /// [`write_with_checksum`]: Scfg::write_with_checksum
+    pub fn verify_checksum(src: &str) -> Result<(), ChecksumError> {
+        let trimmed = src.strip_suffix('\n').unwrap_or(src);
+        let start = trimmed.rfind('\n').map_or(0, |i| i + 1);
+        let expected = trimmed[start..]
+            .strip_prefix(PREFIX)
+            .ok_or(ChecksumError::Missing)?;
+        let content = &src[..start];
+        content.parse::<Scfg>().map_err(ChecksumError::Parse)?;
+
+        let actual = sha256_hex(content.as_bytes());
+        if actual != expected {
+            return Err(ChecksumError::Mismatch {
+                expected: expected.to_owned(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn written() -> String {
+        let src = "listen 0.0.0.0:6697 {\n\tcertificate cert.pem\n}\n\ndomain example.com\n";
+        let doc = src.parse::<Scfg>().unwrap();
+        let mut out = Vec::new();
+        doc.write_with_checksum(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn valid() {
+        let out = written();
+        let last = out.lines().last().unwrap();
+        assert!(last.starts_with("# checksum: sha256:"));
+        assert_eq!(last.len(), PREFIX.len() + 64);
+        Scfg::verify_checksum(&out).unwrap();
+    }
+
+    #[test]
+    fn tampered() {
+        let out = written().replace("example.com", "example.org");
+        match Scfg::verify_checksum(&out) {
+            Err(ChecksumError::Mismatch { expected, actual }) => assert_ne!(expected, actual),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn missing() {
+        let out = written();
+        let stripped = &out[..out.find(PREFIX).unwrap()];
+        assert!(matches!(
+            Scfg::verify_checksum(stripped),
+            Err(ChecksumError::Missing)
+        ));
+        assert!(matches!(
+            Scfg::verify_checksum(""),
+            Err(ChecksumError::Missing)
+        ));
+    }
+
+    #[test]
+    fn invalid_content() {
+        let content = "block {\n";
+        let src = format!("{}{}{}\n", content, PREFIX, sha256_hex(content.as_bytes()));
+        assert!(matches!(
+            Scfg::verify_checksum(&src),
+            Err(ChecksumError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+//! Merging repeated directives into one, and splitting them back.
+use crate::{Directive, Scfg};
+use std::fmt;
+
+/// How [`Scfg::coalesce`] treats a group of directives holding a child block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildBlocks {
+    /// The group is an error.
+    Error,
+    /// The group is left unchanged.
+    Skip,
+}
+
+/// An error returned by [`Scfg::coalesce`] and [`Scfg::explode`] for
+/// directives whose child blocks can't be merged or split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoalesceError {
+    /// The name of the directive.
+    pub name: String,
+    /// The line of the directive, if known.
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for CoalesceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "directive {:?} has a child block", self.name)
+    }
+}
+
+impl std::error::Error for CoalesceError {}
+
+impl Scfg {
+    /// Merges the top-level directives named `name` into one, whose
+    /// parameters are theirs in document order. The merged directive keeps the
+    /// line of the first one.
+    ///
+    /// Child blocks can't be meaningfully concatenated, so a group of several
+    /// directives of which any has a child block is either an error or left
+    /// unchanged, as `blocks` says. A group of a single directive is always
+    /// left unchanged.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "lines-served Tōhoku\nlines-served Hokkaido\n".parse().unwrap();
+    /// doc.coalesce("lines-served", ChildBlocks::Error).unwrap();
+    /// assert_eq!(doc, "lines-served Tōhoku Hokkaido\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error for a directive with a child block, with
+    /// [`ChildBlocks::Error`]. The document is then unchanged.
+    pub fn coalesce(&mut self, name: &str, blocks: ChildBlocks) -> Result<(), CoalesceError> {
+        let directives = match self.directives.get_mut(name) {
+            Some(directives) if directives.len() > 1 => directives,
+            _ => return Ok(()),
+        };
+        if let Some(directive) = directives.iter().find(|d| d.child.is_some()) {
+            return match blocks {
+                ChildBlocks::Error => Err(CoalesceError {
+                    name: name.to_owned(),
+                    line: directive.line,
+                }),
+                ChildBlocks::Skip => Ok(()),
+            };
+        }
+        let mut merged = Directive {
+            line: directives[0].line,
+            ..Directive::default()
+        };
+        for directive in directives.drain(..) {
+            merged.params.extend(directive.params);
+        }
+        directives.push(merged);
+        Ok(())
+    }
+
+    /// Merges every group of directives sharing a name like [`coalesce`], at
+    /// every level of nesting. With [`ChildBlocks::Skip`], the child blocks of
+    /// skipped groups are still coalesced.
+    ///
+    /// # Errors
+    /// See [`coalesce`]. The document may then be partially coalesced.
+    ///
+    /// [`coalesce`]: Scfg::coalesce
+    pub fn coalesce_all(&mut self, blocks: ChildBlocks) -> Result<(), CoalesceError> {
+        let names: Vec<String> = self.directives.keys().cloned().collect();
+        for name in names {
+            self.coalesce(&name, blocks)?;
+        }
+        for directive in self.directives.values_mut().flatten() {
+            if let Some(ref mut child) = directive.child {
+                child.coalesce_all(blocks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits each top-level directive named `name` into one directive per
+    /// parameter, in order, keeping its line. This is the inverse of
+    /// [`coalesce`]. Directives without parameters are left unchanged.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "lines-served Tōhoku Hokkaido\n".parse().unwrap();
+    /// doc.explode("lines-served").unwrap();
+    /// assert_eq!(doc, "lines-served Tōhoku\nlines-served Hokkaido\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error for a directive with several parameters and a child
+    /// block, which can't be split. The document is then unchanged.
+    ///
+    /// [`coalesce`]: Scfg::coalesce
+    pub fn explode(&mut self, name: &str) -> Result<(), CoalesceError> {
+        let directives = match self.directives.get_mut(name) {
+            Some(directives) => directives,
+            None => return Ok(()),
+        };
+        if let Some(directive) = directives
+            .iter()
+            .find(|d| d.params.len() > 1 && d.child.is_some())
+        {
+            return Err(CoalesceError {
+                name: name.to_owned(),
+                line: directive.line,
+            });
+        }
+        let mut exploded = Vec::with_capacity(directives.len());
+        for directive in directives.drain(..) {
+            if directive.params.len() <= 1 {
+                exploded.push(directive);
+                continue;
+            }
+            let line = directive.line;
+            exploded.extend(directive.params.into_iter().map(|param| Directive {
+                params: vec![param],
+                child: None,
+                line,
+            }));
+        }
+        *directives = exploded;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn coalesce() {
+        let src = "lines-served Tōhoku\nlines-served\nlines-served Hokkaido \"Jōetsu\"\nmodel E5\n";
+        let mut doc: Scfg = src.parse().unwrap();
+        doc.coalesce("lines-served", ChildBlocks::Error).unwrap();
+        doc.coalesce("missing", ChildBlocks::Error).unwrap();
+        let merged = doc.get_all("lines-served").unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].params(), ["Tōhoku", "Hokkaido", "Jōetsu"]);
+        assert_eq!(merged[0].line, Some(1));
+        assert_eq!(doc.get("model").unwrap().params(), ["E5"]);
+    }
+
+    #[test]
+    fn child_blocks() {
+        let src = "a 1\na 2 {\n\tb x\n\tb y\n}\nc {\n\td 1\n\td 2\n}\n";
+        let mut doc: Scfg = src.parse().unwrap();
+        let err = doc.coalesce("a", ChildBlocks::Error).unwrap_err();
+        assert_eq!(
+            err,
+            CoalesceError {
+                name: "a".to_owned(),
+                line: Some(2)
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            r#"line 2: directive "a" has a child block"#
+        );
+        assert_eq!(doc, src.parse().unwrap());
+        assert!(doc.coalesce_all(ChildBlocks::Error).is_err());
+
+        doc.coalesce("c", ChildBlocks::Error).unwrap();
+        doc.coalesce_all(ChildBlocks::Skip).unwrap();
+        let exp = "a 1\na 2 {\n\tb x y\n}\nc {\n\td 1 2\n}\n";
+        assert_eq!(doc, exp.parse().unwrap());
+    }
+
+    #[test]
+    fn round_trip() {
+        let src = "a 1 2 3\na\na 4\nb x {\n}\n";
+        let mut doc: Scfg = src.parse().unwrap();
+        doc.explode("a").unwrap();
+        let exploded: Vec<_> = doc
+            .get_all("a")
+            .unwrap()
+            .iter()
+            .map(|d| d.params().join(" "))
+            .collect();
+        assert_eq!(exploded, ["1", "2", "3", "", "4"]);
+        doc.explode("b").unwrap();
+        doc.coalesce("a", ChildBlocks::Error).unwrap();
+        assert_eq!(doc, "a 1 2 3 4\nb x {\n}\n".parse().unwrap());
+        doc.explode("a").unwrap();
+        doc.coalesce("a", ChildBlocks::Error).unwrap();
+        assert_eq!(doc, "a 1 2 3 4\nb x {\n}\n".parse().unwrap());
+
+        let mut doc: Scfg = "b x y {\n}\n".parse().unwrap();
+        assert_eq!(
+            doc.explode("b"),
+            Err(CoalesceError {
+                name: "b".to_owned(),
+                line: Some(1)
+            })
+        );
+    }
+}
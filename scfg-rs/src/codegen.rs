@@ -0,0 +1,737 @@
+//! Generation of typed Rust code from an annotated sample document.
+//!
+//! This is available with the `codegen` feature, and is intended to be used
+//! from build scripts, with the output written to `OUT_DIR` and pulled in with
+//! [`include!`].
+//!
+//! The sample is an scfg document describing the shape of a configuration:
+//! - each directive name may end with `?` if the directive is optional, or
+//!   with `*` if it may appear any number of times. Otherwise it must appear
+//!   exactly once.
+//! - the parameters of each directive are the types of its parameters: one of
+//!   `string`, `bool`, `char`, the integer types (`u8` to `u128`, `i8` to
+//!   `i128`, `usize` and `isize`), `f32` or `f64`.
+//! - a directive without parameters or block is a flag, which is true if the
+//!   directive is present.
+//! - a directive with a block describes a nested struct. If it also has
+//!   parameters, they are loaded in the `params` field of that struct.
+//!
+//! ```
+//! # use scfg::*;
+//! let sample: Scfg = r#"
+//! domain string
+//! listen* string {
+//!     tls? {
+//!         certificate string
+//!         key string
+//!     }
+//! }
+//! verbose
+//! "#
+//! .parse()
+//! .unwrap();
+//! let code = codegen::generate(&sample, "Config").unwrap();
+//! assert!(code.contains("pub struct Config {"));
+//! assert!(code.contains("pub listen: Vec<ConfigListen>,"));
+//! assert!(code.contains("pub tls: Option<ConfigListenTls>,"));
+//! ```
+//!
+//! The generated code defines the structs, with the root struct named as
+//! requested, a `LoadError` type, and a `load(&Scfg) -> Result<_, LoadError>`
+//! function. Fields are named after the directives, converted to snake case,
+//! and nested structs are named after their parent and directive names,
+//! converted to pascal case, which must not give two types the same name.
+//! Directives which aren't in the sample are ignored by `load`.
+use crate::Scfg;
+use std::fmt::{self, Write};
+
+/// An error returned by [`generate`] for an invalid sample.
+#[derive(Debug)]
+pub struct Error {
+    path: String,
+    message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "codegen error at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Generates the Rust source loading documents described by `sample` into a
+/// struct named `name`. See the [module documentation](self) for the format
+/// of the sample and the generated code.
+pub fn generate(sample: &Scfg, name: &str) -> Result<String, Error> {
+    if name == "LoadError" {
+        return Err(error(
+            "",
+            "type name `LoadError` collides with the error type",
+        ));
+    }
+    let mut structs = Vec::new();
+    collect_struct(sample, name.to_owned(), Vec::new(), "", &mut structs)?;
+
+    let mut helpers = Helpers::default();
+    let mut out = String::from("// @generated by scfg::codegen, do not edit.\n");
+    for s in &structs {
+        s.write_definition(&mut out);
+    }
+    for s in &structs {
+        s.write_load(&mut helpers, &mut out);
+    }
+    write_support(name, &helpers, &mut out);
+    Ok(out)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cardinality {
+    One,
+    Optional,
+    Repeated,
+}
+
+enum Value {
+    Flag,
+    Params(Vec<&'static str>),
+    Struct(String),
+}
+
+struct Field {
+    name: String,
+    ident: String,
+    cardinality: Cardinality,
+    value: Value,
+}
+
+struct Struct {
+    name: String,
+    /// The path of the directive holding the block, empty for the root struct.
+    path: String,
+    /// The parameter types of the directive holding the block, `None` for the
+    /// root struct.
+    params: Option<Vec<&'static str>>,
+    fields: Vec<Field>,
+}
+
+/// Tracks the helper functions used by the generated code, so that only
+/// those are emitted.
+#[derive(Default)]
+struct Helpers {
+    one: bool,
+    optional: bool,
+    repeated: bool,
+    child: bool,
+    params: bool,
+}
+
+fn error(path: &str, message: impl Into<String>) -> Error {
+    Error {
+        path: if path.is_empty() {
+            String::from("<root>")
+        } else {
+            path.to_owned()
+        },
+        message: message.into(),
+    }
+}
+
+fn collect_struct(
+    block: &Scfg,
+    name: String,
+    params: Vec<&'static str>,
+    path: &str,
+    structs: &mut Vec<Struct>,
+) -> Result<(), Error> {
+    let index = structs.len();
+    let is_root = structs.is_empty();
+    structs.push(Struct {
+        name: name.clone(),
+        path: path.to_owned(),
+        params: if is_root { None } else { Some(params) },
+        fields: Vec::new(),
+    });
+
+    let mut names: Vec<_> = block.directives.iter().collect();
+    names.sort_by(|a, b| a.0.cmp(b.0));
+    let mut fields = Vec::new();
+    for (raw_name, directives) in names {
+        let (field_name, cardinality) = if let Some(n) = raw_name.strip_suffix('?') {
+            (n, Cardinality::Optional)
+        } else if let Some(n) = raw_name.strip_suffix('*') {
+            (n, Cardinality::Repeated)
+        } else {
+            (raw_name.as_str(), Cardinality::One)
+        };
+        let field_path = if path.is_empty() {
+            field_name.to_owned()
+        } else {
+            format!("{}.{}", path, field_name)
+        };
+        if directives.len() > 1 {
+            return Err(error(&field_path, "directive described more than once"));
+        }
+        let directive = &directives[0];
+
+        let ident = field_ident(field_name)
+            .ok_or_else(|| error(&field_path, "name can't be made into a field name"))?;
+        let has_params = !is_root && !structs[index].params.as_ref().unwrap().is_empty();
+        if ident == "params" && has_params {
+            return Err(error(
+                &field_path,
+                "`params` is reserved in blocks with parameters",
+            ));
+        }
+        if fields.iter().any(|f: &Field| f.ident == ident) {
+            return Err(error(&field_path, "directive described more than once"));
+        }
+
+        let types = directive
+            .params
+            .iter()
+            .map(|ty| {
+                rust_type(ty).ok_or_else(|| error(&field_path, format!("unknown type `{}`", ty)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let value = match directive.child {
+            Some(ref child) => {
+                let type_name = type_ident(field_name)
+                    .ok_or_else(|| error(&field_path, "name can't be made into a type name"))?;
+                let struct_name = format!("{}{}", name, type_name);
+                if struct_name == "LoadError" {
+                    let message = "type name `LoadError` collides with the error type";
+                    return Err(error(&field_path, message));
+                }
+                if let Some(other) = structs.iter().find(|s| s.name == struct_name) {
+                    let other = if other.path.is_empty() {
+                        "the root struct".to_owned()
+                    } else {
+                        format!("`{}`", other.path)
+                    };
+                    let message = format!("type name `{}` collides with {}", struct_name, other);
+                    return Err(error(&field_path, message));
+                }
+                collect_struct(child, struct_name.clone(), types, &field_path, structs)?;
+                Value::Struct(struct_name)
+            }
+            None if types.is_empty() => {
+                if cardinality != Cardinality::One {
+                    return Err(error(&field_path, "flags can't be optional or repeated"));
+                }
+                Value::Flag
+            }
+            None => Value::Params(types),
+        };
+        fields.push(Field {
+            name: field_name.to_owned(),
+            ident,
+            cardinality,
+            value,
+        });
+    }
+    structs[index].fields = fields;
+    Ok(())
+}
+
+fn rust_type(ty: &str) -> Option<&'static str> {
+    const TYPES: &[&str] = &[
+        "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+        "i128", "isize", "f32", "f64",
+    ];
+    if ty == "string" {
+        return Some("String");
+    }
+    TYPES.iter().copied().find(|&t| t == ty)
+}
+
+/// Converts a directive name to a snake case identifier, or a raw identifier
+/// for keywords.
+fn field_ident(name: &str) -> Option<String> {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let",
+        "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+        "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+        "virtual", "where", "while", "yield",
+    ];
+    let mut ident = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii() {
+            return None;
+        }
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && prev_lower {
+                ident.push('_');
+            }
+            ident.push(c.to_ascii_lowercase());
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            ident.push('_');
+            prev_lower = false;
+        }
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) || ident.chars().all(|c| c == '_') {
+        return None;
+    }
+    match ident.as_str() {
+        "crate" | "self" | "super" => None,
+        kw if KEYWORDS.contains(&kw) => Some(format!("r#{}", ident)),
+        _ => Some(ident),
+    }
+}
+
+/// Converts a directive name to a pascal case identifier fragment.
+fn type_ident(name: &str) -> Option<String> {
+    if !name.is_ascii() {
+        return None;
+    }
+    let ident: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect();
+    if ident.is_empty() {
+        None
+    } else {
+        Some(ident)
+    }
+}
+
+fn params_type(types: &[&str]) -> String {
+    match types {
+        [ty] => (*ty).to_owned(),
+        _ => format!("({})", types.join(", ")),
+    }
+}
+
+/// Returns an expression loading the parameters of `directive`, which must
+/// already have been checked to have `types.len()` parameters. Tuples are
+/// split over several lines, with the closing parenthesis at `indent`.
+fn params_expr(types: &[&str], path: &str, indent: &str) -> String {
+    let param = |i| format!("__scfg_param(directive, {}, {})?", path, i);
+    if types.len() == 1 {
+        return param(0);
+    }
+    let mut expr = String::from("(\n");
+    for i in 0..types.len() {
+        writeln!(expr, "{}    {},", indent, param(i)).unwrap();
+    }
+    expr.push_str(indent);
+    expr.push(')');
+    expr
+}
+
+impl Field {
+    fn value_type(&self) -> String {
+        match self.value {
+            Value::Flag => String::from("bool"),
+            Value::Params(ref types) => params_type(types),
+            Value::Struct(ref name) => name.clone(),
+        }
+    }
+
+    fn field_type(&self) -> String {
+        let ty = self.value_type();
+        match self.cardinality {
+            Cardinality::One => ty,
+            Cardinality::Optional => format!("Option<{}>", ty),
+            Cardinality::Repeated => format!("Vec<{}>", ty),
+        }
+    }
+
+    /// Returns the statement checking the parameter count of a single
+    /// directive bound to `directive`, if any, and the expression loading its
+    /// value. The path of the directive must be bound to `path`.
+    fn value_expr(&self, helpers: &mut Helpers, indent: &str) -> (Option<String>, String) {
+        match self.value {
+            Value::Flag => unreachable!(),
+            Value::Params(ref types) => {
+                helpers.params = true;
+                let check = format!("__scfg_arity(directive, &path, {})?;", types.len());
+                (Some(check), params_expr(types, "&path", indent))
+            }
+            Value::Struct(ref name) => (None, format!("{}::load(directive, &path)?", name)),
+        }
+    }
+
+    /// Returns an expression loading the field from the block bound to `doc`,
+    /// whose path is bound to `path`. The expression is indented for a struct
+    /// expression field.
+    fn load_expr(&self, helpers: &mut Helpers) -> String {
+        const INDENT: &str = "            ";
+        if let Value::Flag = self.value {
+            return format!("doc.contains({:?})", self.name);
+        }
+        let mut out = String::new();
+        match self.cardinality {
+            Cardinality::One => {
+                helpers.one = true;
+                let (check, value) = self.value_expr(helpers, &format!("{}    ", INDENT));
+                out.push_str("{\n");
+                writeln!(
+                    out,
+                    "{}    let (directive, path) = __scfg_one(doc, path, {:?})?;",
+                    INDENT, self.name
+                )
+                .unwrap();
+                if let Some(check) = check {
+                    writeln!(out, "{}    {}", INDENT, check).unwrap();
+                }
+                writeln!(out, "{}    {}", INDENT, value).unwrap();
+                write!(out, "{}}}", INDENT).unwrap();
+            }
+            Cardinality::Optional => {
+                helpers.optional = true;
+                let (check, value) = self.value_expr(helpers, &format!("{}        ", INDENT));
+                writeln!(out, "match __scfg_optional(doc, path, {:?})? {{", self.name).unwrap();
+                match check {
+                    Some(check) => {
+                        writeln!(out, "{}    Some((directive, path)) => {{", INDENT).unwrap();
+                        writeln!(out, "{}        {}", INDENT, check).unwrap();
+                        writeln!(out, "{}        Some({})", INDENT, value).unwrap();
+                        writeln!(out, "{}    }}", INDENT).unwrap();
+                    }
+                    None => {
+                        writeln!(
+                            out,
+                            "{}    Some((directive, path)) => Some({}),",
+                            INDENT, value
+                        )
+                        .unwrap();
+                    }
+                }
+                writeln!(out, "{}    None => None,", INDENT).unwrap();
+                write!(out, "{}}}", INDENT).unwrap();
+            }
+            Cardinality::Repeated => {
+                helpers.repeated = true;
+                let (check, value) = self.value_expr(helpers, &format!("{}        ", INDENT));
+                writeln!(out, "__scfg_repeated(doc, path, {:?})", self.name).unwrap();
+                writeln!(
+                    out,
+                    "{}    .map(|(directive, path)| -> Result<_, LoadError> {{",
+                    INDENT
+                )
+                .unwrap();
+                match check {
+                    Some(check) => {
+                        writeln!(out, "{}        {}", INDENT, check).unwrap();
+                        writeln!(out, "{}        Ok({})", INDENT, value).unwrap();
+                    }
+                    // avoid wrapping the result of a nested load in `Ok(...?)`
+                    None => {
+                        let value = value.strip_suffix('?').unwrap_or(&value);
+                        writeln!(out, "{}        {}", INDENT, value).unwrap();
+                    }
+                }
+                writeln!(out, "{}    }})", INDENT).unwrap();
+                write!(out, "{}    .collect::<Result<_, _>>()?", INDENT).unwrap();
+            }
+        }
+        out
+    }
+}
+
+impl Struct {
+    fn write_definition(&self, out: &mut String) {
+        out.push('\n');
+        out.push_str("#[derive(Debug, Clone, PartialEq)]\n");
+        let params = self.params.as_deref().unwrap_or_default();
+        if params.is_empty() && self.fields.is_empty() {
+            writeln!(out, "pub struct {} {{}}", self.name).unwrap();
+            return;
+        }
+        writeln!(out, "pub struct {} {{", self.name).unwrap();
+        if !params.is_empty() {
+            writeln!(out, "    pub params: {},", params_type(params)).unwrap();
+        }
+        for field in &self.fields {
+            writeln!(out, "    pub {}: {},", field.ident, field.field_type()).unwrap();
+        }
+        out.push_str("}\n");
+    }
+
+    fn write_load(&self, helpers: &mut Helpers, out: &mut String) {
+        out.push('\n');
+        writeln!(out, "impl {} {{", self.name).unwrap();
+        let params = match self.params {
+            None => {
+                out.push_str(
+                    "    fn load(doc: &::scfg::Scfg, path: &str) -> Result<Self, LoadError> {\n",
+                );
+                if self.fields.is_empty() {
+                    out.push_str("        let _ = (doc, path);\n");
+                }
+                &[][..]
+            }
+            Some(ref params) => {
+                helpers.params = true;
+                helpers.child = true;
+                out.push_str(
+                    "    fn load(directive: &::scfg::Directive, path: &str) -> Result<Self, LoadError> {\n",
+                );
+                writeln!(
+                    out,
+                    "        __scfg_arity(directive, path, {})?;",
+                    params.len()
+                )
+                .unwrap();
+                if self.fields.is_empty() {
+                    out.push_str("        __scfg_child(directive, path)?;\n");
+                } else {
+                    out.push_str("        let doc = __scfg_child(directive, path)?;\n");
+                }
+                params
+            }
+        };
+
+        if self.fields.is_empty() && params.is_empty() {
+            writeln!(out, "        Ok({} {{}})", self.name).unwrap();
+        } else {
+            writeln!(out, "        Ok({} {{", self.name).unwrap();
+            if !params.is_empty() {
+                writeln!(
+                    out,
+                    "            params: {},",
+                    params_expr(params, "path", "            ")
+                )
+                .unwrap();
+            }
+            for field in &self.fields {
+                let expr = field.load_expr(helpers);
+                writeln!(out, "            {}: {},", field.ident, expr).unwrap();
+            }
+            out.push_str("        })\n");
+        }
+        out.push_str("    }\n}\n");
+    }
+}
+
+fn write_support(name: &str, helpers: &Helpers, out: &mut String) {
+    write!(
+        out,
+        r#"
+/// An error returned by [`load`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadError {{
+    /// The path of the offending directive, with names separated by `.`.
+    pub path: String,
+    /// What is wrong with the directive.
+    pub message: String,
+}}
+
+impl ::std::fmt::Display for LoadError {{
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {{
+        write!(f, "{{}}: {{}}", self.path, self.message)
+    }}
+}}
+
+impl ::std::error::Error for LoadError {{}}
+
+/// Loads a [`{name}`] from a parsed document.
+pub fn load(doc: &::scfg::Scfg) -> Result<{name}, LoadError> {{
+    {name}::load(doc, "")
+}}
+"#,
+        name = name
+    )
+    .unwrap();
+
+    let lookup = helpers.one || helpers.optional || helpers.repeated;
+    if lookup || helpers.params || helpers.child {
+        out.push_str(
+            r#"
+fn __scfg_error(path: &str, message: impl ::std::fmt::Display) -> LoadError {
+    LoadError {
+        path: path.to_owned(),
+        message: message.to_string(),
+    }
+}
+"#,
+        );
+    }
+    if lookup {
+        out.push_str(
+            r#"
+fn __scfg_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+"#,
+        );
+    }
+    if helpers.one {
+        out.push_str(
+            r#"
+fn __scfg_one<'a>(
+    doc: &'a ::scfg::Scfg,
+    path: &str,
+    name: &str,
+) -> Result<(&'a ::scfg::Directive, String), LoadError> {
+    let path = __scfg_path(path, name);
+    match doc.get_all(name).unwrap_or_default() {
+        [directive] => Ok((directive, path)),
+        [] => Err(__scfg_error(&path, "missing directive")),
+        _ => Err(__scfg_error(&path, "directive may only appear once")),
+    }
+}
+"#,
+        );
+    }
+    if helpers.optional {
+        out.push_str(
+            r#"
+fn __scfg_optional<'a>(
+    doc: &'a ::scfg::Scfg,
+    path: &str,
+    name: &str,
+) -> Result<Option<(&'a ::scfg::Directive, String)>, LoadError> {
+    let path = __scfg_path(path, name);
+    match doc.get_all(name).unwrap_or_default() {
+        [] => Ok(None),
+        [directive] => Ok(Some((directive, path))),
+        _ => Err(__scfg_error(&path, "directive may only appear once")),
+    }
+}
+"#,
+        );
+    }
+    if helpers.repeated {
+        out.push_str(
+            r#"
+fn __scfg_repeated<'a>(
+    doc: &'a ::scfg::Scfg,
+    path: &str,
+    name: &str,
+) -> impl Iterator<Item = (&'a ::scfg::Directive, String)> {
+    let path = __scfg_path(path, name);
+    doc.get_all(name)
+        .unwrap_or_default()
+        .iter()
+        .map(move |directive| (directive, path.clone()))
+}
+"#,
+        );
+    }
+    if helpers.child {
+        out.push_str(
+            r#"
+fn __scfg_child<'a>(
+    directive: &'a ::scfg::Directive,
+    path: &str,
+) -> Result<&'a ::scfg::Scfg, LoadError> {
+    directive
+        .child()
+        .ok_or_else(|| __scfg_error(path, "expected a block"))
+}
+"#,
+        );
+    }
+    if helpers.params {
+        out.push_str(
+            r#"
+fn __scfg_arity(directive: &::scfg::Directive, path: &str, n: usize) -> Result<(), LoadError> {
+    let found = directive.params().len();
+    if found != n {
+        let message = format!("expected {} parameters, found {}", n, found);
+        return Err(__scfg_error(path, message));
+    }
+    Ok(())
+}
+
+fn __scfg_param<T>(directive: &::scfg::Directive, path: &str, index: usize) -> Result<T, LoadError>
+where
+    T: ::std::str::FromStr,
+    T::Err: ::std::fmt::Display,
+{
+    let param = &directive.params()[index];
+    param
+        .parse()
+        .map_err(|err| __scfg_error(path, format!("invalid parameter {:?}: {}", param, err)))
+}
+"#,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn golden_code() -> String {
+        let sample: Scfg = include_str!("../tests/data/codegen.scfg").parse().unwrap();
+        generate(&sample, "Config").unwrap()
+    }
+
+    #[test]
+    fn golden() {
+        assert_eq!(golden_code(), include_str!("../tests/data/codegen.rs"));
+    }
+
+    #[test]
+    #[ignore]
+    fn regenerate() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/codegen.rs");
+        std::fs::write(path, golden_code()).unwrap();
+    }
+
+    #[test]
+    fn idents() {
+        assert_eq!(field_ident("max-speed").unwrap(), "max_speed");
+        assert_eq!(field_ident("maxSpeed").unwrap(), "max_speed");
+        assert_eq!(field_ident("type").unwrap(), "r#type");
+        assert_eq!(field_ident("http2").unwrap(), "http2");
+        assert!(field_ident("2fa").is_none());
+        assert!(field_ident("-").is_none());
+        assert!(field_ident("self").is_none());
+        assert!(field_ident("vitesse-max-é").is_none());
+        assert_eq!(type_ident("lines-served").unwrap(), "LinesServed");
+        assert_eq!(type_ident("tls").unwrap(), "Tls");
+    }
+
+    #[test]
+    fn invalid_samples() {
+        let cases = [
+            ("port u16\nport u16\n", "port"),
+            ("a string\na? string\n", "a"),
+            ("port integer\n", "port"),
+            ("verbose?\n", "verbose"),
+            ("listen string {\n\tparams string\n}\n", "listen.params"),
+            ("é string\n", "é"),
+            ("listen {\n\ttls {\n\t}\n}\nlisten-tls {\n}\n", "listen-tls"),
+            ("listen {\n\ttls {\n\t}\n}\nlisten_tls {\n}\n", "listen_tls"),
+        ];
+        for (src, path) in cases.iter() {
+            let sample: Scfg = src.parse().unwrap();
+            let err = generate(&sample, "Config").unwrap_err();
+            assert_eq!(err.path, *path, "{}", src);
+        }
+
+        let sample: Scfg = "listen {\n\ttls {\n\t}\n}\nlisten-tls {\n}\n"
+            .parse()
+            .unwrap();
+        let err = generate(&sample, "Config").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "codegen error at listen-tls: type name `ConfigListenTls` collides with `listen.tls`"
+        );
+        let sample: Scfg = "error {\n}\n".parse().unwrap();
+        let err = generate(&sample, "Load").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "codegen error at error: type name `LoadError` collides with the error type"
+        );
+        assert!(generate(&Scfg::new(), "LoadError").is_err());
+    }
+}
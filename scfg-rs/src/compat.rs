@@ -0,0 +1,1626 @@
+//! Writers for the configuration formats of other programs.
+use crate::{Directive, Scfg};
+use std::{fmt, io};
+
+/// An error returned by [`Scfg::write_as_systemd_unit`].
+#[derive(Debug)]
+pub enum SystemdExportError {
+    /// A top-level directive has no block, so it is not a section.
+    MissingSection {
+        /// The name of the directive.
+        name: String,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// A section directive has parameters, which have no systemd equivalent.
+    SectionParams {
+        /// The name of the section.
+        section: String,
+        /// The line of the section, if known.
+        line: Option<usize>,
+    },
+    /// A directive inside a section has a block of its own.
+    NestedBlock {
+        /// The name of the enclosing section.
+        section: String,
+        /// The name of the directive.
+        key: String,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// A directive inside a section has more than one parameter, so its value
+    /// is ambiguous.
+    MultipleParams {
+        /// The name of the enclosing section.
+        section: String,
+        /// The name of the directive.
+        key: String,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// The writer returned an error.
+    Io(io::Error),
+}
+
+impl fmt::Display for SystemdExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = match self {
+            SystemdExportError::MissingSection { line, .. }
+            | SystemdExportError::SectionParams { line, .. }
+            | SystemdExportError::NestedBlock { line, .. }
+            | SystemdExportError::MultipleParams { line, .. } => *line,
+            SystemdExportError::Io(err) => return write!(f, "{}", err),
+        };
+        if let Some(line) = line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self {
+            SystemdExportError::MissingSection { name, .. } => {
+                write!(f, "{}: expected a section block", name)
+            }
+            SystemdExportError::SectionParams { section, .. } => {
+                write!(f, "{}: a section cannot have parameters", section)
+            }
+            SystemdExportError::NestedBlock { section, key, .. } => {
+                write!(f, "{}.{}: unexpected block", section, key)
+            }
+            SystemdExportError::MultipleParams { section, key, .. } => {
+                write!(f, "{}.{}: expected at most one parameter", section, key)
+            }
+            SystemdExportError::Io(_) => unreachable!(),
+        }
+    }
+}
+
+impl std::error::Error for SystemdExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SystemdExportError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SystemdExportError {
+    fn from(err: io::Error) -> Self {
+        SystemdExportError::Io(err)
+    }
+}
+
+/// An error returned by [`Scfg::from_systemd_unit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemdParseError {
+    /// A `Key=Value` line comes before the first section header.
+    OutsideSection {
+        /// The line of the assignment.
+        line: usize,
+    },
+    /// A line starting with `[` is not a valid section header.
+    InvalidSection {
+        /// The line of the header.
+        line: usize,
+    },
+    /// A line is neither a section header, an assignment nor a comment, or
+    /// its key is empty.
+    InvalidLine {
+        /// The line number.
+        line: usize,
+    },
+}
+
+impl fmt::Display for SystemdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemdParseError::OutsideSection { line } => {
+                write!(f, "line {}: assignment outside of a section", line)
+            }
+            SystemdParseError::InvalidSection { line } => {
+                write!(f, "line {}: invalid section header", line)
+            }
+            SystemdParseError::InvalidLine { line } => {
+                write!(f, "line {}: expected `Key=Value`", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SystemdParseError {}
+
+/// An error returned by [`Scfg::write_as_wrangler_toml`].
+#[derive(Debug)]
+pub enum WranglerExportError {
+    /// A directive has neither parameters nor a block, so it has no value.
+    MissingValue {
+        /// The dotted path of the directive.
+        key: String,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// A key is given more than once in the same TOML table.
+    DuplicateKey {
+        /// The dotted path of the directive.
+        key: String,
+        /// The line of the repeated directive, if known.
+        line: Option<usize>,
+    },
+    /// A directive with a block has parameters, which have no TOML
+    /// equivalent.
+    TableParams {
+        /// The name of the directive.
+        table: String,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// A directive inside a table has a block of its own.
+    NestedBlock {
+        /// The dotted path of the directive.
+        key: String,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// The writer returned an error.
+    Io(io::Error),
+}
+
+impl fmt::Display for WranglerExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = match self {
+            WranglerExportError::MissingValue { line, .. }
+            | WranglerExportError::DuplicateKey { line, .. }
+            | WranglerExportError::TableParams { line, .. }
+            | WranglerExportError::NestedBlock { line, .. } => *line,
+            WranglerExportError::Io(err) => return write!(f, "{}", err),
+        };
+        if let Some(line) = line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self {
+            WranglerExportError::MissingValue { key, .. } => {
+                write!(f, "{}: expected a value", key)
+            }
+            WranglerExportError::DuplicateKey { key, .. } => write!(f, "{}: duplicate key", key),
+            WranglerExportError::TableParams { table, .. } => {
+                write!(f, "{}: a table cannot have parameters", table)
+            }
+            WranglerExportError::NestedBlock { key, .. } => {
+                write!(f, "{}: unexpected block", key)
+            }
+            WranglerExportError::Io(_) => unreachable!(),
+        }
+    }
+}
+
+impl std::error::Error for WranglerExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WranglerExportError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WranglerExportError {
+    fn from(err: io::Error) -> Self {
+        WranglerExportError::Io(err)
+    }
+}
+
+/// The Wrangler settings which are arrays of tables, written as `[[name]]`.
+const WRANGLER_ARRAYS: [&str; 6] = [
+    "analytics_engine_datasets",
+    "d1_databases",
+    "kv_namespaces",
+    "r2_buckets",
+    "routes",
+    "services",
+];
+
+impl Scfg {
+    /// Writes the `upstream` directives of this document as NGINX `upstream`
+    /// blocks.
+    ///
+    /// Each `upstream` directive must have exactly one parameter, its name.
+    /// The directives in its block, such as `server`, are written as NGINX
+    /// directives terminated by `;`, and must not have blocks of their own.
+    /// Other top-level directives are ignored, so the upstreams can be
+    /// extracted from a larger document.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "upstream backend {\n\tserver 10.0.0.1:8080\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_nginx_upstream(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "upstream backend {\n    server 10.0.0.1:8080;\n}\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the document
+    /// does not have the structure described above, or any error returned by
+    /// `writer`.
+    pub fn write_as_nginx_upstream<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let upstreams = self.get_all("upstream").unwrap_or_default();
+        for (i, upstream) in upstreams.iter().enumerate() {
+            let name = match upstream.params() {
+                [name] => name,
+                _ => return Err(invalid_data("upstream", upstream, "expected a name")),
+            };
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writeln!(writer, "upstream {} {{", nginx_quote(name))?;
+            if let Some(child) = upstream.child() {
+                for (name, directives) in &child.directives {
+                    for directive in directives {
+                        if directive.child().is_some() {
+                            return Err(invalid_data(name, directive, "unexpected block"));
+                        }
+                        write!(writer, "    {}", nginx_quote(name))?;
+                        for param in directive.params() {
+                            write!(writer, " {}", nginx_quote(param))?;
+                        }
+                        writeln!(writer, ";")?;
+                    }
+                }
+            }
+            writeln!(writer, "}}")?;
+        }
+        Ok(())
+    }
+
+    /// Writes this document as an NGINX configuration file.
+    ///
+    /// Directives without a block are written as `name params;`, and
+    /// directives with a block as `name params {`, followed by the block
+    /// indented by four more spaces and a closing brace. As in [`write`], a
+    /// block is followed by a blank line when more directives follow it.
+    /// Words are quoted with double quotes only if they are empty or hold
+    /// whitespace, quotes, backslashes, `;`, braces or `#`, which NGINX
+    /// would otherwise split on or misread.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "server {\n\tlisten 80\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_nginx_config(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "server {\n    listen 80;\n}\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns any error returned by `writer`.
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_as_nginx_config<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        self.write_nginx_block(writer, 0)
+    }
+
+    fn write_nginx_block<W>(&self, writer: &mut W, depth: usize) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let indent = "    ".repeat(depth);
+        let mut after_block = false;
+        for (name, directives) in &self.directives {
+            for directive in directives {
+                if after_block {
+                    writeln!(writer)?;
+                }
+                write!(writer, "{}{}", indent, nginx_quote(name))?;
+                for param in directive.params() {
+                    write!(writer, " {}", nginx_quote(param))?;
+                }
+                after_block = directive.child().is_some();
+                match directive.child() {
+                    Some(child) => {
+                        writeln!(writer, " {{")?;
+                        child.write_nginx_block(writer, depth + 1)?;
+                        writeln!(writer, "{}}}", indent)?;
+                    }
+                    None => writeln!(writer, ";")?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes this document as a systemd unit file.
+    ///
+    /// Each top-level directive is a section, and must have a block and no
+    /// parameters. Each directive in the block becomes a `Key=value` line, and
+    /// must have at most one parameter and no block; a directive without
+    /// parameters is written as `Key=`, which resets list settings in systemd.
+    /// Sections are separated by blank lines.
+    ///
+    /// Unless the `preserve_order` feature is enabled, sections and keys are
+    /// written sorted by name. Directives sharing a name keep their order.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "Service {\n\tExecStart /usr/bin/foo\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_systemd_unit(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "[Service]\nExecStart=/usr/bin/foo\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a [`SystemdExportError`] if the document does not have the
+    /// structure described above, or if `writer` returns an error.
+    pub fn write_as_systemd_unit<W>(&self, writer: &mut W) -> Result<(), SystemdExportError>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut first = true;
+        for (section, directives) in &self.directives {
+            for directive in directives {
+                let child = match directive.child() {
+                    Some(child) => child,
+                    None => {
+                        return Err(SystemdExportError::MissingSection {
+                            name: section.clone(),
+                            line: directive.line(),
+                        })
+                    }
+                };
+                if !directive.params().is_empty() {
+                    return Err(SystemdExportError::SectionParams {
+                        section: section.clone(),
+                        line: directive.line(),
+                    });
+                }
+                if !first {
+                    writeln!(writer)?;
+                }
+                first = false;
+                writeln!(writer, "[{}]", section)?;
+                for (key, entries) in &child.directives {
+                    for entry in entries {
+                        if entry.child().is_some() {
+                            return Err(SystemdExportError::NestedBlock {
+                                section: section.clone(),
+                                key: key.clone(),
+                                line: entry.line(),
+                            });
+                        }
+                        let value = match entry.params() {
+                            [] => "",
+                            [value] => value,
+                            _ => {
+                                return Err(SystemdExportError::MultipleParams {
+                                    section: section.clone(),
+                                    key: key.clone(),
+                                    line: entry.line(),
+                                })
+                            }
+                        };
+                        writeln!(writer, "{}={}", key, value)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a systemd unit file, the inverse of [`write_as_systemd_unit`].
+    ///
+    /// Each `[Section]` header becomes a top-level directive named after the
+    /// section, without parameters, whose block holds a directive for every
+    /// `Key=Value` assignment of the section. The value, with surrounding
+    /// whitespace removed, is the only parameter of the directive; an empty
+    /// value gives a directive without parameters. Quotes and escapes in
+    /// values are kept as is, since their meaning depends on the key.
+    ///
+    /// Lines ending with `\` continue on the next line, the backslash being
+    /// replaced by a space. Empty lines and lines starting with `#` or `;` are
+    /// comments, and are skipped, even inside continued lines. A section that
+    /// appears several times gives several directives.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc = Scfg::from_systemd_unit("[Service]\nExecStart=/usr/bin/foo\n").unwrap();
+    /// let service = doc.get("Service").unwrap().child().unwrap();
+    /// assert_eq!(service.get("ExecStart").unwrap().params(), ["/usr/bin/foo"]);
+    /// ```
+    ///
+    /// [`write_as_systemd_unit`]: Scfg::write_as_systemd_unit
+    pub fn from_systemd_unit(src: &str) -> Result<Scfg, SystemdParseError> {
+        let mut doc = Scfg::new();
+        let mut section: Option<&mut Scfg> = None;
+        let mut lines = src.lines().enumerate().map(|(i, line)| (i + 1, line));
+        while let Some((lineno, line)) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') {
+                let name = line
+                    .strip_prefix('[')
+                    .and_then(|line| line.strip_suffix(']'))
+                    .filter(|name| !name.is_empty() && !name.contains(['[', ']']))
+                    .ok_or(SystemdParseError::InvalidSection { line: lineno })?;
+                let directive = Directive {
+                    line: Some(lineno),
+                    ..Directive::new()
+                };
+                section = Some(doc.add_directive(name, directive).get_or_create_child());
+                continue;
+            }
+
+            let mut line = line.to_owned();
+            while line.ends_with('\\') {
+                line.pop();
+                line.push(' ');
+                let next = lines.find(|(_, next)| !next.trim_start().starts_with(['#', ';']));
+                match next {
+                    Some((_, next)) => line.push_str(next.trim_end()),
+                    None => break,
+                }
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim_end(), value.trim()))
+                .filter(|(key, _)| !key.is_empty())
+                .ok_or(SystemdParseError::InvalidLine { line: lineno })?;
+            let section = section
+                .as_mut()
+                .ok_or(SystemdParseError::OutsideSection { line: lineno })?;
+            let directive = Directive {
+                params: if value.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![value.to_owned()]
+                },
+                child: None,
+                line: Some(lineno),
+            };
+            section.add_directive(key, directive);
+        }
+        Ok(doc)
+    }
+
+    /// Writes this document in the syntax of Apache HTTP Server
+    /// configuration files, such as a `VirtualHost` section.
+    ///
+    /// Directives with a block become sections, `<Name params>` followed by
+    /// the directives of the block, indented by four spaces, and `</Name>`.
+    /// Sections may be nested. Other directives are written as `Name params`
+    /// lines. A blank line follows every top-level section which is not the
+    /// last directive. Parameters are quoted with double quotes if they are
+    /// empty or hold whitespace, quotes, backslashes or angle brackets.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "VirtualHost *:80 {\n\tServerName example.com\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_apache_vhost(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "<VirtualHost *:80>\n    ServerName example.com\n</VirtualHost>\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns any error returned by `writer`.
+    pub fn write_as_apache_vhost<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        self.write_apache_block(writer, 0)
+    }
+
+    fn write_apache_block<W>(&self, writer: &mut W, depth: usize) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let indent = "    ".repeat(depth);
+        let mut blank = false;
+        for (name, directives) in &self.directives {
+            for directive in directives {
+                if blank {
+                    writeln!(writer)?;
+                    blank = false;
+                }
+                let mut line = format!("{}{}", indent, name);
+                for param in directive.params() {
+                    line.push(' ');
+                    line.push_str(&apache_quote(param));
+                }
+                match directive.child() {
+                    Some(child) => {
+                        writeln!(writer, "{}<{}>", indent, &line[indent.len()..])?;
+                        child.write_apache_block(writer, depth + 1)?;
+                        writeln!(writer, "{}</{}>", indent, name)?;
+                        blank = depth == 0;
+                    }
+                    None => writeln!(writer, "{}", line)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes this document as an HAProxy configuration file.
+    ///
+    /// Each top-level directive is a section header, such as `frontend main`,
+    /// and the directives in its block are written below it, indented by four
+    /// spaces. Those directives must not have blocks of their own. Sections
+    /// are separated by blank lines. Parameters are quoted with double quotes
+    /// if they are empty or hold whitespace, quotes, backslashes, `#` or `$`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "backend servers {\n\tserver web1 192.168.1.1:80\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_haproxy(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "backend servers\n    server web1 192.168.1.1:80\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if a directive
+    /// in a section has a block, or any error returned by `writer`.
+    pub fn write_as_haproxy<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut first = true;
+        for (name, sections) in &self.directives {
+            for section in sections {
+                if !first {
+                    writeln!(writer)?;
+                }
+                first = false;
+                write_haproxy_line(writer, "", name, section)?;
+                let child = match section.child() {
+                    Some(child) => child,
+                    None => continue,
+                };
+                for (name, directives) in &child.directives {
+                    for directive in directives {
+                        if directive.child().is_some() {
+                            return Err(invalid_data(name, directive, "unexpected block"));
+                        }
+                        write_haproxy_line(writer, "    ", name, directive)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes this document as a Caddyfile.
+    ///
+    /// Each top-level directive with a block is a site block, or another
+    /// labeled block of the Caddyfile: its parameters, such as the site
+    /// addresses, are the label, and its name is written in a comment above
+    /// it. A directive without parameters gives an unlabeled block, like the
+    /// global options block. Top-level blocks are separated by blank lines.
+    /// The directives in blocks are written as `name params`, and their own
+    /// blocks as `name params {`, indented with one tab per level. Words are
+    /// quoted with double quotes if they are empty or hold whitespace, quotes,
+    /// backslashes, braces or `#`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "site example.com {\n\troot * /var/www\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_caddy(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "# site\nexample.com {\n\troot * /var/www\n}\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns any error returned by `writer`.
+    pub fn write_as_caddy<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut first = true;
+        for (name, directives) in &self.directives {
+            for directive in directives {
+                if !first {
+                    writeln!(writer)?;
+                }
+                first = false;
+                let child = match directive.child() {
+                    Some(child) => child,
+                    None => {
+                        write_caddy_line(writer, 0, name, directive.params())?;
+                        writeln!(writer)?;
+                        continue;
+                    }
+                };
+                writeln!(writer, "# {}", name)?;
+                let label: Vec<_> = directive.params().iter().map(|p| caddy_quote(p)).collect();
+                if label.is_empty() {
+                    writeln!(writer, "{{")?;
+                } else {
+                    writeln!(writer, "{} {{", label.join(" "))?;
+                }
+                child.write_caddy_block(writer, 1)?;
+                writeln!(writer, "}}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes this document as a `wrangler.toml` file, the configuration of
+    /// Cloudflare Workers.
+    ///
+    /// Top-level directives without a block become TOML keys. A directive
+    /// with one parameter gives a single value, and one with several
+    /// parameters gives an array. Parameters which are `true`, `false` or an
+    /// integer are written as such, and other parameters as strings.
+    ///
+    /// A `vars` block becomes an inline table, `vars = { KEY = "value" }`.
+    /// Blocks of the settings which Wrangler expects as arrays of tables, such
+    /// as `routes` or `kv_namespaces`, are written as `[[routes]]` tables,
+    /// one per directive, and other blocks, such as `build`, as `[build]`
+    /// tables. The keys of a block are written like top-level keys. Tables
+    /// come after the top-level keys, as TOML requires.
+    ///
+    /// Unless the `preserve_order` feature is enabled, keys and tables are
+    /// written sorted by name.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "name worker\nroutes {\n\tpattern example.com/*\n}\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_wrangler_toml(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "name = \"worker\"\n\n[[routes]]\npattern = \"example.com/*\"\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a [`WranglerExportError`] if a directive has no value, if a key
+    /// is repeated in a table, if a table has parameters or nested blocks, or
+    /// if `writer` returns an error.
+    pub fn write_as_wrangler_toml<W>(&self, writer: &mut W) -> Result<(), WranglerExportError>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut tables = Vec::new();
+        for (name, directives) in &self.directives {
+            let is_table = directives.iter().any(|d| d.child().is_some());
+            if is_table && name != "vars" {
+                tables.push((name, directives));
+                continue;
+            }
+            if let Some(directive) = directives.get(1) {
+                return Err(WranglerExportError::DuplicateKey {
+                    key: name.clone(),
+                    line: directive.line(),
+                });
+            }
+            let directive = &directives[0];
+            match directive.child() {
+                Some(child) => {
+                    let entries = wrangler_entries(name, directive)?;
+                    write!(writer, "{} = {{", toml_key(name))?;
+                    for (i, (key, value)) in entries.iter().enumerate() {
+                        let sep = if i == 0 { " " } else { ", " };
+                        write!(writer, "{}{} = {}", sep, toml_key(key), value)?;
+                    }
+                    let end = if child.directives.is_empty() {
+                        "}"
+                    } else {
+                        " }"
+                    };
+                    writeln!(writer, "{}", end)?;
+                }
+                None => writeln!(
+                    writer,
+                    "{} = {}",
+                    toml_key(name),
+                    wrangler_value(name, directive)?
+                )?,
+            }
+        }
+        let mut first = self.directives.len() == tables.len();
+        for (name, directives) in tables {
+            let array = WRANGLER_ARRAYS.contains(&name.as_str());
+            if !array && directives.len() > 1 {
+                return Err(WranglerExportError::DuplicateKey {
+                    key: name.clone(),
+                    line: directives[1].line(),
+                });
+            }
+            for directive in directives {
+                let entries = wrangler_entries(name, directive)?;
+                if !first {
+                    writeln!(writer)?;
+                }
+                first = false;
+                if array {
+                    writeln!(writer, "[[{}]]", toml_key(name))?;
+                } else {
+                    writeln!(writer, "[{}]", toml_key(name))?;
+                }
+                for (key, value) in entries {
+                    writeln!(writer, "{} = {}", toml_key(key), value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a Kubernetes ConfigMap manifest, in YAML, named `name` in
+    /// `namespace`, which holds this document as the `config.scfg` file of its
+    /// `data`.
+    ///
+    /// The document is written as by [`write`], in a literal block scalar, so
+    /// it is embedded as is. `name` and `namespace` are quoted if needed.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "listen :8080\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_as_kubernetes_configmap("app", "default", &mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "apiVersion: v1
+    /// kind: ConfigMap
+    /// metadata:
+    ///   name: app
+    ///   namespace: default
+    /// data:
+    ///   config.scfg: |
+    ///     listen :8080
+    /// "
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the
+    /// document cannot be written, or holds a control character other than a
+    /// tab, which a YAML block scalar cannot hold, or any error returned by
+    /// `writer`.
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_as_kubernetes_configmap<W>(
+        &self,
+        name: &str,
+        namespace: &str,
+        writer: &mut W,
+    ) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut text = Vec::new();
+        self.write(&mut text)?;
+        let text = String::from_utf8(text).expect("scfg output is UTF-8");
+        if let Some(c) = text
+            .chars()
+            .find(|&c| c.is_control() && c != '\t' && c != '\n')
+        {
+            let message = format!("cannot embed the character {:?} in YAML", c);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        writeln!(writer, "apiVersion: v1")?;
+        writeln!(writer, "kind: ConfigMap")?;
+        writeln!(writer, "metadata:")?;
+        writeln!(writer, "  name: {}", yaml_quote(name))?;
+        writeln!(writer, "  namespace: {}", yaml_quote(namespace))?;
+        writeln!(writer, "data:")?;
+        if text.is_empty() {
+            return writeln!(writer, "  config.scfg: \"\"");
+        }
+        writeln!(writer, "  config.scfg: |")?;
+        for line in text.lines() {
+            if line.is_empty() {
+                writeln!(writer)?;
+            } else {
+                writeln!(writer, "    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_caddy_block<W>(&self, writer: &mut W, depth: usize) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        for (name, directives) in &self.directives {
+            for directive in directives {
+                write_caddy_line(writer, depth, name, directive.params())?;
+                match directive.child() {
+                    Some(child) => {
+                        writeln!(writer, " {{")?;
+                        child.write_caddy_block(writer, depth + 1)?;
+                        writeln!(writer, "{}}}", "\t".repeat(depth))?;
+                    }
+                    None => writeln!(writer)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a Caddyfile directive, without its block or line ending.
+fn write_caddy_line<W>(
+    writer: &mut W,
+    depth: usize,
+    name: &str,
+    params: &[String],
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized,
+{
+    write!(writer, "{}{}", "\t".repeat(depth), caddy_quote(name))?;
+    for param in params {
+        write!(writer, " {}", caddy_quote(param))?;
+    }
+    Ok(())
+}
+
+fn write_haproxy_line<W>(
+    writer: &mut W,
+    indent: &str,
+    name: &str,
+    directive: &Directive,
+) -> io::Result<()>
+where
+    W: io::Write + ?Sized,
+{
+    write!(writer, "{}{}", indent, haproxy_quote(name))?;
+    for param in directive.params() {
+        write!(writer, " {}", haproxy_quote(param))?;
+    }
+    writeln!(writer)
+}
+
+/// Returns the keys of the block of the `table` directive with their TOML
+/// values.
+fn wrangler_entries<'a>(
+    table: &str,
+    directive: &'a Directive,
+) -> Result<Vec<(&'a str, String)>, WranglerExportError> {
+    if !directive.params().is_empty() {
+        return Err(WranglerExportError::TableParams {
+            table: table.to_owned(),
+            line: directive.line(),
+        });
+    }
+    let mut entries = Vec::new();
+    let child = match directive.child() {
+        Some(child) => child,
+        None => return Ok(entries),
+    };
+    for (name, directives) in &child.directives {
+        let key = format!("{}.{}", table, name);
+        for (i, entry) in directives.iter().enumerate() {
+            if entry.child().is_some() {
+                return Err(WranglerExportError::NestedBlock {
+                    key,
+                    line: entry.line(),
+                });
+            }
+            if i > 0 {
+                return Err(WranglerExportError::DuplicateKey {
+                    key,
+                    line: entry.line(),
+                });
+            }
+            entries.push((name.as_str(), wrangler_value(&key, entry)?));
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the TOML value of the parameters of the `key` directive.
+fn wrangler_value(key: &str, directive: &Directive) -> Result<String, WranglerExportError> {
+    match directive.params() {
+        [] => Err(WranglerExportError::MissingValue {
+            key: key.to_owned(),
+            line: directive.line(),
+        }),
+        [value] => Ok(toml_scalar(value)),
+        values => {
+            let values: Vec<_> = values.iter().map(|v| toml_scalar(v)).collect();
+            Ok(format!("[{}]", values.join(", ")))
+        }
+    }
+}
+
+/// Returns `word` as a TOML boolean or integer if it is written as one, and
+/// as a TOML string otherwise.
+fn toml_scalar(word: &str) -> String {
+    let is_integer = word.parse::<i64>().ok().map(|n| n.to_string()).as_deref() == Some(word);
+    if word == "true" || word == "false" || is_integer {
+        word.to_owned()
+    } else {
+        toml_string(word)
+    }
+}
+
+/// Returns `key` as a bare TOML key if it can be one, and as a quoted key
+/// otherwise.
+fn toml_key(key: &str) -> String {
+    let bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if !key.is_empty() && key.chars().all(bare) {
+        key.to_owned()
+    } else {
+        toml_string(key)
+    }
+}
+
+/// Quotes `word` as a TOML basic string.
+fn toml_string(word: &str) -> String {
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('"');
+    for c in word.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn invalid_data(name: &str, directive: &Directive, message: &str) -> io::Error {
+    let message = match directive.line() {
+        Some(line) => format!("line {}: {}: {}", line, name, message),
+        None => format!("{}: {}", name, message),
+    };
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Quotes `word` in double quotes, escaping `"`, `\` and the `escaped`
+/// characters, if it is empty or holds whitespace or one of the `special`
+/// characters of the target format.
+fn double_quote(word: &str, special: &str, escaped: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || special.contains(c)) {
+        return word.to_owned();
+    }
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('"');
+    for c in word.chars() {
+        if c == '"' || c == '\\' || escaped.contains(c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes `word` for NGINX, where `;` ends a directive and `#` a line.
+fn nginx_quote(word: &str) -> String {
+    double_quote(word, "\"';{}#\\", "")
+}
+
+/// Quotes `word` for Apache, where angle brackets delimit sections.
+fn apache_quote(word: &str) -> String {
+    double_quote(word, "\"'\\<>", "")
+}
+
+/// Quotes `word` for HAProxy, which expands `$` variables even in double
+/// quotes.
+fn haproxy_quote(word: &str) -> String {
+    double_quote(word, "\"'\\#$", "$")
+}
+
+/// Quotes `word` as a YAML double-quoted scalar, unless it is a plain
+/// scalar read back as the same string, like Kubernetes names.
+fn yaml_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_';
+    let reserved = ["true", "false", "null", "yes", "no", "on", "off", "~"];
+    let is_number = word.parse::<f64>().is_ok();
+    if !word.is_empty()
+        && word.chars().all(plain)
+        && word.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !reserved.contains(&word.to_lowercase().as_str())
+        && !is_number
+    {
+        return word.to_owned();
+    }
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('"');
+    for c in word.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes `word` for a Caddyfile, where braces delimit blocks and
+/// placeholders, and backticks quote tokens too.
+fn caddy_quote(word: &str) -> String {
+    double_quote(word, "\"\\{}#`", "")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn nginx(src: &str) -> io::Result<String> {
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_nginx_upstream(&mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn nginx_upstream() -> io::Result<()> {
+        let src = r#"upstream backend {
+    server 10.0.0.1:8080 weight=5
+    server 10.0.0.2:8080
+    server unix:/run/backend.sock backup
+}
+
+upstream api {
+    server api1.example.com
+    server api2.example.com max_fails=3
+    keepalive 32
+}
+
+log-level debug
+"#;
+        let expected = r#"upstream backend {
+    server 10.0.0.1:8080 weight=5;
+    server 10.0.0.2:8080;
+    server unix:/run/backend.sock backup;
+}
+
+upstream api {
+    keepalive 32;
+    server api1.example.com;
+    server api2.example.com max_fails=3;
+}
+"#;
+        #[cfg(feature = "preserve_order")]
+        let expected = expected.replace(
+            "    keepalive 32;\n    server api1.example.com;\n    server api2.example.com max_fails=3;\n",
+            "    server api1.example.com;\n    server api2.example.com max_fails=3;\n    keepalive 32;\n",
+        );
+        assert_eq!(nginx(src)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn nginx_config() -> io::Result<()> {
+        let src = r#"events {
+}
+http {
+    server {
+        listen 80
+        listen [::]:80
+        location / {
+            root /var/www/html
+            try_files $uri $uri/ =404
+        }
+        location ~ "\.php$" {
+            fastcgi_pass unix:/run/php/php-fpm.sock
+        }
+        server_name example.com www.example.com
+    }
+    tcp_nopush on
+}
+worker_processes auto
+"#;
+        let expected = r#"events {
+}
+
+http {
+    server {
+        listen 80;
+        listen [::]:80;
+        location / {
+            root /var/www/html;
+            try_files $uri $uri/ =404;
+        }
+
+        location ~ "\\.php$" {
+            fastcgi_pass unix:/run/php/php-fpm.sock;
+        }
+
+        server_name example.com www.example.com;
+    }
+
+    tcp_nopush on;
+}
+
+worker_processes auto;
+"#;
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_nginx_config(&mut out)?;
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let doc: Scfg = "add_header X-Note \"a b;c\"\nempty \"\"\n".parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_nginx_config(&mut out)?;
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "add_header X-Note \"a b;c\";\nempty \"\";\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nginx_upstream_errors() {
+        let err = nginx("upstream {\n\tserver a\n}\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 1: upstream: expected a name");
+        let err = nginx("upstream a {\n\tserver a {\n\t}\n}\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: server: unexpected block");
+    }
+
+    fn systemd(src: &str) -> Result<String, SystemdExportError> {
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_systemd_unit(&mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn systemd_unit() -> Result<(), SystemdExportError> {
+        let src = r#"Install {
+    WantedBy multi-user.target
+}
+
+Service {
+    Environment "RUST_LOG=info"
+    ExecStart "/usr/local/bin/shinkansen --config /etc/shinkansen.scfg"
+    ExecStartPre "/usr/bin/mkdir -p /var/lib/shinkansen"
+    ExecStartPre "/usr/bin/chown shinkansen /var/lib/shinkansen"
+    Restart on-failure
+    User shinkansen
+}
+
+Unit {
+    After network-online.target
+    Description "Shinkansen timetable server"
+}
+"#;
+        let expected = r#"[Install]
+WantedBy=multi-user.target
+
+[Service]
+Environment=RUST_LOG=info
+ExecStart=/usr/local/bin/shinkansen --config /etc/shinkansen.scfg
+ExecStartPre=/usr/bin/mkdir -p /var/lib/shinkansen
+ExecStartPre=/usr/bin/chown shinkansen /var/lib/shinkansen
+Restart=on-failure
+User=shinkansen
+
+[Unit]
+After=network-online.target
+Description=Shinkansen timetable server
+"#;
+        assert_eq!(systemd(src)?, expected);
+        assert_eq!(
+            systemd("Service {\n\tExecStart\n}\n")?,
+            "[Service]\nExecStart=\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn systemd_unit_errors() {
+        let err = systemd("Unit {\n}\nDescription foo\n").unwrap_err();
+        assert!(matches!(err, SystemdExportError::MissingSection { .. }));
+        assert_eq!(
+            err.to_string(),
+            "line 3: Description: expected a section block"
+        );
+        let err = systemd("Service main {\n}\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: Service: a section cannot have parameters"
+        );
+        let err = systemd("Service {\n\tExec {\n\t}\n}\n").unwrap_err();
+        assert!(matches!(err, SystemdExportError::NestedBlock { .. }));
+        assert_eq!(err.to_string(), "line 2: Service.Exec: unexpected block");
+        let err = systemd("Service {\n\tExecStart /bin/foo --bar\n}\n").unwrap_err();
+        assert!(matches!(err, SystemdExportError::MultipleParams { .. }));
+        assert_eq!(
+            err.to_string(),
+            "line 2: Service.ExecStart: expected at most one parameter"
+        );
+    }
+
+    #[test]
+    fn from_systemd_unit() -> Result<(), SystemdParseError> {
+        let doc = Scfg::from_systemd_unit("[Service]\nExecStart=/usr/bin/foo\nRestart=always\n")?;
+        let mut exp = Scfg::new();
+        let service = exp.add("Service").get_or_create_child();
+        service.add("ExecStart").append_param("/usr/bin/foo");
+        service.add("Restart").append_param("always");
+        assert_eq!(doc, exp);
+        let service = doc.get("Service").unwrap();
+        assert_eq!(service.line(), Some(1));
+        assert_eq!(
+            service.child().unwrap().get("Restart").unwrap().line(),
+            Some(3)
+        );
+
+        let src = r#"# shinkansen.service
+[Unit]
+Description = Shinkansen timetable server
+
+[Service]
+; the server
+ExecStart=/usr/local/bin/shinkansen \
+    # comment
+    --config /etc/shinkansen.scfg
+Environment="RUST_LOG=info" "RUST_BACKTRACE=1"
+ExecStartPre=
+Environment=A=B
+"#;
+        let doc = Scfg::from_systemd_unit(src)?;
+        let unit = doc.get("Unit").unwrap().child().unwrap();
+        assert_eq!(
+            unit.get("Description").unwrap().params(),
+            ["Shinkansen timetable server"]
+        );
+        let service = doc.get("Service").unwrap().child().unwrap();
+        assert_eq!(
+            service.get("ExecStart").unwrap().params(),
+            ["/usr/local/bin/shinkansen      --config /etc/shinkansen.scfg"]
+        );
+        assert_eq!(service.get("ExecStart").unwrap().line(), Some(7));
+        let env: Vec<_> = service
+            .get_all("Environment")
+            .unwrap()
+            .iter()
+            .map(|directive| directive.params())
+            .collect();
+        assert_eq!(env, [[r#""RUST_LOG=info" "RUST_BACKTRACE=1""#], ["A=B"]]);
+        assert!(service.get("ExecStartPre").unwrap().params().is_empty());
+        assert_eq!(doc.get("Service").unwrap().line(), Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn from_systemd_unit_errors() {
+        let err = |src| Scfg::from_systemd_unit(src).unwrap_err();
+        assert_eq!(
+            err("# unit\nA=b\n"),
+            SystemdParseError::OutsideSection { line: 2 }
+        );
+        assert_eq!(
+            err("[Unit\n"),
+            SystemdParseError::InvalidSection { line: 1 }
+        );
+        assert_eq!(err("[]\n"), SystemdParseError::InvalidSection { line: 1 });
+        assert_eq!(
+            err("[Unit]\nA\n"),
+            SystemdParseError::InvalidLine { line: 2 }
+        );
+        assert_eq!(
+            err("[Unit]\n=b\n"),
+            SystemdParseError::InvalidLine { line: 2 }
+        );
+        assert_eq!(
+            err("[Unit]\nA\n").to_string(),
+            "line 2: expected `Key=Value`"
+        );
+    }
+
+    #[test]
+    fn systemd_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let src = "[Service]\nExecStart=/usr/bin/foo --bar\nExecStartPre=\nRestart=always\n\n[Unit]\nDescription=Foo\n";
+        let doc = Scfg::from_systemd_unit(src)?;
+        let mut out = Vec::new();
+        doc.write_as_systemd_unit(&mut out)?;
+        assert_eq!(String::from_utf8(out)?, src);
+        Ok(())
+    }
+
+    #[test]
+    fn apache_vhost() -> io::Result<()> {
+        let src = r#"Listen 80
+
+VirtualHost *:80 {
+    DocumentRoot "/var/www/example"
+    ServerName example.com
+    Directory "/var/www/example" {
+        Options Indexes FollowSymLinks
+        Require all granted
+    }
+    ErrorLog "/var/log/apache2/error log"
+}
+
+VirtualHost *:8080 {
+}
+"#;
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_apache_vhost(&mut out)?;
+        let expected = r#"Listen 80
+<VirtualHost *:80>
+    <Directory /var/www/example>
+        Options Indexes FollowSymLinks
+        Require all granted
+    </Directory>
+    DocumentRoot /var/www/example
+    ErrorLog "/var/log/apache2/error log"
+    ServerName example.com
+</VirtualHost>
+
+<VirtualHost *:8080>
+</VirtualHost>
+"#;
+        #[cfg(feature = "preserve_order")]
+        let expected = expected.replace(
+            "    <Directory /var/www/example>\n        Options Indexes FollowSymLinks\n        Require all granted\n    </Directory>\n    DocumentRoot /var/www/example\n    ErrorLog \"/var/log/apache2/error log\"\n    ServerName example.com\n",
+            "    DocumentRoot /var/www/example\n    ServerName example.com\n    <Directory /var/www/example>\n        Options Indexes FollowSymLinks\n        Require all granted\n    </Directory>\n    ErrorLog \"/var/log/apache2/error log\"\n",
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn apache_quoting() {
+        assert_eq!(apache_quote("*:80"), "*:80");
+        assert_eq!(apache_quote(""), r#""""#);
+        assert_eq!(apache_quote("a b"), r#""a b""#);
+        assert_eq!(apache_quote(r#"<a>"\"#), r#""<a>\"\\""#);
+    }
+
+    #[test]
+    fn caddy() -> io::Result<()> {
+        let src = r#"global {
+    email admin@example.com
+}
+import common.caddy
+site example.com www.example.com {
+    encode gzip
+    reverse_proxy /api/* localhost:8080 {
+        header_up Host "{upstream_hostport}"
+    }
+    root * /var/www
+}
+"#;
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_caddy(&mut out)?;
+        let expected = r#"# global
+{
+	email admin@example.com
+}
+
+import common.caddy
+
+# site
+example.com www.example.com {
+	encode gzip
+	reverse_proxy /api/* localhost:8080 {
+		header_up Host "{upstream_hostport}"
+	}
+	root * /var/www
+}
+"#;
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn caddy_quoting() {
+        assert_eq!(caddy_quote("localhost:8080"), "localhost:8080");
+        assert_eq!(caddy_quote(""), r#""""#);
+        assert_eq!(caddy_quote("a b"), r#""a b""#);
+        assert_eq!(caddy_quote(r#"{a}"\"#), r#""{a}\"\\""#);
+    }
+
+    #[test]
+    fn haproxy() -> io::Result<()> {
+        let src = r#"backend servers {
+    server web1 192.168.1.1:80 check
+    server web2 192.168.1.2:80 check
+}
+frontend main :80 {
+    default_backend servers
+    http-request set-header X-Forwarded-Proto "https scheme"
+}
+global
+"#;
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_haproxy(&mut out)?;
+        let expected = r#"backend servers
+    server web1 192.168.1.1:80 check
+    server web2 192.168.1.2:80 check
+
+frontend main :80
+    default_backend servers
+    http-request set-header X-Forwarded-Proto "https scheme"
+
+global
+"#;
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let doc: Scfg = "frontend main {\n\tacl {\n\t}\n}\n".parse().unwrap();
+        let err = doc.write_as_haproxy(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 2: acl: unexpected block");
+        Ok(())
+    }
+
+    #[test]
+    fn haproxy_quoting() {
+        assert_eq!(haproxy_quote("192.168.1.1:80"), "192.168.1.1:80");
+        assert_eq!(haproxy_quote(""), r#""""#);
+        assert_eq!(haproxy_quote("a b"), r#""a b""#);
+        assert_eq!(haproxy_quote(r#"a#"\$b"#), r#""a#\"\\\$b""#);
+    }
+
+    fn wrangler(src: &str) -> Result<String, WranglerExportError> {
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_wrangler_toml(&mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn wrangler_toml() -> Result<(), WranglerExportError> {
+        let src = r#"account_id 0123456789abcdef
+compatibility_flags nodejs_compat streams_enable_constructors
+main src/index.js
+name my-worker
+routes {
+    pattern example.com/*
+    zone_name example.com
+}
+routes {
+    custom_domain true
+    pattern api.example.com
+}
+vars {
+    API_HOST api.example.com
+    RETRIES 3
+}
+workers_dev false
+"#;
+        let expected = r#"account_id = "0123456789abcdef"
+compatibility_flags = ["nodejs_compat", "streams_enable_constructors"]
+main = "src/index.js"
+name = "my-worker"
+vars = { API_HOST = "api.example.com", RETRIES = 3 }
+workers_dev = false
+
+[[routes]]
+pattern = "example.com/*"
+zone_name = "example.com"
+
+[[routes]]
+custom_domain = true
+pattern = "api.example.com"
+"#;
+        let out = wrangler(src)?;
+        assert_eq!(out, expected);
+        #[cfg(feature = "toml")]
+        {
+            let parsed: toml::Value = out.parse().unwrap();
+            assert_eq!(
+                parsed["routes"][1]["custom_domain"],
+                toml::Value::Boolean(true)
+            );
+            assert_eq!(parsed["vars"]["RETRIES"], toml::Value::Integer(3));
+        }
+
+        let out = wrangler("build {\n\tcommand \"npm run build\"\n}\nvars {\n}\n")?;
+        assert_eq!(out, "vars = {}\n\n[build]\ncommand = \"npm run build\"\n");
+        Ok(())
+    }
+
+    #[test]
+    fn wrangler_toml_errors() {
+        let err = |src| wrangler(src).unwrap_err().to_string();
+        assert_eq!(err("name a\nname b\n"), "line 2: name: duplicate key");
+        assert_eq!(
+            err("workers_dev\n"),
+            "line 1: workers_dev: expected a value"
+        );
+        assert_eq!(
+            err("build {\n}\nbuild {\n}\n"),
+            "line 3: build: duplicate key"
+        );
+        assert_eq!(
+            err("routes a {\n}\n"),
+            "line 1: routes: a table cannot have parameters"
+        );
+        assert_eq!(
+            err("vars {\n\tA {\n\t}\n}\n"),
+            "line 2: vars.A: unexpected block"
+        );
+        assert_eq!(
+            err("vars {\n\tA 1\n\tA 2\n}\n"),
+            "line 3: vars.A: duplicate key"
+        );
+    }
+
+    #[test]
+    fn toml_quoting() {
+        assert_eq!(toml_scalar("42"), "42");
+        assert_eq!(toml_scalar("-7"), "-7");
+        assert_eq!(toml_scalar("007"), r#""007""#);
+        assert_eq!(toml_scalar("True"), r#""True""#);
+        assert_eq!(toml_string("a\"\\\n\u{1}"), r#""a\"\\\n\u0001""#);
+        assert_eq!(toml_key("zone_name"), "zone_name");
+        assert_eq!(toml_key("a.b"), r#""a.b""#);
+        assert_eq!(toml_key(""), r#""""#);
+    }
+
+    #[test]
+    fn kubernetes_configmap() -> io::Result<()> {
+        let src = "server {\n\tlisten :8080\n\troot \"/var/www/my site\"\n}\n\ntls off\n";
+        let doc: Scfg = src.parse().unwrap();
+        let mut out = Vec::new();
+        doc.write_as_kubernetes_configmap("web-config", "prod", &mut out)?;
+        let out = String::from_utf8(out).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&out).unwrap();
+        assert_eq!(yaml["apiVersion"], "v1");
+        assert_eq!(yaml["kind"], "ConfigMap");
+        assert_eq!(yaml["metadata"]["name"], "web-config");
+        assert_eq!(yaml["metadata"]["namespace"], "prod");
+        let mut text = Vec::new();
+        doc.write(&mut text)?;
+        let embedded = yaml["data"]["config.scfg"].as_str().unwrap();
+        assert_eq!(embedded.as_bytes(), &text[..]);
+        assert_eq!(embedded.parse::<Scfg>().unwrap(), doc);
+
+        let mut out = Vec::new();
+        Scfg::new().write_as_kubernetes_configmap("true", "a b", &mut out)?;
+        let yaml: serde_yaml::Value = serde_yaml::from_slice(&out).unwrap();
+        assert_eq!(yaml["metadata"]["name"], "true");
+        assert_eq!(yaml["metadata"]["namespace"], "a b");
+        assert_eq!(yaml["data"]["config.scfg"], "");
+
+        let doc: Scfg = "a \"x\ry\"\n".parse().unwrap();
+        let err = doc
+            .write_as_kubernetes_configmap("a", "b", &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_quoting() {
+        assert_eq!(yaml_quote("web-config"), "web-config");
+        assert_eq!(yaml_quote("v1.2"), "v1.2");
+        assert_eq!(yaml_quote("Yes"), r#""Yes""#);
+        assert_eq!(yaml_quote("1e3"), r#""1e3""#);
+        assert_eq!(yaml_quote(""), r#""""#);
+        assert_eq!(yaml_quote("a: \"b\""), r#""a: \"b\"""#);
+    }
+
+    #[test]
+    fn nginx_quoting() {
+        assert_eq!(nginx_quote("10.0.0.1:80"), "10.0.0.1:80");
+        assert_eq!(nginx_quote(""), r#""""#);
+        assert_eq!(nginx_quote("a b"), r#""a b""#);
+        assert_eq!(nginx_quote(r#"a;"\"#), r#""a;\"\\""#);
+    }
+}
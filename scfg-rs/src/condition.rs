@@ -0,0 +1,420 @@
+//! Conditional blocks, an extension to scfg evaluated on request.
+use crate::{Directive, Scfg};
+use std::fmt;
+
+/// How [`Scfg::evaluate_conditions_with`] treats a test of a key the context
+/// does not know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownKeys {
+    /// The test is an error.
+    Error,
+    /// The test is false.
+    False,
+}
+
+/// Options of [`Scfg::evaluate_conditions_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionOptions {
+    /// The name of conditional directives, `@if` by default.
+    pub marker: String,
+    /// The name of the alternative following a conditional block, `@else` by
+    /// default.
+    pub else_marker: String,
+    /// How tests of unknown keys are treated, an error by default.
+    pub unknown_keys: UnknownKeys,
+}
+
+impl Default for ConditionOptions {
+    fn default() -> Self {
+        ConditionOptions {
+            marker: "@if".to_owned(),
+            else_marker: "@else".to_owned(),
+            unknown_keys: UnknownKeys::Error,
+        }
+    }
+}
+
+/// An error returned by [`Scfg::evaluate_conditions`] for a malformed
+/// conditional block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionError {
+    /// A conditional directive has no block.
+    MissingBlock {
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// A conditional directive has no tests.
+    MissingTest {
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// A parameter of a conditional directive is not a `key=value` test.
+    InvalidTest {
+        /// The line of the directive, if known.
+        line: Option<usize>,
+        /// The parameter.
+        test: String,
+    },
+    /// A test is of a key the context does not know, with
+    /// [`UnknownKeys::Error`].
+    UnknownKey {
+        /// The line of the directive, if known.
+        line: Option<usize>,
+        /// The key.
+        key: String,
+    },
+    /// An alternative does not directly follow a conditional block, has
+    /// parameters or no block, or has no line to tell what it follows.
+    InvalidElse {
+        /// The line of the alternative, if known.
+        line: Option<usize>,
+    },
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = match self {
+            ConditionError::MissingBlock { line }
+            | ConditionError::MissingTest { line }
+            | ConditionError::InvalidTest { line, .. }
+            | ConditionError::UnknownKey { line, .. }
+            | ConditionError::InvalidElse { line } => *line,
+        };
+        if let Some(line) = line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self {
+            ConditionError::MissingBlock { .. } => write!(f, "expected a conditional block"),
+            ConditionError::MissingTest { .. } => write!(f, "expected a key=value test"),
+            ConditionError::InvalidTest { test, .. } => {
+                write!(f, "{:?} is not a key=value test", test)
+            }
+            ConditionError::UnknownKey { key, .. } => write!(f, "unknown key {:?}", key),
+            ConditionError::InvalidElse { .. } => write!(
+                f,
+                "an alternative must be a block without parameters right after a conditional block"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+impl Scfg {
+    /// Evaluates the conditional blocks of the document with the default
+    /// [`ConditionOptions`]. `ctx` tells whether a key has a value.
+    ///
+    /// This is an extension of scfg: conditional blocks are ordinary
+    /// directives until this is called, and parsing never evaluates them.
+    ///
+    /// A conditional block is a directive named `@if`, whose parameters are
+    /// `key=value` tests, all of which must hold. Its alternative is an
+    /// `@else` block directly following it:
+    ///
+    /// ```text
+    /// @if env=prod region=eu {
+    ///     listen 0.0.0.0:443
+    /// }
+    /// @else {
+    ///     listen 127.0.0.1:8080
+    /// }
+    /// ```
+    ///
+    /// A document keeps the directives sharing a name together, so an `@else`
+    /// is matched to its `@if` by their lines, and only parsed documents can
+    /// have alternatives.
+    ///
+    /// A block whose tests all hold is replaced by its directives, and one
+    /// whose tests don't by the directives of its `@else`, if any. The directives are appended to the block holding the `@if`,
+    /// after its own directives of the same names. Conditional blocks are
+    /// evaluated at every level of nesting, including inside other ones.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "@if env=prod {\n\tlisten :443\n}\n@else {\n\tlisten :8080\n}\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// doc.evaluate_conditions(&|key, value| key == "env" && value == "dev")
+    ///     .unwrap();
+    /// assert_eq!(doc, "listen :8080\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a conditional block is malformed. The document may
+    /// then be partially evaluated.
+    pub fn evaluate_conditions<F>(&mut self, ctx: &F) -> Result<(), ConditionError>
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        let ctx = |key: &str, value: &str| Some(ctx(key, value));
+        self.evaluate_conditions_with(&ctx, &ConditionOptions::default())
+    }
+
+    /// Evaluates the conditional blocks of the document like
+    /// [`evaluate_conditions`], with `options`. `ctx` tells whether a key has
+    /// a value, or returns `None` if it does not know the key.
+    ///
+    /// # Errors
+    /// Returns an error if a conditional block is malformed, or if `ctx` does
+    /// not know a key, with [`UnknownKeys::Error`].
+    ///
+    /// [`evaluate_conditions`]: Scfg::evaluate_conditions
+    pub fn evaluate_conditions_with<F>(
+        &mut self,
+        ctx: &F,
+        options: &ConditionOptions,
+    ) -> Result<(), ConditionError>
+    where
+        F: Fn(&str, &str) -> Option<bool>,
+    {
+        let conditionals = self.remove(options.marker.as_str()).unwrap_or_default();
+        let alternatives = self
+            .remove(options.else_marker.as_str())
+            .unwrap_or_default();
+        let mut paired = vec![None; conditionals.len()];
+        for alternative in &alternatives {
+            let invalid = ConditionError::InvalidElse {
+                line: alternative.line,
+            };
+            let line = alternative.line.ok_or_else(|| invalid.clone())?;
+            // the closest sibling before the alternative must be the `@if`
+            let previous = self
+                .directives
+                .values()
+                .flatten()
+                .chain(&conditionals)
+                .chain(&alternatives)
+                .filter_map(|d| d.line)
+                .filter(|&l| l < line)
+                .max();
+            let i = conditionals
+                .iter()
+                .position(|d| previous.is_some() && d.line == previous)
+                .ok_or_else(|| invalid.clone())?;
+            if !alternative.params.is_empty() {
+                return Err(invalid);
+            }
+            match alternative.child {
+                Some(ref child) => paired[i] = Some(child.clone()),
+                None => return Err(invalid),
+            }
+        }
+        for directive in self.directives.values_mut().flatten() {
+            if let Some(ref mut child) = directive.child {
+                child.evaluate_conditions_with(ctx, options)?;
+            }
+        }
+        for (conditional, alternative) in conditionals.into_iter().zip(paired) {
+            let mut chosen = conditional.evaluate(alternative, ctx, options)?;
+            chosen.evaluate_conditions_with(ctx, options)?;
+            self.append(chosen);
+        }
+        Ok(())
+    }
+}
+
+impl Directive {
+    /// Returns the directives a conditional directive with the block of its
+    /// `alternative`, if any, is replaced by.
+    fn evaluate<F>(
+        self,
+        alternative: Option<Scfg>,
+        ctx: &F,
+        options: &ConditionOptions,
+    ) -> Result<Scfg, ConditionError>
+    where
+        F: Fn(&str, &str) -> Option<bool>,
+    {
+        let line = self.line;
+        let block = self.child.ok_or(ConditionError::MissingBlock { line })?;
+        if self.params.is_empty() {
+            return Err(ConditionError::MissingTest { line });
+        }
+        let mut holds = true;
+        for test in &self.params {
+            let (key, value) = match test.find('=') {
+                Some(i) => (&test[..i], &test[i + 1..]),
+                None => {
+                    return Err(ConditionError::InvalidTest {
+                        line,
+                        test: test.clone(),
+                    })
+                }
+            };
+            match (ctx(key, value), options.unknown_keys) {
+                (Some(result), _) => holds &= result,
+                (None, UnknownKeys::False) => holds = false,
+                (None, UnknownKeys::Error) => {
+                    return Err(ConditionError::UnknownKey {
+                        line,
+                        key: key.to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(if holds {
+            block
+        } else {
+            alternative.unwrap_or_default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn evaluate(src: &str, ctx: &[(&str, &str)]) -> Result<Scfg, ConditionError> {
+        let ctx: HashMap<_, _> = ctx.iter().copied().collect();
+        let mut doc: Scfg = src.parse().unwrap();
+        doc.evaluate_conditions(&|key, value| ctx.get(key) == Some(&value))?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn conditions() {
+        let src = r#"listen :80
+@if env=prod {
+    listen :443
+    tls {
+        certificate cert.pem
+    }
+}
+@else {
+    debug true
+}
+@if env=prod region=eu {
+    region eu
+}
+"#;
+        let prod = evaluate(src, &[("env", "prod")]).unwrap();
+        let exp = "listen :80\nlisten :443\ntls {\n\tcertificate cert.pem\n}\n";
+        assert_eq!(prod, exp.parse().unwrap());
+        let prod_eu = evaluate(src, &[("env", "prod"), ("region", "eu")]).unwrap();
+        assert_eq!(prod_eu.get("region").unwrap().params(), ["eu"]);
+        let dev = evaluate(src, &[("env", "dev")]).unwrap();
+        assert_eq!(dev, "listen :80\ndebug true\n".parse().unwrap());
+    }
+
+    #[test]
+    fn nested() {
+        let src = r#"server {
+    @if env=prod {
+        @if region=eu {
+            listen eu.example.com
+        }
+        @else {
+            listen example.com
+        }
+    }
+    @else {
+        @if region=eu {
+            listen localhost
+        }
+    }
+}
+"#;
+        let listen = |ctx: &[(&str, &str)]| {
+            let doc = evaluate(src, ctx).unwrap();
+            assert!(!doc.collect_all_names().iter().any(|n| n.starts_with('@')));
+            doc.get_path(&["server", "listen"])
+                .map(|d| d.params()[0].clone())
+        };
+        assert_eq!(
+            listen(&[("env", "prod"), ("region", "eu")]).as_deref(),
+            Some("eu.example.com")
+        );
+        assert_eq!(
+            listen(&[("env", "prod"), ("region", "us")]).as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            listen(&[("env", "dev"), ("region", "eu")]).as_deref(),
+            Some("localhost")
+        );
+        assert_eq!(listen(&[("env", "dev")]), None);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let src = "@if env=prod {\n\ta 1\n}\n@else {\n\tb 2\n}\n";
+        let ctx = |key: &str, value: &str| match key {
+            "env" => Some(value == "prod"),
+            _ => None,
+        };
+        let mut doc: Scfg = src.replace("env", "zone").parse().unwrap();
+        let err = doc
+            .evaluate_conditions_with(&ctx, &ConditionOptions::default())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ConditionError::UnknownKey {
+                line: Some(1),
+                key: "zone".to_owned()
+            }
+        );
+        assert_eq!(err.to_string(), r#"line 1: unknown key "zone""#);
+
+        let options = ConditionOptions {
+            unknown_keys: UnknownKeys::False,
+            marker: "%when".to_owned(),
+            else_marker: "%otherwise".to_owned(),
+        };
+        let mut doc: Scfg = src
+            .replace("env", "zone")
+            .replace("@if", "%when")
+            .replace("@else", "%otherwise")
+            .parse()
+            .unwrap();
+        doc.evaluate_conditions_with(&ctx, &options).unwrap();
+        assert_eq!(doc, "b 2\n".parse().unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src| evaluate(src, &[]).unwrap_err();
+        assert_eq!(
+            err("@if a=b\n"),
+            ConditionError::MissingBlock { line: Some(1) }
+        );
+        assert_eq!(
+            err("@if {\n}\n"),
+            ConditionError::MissingTest { line: Some(1) }
+        );
+        assert_eq!(
+            err("x {\n\t@if a {\n\t}\n}\n").to_string(),
+            r#"line 2: "a" is not a key=value test"#
+        );
+        assert_eq!(
+            err("@else {\n}\n"),
+            ConditionError::InvalidElse { line: Some(1) }
+        );
+        assert_eq!(
+            evaluate("@if a=b {\n\t@else {\n\t}\n}\n", &[("a", "b")]).unwrap_err(),
+            ConditionError::InvalidElse { line: Some(2) }
+        );
+        assert_eq!(
+            err("@if a=b {\n}\n@else {\n}\n@else {\n}\n"),
+            ConditionError::InvalidElse { line: Some(5) }
+        );
+        assert_eq!(
+            err("@if a=b {\n}\nx 1\n@else {\n}\n"),
+            ConditionError::InvalidElse { line: Some(4) }
+        );
+        assert_eq!(
+            err("@if a=b {\n}\n@else x {\n}\n"),
+            ConditionError::InvalidElse { line: Some(3) }
+        );
+        assert_eq!(
+            err("@if a=b {\n}\n@else\n"),
+            ConditionError::InvalidElse { line: Some(3) }
+        );
+
+        let mut doc = Scfg::new();
+        doc.add("@if").params.push("a=b".to_owned());
+        doc.add("@else").get_or_create_child();
+        let err = doc.evaluate_conditions(&|_, _| true).unwrap_err();
+        assert_eq!(err, ConditionError::InvalidElse { line: None });
+    }
+}
@@ -0,0 +1,364 @@
+//! Conversions between scfg documents and other data formats.
+//!
+//! Both the JSON (`json` feature) and TOML (`toml` feature) conversions use the
+//! same mapping. A document becomes an object with one key per directive name,
+//! each mapping to an array of the directives with that name. A directive
+//! becomes an object with a `params` key holding an array of strings, and, if
+//! the directive has a child block, a `child` key holding the converted child.
+//!
+//! ```text
+//! train "Shinkansen" {
+//!     model "E5"
+//! }
+//! ```
+//!
+//! becomes
+//!
+//! ```json
+//! {"train": [{"params": ["Shinkansen"], "child": {"model": [{"params": ["E5"]}]}}]}
+//! ```
+//!
+//! JSON values can be converted back to documents. Only values with exactly
+//! this shape are accepted, so that converting them back to JSON gives the same
+//! value.
+use crate::{Directive, Scfg};
+
+#[cfg(test)]
+static SRC: &str = r#"train "Shinkansen" {
+    model "E5" {
+        max-speed 320km/h
+        lines-served "Tōhoku" "Hokkaido"
+    }
+}
+flag
+"#;
+
+#[cfg(feature = "json")]
+pub use json::FromJsonError;
+
+#[cfg(feature = "json")]
+mod json {
+    use super::*;
+    use serde_json::{Map, Value};
+    use std::fmt;
+
+    impl Scfg {
+        /// Converts this document to a JSON value.
+        pub fn to_json_value(&self) -> Value {
+            let map = self
+                .directives
+                .iter()
+                .map(|(name, directives)| {
+                    let directives = directives.iter().map(Directive::to_json_value).collect();
+                    (name.clone(), Value::Array(directives))
+                })
+                .collect::<Map<_, _>>();
+            Value::Object(map)
+        }
+
+        /// Converts this document to a JSON value, consuming it.
+        pub fn into_json_value(self) -> Value {
+            let map = self
+                .directives
+                .into_iter()
+                .map(|(name, directives)| {
+                    let directives = directives
+                        .into_iter()
+                        .map(Directive::into_json_value)
+                        .collect();
+                    (name, Value::Array(directives))
+                })
+                .collect::<Map<_, _>>();
+            Value::Object(map)
+        }
+
+        /// Converts this document to a JSON string, consuming it.
+        pub fn into_json_string(self) -> Result<String, serde_json::Error> {
+            serde_json::to_string(&self.into_json_value())
+        }
+
+        /// Converts a JSON value to a document, the inverse of
+        /// [`to_json_value`](Scfg::to_json_value).
+        ///
+        /// ```
+        /// # use scfg::*;
+        /// let value = serde_json::json!({"domain": [{"params": ["example.com"]}]});
+        /// let doc = Scfg::from_json_value(&value).unwrap();
+        /// assert_eq!(doc.get("domain").unwrap().params(), ["example.com"]);
+        /// assert_eq!(doc.to_json_value(), value);
+        /// ```
+        ///
+        /// # Errors
+        /// Returns an error naming the offending part of `value` if it does not
+        /// have the shape produced by `to_json_value`: an object mapping each
+        /// name to a non-empty array of directives, each an object with a
+        /// `params` array of strings, an optional `child` object holding a
+        /// block of the same shape, and no other keys.
+        pub fn from_json_value(value: &Value) -> Result<Scfg, FromJsonError> {
+            block_from_json(value, &mut String::new())
+        }
+    }
+
+    /// An error returned by [`Scfg::from_json_value`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FromJsonError {
+        pointer: String,
+        message: String,
+    }
+
+    impl FromJsonError {
+        fn new(pointer: &str, message: impl Into<String>) -> Self {
+            FromJsonError {
+                pointer: pointer.to_owned(),
+                message: message.into(),
+            }
+        }
+
+        /// Returns the location of the offending value, as a JSON pointer such
+        /// as `/train/0/params`. The pointer of the root value is empty.
+        pub fn pointer(&self) -> &str {
+            &self.pointer
+        }
+    }
+
+    impl fmt::Display for FromJsonError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.pointer.is_empty() {
+                write!(f, "at the root: {}", self.message)
+            } else {
+                write!(f, "at {}: {}", self.pointer, self.message)
+            }
+        }
+    }
+
+    impl std::error::Error for FromJsonError {}
+
+    /// Appends a reference token to a JSON pointer, escaped as per RFC 6901.
+    fn push_token(pointer: &mut String, token: &str) {
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    }
+
+    fn block_from_json(value: &Value, pointer: &mut String) -> Result<Scfg, FromJsonError> {
+        let map = value
+            .as_object()
+            .ok_or_else(|| FromJsonError::new(pointer, "expected an object"))?;
+        let mut block = Scfg::new();
+        for (name, directives) in map {
+            let len = pointer.len();
+            push_token(pointer, name);
+            let directives = match directives.as_array() {
+                Some(directives) if !directives.is_empty() => directives,
+                _ => return Err(FromJsonError::new(pointer, "expected a non-empty array")),
+            };
+            for (i, directive) in directives.iter().enumerate() {
+                let len = pointer.len();
+                push_token(pointer, &i.to_string());
+                block.add_directive(name.as_str(), directive_from_json(directive, pointer)?);
+                pointer.truncate(len);
+            }
+            pointer.truncate(len);
+        }
+        Ok(block)
+    }
+
+    fn directive_from_json(
+        value: &Value,
+        pointer: &mut String,
+    ) -> Result<Directive, FromJsonError> {
+        let map = value
+            .as_object()
+            .ok_or_else(|| FromJsonError::new(pointer, "expected an object"))?;
+        let mut directive = Directive::new();
+        let len = pointer.len();
+        for (key, value) in map {
+            push_token(pointer, key);
+            match key.as_str() {
+                "params" => {
+                    let params = value
+                        .as_array()
+                        .ok_or_else(|| FromJsonError::new(pointer, "expected an array"))?;
+                    for (i, param) in params.iter().enumerate() {
+                        let param = param.as_str().ok_or_else(|| {
+                            push_token(pointer, &i.to_string());
+                            FromJsonError::new(pointer, "expected a string")
+                        })?;
+                        directive.params.push(param.to_owned());
+                    }
+                }
+                "child" => directive.child = Some(block_from_json(value, pointer)?),
+                _ => {
+                    pointer.truncate(len);
+                    let message = format!("unexpected key {:?}", key);
+                    return Err(FromJsonError::new(pointer, message));
+                }
+            }
+            pointer.truncate(len);
+        }
+        if !map.contains_key("params") {
+            return Err(FromJsonError::new(pointer, "missing key \"params\""));
+        }
+        Ok(directive)
+    }
+
+    impl Directive {
+        fn to_json_value(&self) -> Value {
+            let mut map = Map::new();
+            let params = self.params.iter().cloned().map(Value::String).collect();
+            map.insert("params".into(), Value::Array(params));
+            if let Some(ref child) = self.child {
+                map.insert("child".into(), child.to_json_value());
+            }
+            Value::Object(map)
+        }
+
+        fn into_json_value(self) -> Value {
+            let mut map = Map::new();
+            let params = self.params.into_iter().map(Value::String).collect();
+            map.insert("params".into(), Value::Array(params));
+            if let Some(child) = self.child {
+                map.insert("child".into(), child.into_json_value());
+            }
+            Value::Object(map)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use serde_json::json;
+
+        #[test]
+        fn json() {
+            let doc = SRC.parse::<Scfg>().unwrap();
+            let value = doc.to_json_value();
+            assert_eq!(
+                value,
+                json!({
+                    "train": [{
+                        "params": ["Shinkansen"],
+                        "child": {
+                            "model": [{
+                                "params": ["E5"],
+                                "child": {
+                                    "max-speed": [{"params": ["320km/h"]}],
+                                    "lines-served": [{"params": ["Tōhoku", "Hokkaido"]}],
+                                },
+                            }],
+                        },
+                    }],
+                    "flag": [{"params": []}],
+                })
+            );
+            let string = value.to_string();
+            assert_eq!(Scfg::from_json_value(&value).unwrap(), doc);
+            assert_eq!(doc.clone().into_json_value(), value);
+            assert_eq!(doc.into_json_string().unwrap(), string);
+        }
+
+        #[test]
+        fn from_json_errors() {
+            let err = |value| Scfg::from_json_value(&value).unwrap_err().to_string();
+            assert_eq!(err(json!([])), "at the root: expected an object");
+            assert_eq!(err(json!({"a": []})), "at /a: expected a non-empty array");
+            assert_eq!(
+                err(json!({"a/b": [{"params": ["x", 1]}]})),
+                "at /a~1b/0/params/1: expected a string"
+            );
+            assert_eq!(err(json!({"a": [{}]})), r#"at /a/0: missing key "params""#);
+            assert_eq!(
+                err(json!({"a": [{"params": [], "child": {"b": [{"params": [], "x": 1}]}}]})),
+                r#"at /a/0/child/b/0: unexpected key "x""#
+            );
+            assert_eq!(
+                err(json!({"a": [{"params": [], "child": []}]})),
+                "at /a/0/child: expected an object"
+            );
+            let err = Scfg::from_json_value(&json!({"a": 1})).unwrap_err();
+            assert_eq!(err.pointer(), "/a");
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+mod toml {
+    use super::*;
+    use ::toml::{Table, Value};
+
+    impl Scfg {
+        /// Converts this document to a TOML table.
+        pub fn to_toml_value(&self) -> Table {
+            self.directives
+                .iter()
+                .map(|(name, directives)| {
+                    let directives = directives.iter().map(Directive::to_toml_value).collect();
+                    (name.clone(), Value::Array(directives))
+                })
+                .collect()
+        }
+
+        /// Converts this document to a TOML table, consuming it.
+        pub fn into_toml_value(self) -> Table {
+            self.directives
+                .into_iter()
+                .map(|(name, directives)| {
+                    let directives = directives
+                        .into_iter()
+                        .map(Directive::into_toml_value)
+                        .collect();
+                    (name, Value::Array(directives))
+                })
+                .collect()
+        }
+
+        /// Converts this document to a TOML string, consuming it.
+        pub fn into_toml_string(self) -> Result<String, ::toml::ser::Error> {
+            ::toml::to_string(&self.into_toml_value())
+        }
+    }
+
+    impl Directive {
+        fn to_toml_value(&self) -> Value {
+            let mut table = Table::new();
+            let params = self.params.iter().cloned().map(Value::String).collect();
+            table.insert("params".into(), Value::Array(params));
+            if let Some(ref child) = self.child {
+                table.insert("child".into(), Value::Table(child.to_toml_value()));
+            }
+            Value::Table(table)
+        }
+
+        fn into_toml_value(self) -> Value {
+            let mut table = Table::new();
+            let params = self.params.into_iter().map(Value::String).collect();
+            table.insert("params".into(), Value::Array(params));
+            if let Some(child) = self.child {
+                table.insert("child".into(), Value::Table(child.into_toml_value()));
+            }
+            Value::Table(table)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn toml() {
+            let doc = SRC.parse::<Scfg>().unwrap();
+            let value = doc.to_toml_value();
+            let train = &value["train"][0];
+            assert_eq!(train["params"][0].as_str(), Some("Shinkansen"));
+            let model = &train["child"]["model"][0];
+            assert_eq!(
+                model["child"]["lines-served"][0]["params"][1].as_str(),
+                Some("Hokkaido")
+            );
+            assert!(value["flag"][0].get("child").is_none());
+
+            let string = ::toml::to_string(&value).unwrap();
+            assert_eq!(doc.clone().into_toml_value(), value);
+            assert_eq!(doc.into_toml_string().unwrap(), string);
+        }
+    }
+}
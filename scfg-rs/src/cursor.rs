@@ -0,0 +1,307 @@
+//! A cursor for navigating and editing nested documents.
+use crate::{Directive, Scfg};
+
+/// A position in a document, from which the surrounding directives can be
+/// inspected and edited, see [`Scfg::cursor`].
+///
+/// The cursor starts at the root of the document, outside of any directive.
+/// It then points at a directive, addressed by its [`path`]: the name of each
+/// directive leading to it, along with its index among the directives sharing
+/// that name. The cursor borrows the document mutably, and every edit is made
+/// to the document directly, so it is consistent at all times.
+///
+/// Movements return `false`, leaving the cursor in place, if the target does
+/// not exist.
+///
+/// ```
+/// # use scfg::*;
+/// let mut doc: Scfg = "server {\n\tlisten 80\n\tlisten 443\n}\n".parse().unwrap();
+/// let mut cursor = doc.cursor();
+/// assert!(cursor.enter("server", 0));
+/// assert!(cursor.enter("listen", 1));
+/// cursor.set_params(["8443"]);
+/// assert!(cursor.parent());
+/// cursor.add_here("root").append_param("/var/www");
+/// assert_eq!(
+///     doc,
+///     "server {\n\tlisten 80\n\tlisten 8443\n\troot /var/www\n}\n".parse().unwrap()
+/// );
+/// ```
+///
+/// [`path`]: ScfgCursor::path
+#[derive(Debug)]
+pub struct ScfgCursor<'a> {
+    root: &'a mut Scfg,
+    path: Vec<(String, usize)>,
+}
+
+impl<'a> ScfgCursor<'a> {
+    /// Creates a cursor at the root of `root`.
+    pub fn new(root: &'a mut Scfg) -> Self {
+        ScfgCursor {
+            root,
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns the path of the current directive: the name and index of each
+    /// directive leading to it, including its own. The path of the root is
+    /// empty.
+    pub fn path(&self) -> Vec<(String, usize)> {
+        self.path.clone()
+    }
+
+    /// Returns `true` if the cursor is at the root of the document.
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// Returns the current directive, or `None` at the root.
+    pub fn current(&self) -> Option<&Directive> {
+        let ((name, index), init) = self.path.split_last()?;
+        Some(&block_at(self.root, init).directives[name][*index])
+    }
+
+    /// Returns the current directive mutably, or `None` at the root.
+    pub fn current_mut(&mut self) -> Option<&mut Directive> {
+        let ((name, index), init) = self.path.split_last()?;
+        let block = block_at_mut(self.root, init);
+        Some(&mut block.directives.get_mut(name).unwrap()[*index])
+    }
+
+    /// Returns the block the cursor would [`enter`] into: the child of the
+    /// current directive, or the document itself at the root. Returns `None`
+    /// if the current directive has no child.
+    ///
+    /// [`enter`]: ScfgCursor::enter
+    pub fn block(&self) -> Option<&Scfg> {
+        match self.current() {
+            Some(directive) => directive.child(),
+            None => Some(self.root),
+        }
+    }
+
+    /// Moves to the directive at `index` among those named `name` in the
+    /// child block of the current directive, or in the document at the root.
+    pub fn enter(&mut self, name: &str, index: usize) -> bool {
+        let directives = self.block().and_then(|block| block.get_all(name));
+        let exists = matches!(directives, Some(directives) if index < directives.len());
+        if exists {
+            self.path.push((name.to_owned(), index));
+        }
+        exists
+    }
+
+    /// Moves to the directive containing the current one. Returns `false` at
+    /// the root.
+    pub fn parent(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    /// Moves to the directive following the current one in its block: the
+    /// next directive with the same name, or else the first directive of the
+    /// next name, in the order [`Scfg::write`] writes them. Returns `false` at
+    /// the root or after the last directive of the block.
+    pub fn next_sibling(&mut self) -> bool {
+        let ((name, index), init) = match self.path.split_last() {
+            Some(split) => split,
+            None => return false,
+        };
+        let block = block_at(self.root, init);
+        let next = if index + 1 < block.directives[name].len() {
+            (name.clone(), index + 1)
+        } else {
+            match block.directives.keys().skip_while(|&n| n != name).nth(1) {
+                Some(next) => (next.clone(), 0),
+                None => return false,
+            }
+        };
+        *self.path.last_mut().unwrap() = next;
+        true
+    }
+
+    /// Replaces the parameters of the current directive. Returns `false`,
+    /// leaving the document unchanged, at the root.
+    ///
+    /// # Note
+    /// This does not validate that the parameters are legal scfg words.
+    pub fn set_params<I>(&mut self, params: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        match self.current_mut() {
+            Some(directive) => {
+                directive.params = params.into_iter().map(Into::into).collect();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds a directive to the block the cursor would [`enter`] into,
+    /// creating the current directive's child if needed, and returns it. The
+    /// cursor does not move.
+    ///
+    /// # Note
+    /// This does not validate that `name` is a legal scfg word.
+    ///
+    /// [`enter`]: ScfgCursor::enter
+    pub fn add_here(&mut self, name: impl Into<String>) -> &mut Directive {
+        let block = match self.path.split_last() {
+            Some(((name, index), init)) => block_at_mut(self.root, init)
+                .directives
+                .get_mut(name)
+                .unwrap()[*index]
+                .get_or_create_child(),
+            None => &mut *self.root,
+        };
+        block.add(name)
+    }
+
+    /// Removes the current directive from the document, returning it, and
+    /// moves to its parent. Returns `None` at the root.
+    pub fn remove_current(&mut self) -> Option<Directive> {
+        let (name, index) = self.path.pop()?;
+        block_at_mut(self.root, &self.path).remove_at(&name, index)
+    }
+}
+
+impl Scfg {
+    /// Returns a cursor at the root of the document, see [`ScfgCursor`].
+    pub fn cursor(&mut self) -> ScfgCursor<'_> {
+        ScfgCursor::new(self)
+    }
+}
+
+/// Returns the child block of the directive at `path`, or `root` if `path` is
+/// empty. The path must be valid.
+fn block_at<'a>(root: &'a Scfg, path: &[(String, usize)]) -> &'a Scfg {
+    path.iter().fold(root, |block, (name, index)| {
+        block.directives[name][*index].child().unwrap()
+    })
+}
+
+/// Like [`block_at`], mutably.
+fn block_at_mut<'a>(root: &'a mut Scfg, path: &[(String, usize)]) -> &'a mut Scfg {
+    path.iter().fold(root, |block, (name, index)| {
+        block.directives.get_mut(name).unwrap()[*index]
+            .child
+            .as_mut()
+            .unwrap()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static SHINKANSEN: &str = r#"train "Shinkansen" {
+    model "E5" {
+        max-speed 320km/h
+        weight 453.5t
+
+        lines-served "Tōhoku" "Hokkaido"
+    }
+
+    model "E7" {
+        max-speed 275km/h
+        weight 540t
+
+        lines-served "Hokuriku" "Jōetsu"
+    }
+}"#;
+
+    #[test]
+    fn navigate() {
+        let mut doc: Scfg = SHINKANSEN.parse().unwrap();
+        let mut cursor = doc.cursor();
+        assert!(cursor.is_root());
+        assert_eq!(cursor.current(), None);
+        assert!(!cursor.parent());
+        assert!(!cursor.next_sibling());
+        assert!(!cursor.enter("model", 0));
+
+        assert!(cursor.enter("train", 0));
+        assert_eq!(cursor.current().unwrap().params(), ["Shinkansen"]);
+        assert!(!cursor.next_sibling());
+        assert!(cursor.enter("model", 0));
+        assert!(cursor.next_sibling());
+        assert_eq!(cursor.current().unwrap().params(), ["E7"]);
+        assert!(cursor.parent());
+        assert!(cursor.enter("model", 1));
+        assert!(!cursor.enter("model", 0));
+        #[cfg(not(feature = "preserve_order"))]
+        let names = ["lines-served", "max-speed", "weight"];
+        #[cfg(feature = "preserve_order")]
+        let names = ["max-speed", "weight", "lines-served"];
+        assert!(cursor.enter(names[0], 0));
+        assert_eq!(
+            cursor.path(),
+            [
+                ("train".to_owned(), 0),
+                ("model".to_owned(), 1),
+                (names[0].to_owned(), 0)
+            ]
+        );
+        assert_eq!(cursor.block(), None);
+        for name in &names[1..] {
+            assert!(cursor.next_sibling());
+            assert_eq!(cursor.path()[2], (name.to_string(), 0));
+        }
+        assert!(!cursor.next_sibling());
+        assert!(cursor.parent());
+        assert_eq!(cursor.current().unwrap().params(), ["E7"]);
+        assert!(!cursor.next_sibling());
+    }
+
+    #[test]
+    fn edit() {
+        let mut doc: Scfg = SHINKANSEN.parse().unwrap();
+        let mut cursor = doc.cursor();
+        assert!(!cursor.set_params(["x"]));
+        assert_eq!(cursor.remove_current(), None);
+        assert!(cursor.enter("train", 0));
+        assert!(cursor.enter("model", 0));
+        assert!(cursor.enter("max-speed", 0));
+        assert!(cursor.set_params(["300km/h"]));
+        assert!(cursor.next_sibling());
+        let weight = cursor.remove_current().unwrap();
+        assert_eq!(weight.params(), ["453.5t"]);
+        assert_eq!(cursor.current().unwrap().params(), ["E5"]);
+        cursor.add_here("in-service").append_param("2011");
+        assert!(cursor.enter("in-service", 0));
+        cursor.add_here("note").append_param("first");
+        assert!(cursor.parent());
+        assert!(cursor.parent());
+        assert!(cursor.parent());
+        cursor.add_here("operator").append_param("JR East");
+
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        let exp = r#"operator "JR East"
+
+train Shinkansen {
+	model E5 {
+		in-service 2011 {
+			note first
+		}
+
+		lines-served Tōhoku Hokkaido
+		max-speed 300km/h
+	}
+
+	model E7 {
+		lines-served Hokuriku Jōetsu
+		max-speed 275km/h
+		weight 540t
+	}
+}
+"#;
+        let exp: Scfg = exp.parse().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap().parse::<Scfg>().unwrap(),
+            exp
+        );
+    }
+}
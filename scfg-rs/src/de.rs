@@ -0,0 +1,1269 @@
+//! Deserializing Rust values from scfg documents with [serde]. Available with
+//! the `serde` feature.
+//!
+//! A document, or the child block of a directive, deserializes as a struct or
+//! a map, with one entry per directive name. The value of an entry is built
+//! from the directives with that name:
+//!
+//! - A scalar (a number, a string, a `char` or a unit enum variant) is the
+//!   single parameter of a single directive, like `port 8080`. A `bool` may
+//!   also be a directive without parameters, which is `true`.
+//! - An `Option` is `None` if the directive is missing.
+//! - A sequence has an element per directive, or, if there is a single
+//!   directive without a block, an element per parameter, so both
+//!   `listen a b` and `listen a` followed by `listen b` are `["a", "b"]`. A
+//!   tuple element takes a parameter per field, so `route a 1 b 2` is
+//!   `[("a", 1), ("b", 2)]`.
+//! - A struct is the child block of a single directive, which must have no
+//!   parameters. A missing block is an empty one.
+//! - A map keyed by the first parameter of each directive, if they all have
+//!   one, like `model E5 { ... }`. The value is built from the rest of the
+//!   parameters and the block, like a directive of its own. Otherwise, a map
+//!   is the child block of a single directive, like a struct.
+//! - A tuple holds the parameters of a single directive.
+//! - An enum variant is named by the first parameter, followed by its content,
+//!   like `storage s3 { bucket b }` for a struct variant.
+//!
+//! ```
+//! # use scfg::*;
+//! #[derive(serde::Deserialize, Debug, PartialEq)]
+//! struct Config {
+//!     domain: String,
+//!     listen: Vec<String>,
+//!     tls: Option<Tls>,
+//! }
+//!
+//! #[derive(serde::Deserialize, Debug, PartialEq)]
+//! struct Tls {
+//!     certificate: String,
+//!     key: String,
+//! }
+//!
+//! let doc: Scfg = r#"domain example.com
+//! listen 0.0.0.0:6697
+//! listen 127.0.0.1:6667
+//! tls {
+//!     certificate cert.pem
+//!     key key.pem
+//! }
+//! "#
+//! .parse()
+//! .unwrap();
+//! let config: Config = de::from_scfg(&doc).unwrap();
+//! assert_eq!(config.listen, ["0.0.0.0:6697", "127.0.0.1:6667"]);
+//! assert_eq!(config.tls.unwrap().key, "key.pem");
+//! ```
+//!
+//! [serde]: https://serde.rs
+use crate::{suggest::did_you_mean, Directive, Scfg};
+use serde::de::{
+    self, value::BorrowedStrDeserializer, DeserializeSeed, Deserializer as _, IntoDeserializer,
+    Visitor,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+
+/// Deserializes a value from the directives of `doc`.
+pub fn from_scfg<'de, T>(doc: &'de Scfg) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(BlockDeserializer {
+        block: Some(doc),
+        line: None,
+    })
+}
+
+/// Updates `value` from the directives of `doc`, leaving the fields whose
+/// directives are missing from `doc` unchanged.
+///
+/// `value` must be a struct. It is serialized into a document laid out the way
+/// [`from_scfg`] reads it, `doc` is laid over that document, and the result is
+/// deserialized back into `value`. The directives of `doc` replace those of
+/// the same name, except for blocks without parameters, like nested structs,
+/// which are merged recursively into the block they replace. Sequences and
+/// maps are replaced as a whole.
+///
+/// serde has no way to deserialize into an existing value while keeping the
+/// fields which are missing, which is why `T` must also be `Serialize`. As a
+/// consequence, only the fields which survive a round trip through a document
+/// are kept: a field skipped by serde, with `#[serde(skip)]` for instance, is
+/// reset to its default value, even if `doc` does not mention it.
+///
+/// ```
+/// # use scfg::*;
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Config {
+///     domain: String,
+///     port: u16,
+/// }
+///
+/// let mut config = Config {
+///     domain: "example.com".to_owned(),
+///     port: 6667,
+/// };
+/// de::merge_from_scfg(&mut config, &"port 6697\n".parse().unwrap()).unwrap();
+/// assert_eq!(config.domain, "example.com");
+/// assert_eq!(config.port, 6697);
+/// ```
+///
+/// # Errors
+/// Returns an error if `value` can't be serialized into directives which read
+/// back as `value`, for instance if it isn't a struct, holds bytes or a `None`
+/// outside of a field, or if the merged document can't be deserialized.
+/// `value` is then unchanged.
+pub fn merge_from_scfg<T>(value: &mut T, doc: &Scfg) -> Result<(), Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut merged = crate::ser::to_scfg(value)?;
+    overlay(&mut merged, doc);
+    *value = from_scfg(&merged)?;
+    Ok(())
+}
+
+/// Lays `doc` over `base`, see [`merge_from_scfg`].
+fn overlay(base: &mut Scfg, doc: &Scfg) {
+    fn as_block(directives: &mut [Directive]) -> Option<&mut Scfg> {
+        match directives {
+            [directive] if directive.params.is_empty() => directive.child.as_mut(),
+            _ => None,
+        }
+    }
+
+    for (name, directives) in &doc.directives {
+        let group = base.directives.entry(name.clone()).or_default();
+        let mut over = directives.clone();
+        if let (Some(block), Some(over_block)) = (as_block(group), as_block(&mut over)) {
+            overlay(block, over_block);
+        } else {
+            *group = over;
+        }
+    }
+}
+
+/// An error returned when a document can't be deserialized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    path: Vec<String>,
+    line: Option<usize>,
+    message: String,
+    suggestion: Option<String>,
+}
+
+impl Error {
+    /// The names of the directives leading to the offending directive,
+    /// including its own name. This is empty for errors about the document
+    /// itself, like a missing top-level directive.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// The line of the offending directive, if known. For missing directives,
+    /// this is the line of the directive holding the block they are missing
+    /// from.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// The description of the error, without its location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// For an unknown directive in a struct denying unknown fields, the
+    /// closest field name, if it is close enough to be a plausible typo. The
+    /// suggestion is also part of the [`message`](Error::message).
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    /// Sets the line of the error, unless it is already known.
+    fn at(mut self, line: Option<usize>) -> Self {
+        if self.line.is_none() {
+            self.line = line;
+        }
+        self
+    }
+
+    /// Records that the error occurred in the directives `name`.
+    fn in_directive(mut self, name: &str) -> Self {
+        self.path.insert(0, name.to_owned());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join("."))?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            path: Vec::new(),
+            line: None,
+            message: msg.to_string(),
+            suggestion: None,
+        }
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        // reuse serde's wording of the message.
+        let mut err = Error::custom(<de::value::Error as de::Error>::unknown_field(
+            field, expected,
+        ));
+        if let Some(suggestion) = did_you_mean(field, expected.iter().copied()) {
+            err.message
+                .push_str(&format!(", did you mean `{}`?", suggestion));
+            err.suggestion = Some(suggestion.to_owned());
+        }
+        err
+    }
+}
+
+/// Deserializes a block, or an empty block if `block` is `None`.
+struct BlockDeserializer<'de> {
+    block: Option<&'de Scfg>,
+    /// The line of the directive holding the block.
+    line: Option<usize>,
+}
+
+impl<'de> de::Deserializer<'de> for BlockDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let line = self.line;
+        let access = BlockAccess {
+            groups: self.block.into_iter().flat_map(|block| &block.directives),
+            group: None,
+        };
+        visitor.visit_map(access).map_err(|err| err.at(line))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// Yields the groups of directives of a block as map entries.
+struct BlockAccess<'de, I> {
+    groups: I,
+    group: Option<(&'de str, &'de [Directive])>,
+}
+
+impl<'de, I> de::MapAccess<'de> for BlockAccess<'de, I>
+where
+    I: Iterator<Item = (&'de String, &'de Vec<Directive>)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let (name, directives) = match self.groups.next() {
+            Some(group) => group,
+            None => return Ok(None),
+        };
+        self.group = Some((name, directives));
+        let key = seed
+            .deserialize(BorrowedStrDeserializer::new(name))
+            .map_err(|err: Error| err.at(directives.first().and_then(|d| d.line)))?;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (name, directives) = self.group.take().expect("value requested before key");
+        seed.deserialize(GroupDeserializer { directives })
+            .map_err(|err| err.in_directive(name))
+    }
+}
+
+/// Deserializes the directives sharing a name.
+struct GroupDeserializer<'de> {
+    directives: &'de [Directive],
+}
+
+impl<'de> GroupDeserializer<'de> {
+    fn single(&self) -> Result<DirectiveDeserializer<'de>, Error> {
+        match self.directives {
+            [directive] => Ok(DirectiveDeserializer::new(directive)),
+            directives => {
+                let message = format!("expected 1 directive, found {}", directives.len());
+                Err(de::Error::custom(message))
+                    .map_err(|err: Error| err.at(directives.get(1).and_then(|d| d.line)))
+            }
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for GroupDeserializer<'de> {
+    type Error = Error;
+
+    forward_to_single! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16
+        deserialize_i32 deserialize_i64 deserialize_i128 deserialize_u8
+        deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.directives {
+            [directive] if directive.child.is_none() && directive.params.len() != 1 => {
+                DirectiveDeserializer::new(directive).deserialize_seq(visitor)
+            }
+            directives => visitor.visit_seq(DirectivesAccess {
+                directives: directives.iter(),
+            }),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.single()?.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.directives.iter().all(|d| !d.params.is_empty()) {
+            visitor.visit_map(KeyedAccess {
+                directives: self.directives.iter(),
+                value: None,
+            })
+        } else {
+            self.single()?.deserialize_map(visitor)
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+}
+
+/// Yields each directive as an element.
+struct DirectivesAccess<'de> {
+    directives: std::slice::Iter<'de, Directive>,
+}
+
+impl<'de> de::SeqAccess<'de> for DirectivesAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.directives.next() {
+            Some(directive) => seed
+                .deserialize(DirectiveDeserializer::new(directive))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.directives.len())
+    }
+}
+
+/// Yields each directive as an entry keyed by its first parameter.
+struct KeyedAccess<'de> {
+    directives: std::slice::Iter<'de, Directive>,
+    value: Option<DirectiveDeserializer<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for KeyedAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let directive = match self.directives.next() {
+            Some(directive) => directive,
+            None => return Ok(None),
+        };
+        let value = DirectiveDeserializer {
+            params: &directive.params[1..],
+            child: directive.child.as_ref(),
+            line: directive.line,
+        };
+        self.value = Some(value);
+        let key = seed
+            .deserialize(ParamDeserializer(&directive.params[0]))
+            .map_err(|err| err.at(directive.line))?;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(self.value.take().expect("value requested before key"))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.directives.len())
+    }
+}
+
+/// Deserializes the parameters and child block of a directive.
+struct DirectiveDeserializer<'de> {
+    params: &'de [String],
+    child: Option<&'de Scfg>,
+    line: Option<usize>,
+}
+
+impl<'de> DirectiveDeserializer<'de> {
+    fn new(directive: &'de Directive) -> Self {
+        DirectiveDeserializer {
+            params: &directive.params,
+            child: directive.child.as_ref(),
+            line: directive.line,
+        }
+    }
+
+    fn error(&self, message: impl fmt::Display) -> Error {
+        <Error as de::Error>::custom(message).at(self.line)
+    }
+
+    fn no_child(&self) -> Result<(), Error> {
+        match self.child {
+            Some(_) => Err(self.error("unexpected block")),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns a deserializer for the single parameter.
+    fn param(&self) -> Result<ParamDeserializer<'de>, Error> {
+        self.no_child()?;
+        match self.params {
+            [param] => Ok(ParamDeserializer(param)),
+            params => Err(self.error(format_args!("expected 1 parameter, found {}", params.len()))),
+        }
+    }
+
+    /// Deserializes the parameters as a sequence with `visitor`.
+    fn visit_params<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        self.no_child()?;
+        let access = ParamsAccess {
+            params: &mut self.params.iter(),
+            len: None,
+            line: self.line,
+        };
+        visitor.visit_seq(access).map_err(|err| err.at(self.line))
+    }
+
+    fn block(&self) -> Result<BlockDeserializer<'de>, Error> {
+        if !self.params.is_empty() {
+            return Err(self.error(format_args!(
+                "expected no parameters before the block, found {}",
+                self.params.len()
+            )));
+        }
+        Ok(BlockDeserializer {
+            block: self.child,
+            line: self.line,
+        })
+    }
+}
+
+macro_rules! forward_to_param {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let line = self.line;
+                self.param()?.$method(visitor).map_err(|err| err.at(line))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for DirectiveDeserializer<'de> {
+    type Error = Error;
+
+    forward_to_param! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match (self.params, self.child) {
+            (_, Some(_)) => self.block()?.deserialize_any(visitor),
+            ([], None) => visitor.visit_unit(),
+            ([param], None) => visitor.visit_borrowed_str(param),
+            _ => self.visit_params(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.params.is_empty() && self.child.is_none() {
+            return visitor.visit_bool(true);
+        }
+        let line = self.line;
+        self.param()?
+            .deserialize_bool(visitor)
+            .map_err(|err| err.at(line))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.no_child()?;
+        if !self.params.is_empty() {
+            return Err(self.error(format_args!(
+                "expected no parameters, found {}",
+                self.params.len()
+            )));
+        }
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.visit_params(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.block()?.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.block()?.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.params.is_empty() {
+            return Err(self.error("expected a variant name"));
+        }
+        let line = self.line;
+        visitor.visit_enum(self).map_err(|err| err.at(line))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for DirectiveDeserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(&self.params[0]))?;
+        let content = DirectiveDeserializer {
+            params: &self.params[1..],
+            ..self
+        };
+        Ok((variant, content))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for DirectiveDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserializer::deserialize_unit(self, de::IgnoredAny).map(|_| ())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.block()?.deserialize_any(visitor)
+    }
+}
+
+/// Yields the elements held by parameters, `len` of them if known, see
+/// [`ElementDeserializer`].
+struct ParamsAccess<'a, 'de> {
+    params: &'a mut std::slice::Iter<'de, String>,
+    len: Option<usize>,
+    line: Option<usize>,
+}
+
+impl<'de> de::SeqAccess<'de> for ParamsAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.len == Some(0) || self.params.as_slice().is_empty() {
+            return Ok(None);
+        }
+        self.len = self.len.map(|len| len - 1);
+        let element = ElementDeserializer {
+            params: &mut *self.params,
+            line: self.line,
+        };
+        seed.deserialize(element)
+            .map(Some)
+            .map_err(|err| err.at(self.line))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        let left = self.params.len();
+        Some(self.len.map_or(left, |len| len.min(left)))
+    }
+}
+
+/// Deserializes an element from the parameters it takes: one for a scalar, one
+/// per field for a tuple, all of those left for a sequence, and the variant
+/// name followed by its content for an enum.
+struct ElementDeserializer<'a, 'de> {
+    params: &'a mut std::slice::Iter<'de, String>,
+    line: Option<usize>,
+}
+
+impl<'a, 'de> ElementDeserializer<'a, 'de> {
+    fn param(self) -> Result<ParamDeserializer<'de>, Error> {
+        match self.params.next() {
+            Some(param) => Ok(ParamDeserializer(param)),
+            None => Err(de::Error::custom("missing parameter")),
+        }
+    }
+
+    fn elements(self, len: Option<usize>) -> ParamsAccess<'a, 'de> {
+        ParamsAccess {
+            params: self.params,
+            len,
+            line: self.line,
+        }
+    }
+}
+
+macro_rules! forward_to_element_param {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.param()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementDeserializer<'_, 'de> {
+    type Error = Error;
+
+    forward_to_element_param! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16
+        deserialize_i32 deserialize_i64 deserialize_i128 deserialize_u8
+        deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.elements(None))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.elements(Some(len)))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.elements(Some(len)))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.param()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for ElementDeserializer<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = match self.params.next() {
+            Some(variant) => variant,
+            None => return Err(de::Error::custom("expected a variant name")),
+        };
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ElementDeserializer<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.elements(Some(len)))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(de::Error::custom("expected a block"))
+    }
+}
+
+/// Deserializes a single parameter.
+struct ParamDeserializer<'de>(&'de str);
+
+macro_rules! parse_param {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(err) => Err(de::Error::custom(format_args!(
+                        "invalid parameter {:?}: {}",
+                        self.0, err
+                    ))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ParamDeserializer<'de> {
+    type Error = Error;
+
+    parse_param! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            "true" => visitor.visit_bool(true),
+            "false" => visitor.visit_bool(false),
+            value => Err(de::Error::custom(format_args!(
+                "invalid parameter {:?}: expected true or false",
+                value
+            ))),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_bytes(self.0.as_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn from_src<T: serde::de::DeserializeOwned>(src: &str) -> Result<T, Error> {
+        from_scfg(&src.parse().unwrap())
+    }
+
+    #[test]
+    fn merge() {
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        struct Config {
+            domain: String,
+            port: u16,
+            listen: Vec<String>,
+            tls: Tls,
+            motd: Option<String>,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        struct Tls {
+            certificate: String,
+            key: String,
+        }
+
+        let config = Config {
+            domain: "example.com".to_owned(),
+            port: 6667,
+            listen: vec!["0.0.0.0:6667".to_owned(), "[::]:6667".to_owned()],
+            tls: Tls {
+                certificate: "cert.pem".to_owned(),
+                key: "key.pem".to_owned(),
+            },
+            motd: Some("hello".to_owned()),
+        };
+        let mut merged = config.clone();
+        let doc = "port 6697\ntls {\n\tkey new.pem\n}\n".parse().unwrap();
+        merge_from_scfg(&mut merged, &doc).unwrap();
+        let mut exp = config.clone();
+        exp.port = 6697;
+        exp.tls.key = "new.pem".to_owned();
+        assert_eq!(merged, exp);
+
+        let doc = "listen 127.0.0.1:6697\n".parse().unwrap();
+        merge_from_scfg(&mut merged, &doc).unwrap();
+        assert_eq!(merged.listen, ["127.0.0.1:6697"]);
+
+        let doc = "port x\ndomain a.example.com\n".parse().unwrap();
+        let err = merge_from_scfg(&mut merged, &doc).unwrap_err();
+        assert_eq!(err.path(), ["port"]);
+        assert_eq!(err.line(), Some(1));
+        assert_eq!(merged.domain, "example.com");
+
+        assert!(merge_from_scfg(&mut 42, &doc).is_err());
+    }
+
+    #[test]
+    fn merge_nested() {
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        struct Config {
+            port: u16,
+            tags: BTreeMap<String, Vec<String>>,
+            routes: Vec<(String, u16)>,
+        }
+
+        let mut tags = BTreeMap::new();
+        tags.insert("k".to_owned(), vec!["a".to_owned(), "b".to_owned()]);
+        let config = Config {
+            port: 1,
+            tags,
+            routes: vec![("a".to_owned(), 1)],
+        };
+        let mut merged = config.clone();
+        merge_from_scfg(&mut merged, &"port 2\n".parse().unwrap()).unwrap();
+        assert_eq!(merged, Config { port: 2, ..config });
+    }
+
+    #[test]
+    fn merge_skipped_field() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Config {
+            port: u16,
+            #[serde(skip)]
+            cache: u32,
+        }
+
+        let mut config = Config { port: 1, cache: 5 };
+        merge_from_scfg(&mut config, &"port 2\n".parse().unwrap()).unwrap();
+        // the skipped field does not survive the round trip
+        assert_eq!(config, Config { port: 2, cache: 0 });
+    }
+
+    #[test]
+    fn unknown_field_suggestion() {
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Tls {
+            certificate: String,
+            key: String,
+        }
+
+        let err = from_src::<Tls>("certifcate c\nkey k\n").unwrap_err();
+        assert_eq!(err.suggestion(), Some("certificate"));
+        assert_eq!(
+            err.to_string(),
+            "line 1: unknown field `certifcate`, expected `certificate` or `key`, did you mean `certificate`?"
+        );
+        let err = from_src::<Tls>("certificate c\nkye k\n").unwrap_err();
+        assert_eq!(err.suggestion(), Some("key"));
+        let err = from_src::<Tls>("certificate c\nproxy p\n").unwrap_err();
+        assert_eq!(err.suggestion(), None);
+        assert_eq!(
+            err.message(),
+            "unknown field `proxy`, expected `certificate` or `key`"
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Scalars {
+            port: u16,
+            ratio: f64,
+            name: String,
+            initial: char,
+            verbose: bool,
+            color: bool,
+            level: Level,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Level {
+            Debug,
+            Info,
+        }
+
+        let src =
+            "port 8080\nratio 0.5\nname \"a b\"\ninitial x\nverbose\ncolor false\nlevel info\n";
+        let scalars: Scalars = from_src(src).unwrap();
+        assert_eq!(
+            scalars,
+            Scalars {
+                port: 8080,
+                ratio: 0.5,
+                name: "a b".to_owned(),
+                initial: 'x',
+                verbose: true,
+                color: false,
+                level: Level::Info,
+            }
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Sequences {
+            params: Vec<String>,
+            repeated: Vec<u32>,
+            single: Vec<u32>,
+            empty: Vec<u32>,
+            pair: (String, u8),
+            routes: Vec<(String, u8)>,
+        }
+
+        let src =
+            "params a b c\nrepeated 1\nrepeated 2\nsingle 3\nempty\npair a 1\nroutes a 1 b 2\n";
+        let sequences: Sequences = from_src(src).unwrap();
+        assert_eq!(sequences.params, ["a", "b", "c"]);
+        assert_eq!(sequences.repeated, [1, 2]);
+        assert_eq!(sequences.single, [3]);
+        assert!(sequences.empty.is_empty());
+        assert_eq!(sequences.pair, ("a".to_owned(), 1));
+        assert_eq!(sequences.routes, [("a".to_owned(), 1), ("b".to_owned(), 2)]);
+    }
+
+    #[test]
+    fn maps_and_enums() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Model {
+            #[serde(rename = "max-speed")]
+            max_speed: String,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Storage {
+            Memory,
+            Disk(String),
+            S3 { bucket: String },
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Trains {
+            model: BTreeMap<String, Model>,
+            env: BTreeMap<String, String>,
+            storage: Vec<Storage>,
+        }
+
+        let src = r#"model E5 {
+    max-speed 320km/h
+}
+model E7 {
+    max-speed 275km/h
+}
+env {
+    HOME /root
+}
+storage memory
+storage disk /var/lib
+storage s3 {
+    bucket trains
+}
+"#;
+        let trains: Trains = from_src(src).unwrap();
+        assert_eq!(trains.model.len(), 2);
+        assert_eq!(trains.model["E7"].max_speed, "275km/h");
+        assert_eq!(trains.env["HOME"], "/root");
+        assert_eq!(
+            trains.storage,
+            [
+                Storage::Memory,
+                Storage::Disk("/var/lib".to_owned()),
+                Storage::S3 {
+                    bucket: "trains".to_owned()
+                }
+            ]
+        );
+
+        let untyped: BTreeMap<String, BTreeMap<String, String>> =
+            from_src("a {\n\tb c\n}\n").unwrap();
+        assert_eq!(untyped["a"]["b"], "c");
+    }
+
+    #[test]
+    fn errors() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Config {
+            domain: String,
+            tls: Option<Tls>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Tls {
+            port: u16,
+            key: String,
+        }
+
+        let message = |src| from_src::<Config>(src).unwrap_err().to_string();
+        assert_eq!(message("other 1\n"), "missing field `domain`");
+        assert_eq!(
+            message("domain a\ndomain b\n"),
+            "line 2: domain: expected 1 directive, found 2"
+        );
+        assert_eq!(
+            message("domain a b\n"),
+            "line 1: domain: expected 1 parameter, found 2"
+        );
+        assert_eq!(
+            message("domain a\ntls {\n\tport 99999\n\tkey k\n}\n"),
+            "line 3: tls.port: invalid parameter \"99999\": number too large to fit in target type"
+        );
+        assert_eq!(
+            message("domain a\ntls {\n\tport 1\n}\n"),
+            "line 2: tls: missing field `key`"
+        );
+        assert_eq!(
+            message("domain a\ntls x {\n}\n"),
+            "line 2: tls: expected no parameters before the block, found 1"
+        );
+
+        let err = from_src::<Config>("domain a\ntls {\n\tport x\n}\n").unwrap_err();
+        assert_eq!(err.path(), ["tls", "port"]);
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(
+            err.message(),
+            "invalid parameter \"x\": invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn borrowed() {
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+        }
+
+        let doc: Scfg = "name Shinkansen\n".parse().unwrap();
+        let borrowed: Borrowed = from_scfg(&doc).unwrap();
+        assert_eq!(borrowed.name, "Shinkansen");
+    }
+}
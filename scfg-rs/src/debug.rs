@@ -0,0 +1,157 @@
+//! The `Debug` implementations of documents and directives.
+//!
+//! The plain form, `{:?}`, shows the structure on a single line. The alternate
+//! form, `{:#?}`, shows the scfg text of the document instead, which is far
+//! easier to read for nested documents.
+use crate::{quote_word, Directive, Scfg};
+use std::fmt;
+
+impl fmt::Debug for Scfg {
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "model E5 {\n\tmax-speed 320km/h\n}\n".parse().unwrap();
+    /// assert_eq!(
+    ///     format!("{:#?}", doc),
+    ///     "Scfg(\n    model E5 {\n        max-speed 320km/h\n    }\n)"
+    /// );
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f
+                .debug_struct("Scfg")
+                .field("directives", &self.directives)
+                .finish();
+        }
+        if self.directives.values().all(Vec::is_empty) {
+            return f.write_str("Scfg()");
+        }
+        f.write_str("Scfg(\n")?;
+        write_block(f, self, 1)?;
+        f.write_str(")")
+    }
+}
+
+impl fmt::Debug for Directive {
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "model E5 {\n\tmax-speed 320km/h\n}\n".parse().unwrap();
+    /// assert_eq!(
+    ///     format!("{:#?}", doc.get("model").unwrap()),
+    ///     "Directive(\n    E5 {\n        max-speed 320km/h\n    }\n)"
+    /// );
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f
+                .debug_struct("Directive")
+                .field("params", &self.params)
+                .field("child", &self.child)
+                .field("line", &self.line)
+                .finish();
+        }
+        if self.params.is_empty() && self.child.is_none() {
+            return f.write_str("Directive()");
+        }
+        f.write_str("Directive(\n")?;
+        write_directive(f, None, self, 1)?;
+        f.write_str(")")
+    }
+}
+
+/// Writes the directives of `block` as scfg text, indented by `depth` levels.
+fn write_block(f: &mut fmt::Formatter<'_>, block: &Scfg, depth: usize) -> fmt::Result {
+    for (name, directives) in &block.directives {
+        for directive in directives {
+            write_directive(f, Some(name), directive, depth)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `directive` as scfg text, indented by `depth` levels, starting with
+/// `name` if any.
+fn write_directive(
+    f: &mut fmt::Formatter<'_>,
+    name: Option<&str>,
+    directive: &Directive,
+    depth: usize,
+) -> fmt::Result {
+    let indent = "    ".repeat(depth);
+    f.write_str(&indent)?;
+    let mut words = name
+        .into_iter()
+        .chain(directive.params.iter().map(String::as_str));
+    if let Some(word) = words.next() {
+        write_word(f, word)?;
+    }
+    for word in words {
+        f.write_str(" ")?;
+        write_word(f, word)?;
+    }
+    if let Some(ref child) = directive.child {
+        if name.is_some() || !directive.params.is_empty() {
+            f.write_str(" ")?;
+        }
+        f.write_str("{\n")?;
+        write_block(f, child, depth + 1)?;
+        write!(f, "{}}}", indent)?;
+    }
+    f.write_str("\n")
+}
+
+/// Writes `word` quoted like [`Scfg::write`] would, or, if it can't be
+/// written, as a Rust string literal with its newlines escaped.
+fn write_word(f: &mut fmt::Formatter<'_>, word: &str) -> fmt::Result {
+    match quote_word(word) {
+        Ok(quoted) => f.write_str(&quoted),
+        Err(_) => write!(f, "{:?}", word),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let doc: Scfg = "a 1 {\n\tb x {\n\t\tc\n\t}\n\tb 'y z'\n}\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            format!("{:#?}", doc),
+            r#"Scfg(
+    a 1 {
+        b x {
+            c
+        }
+        b 'y z'
+    }
+)"#
+        );
+        assert_eq!(
+            format!("{:?}", doc),
+            r#"Scfg { directives: {"a": [Directive { params: ["1"], child: Some(Scfg { directives: {"b": [Directive { params: ["x"], child: Some(Scfg { directives: {"c": [Directive { params: [], child: None, line: Some(3) }]} }), line: Some(2) }, Directive { params: ["y z"], child: None, line: Some(5) }]} }), line: Some(1) }]} }"#
+        );
+        assert_eq!(format!("{:#?}", Scfg::new()), "Scfg()");
+        assert_eq!(format!("{:?}", Scfg::new()), "Scfg { directives: {} }");
+    }
+
+    #[test]
+    fn invalid_words() {
+        let mut directive = Directive::new();
+        directive.append_param("line1\nline2").append_param("");
+        directive.get_or_create_child().add("a\"b");
+        assert_eq!(
+            format!("{:#?}", directive),
+            "Directive(\n    \"line1\\nline2\" '' {\n        'a\"b'\n    }\n)"
+        );
+        assert_eq!(
+            format!("{:?}", directive),
+            r#"Directive { params: ["line1\nline2", ""], child: Some(Scfg { directives: {"a\"b": [Directive { params: [], child: None, line: None }]} }), line: None }"#
+        );
+        assert_eq!(format!("{:#?}", Directive::new()), "Directive()");
+        let mut doc = Scfg::new();
+        doc.add("x\ny");
+        assert_eq!(format!("{:#?}", doc), "Scfg(\n    \"x\\ny\"\n)");
+    }
+}
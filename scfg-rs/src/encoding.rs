@@ -0,0 +1,103 @@
+//! Writing documents in encodings other than UTF-8.
+use crate::Scfg;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use std::io;
+
+impl Scfg {
+    /// Writes this document like [`write`], transcoded to `encoding`.
+    /// Available with the `encoding` feature.
+    ///
+    /// UTF-16 output has no byte order mark.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "city Montréal\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_with_encoding(encoding_rs::WINDOWS_1252, &mut out).unwrap();
+    /// assert_eq!(out, b"city Montr\xe9al\n");
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the document
+    /// contains a character which `encoding` can't represent, of kind
+    /// [`io::ErrorKind::InvalidInput`] if `encoding` can't be used for output,
+    /// like the `replacement` encoding, or any error returned by `writer`.
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_with_encoding<W>(
+        &self,
+        encoding: &'static Encoding,
+        writer: &mut W,
+    ) -> io::Result<()>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut utf8 = Vec::new();
+        self.write(&mut utf8)?;
+        // `write` only produces valid UTF-8
+        let utf8 = String::from_utf8(utf8).unwrap();
+
+        if encoding == UTF_16LE || encoding == UTF_16BE {
+            let mut out = Vec::with_capacity(utf8.len() * 2);
+            for unit in utf8.encode_utf16() {
+                if encoding == UTF_16LE {
+                    out.extend_from_slice(&unit.to_le_bytes());
+                } else {
+                    out.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            return writer.write_all(&out);
+        }
+        if encoding.output_encoding() != encoding {
+            let message = format!("cannot write documents in {}", encoding.name());
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+
+        let (out, _, unmappable) = encoding.encode(&utf8);
+        if unmappable {
+            let c = utf8
+                .chars()
+                .find(|c| encoding.encode(c.encode_utf8(&mut [0; 4])).2)
+                .unwrap();
+            let message = format!("{:?} cannot be encoded in {}", c, encoding.name());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        writer.write_all(&out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use encoding_rs::{REPLACEMENT, WINDOWS_1252};
+
+    static SRC: &str = "train Shinkansen {\n\tlines-served Tōhoku\n}\n";
+
+    #[test]
+    fn unrepresentable() {
+        let doc: Scfg = SRC.parse().unwrap();
+        let mut out = Vec::new();
+        let err = doc.write_with_encoding(WINDOWS_1252, &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "'ō' cannot be encoded in windows-1252");
+        assert!(out.is_empty());
+
+        let err = doc.write_with_encoding(REPLACEMENT, &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn utf16() {
+        let doc: Scfg = SRC.parse().unwrap();
+        let mut utf8 = Vec::new();
+        doc.write(&mut utf8).unwrap();
+        for &encoding in &[UTF_16LE, UTF_16BE] {
+            let mut out = Vec::new();
+            doc.write_with_encoding(encoding, &mut out).unwrap();
+            let (decoded, had_errors) = encoding.decode_without_bom_handling(&out);
+            assert!(!had_errors);
+            assert_eq!(decoded.as_bytes(), utf8.as_slice());
+            assert_eq!(out.len(), decoded.encode_utf16().count() * 2);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Example documents generated from Rust types, with their doc comments.
+use crate::{de::Error, quote_word, ser, Directive, Scfg};
+use serde::{ser::Error as _, Serialize};
+
+/// A type whose fields are documented, for [`example`].
+///
+/// This is usually derived, with the `macros` feature, from the doc comments
+/// of the fields of a struct:
+///
+/// ```
+/// # #[cfg(feature = "macros")]
+/// # {
+/// #[derive(serde::Serialize, Default, scfg::Documented)]
+/// struct Config {
+///     /// The port to listen on.
+///     port: u16,
+/// }
+///
+/// assert_eq!(
+///     scfg::example::<Config>().unwrap(),
+///     "# The port to listen on.\nport 0\n"
+/// );
+/// # }
+/// ```
+///
+/// The derive names fields like serde does, following the `rename` and
+/// `rename_all` attributes, leaves out the fields serde skips, and takes two
+/// field attributes:
+///
+/// - `#[scfg(block)]` documents the fields of a struct field, whose type, or
+///   the type held by an `Option` or a `Vec`, must implement `Documented`
+///   too.
+/// - `#[scfg(example = "...")]` gives the parameters written after the name
+///   of an `Option` field which is `None`.
+///
+/// Generic types are not supported by the derive.
+pub trait Documented {
+    /// Returns the fields of the type, in declaration order.
+    fn fields() -> &'static [FieldDoc];
+}
+
+/// The documentation of a field of a [`Documented`] type.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldDoc {
+    /// The name of the directive of the field.
+    pub name: &'static str,
+    /// The doc comment of the field, without the comment markers, or an empty
+    /// string.
+    pub doc: &'static str,
+    /// Whether the field is an `Option`.
+    pub optional: bool,
+    /// The parameters written for an `Option` field which is `None`, if any.
+    pub example: Option<&'static str>,
+    /// The fields of the child block of the field, if documented.
+    pub block: Option<fn() -> &'static [FieldDoc]>,
+}
+
+/// Returns an example document for `T`, holding its default value, see
+/// [`example_of`].
+///
+/// # Errors
+/// See [`example_of`].
+pub fn example<T>() -> Result<String, Error>
+where
+    T: Default + Serialize + Documented,
+{
+    example_of(&T::default())
+}
+
+/// Returns an example document holding `value`, laid out the way
+/// [`de::from_scfg`] reads it, with the doc comment of each field as a `#`
+/// comment before its directives.
+///
+/// Directives are written in the declaration order of the fields, followed by
+/// those of undocumented fields, if any. `Option` fields which are `None` are
+/// written commented out, with their [`FieldDoc::example`] parameters, so the
+/// example reads back as `value` as long as those fields are `None`.
+///
+/// # Errors
+/// Returns an error if `value` can't be serialized into a document, like with
+/// [`de::merge_from_scfg`].
+///
+/// [`de::from_scfg`]: crate::de::from_scfg
+/// [`de::merge_from_scfg`]: crate::de::merge_from_scfg
+pub fn example_of<T>(value: &T) -> Result<String, Error>
+where
+    T: Serialize + Documented + ?Sized,
+{
+    let doc = ser::to_scfg(value)?;
+    let mut out = String::new();
+    write_block(&mut out, &doc, T::fields(), 0)?;
+    Ok(out)
+}
+
+/// Writes the directives of `block`, documented by `fields`, indented by
+/// `depth` tabs.
+fn write_block(
+    out: &mut String,
+    block: &Scfg,
+    fields: &[FieldDoc],
+    depth: usize,
+) -> Result<(), Error> {
+    // whether a blank line separates the next field from the previous one
+    let mut separate = None;
+    for field in fields {
+        let directives = block.get_all(field.name).unwrap_or_default();
+        if directives.is_empty() && !field.optional {
+            continue;
+        }
+        if separate.unwrap_or(false) || (separate.is_some() && !field.doc.is_empty()) {
+            out.push('\n');
+        }
+        for line in field.doc.lines() {
+            push_indent(out, depth);
+            match line {
+                "" => out.push_str("#\n"),
+                line => {
+                    out.push_str("# ");
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+        if directives.is_empty() {
+            push_indent(out, depth);
+            out.push_str("# ");
+            out.push_str(&quote(field.name)?);
+            if let Some(example) = field.example {
+                out.push(' ');
+                out.push_str(example);
+            }
+            out.push('\n');
+        }
+        let children = field.block.map_or(&[][..], |fields| fields());
+        for directive in directives {
+            write_directive(out, field.name, directive, children, depth)?;
+        }
+        separate = Some(!field.doc.is_empty() || directives.iter().any(|d| d.child.is_some()));
+    }
+    for (name, directives) in &block.directives {
+        if fields.iter().any(|field| field.name == name) {
+            continue;
+        }
+        for directive in directives {
+            write_directive(out, name, directive, &[], depth)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `directive`, whose child block is documented by `fields`.
+fn write_directive(
+    out: &mut String,
+    name: &str,
+    directive: &Directive,
+    fields: &[FieldDoc],
+    depth: usize,
+) -> Result<(), Error> {
+    push_indent(out, depth);
+    out.push_str(&quote(name)?);
+    for param in &directive.params {
+        out.push(' ');
+        out.push_str(&quote(param)?);
+    }
+    if let Some(ref child) = directive.child {
+        out.push_str(" {\n");
+        write_block(out, child, fields, depth + 1)?;
+        push_indent(out, depth);
+        out.push('}');
+    }
+    out.push('\n');
+    Ok(())
+}
+
+/// Indents a line by `depth` tabs.
+fn push_indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push('\t');
+    }
+}
+
+/// Quotes `word` like [`Scfg::write`] does.
+fn quote(word: &str) -> Result<std::borrow::Cow<'_, str>, Error> {
+    quote_word(word).map_err(Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Server {
+        name: Option<String>,
+        hosts: Vec<String>,
+        limits: BTreeMap<String, u32>,
+        tls: Option<bool>,
+    }
+
+    impl Documented for Server {
+        fn fields() -> &'static [FieldDoc] {
+            static FIELDS: &[FieldDoc] = &[
+                FieldDoc {
+                    name: "name",
+                    doc: "The name of the server.\n\nUsed in logs.",
+                    optional: true,
+                    example: Some("main"),
+                    block: None,
+                },
+                FieldDoc {
+                    name: "hosts",
+                    doc: "",
+                    optional: false,
+                    example: None,
+                    block: None,
+                },
+                FieldDoc {
+                    name: "tls",
+                    doc: "",
+                    optional: true,
+                    example: None,
+                    block: None,
+                },
+            ];
+            FIELDS
+        }
+    }
+
+    #[test]
+    fn manual_impl() {
+        let mut limits = BTreeMap::new();
+        limits.insert("conns".to_owned(), 10);
+        let server = Server {
+            name: None,
+            hosts: vec!["a b".to_owned(), "c".to_owned()],
+            limits,
+            tls: Some(true),
+        };
+        let exp = "# The name of the server.\n#\n# Used in logs.\n# name main\n\nhosts 'a b'\nhosts c\ntls true\nlimits conns 10\n";
+        assert_eq!(example_of(&server).unwrap(), exp);
+
+        let mut server = server;
+        server.hosts.push("x\ny".to_owned());
+        assert!(example_of(&server).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! A source formatter that keeps comments.
+use crate::{ParseError, Scfg};
+use std::collections::HashMap;
+use std::mem;
+
+/// Reformats the scfg source `src`, keeping its comments.
+///
+/// Unlike parsing and writing the document again, the source is reformatted
+/// line by line: every line is indented with one tab per block level, runs of
+/// blank lines are collapsed into one, and blank lines at the start and end of
+/// blocks are removed. The content of each line, including comments, quoting
+/// and directive order, is left as is.
+///
+/// ```
+/// let src = "# trains\ntrain E5 {\n    # in km/h\n  max-speed 320\n\n\n}\n\n";
+/// assert_eq!(
+///     scfg::format(src).unwrap(),
+///     "# trains\ntrain E5 {\n\t# in km/h\n\tmax-speed 320\n}\n"
+/// );
+/// ```
+///
+/// # Errors
+/// Returns an error if `src` is not a valid scfg document, in which case
+/// nothing is reformatted.
+pub fn format(src: &str) -> Result<String, ParseError> {
+    src.parse::<Scfg>()?;
+
+    let mut out = String::with_capacity(src.len());
+    let mut depth = 0;
+    let mut blank = false;
+    let mut after_open = true;
+    for line in src.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            blank = true;
+            continue;
+        }
+        // the document parsed, so every line splits.
+        let words = shell_words::split(line).unwrap_or_default();
+        let closes = words.len() == 1 && line.ends_with('}');
+        let opens = !words.is_empty() && words[words.len() - 1] == "{" && line.ends_with('{');
+        if closes {
+            depth -= 1;
+        } else if blank && !after_open {
+            out.push('\n');
+        }
+        blank = false;
+        for _ in 0..depth {
+            out.push('\t');
+        }
+        out.push_str(line);
+        out.push('\n');
+        if opens {
+            depth += 1;
+        }
+        after_open = opens;
+    }
+    Ok(out)
+}
+
+/// Copies the comments of the scfg source `from` onto the matching directives
+/// of the source `src`, to keep the annotations of a document regenerated from
+/// scratch.
+///
+/// The leading comments of a directive are the comment lines between it and
+/// the previous directive or brace, and its trailing comment is the comment
+/// ending its line. Comment lines before a closing brace, or at the end of the
+/// document, belong to the end of that block. A directive of `src` without
+/// leading comments, or without a trailing comment, takes those of the
+/// matching directive of `from`. Comments already in `src` are kept, and the
+/// comments of `from` which match nothing are dropped.
+///
+/// Directives are matched by their path of names from the top level. When a
+/// name is repeated within a block, the n-th directive of that name matches the
+/// n-th directive of that name in the matching block of `from`, whatever their
+/// parameters.
+///
+/// ```
+/// let old = "# the public name\ndomain example.org\nport 80 # http\n";
+/// let new = "domain example.com\nport 443\n";
+/// assert_eq!(
+///     scfg::adopt_comments(new, old).unwrap(),
+///     "# the public name\ndomain example.com\nport 443 # http\n"
+/// );
+/// ```
+///
+/// # Errors
+/// Returns an error if `src` or `from` is not a valid scfg document.
+pub fn adopt_comments(src: &str, from: &str) -> Result<String, ParseError> {
+    src.parse::<Scfg>()?;
+    from.parse::<Scfg>()?;
+
+    let mut comments = HashMap::new();
+    let mut paths = Paths::new();
+    let mut leading = Vec::new();
+    for line in from.lines() {
+        match Line::new(line) {
+            Line::Blank => {}
+            Line::Comment(comment) => leading.push(comment),
+            Line::Directive {
+                name,
+                opens,
+                comment,
+            } => {
+                let key = paths.directive(name, opens);
+                comments.insert(key, (mem::take(&mut leading), comment));
+            }
+            Line::Close => {
+                comments.insert(paths.close(), (mem::take(&mut leading), None));
+            }
+        }
+    }
+    comments.insert(paths.end(), (leading, None));
+
+    let mut out = String::with_capacity(src.len());
+    let mut paths = Paths::new();
+    // whether comment lines precede the line, since the last directive or brace
+    let mut commented = false;
+    for line in src.lines() {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let adopted = match Line::new(line) {
+            Line::Blank => None,
+            Line::Comment(_) => {
+                commented = true;
+                None
+            }
+            Line::Directive {
+                name,
+                opens,
+                comment,
+            } => {
+                let key = paths.directive(name, opens);
+                comments.get(&key).map(|(leading, trailing)| {
+                    // a comment after a brace would change the meaning of the line
+                    let ends_with_brace = line.trim_end().ends_with(&['{', '}'][..]);
+                    let trailing = trailing.filter(|_| comment.is_none() && !ends_with_brace);
+                    (indent.to_owned(), leading, trailing)
+                })
+            }
+            Line::Close => comments
+                .get(&paths.close())
+                .map(|(leading, _)| (format!("{}\t", indent), leading, None)),
+        };
+        match adopted {
+            Some((indent, leading, trailing)) => {
+                if !commented {
+                    push_comments(&mut out, &indent, leading);
+                }
+                commented = false;
+                out.push_str(line);
+                if let Some(trailing) = trailing {
+                    out.push(' ');
+                    out.push_str(trailing);
+                }
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    if !commented {
+        push_comments(&mut out, "", &comments[&paths.end()].0);
+    }
+    Ok(out)
+}
+
+/// A line of a valid document, as the parser reads it.
+enum Line<'a> {
+    Blank,
+    Comment(&'a str),
+    Directive {
+        name: String,
+        opens: bool,
+        /// The comment ending the line, from its `#`.
+        comment: Option<&'a str>,
+    },
+    Close,
+}
+
+impl<'a> Line<'a> {
+    fn new(line: &'a str) -> Self {
+        let line = line.trim();
+        // the document parsed, so every line splits.
+        let mut words = shell_words::split(line).unwrap_or_default();
+        if words.is_empty() {
+            return if line.is_empty() {
+                Line::Blank
+            } else {
+                Line::Comment(line)
+            };
+        }
+        if words.len() == 1 && line.ends_with('}') {
+            return Line::Close;
+        }
+        let opens = words[words.len() - 1] == "{" && line.ends_with('{');
+        let name = if opens && words.len() == 1 {
+            String::new()
+        } else {
+            words.swap_remove(0)
+        };
+        Line::Directive {
+            name,
+            opens,
+            comment: trailing_comment(line),
+        }
+    }
+}
+
+/// Returns the comment ending `line`, from the `#` starting a word outside
+/// quotes, like [`shell_words::split`] finds it.
+fn trailing_comment(line: &str) -> Option<&str> {
+    let mut chars = line.char_indices();
+    let mut in_word = false;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '#' if !in_word => return Some(&line[i..]),
+            c if c.is_whitespace() => in_word = false,
+            '\\' => {
+                chars.next();
+                in_word = true;
+            }
+            '\'' | '"' => {
+                in_word = true;
+                while let Some((_, q)) = chars.next() {
+                    if q == c {
+                        break;
+                    }
+                    if q == '\\' && c == '"' {
+                        chars.next();
+                    }
+                }
+            }
+            _ => in_word = true,
+        }
+    }
+    None
+}
+
+/// Where comments are, see [`adopt_comments`]: the path of a directive, made of
+/// the names and occurrence indexes of the directives leading to it, and
+/// whether the comments are at the end of its block rather than before it.
+type Key = (Vec<(String, usize)>, bool);
+
+/// Tracks the path of each line of a document.
+struct Paths {
+    /// The path of the current block.
+    path: Vec<(String, usize)>,
+    /// The number of directives of each name seen in the current block, and in
+    /// each block enclosing it.
+    counts: Vec<HashMap<String, usize>>,
+}
+
+impl Paths {
+    fn new() -> Self {
+        Paths {
+            path: Vec::new(),
+            counts: vec![HashMap::new()],
+        }
+    }
+
+    fn directive(&mut self, name: String, opens: bool) -> Key {
+        let count = self
+            .counts
+            .last_mut()
+            .unwrap()
+            .entry(name.clone())
+            .or_insert(0);
+        let mut path = self.path.clone();
+        path.push((name, *count));
+        *count += 1;
+        if opens {
+            self.path = path.clone();
+            self.counts.push(HashMap::new());
+        }
+        (path, false)
+    }
+
+    fn close(&mut self) -> Key {
+        let key = (self.path.clone(), true);
+        self.path.pop();
+        self.counts.pop();
+        key
+    }
+
+    fn end(&self) -> Key {
+        (Vec::new(), true)
+    }
+}
+
+/// Writes the comment lines `comments`, indented by `indent`.
+fn push_comments(out: &mut String, indent: &str, comments: &[&str]) {
+    for comment in comments {
+        out.push_str(indent);
+        out.push_str(comment);
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reindent() {
+        let src = r#"
+
+# network
+listen 0.0.0.0:6697 {
+
+        # TLS
+    tls {
+certificate  "cert.pem"   # trailing
+      key key.pem
+  }
+
+
+
+    }
+  domain example.com
+empty {
+}
+"#;
+        let exp = "# network
+listen 0.0.0.0:6697 {
+\t# TLS
+\ttls {
+\t\tcertificate  \"cert.pem\"   # trailing
+\t\tkey key.pem
+\t}
+}
+domain example.com
+empty {
+}
+";
+        assert_eq!(format(src).unwrap(), exp);
+        assert_eq!(format(exp).unwrap(), exp);
+        assert_eq!(format("").unwrap(), "");
+        assert_eq!(format("a \"{\"\nb\r\n").unwrap(), "a \"{\"\nb\n");
+    }
+
+    #[test]
+    fn invalid() {
+        let err = format("a {\n}\n}\n").unwrap_err();
+        assert_eq!(err.to_string(), "parsing error at line 3: unexpected '}'");
+    }
+
+    #[test]
+    fn adopt_comments() {
+        let from = r#"# header
+
+# network
+listen a {
+	# TLS
+	tls {
+		certificate cert.pem # PEM
+		# end of tls
+	}
+}
+# second
+listen b
+gone # dropped
+# end
+"#;
+        let src = "listen x {\n\ttls {\n\t\tcertificate new.pem\n\t\tkey key.pem\n\t}\n}\nlisten y # own\nlisten z\n";
+        let exp = "# header
+# network
+listen x {
+\t# TLS
+\ttls {
+\t\tcertificate new.pem # PEM
+\t\tkey key.pem
+\t\t# end of tls
+\t}
+}
+# second
+listen y # own
+listen z
+# end
+";
+        assert_eq!(super::adopt_comments(src, from).unwrap(), exp);
+        assert_eq!(super::adopt_comments(exp, from).unwrap(), exp);
+        assert_eq!(super::adopt_comments(from, from).unwrap(), from);
+
+        // comments of the new source are kept
+        let src = "# own\nlisten x {\n}\n# own end\n";
+        assert_eq!(super::adopt_comments(src, from).unwrap(), src);
+
+        assert!(super::adopt_comments("a {\n", from).is_err());
+        assert!(super::adopt_comments("a\n", "}\n").is_err());
+    }
+}
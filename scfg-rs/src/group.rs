@@ -0,0 +1,292 @@
+//! A view of the directives sharing a name.
+use crate::{Directive, Scfg};
+use std::{convert::Infallible, error::Error, fmt, slice, str::FromStr};
+
+/// The directives sharing a name in a block, see [`Scfg::group`].
+///
+/// A group is a cheap view into the document. The group of a name without
+/// directives is empty.
+#[derive(Debug, Clone, Copy)]
+pub struct Group<'a> {
+    name: &'a str,
+    directives: &'a [Directive],
+}
+
+/// The error returned by [`Group::only`] and [`Group::parsed`], where `E` is
+/// the error of parsing a parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupError<E = Infallible> {
+    /// The group does not hold exactly one directive.
+    Count {
+        /// The name of the group.
+        name: String,
+        /// The number of directives in the group.
+        count: usize,
+        /// The line of the second directive, if any and known.
+        line: Option<usize>,
+    },
+    /// A directive has no parameter.
+    MissingParam {
+        /// The name of the group.
+        name: String,
+        /// The index of the directive in the group.
+        index: usize,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+    },
+    /// The first parameter of a directive could not be parsed.
+    Parse {
+        /// The name of the group.
+        name: String,
+        /// The index of the directive in the group.
+        index: usize,
+        /// The line of the directive, if known.
+        line: Option<usize>,
+        /// The error of parsing the parameter.
+        error: E,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for GroupError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = match *self {
+            GroupError::Count { line, .. }
+            | GroupError::MissingParam { line, .. }
+            | GroupError::Parse { line, .. } => line,
+        };
+        if let Some(line) = line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self {
+            GroupError::Count { name, count, .. } => {
+                write!(f, "expected 1 {:?} directive, found {}", name, count)
+            }
+            GroupError::MissingParam { name, .. } => {
+                write!(f, "{:?} directive: missing parameter 0", name)
+            }
+            GroupError::Parse { name, error, .. } => {
+                write!(f, "{:?} directive: parameter 0: {}", name, error)
+            }
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for GroupError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GroupError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Group<'a> {
+    /// Returns the name of the directives.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the number of directives.
+    pub fn len(&self) -> usize {
+        self.directives.len()
+    }
+
+    /// Returns `true` if the group holds no directives.
+    pub fn is_empty(&self) -> bool {
+        self.directives.is_empty()
+    }
+
+    /// Returns the directives.
+    pub fn as_slice(&self) -> &'a [Directive] {
+        self.directives
+    }
+
+    /// Returns the first directive, if any.
+    pub fn first(&self) -> Option<&'a Directive> {
+        self.directives.first()
+    }
+
+    /// Returns the directive of a group holding exactly one.
+    ///
+    /// # Errors
+    /// Returns [`GroupError::Count`] if the group is empty or holds several
+    /// directives.
+    pub fn only(&self) -> Result<&'a Directive, GroupError> {
+        match self.directives {
+            [directive] => Ok(directive),
+            directives => Err(GroupError::Count {
+                name: self.name.to_owned(),
+                count: directives.len(),
+                line: directives.get(1).and_then(Directive::line),
+            }),
+        }
+    }
+
+    /// Returns an iterator over the directives.
+    pub fn iter(&self) -> slice::Iter<'a, Directive> {
+        self.directives.iter()
+    }
+
+    /// Returns an iterator over the first parameter of each directive,
+    /// skipping the directives without parameters.
+    pub fn params_of_each(&self) -> impl Iterator<Item = &'a str> {
+        self.directives
+            .iter()
+            .filter_map(|directive| directive.params.first())
+            .map(String::as_str)
+    }
+
+    /// Parses the first parameter of each directive.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "port 80\nport 443\n".parse().unwrap();
+    /// assert_eq!(doc.group("port").parsed::<u16>(), Ok(vec![80, 443]));
+    /// assert_eq!(doc.group("missing").parsed::<u16>(), Ok(vec![]));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error for the first directive without parameters, or whose
+    /// first parameter can't be parsed.
+    pub fn parsed<T: FromStr>(&self) -> Result<Vec<T>, GroupError<T::Err>> {
+        self.directives
+            .iter()
+            .enumerate()
+            .map(|(index, directive)| {
+                let param = directive
+                    .params
+                    .first()
+                    .ok_or_else(|| GroupError::MissingParam {
+                        name: self.name.to_owned(),
+                        index,
+                        line: directive.line,
+                    })?;
+                param.parse().map_err(|error| GroupError::Parse {
+                    name: self.name.to_owned(),
+                    index,
+                    line: directive.line,
+                    error,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for Group<'a> {
+    type Item = &'a Directive;
+    type IntoIter = slice::Iter<'a, Directive>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.directives.iter()
+    }
+}
+
+impl Scfg {
+    /// Returns the top-level directives named `name`, see [`Group`]. The group
+    /// is empty if there are none.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "listen :80\nlisten :443\n".parse().unwrap();
+    /// let listen = doc.group("listen");
+    /// assert_eq!(listen.params_of_each().collect::<Vec<_>>(), [":80", ":443"]);
+    /// assert!(listen.only().is_err());
+    /// assert!(doc.group("tls").is_empty());
+    /// ```
+    pub fn group<'a>(&'a self, name: &'a str) -> Group<'a> {
+        Group {
+            name,
+            directives: self.get_all(name).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static SRC: &str = "port 80\nmodel E5\nmodel E7\nmodel\nweight 453.5t\n";
+
+    #[test]
+    fn empty() {
+        let doc: Scfg = SRC.parse().unwrap();
+        let group = doc.group("missing");
+        assert_eq!(group.name(), "missing");
+        assert!(group.is_empty());
+        assert_eq!(group.len(), 0);
+        assert_eq!(group.first(), None);
+        assert_eq!(group.iter().count(), 0);
+        assert_eq!(group.into_iter().count(), 0);
+        assert_eq!(group.params_of_each().count(), 0);
+        assert_eq!(group.parsed::<u16>(), Ok(vec![]));
+        let err = group.only().unwrap_err();
+        assert_eq!(
+            err,
+            GroupError::Count {
+                name: "missing".to_owned(),
+                count: 0,
+                line: None
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            r#"expected 1 "missing" directive, found 0"#
+        );
+    }
+
+    #[test]
+    fn singleton() {
+        let doc: Scfg = SRC.parse().unwrap();
+        let group = doc.group("port");
+        assert!(!group.is_empty());
+        assert_eq!(group.len(), 1);
+        assert_eq!(group.first().unwrap().params(), ["80"]);
+        assert_eq!(group.only().unwrap().line(), Some(1));
+        assert_eq!(group.iter().count(), 1);
+        assert_eq!(group.params_of_each().collect::<Vec<_>>(), ["80"]);
+        assert_eq!(group.parsed::<u16>(), Ok(vec![80]));
+
+        let err = doc.group("weight").parsed::<f64>().unwrap_err();
+        assert!(matches!(
+            err,
+            GroupError::Parse {
+                index: 0,
+                line: Some(5),
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            r#"line 5: "weight" directive: parameter 0: invalid float literal"#
+        );
+    }
+
+    #[test]
+    fn multiple() {
+        let doc: Scfg = SRC.parse().unwrap();
+        let group = doc.group("model");
+        assert_eq!(group.len(), 3);
+        assert_eq!(group.first().unwrap().params(), ["E5"]);
+        assert_eq!(group.as_slice(), doc.get_all("model").unwrap());
+        let lines: Vec<_> = group.into_iter().map(Directive::line).collect();
+        assert_eq!(lines, [Some(2), Some(3), Some(4)]);
+        assert_eq!(group.params_of_each().collect::<Vec<_>>(), ["E5", "E7"]);
+        assert_eq!(
+            group.only().unwrap_err().to_string(),
+            r#"line 3: expected 1 "model" directive, found 3"#
+        );
+        let err = group.parsed::<String>().unwrap_err();
+        assert_eq!(
+            err,
+            GroupError::MissingParam {
+                name: "model".to_owned(),
+                index: 2,
+                line: Some(4)
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            r#"line 4: "model" directive: missing parameter 0"#
+        );
+    }
+}
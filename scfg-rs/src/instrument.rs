@@ -0,0 +1,228 @@
+//! Support for the `tracing` feature, which instruments parsing and writing.
+//!
+//! Parsing a document enters a `document` span, recording the number of bytes
+//! and lines read, and writing one enters a `write` span, recording the
+//! number of bytes and lines written. Each child block enters a nested `block`
+//! span with its directive's name and its depth. Debug events report blocks
+//! nested deeper than [`DEEP_NESTING`], lines longer than [`LONG_LINE`] bytes
+//! and parser warnings.
+use std::io::{self, BufRead, Read, Write};
+
+/// Blocks nested deeper than this are reported by a debug event.
+pub(crate) const DEEP_NESTING: usize = 32;
+
+/// Lines longer than this many bytes are reported by a debug event.
+pub(crate) const LONG_LINE: usize = 64 * 1024;
+
+/// A reader or writer counting the bytes and lines going through it.
+pub(crate) struct Counting<T> {
+    inner: T,
+    bytes: usize,
+    lines: usize,
+}
+
+impl<T> Counting<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Counting {
+            inner,
+            bytes: 0,
+            lines: 0,
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub(crate) fn lines(&self) -> usize {
+        self.lines
+    }
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counting<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.bytes += amt;
+        self.inner.consume(amt)
+    }
+
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let n = self.inner.read_line(buf)?;
+        self.bytes += n;
+        if n > 0 {
+            self.lines += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n;
+        self.lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Scfg;
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Id, Metadata, Subscriber,
+    };
+
+    /// A span as seen by [`Collector`]: its name and fields, and the index of
+    /// its parent.
+    #[derive(Debug, Default)]
+    struct Span {
+        name: &'static str,
+        fields: Vec<String>,
+        parent: Option<usize>,
+    }
+
+    impl Visit for Span {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    #[derive(Default)]
+    struct State {
+        spans: Vec<Span>,
+        stack: Vec<usize>,
+        events: Vec<String>,
+    }
+
+    /// A subscriber recording every span and event.
+    #[derive(Clone, Default)]
+    struct Collector(Arc<Mutex<State>>);
+
+    impl Subscriber for Collector {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes<'_>) -> Id {
+            let mut state = self.0.lock().unwrap();
+            let mut span = Span {
+                name: attrs.metadata().name(),
+                parent: state.stack.last().copied(),
+                ..Span::default()
+            };
+            attrs.record(&mut span);
+            state.spans.push(span);
+            Id::from_u64(state.spans.len() as u64)
+        }
+
+        fn record(&self, id: &Id, values: &span::Record<'_>) {
+            let mut state = self.0.lock().unwrap();
+            values.record(&mut state.spans[id.into_u64() as usize - 1]);
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Span::default();
+            event.record(&mut fields);
+            self.0.lock().unwrap().events.push(fields.fields.join(" "));
+        }
+
+        fn enter(&self, id: &Id) {
+            let mut state = self.0.lock().unwrap();
+            state.stack.push(id.into_u64() as usize - 1);
+        }
+
+        fn exit(&self, _: &Id) {
+            self.0.lock().unwrap().stack.pop();
+        }
+    }
+
+    impl Collector {
+        /// Returns the spans as `/` separated paths from their root.
+        fn paths(&self) -> Vec<String> {
+            let state = self.0.lock().unwrap();
+            let describe = |span: &Span| format!("{}[{}]", span.name, span.fields.join(" "));
+            let mut paths = Vec::new();
+            for span in &state.spans {
+                let mut path = describe(span);
+                let mut parent = span.parent;
+                while let Some(i) = parent {
+                    path = format!("{}/{}", describe(&state.spans[i]), path);
+                    parent = state.spans[i].parent;
+                }
+                paths.push(path);
+            }
+            paths
+        }
+    }
+
+    #[test]
+    fn spans() {
+        let collector = Collector::default();
+        let src = "a {\n\tb x {\n\t\tc\n\t}\n}\nd {\n}\n";
+        let doc: Scfg = tracing::subscriber::with_default(collector.clone(), || {
+            let doc: Scfg = src.parse().unwrap();
+            let mut out = Vec::new();
+            doc.write(&mut out).unwrap();
+            doc
+        });
+        assert_eq!(doc.get("d").unwrap().child(), Some(&Scfg::new()));
+        let document = "document[bytes=26 lines=7]";
+        // the writer separates the top-level blocks with a blank line.
+        let write = "write[bytes=27 lines=8]";
+        assert_eq!(
+            collector.paths(),
+            [
+                document.to_owned(),
+                format!("{}/block[name=a depth=1 line=1]", document),
+                format!(
+                    "{}/block[name=a depth=1 line=1]/block[name=b depth=2 line=2]",
+                    document
+                ),
+                format!("{}/block[name=d depth=1 line=6]", document),
+                write.to_owned(),
+                format!("{}/block[name=a depth=1]", write),
+                format!("{}/block[name=a depth=1]/block[name=b depth=2]", write),
+                format!("{}/block[name=d depth=1]", write),
+            ]
+        );
+    }
+
+    #[test]
+    fn events() {
+        let collector = Collector::default();
+        let src = format!("{}{}\na \u{7}\n", "a {\n".repeat(33), "}\n".repeat(33));
+        let long = format!("a {}\n", "x".repeat(super::LONG_LINE));
+        tracing::subscriber::with_default(collector.clone(), || {
+            src.parse::<Scfg>().unwrap();
+            long.parse::<Scfg>().unwrap();
+        });
+        let events = collector.0.lock().unwrap().events.clone();
+        assert_eq!(
+            events,
+            [
+                "message=deep nesting line=33 depth=33",
+                "message=parameter 0 contains the control character '\\u{7}', which other parsers may not accept line=68",
+                "message=long line line=1 bytes=65539",
+            ]
+        );
+    }
+}
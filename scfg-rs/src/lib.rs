@@ -50,22 +50,113 @@
 //!
 //! assert_eq!(doc, scfg);
 //! ```
-use std::{borrow::Borrow, hash::Hash, io, str::FromStr};
+use std::{
+    borrow::{Borrow, Cow},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    io,
+    num::ParseIntError,
+    ops::{Deref, DerefMut, RangeInclusive},
+    panic,
+    path::Path,
+    str::{FromStr, ParseBoolError},
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
 
 #[cfg(feature = "preserve_order")]
-use indexmap::IndexMap;
+use indexmap::{
+    map::{Entry, OccupiedEntry},
+    IndexMap,
+};
 #[cfg(not(feature = "preserve_order"))]
-use std::collections::BTreeMap;
+use std::collections::{
+    btree_map::{Entry, OccupiedEntry},
+    BTreeMap,
+};
 
+mod builder;
+#[cfg(feature = "checksum")]
+mod checksum;
+mod coalesce;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+#[cfg(feature = "compat")]
+mod compat;
+mod condition;
+#[cfg(any(feature = "json", feature = "toml"))]
+mod convert;
+mod cursor;
+#[cfg(feature = "serde")]
+pub mod de;
+mod debug;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "serde")]
+mod example;
+mod format;
+mod group;
+#[cfg(feature = "tracing")]
+mod instrument;
+mod macros;
+mod param;
 mod parser;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+mod reference;
+mod schema;
+mod select;
+#[cfg(feature = "serde")]
+mod ser;
+mod suggest;
+#[cfg(feature = "serde")]
+mod trace;
+mod visit;
+mod word;
+
+pub use builder::Builder;
+pub use coalesce::{ChildBlocks, CoalesceError};
+pub use condition::{ConditionError, ConditionOptions, UnknownKeys};
+pub use cursor::ScfgCursor;
+pub use format::{adopt_comments, format};
+pub use group::{Group, GroupError};
+pub use param::{DuplicateKeys, EnumParamError, KvError, KvParams, ParamError};
+pub use reference::{ReferenceError, ReferenceOptions};
+pub use schema::{
+    Completion, DirectiveSchema, ParamRule, Rule, Schema, SchemaError, ValidationError,
+    MAX_PARAM_INDEX,
+};
+pub use select::QueryError;
+pub use visit::{Visitor, VisitorMut};
+pub use word::{is_valid_word, quote_word, InvalidEntry, InvalidWord};
+
+#[cfg(feature = "checksum")]
+pub use checksum::ChecksumError;
+#[cfg(feature = "compat")]
+pub use compat::{SystemdExportError, SystemdParseError, WranglerExportError};
+#[cfg(feature = "json")]
+pub use convert::FromJsonError;
+#[cfg(feature = "serde")]
+pub use example::{example, example_of, Documented, FieldDoc};
+#[cfg(feature = "macros")]
+pub use scfg_macros::{include_scfg, scfg_literal, Documented};
 
 pub type ParseError = parser::Error;
+pub use parser::{ParseOptions, StoppedOn, Warning, WarningKind};
 
 /// An scfg document. Implemented as a multimap.
 ///
 /// If the `preserve_order` feature is enabled, the directive names will be kept
 /// in the order of their first appearance.  Otherwise, they will be sorted by name.
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+///
+/// Equality never depends on the order of directive names, even with
+/// `preserve_order`, only on the order of directives sharing a name. Hashing is
+/// consistent with equality: names are hashed in sorted order regardless of the
+/// map backend.
+#[derive(PartialEq, Eq, Clone, Default)]
 pub struct Scfg {
     directives: Map<String, Vec<Directive>>,
 }
@@ -75,12 +166,347 @@ type Map<K, V> = BTreeMap<K, V>;
 #[cfg(feature = "preserve_order")]
 type Map<K, V> = IndexMap<K, V>;
 
+/// A mutable reference to all directives with a particular name, returned by
+/// [`Scfg::get_all_mut`].
+///
+/// It dereferences to the document's own `Vec` of directives. When it is
+/// dropped with the `Vec` empty, the name is removed from the document, so a
+/// document never holds a name without directives.
+#[derive(Debug)]
+pub struct DirectivesMut<'a> {
+    entry: Option<OccupiedEntry<'a, String, Vec<Directive>>>,
+}
+
+impl Deref for DirectivesMut<'_> {
+    type Target = Vec<Directive>;
+
+    fn deref(&self) -> &Vec<Directive> {
+        self.entry.as_ref().unwrap().get()
+    }
+}
+
+impl DerefMut for DirectivesMut<'_> {
+    fn deref_mut(&mut self) -> &mut Vec<Directive> {
+        self.entry.as_mut().unwrap().get_mut()
+    }
+}
+
+impl<'b> IntoIterator for &'b mut DirectivesMut<'_> {
+    type Item = &'b mut Directive;
+    type IntoIter = std::slice::IterMut<'b, Directive>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl Drop for DirectivesMut<'_> {
+    fn drop(&mut self) {
+        match self.entry.take() {
+            #[cfg(not(feature = "preserve_order"))]
+            Some(entry) if entry.get().is_empty() => {
+                entry.remove();
+            }
+            #[cfg(feature = "preserve_order")]
+            Some(entry) if entry.get().is_empty() => {
+                entry.shift_remove();
+            }
+            _ => {}
+        }
+    }
+}
+
 impl Scfg {
     /// Creates a new empty document
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Parses the file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, annotated with
+    /// `path`, see [`ParseError::file_path`].
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Scfg, ParseError> {
+        let path = path.as_ref();
+        let file =
+            std::fs::File::open(path).map_err(|err| ParseError::open(err).with_file_path(path))?;
+        parser::document(io::BufReader::new(file)).map_err(|err| err.with_file_path(path))
+    }
+
+    /// Parses several files in order, and merges them into a single document
+    /// with [`append`].
+    ///
+    /// Files which don't exist are skipped, unless `strict` is true. Errors
+    /// are annotated with the path of the file they occurred in.
+    ///
+    /// [`append`]: Scfg::append
+    pub fn parse_multiple_files<I, P>(paths: I, strict: bool) -> Result<Scfg, ParseError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut doc = Scfg::new();
+        for path in paths {
+            let path = path.as_ref();
+            let file = match std::fs::File::open(path) {
+                Ok(file) => file,
+                Err(err) if !strict && err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(ParseError::open(err).with_file_path(path)),
+            };
+            let file_doc = parser::document(io::BufReader::new(file))
+                .map_err(|err| err.with_file_path(path))?;
+            doc.append(file_doc);
+        }
+        Ok(doc)
+    }
+
+    /// Parses a document from `reader`, giving up if it isn't fully read and
+    /// parsed within `timeout`.
+    ///
+    /// The reader is consumed on a separate thread, since a blocking read
+    /// can't be interrupted. If the timeout expires, that thread is left to
+    /// finish the read in progress, and the reader is dropped when it does.
+    /// For sockets, setting a read timeout on the socket itself, like
+    /// [`TcpStream::set_read_timeout`], avoids leaving the thread behind.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// # use std::time::Duration;
+    /// let reader = std::io::Cursor::new("domain example.com\n");
+    /// let doc = Scfg::parse_with_timeout(reader, Duration::from_secs(1)).unwrap();
+    /// assert!(doc.contains("domain"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error for which [`ParseError::is_timeout`] is true if the
+    /// timeout expires, or any other parsing error.
+    ///
+    /// # Panics
+    /// If `reader` panics before the timeout expires, the panic is resumed on
+    /// the calling thread.
+    ///
+    /// [`TcpStream::set_read_timeout`]: std::net::TcpStream::set_read_timeout
+    pub fn parse_with_timeout<R>(reader: R, timeout: Duration) -> Result<Scfg, ParseError>
+    where
+        R: io::BufRead + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            // the receiver is gone if the timeout expired
+            let _ = sender.send(parser::document(reader));
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(ParseError::timeout(timeout)),
+            // the sender is only dropped unsent if the thread panicked
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                panic::resume_unwind(handle.join().unwrap_err())
+            }
+        }
+    }
+
+    /// Parses a document from `reader` like [`FromStr`], also returning
+    /// warnings about constructs which are valid but may cause problems, such
+    /// as control characters in words (see [`WarningKind`]).
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let src = "greeting \"hello\tworld\"\n";
+    /// let (doc, warnings) = Scfg::parse_with_warnings(src.as_bytes()).unwrap();
+    /// assert_eq!(doc.get("greeting").unwrap().params(), ["hello\tworld"]);
+    /// assert_eq!(warnings[0].line(), 1);
+    /// ```
+    pub fn parse_with_warnings<R>(reader: R) -> Result<(Scfg, Vec<Warning>), ParseError>
+    where
+        R: io::BufRead,
+    {
+        let mut warnings = Vec::new();
+        let doc = parser::document_with_warnings(reader, &ParseOptions::default(), &mut warnings)?;
+        Ok((doc, warnings))
+    }
+
+    /// Parses a document from `reader` like [`FromStr`], with `options`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let options = ParseOptions {
+    ///     unicode_escapes: true,
+    ///     ..Default::default()
+    /// };
+    /// let doc = Scfg::parse_with_options("sep \"\\u0009\"\n".as_bytes(), &options).unwrap();
+    /// assert_eq!(doc.get("sep").unwrap().params(), ["\t"]);
+    /// ```
+    pub fn parse_with_options<R>(reader: R, options: &ParseOptions) -> Result<Scfg, ParseError>
+    where
+        R: io::BufRead,
+    {
+        parser::document_with_warnings(reader, options, &mut Vec::new())
+    }
+
+    /// Parses the body of a block from `reader`, stopping at the end of the
+    /// input or at the first unmatched `}`, whichever comes first.
+    ///
+    /// Unlike parsing a whole document, an unmatched `}` is not an error, so
+    /// this can parse a fragment embedded in another format, or the rest of a
+    /// block whose opening line was already read. The reader is left right
+    /// after the line of the closing brace, so the caller can keep reading.
+    ///
+    /// Line numbers, in the returned directives, in errors and in
+    /// [`StoppedOn::ClosingBrace`], count from 1 at the first line read from
+    /// `reader`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// use std::io::BufRead;
+    ///
+    /// let mut reader = std::io::Cursor::new("model E5\nweight 453.5t\n}\nrest\n");
+    /// let (block, stopped_on) = Scfg::from_block_reader(&mut reader).unwrap();
+    /// assert_eq!(block, "model E5\nweight 453.5t\n".parse().unwrap());
+    /// assert_eq!(stopped_on, StoppedOn::ClosingBrace(3));
+    /// assert_eq!(reader.lines().next().unwrap().unwrap(), "rest");
+    /// ```
+    pub fn from_block_reader<R>(reader: R) -> Result<(Scfg, StoppedOn), ParseError>
+    where
+        R: io::BufRead,
+    {
+        parser::block(reader)
+    }
+
+    /// Moves all directives of `other` into this document. Directives whose
+    /// name is already present are added after the existing ones.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "a 1\nb 1\n".parse().unwrap();
+    /// doc.append("a 2\nc 2\n".parse().unwrap());
+    /// assert_eq!(doc, "a 1\na 2\nb 1\nc 2\n".parse().unwrap());
+    /// ```
+    pub fn append(&mut self, other: Scfg) {
+        for (name, directives) in other.directives {
+            self.directives.entry(name).or_default().extend(directives);
+        }
+    }
+
+    /// Parses `src` like [`FromStr`] and appends its directives to this
+    /// document like [`append`].
+    ///
+    /// `src` is parsed completely before anything is appended, so the
+    /// document is left unchanged if it can't be parsed. Line numbers, in the
+    /// appended directives and in errors, count from 1 at the first line of
+    /// `src`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "listen :80\n".parse().unwrap();
+    /// doc.extend_from_str("listen :443\nlog debug\n").unwrap();
+    /// assert!(doc.extend_from_str("log {\n").is_err());
+    /// assert_eq!(doc, "listen :80\nlisten :443\nlog debug\n".parse().unwrap());
+    /// ```
+    ///
+    /// [`append`]: Scfg::append
+    pub fn extend_from_str(&mut self, src: &str) -> Result<(), ParseError> {
+        self.extend_from_reader(src.as_bytes())
+    }
+
+    /// Parses a document from `reader` and appends its directives to this
+    /// document, see [`extend_from_str`].
+    ///
+    /// # Errors
+    /// Returns an error if `reader` can't be read, or if its content can't be
+    /// parsed. The document is then unchanged.
+    ///
+    /// [`extend_from_str`]: Scfg::extend_from_str
+    pub fn extend_from_reader<R>(&mut self, reader: R) -> Result<(), ParseError>
+    where
+        R: io::BufRead,
+    {
+        let fragment = parser::document(reader)?;
+        self.append(fragment);
+        Ok(())
+    }
+
+    /// Merges the directives of `overlay` into this document, with the
+    /// [`MergeStrategy`] returned by `strategy` for each name, at every level
+    /// of nesting.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "listen :80\nlog info\n".parse().unwrap();
+    /// let overlay: Scfg = "listen :443\nlog debug\n".parse().unwrap();
+    /// doc.apply_overlay(overlay, |name| match name {
+    ///     "listen" => MergeStrategy::Append,
+    ///     _ => MergeStrategy::Replace,
+    /// });
+    /// assert_eq!(doc, "listen :80\nlisten :443\nlog debug\n".parse().unwrap());
+    /// ```
+    pub fn apply_overlay<F>(&mut self, overlay: Scfg, strategy: F)
+    where
+        F: Fn(&str) -> MergeStrategy,
+    {
+        self.apply_overlay_with(overlay, &strategy)
+    }
+
+    fn apply_overlay_with(&mut self, overlay: Scfg, strategy: &dyn Fn(&str) -> MergeStrategy) {
+        for (name, directives) in overlay.directives {
+            let merge = strategy(&name);
+            let existing = self.directives.entry(name).or_default();
+            match merge {
+                MergeStrategy::Replace => *existing = directives,
+                MergeStrategy::Append => existing.extend(directives),
+                MergeStrategy::DeepMerge => {
+                    for directive in directives {
+                        let target = existing.iter_mut().find(|d| d.params == directive.params);
+                        match (target, directive.child) {
+                            (Some(target), Some(child)) => match target.child {
+                                Some(ref mut target_child) => {
+                                    target_child.apply_overlay_with(child, strategy)
+                                }
+                                None => target.child = Some(child),
+                            },
+                            (Some(_), None) => {}
+                            (None, child) => existing.push(Directive { child, ..directive }),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Creates a document from key/value pairs, such as the entries of a map,
+    /// with one directive per pair, named by the key and with the value,
+    /// converted with [`ToString`], as its only parameter.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// # use std::collections::HashMap;
+    /// let mut ports = HashMap::new();
+    /// ports.insert("http", 80);
+    /// ports.insert("https", 443);
+    /// let doc = Scfg::try_from_value_map(ports);
+    /// assert_eq!(doc, "http 80\nhttps 443\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that the keys and values are legal scfg words.
+    pub fn try_from_value_map<K, V, M>(map: M) -> Self
+    where
+        K: Into<String>,
+        V: ToString,
+        M: IntoIterator<Item = (K, V)>,
+    {
+        map.into_iter()
+            .map(|(key, value)| {
+                let directive = Directive {
+                    params: vec![value.to_string()],
+                    child: None,
+                    line: None,
+                };
+                (key, directive)
+            })
+            .collect()
+    }
+
     /// Retrieves the first directive with a particular name.
     ///
     /// This will return `None` if either, the name is not found, or if the name
@@ -93,407 +519,3677 @@ impl Scfg {
         self.directives.get(name).and_then(|d| d.first())
     }
 
-    /// Retrieves the all directives with a particular name.
-    pub fn get_all<Q>(&self, name: &Q) -> Option<&[Directive]>
+    /// Returns a copy of the child block of the first directive with a
+    /// particular name, as a standalone document.
+    ///
+    /// This will return `None` if the name is not found or if its first
+    /// directive has no child block.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "server {\n\tlisten 0.0.0.0:6697\n}\n".parse().unwrap();
+    /// let server = doc.subtree("server").unwrap();
+    /// assert_eq!(server.get("listen").unwrap().params(), ["0.0.0.0:6697"]);
+    /// ```
+    pub fn subtree<Q>(&self, name: &Q) -> Option<Scfg>
     where
         String: Borrow<Q>,
         Q: Ord + Eq + Hash + ?Sized,
     {
-        self.directives.get(name).map(|ds| ds.as_ref())
+        self.get(name).and_then(Directive::child).cloned()
     }
 
-    /// Retrieves a mutable reference to all directives with a particular name.
-    pub fn get_all_mut<Q>(&mut self, name: &Q) -> Option<&mut Vec<Directive>>
+    /// Retrieves the first parameter of the first directive with a particular
+    /// name, parsed as a `T`.
+    ///
+    /// The result distinguishes the three ways a value can be unavailable:
+    ///
+    /// | document                  | result                  |
+    /// |---------------------------|-------------------------|
+    /// | no directive named `name` | `Ok(None)`              |
+    /// | `name` with no parameters | `Ok(Some(None))`        |
+    /// | `name value`              | `Ok(Some(Some(value)))` |
+    /// | `name invalid`            | `Err(error)`            |
+    ///
+    /// so that a directive present without a value, such as a bare flag, is
+    /// not mistaken for a missing one. Parameters after the first are ignored.
+    /// The other typed getters, such as [`get_i64`] and [`get_bool`], follow
+    /// the same rules.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "port 6697\ntls\n".parse().unwrap();
+    /// assert_eq!(doc.get_parsed::<u16, _>("port"), Ok(Some(Some(6697))));
+    /// assert_eq!(doc.get_parsed::<u16, _>("tls"), Ok(Some(None)));
+    /// assert_eq!(doc.get_parsed::<u16, _>("motd"), Ok(None));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns the error of [`FromStr::from_str`] if the parameter cannot be
+    /// parsed as a `T`.
+    ///
+    /// [`get_i64`]: Scfg::get_i64
+    /// [`get_bool`]: Scfg::get_bool
+    pub fn get_parsed<T, Q>(&self, name: &Q) -> Result<Option<Option<T>>, T::Err>
     where
+        T: FromStr,
         String: Borrow<Q>,
         Q: Ord + Eq + Hash + ?Sized,
     {
-        self.directives.get_mut(name)
+        self.get(name).map(|d| d.param_as(0)).transpose()
     }
 
-    /// Does the document contain a directive with `name`.
+    /// Retrieves the first parameter of the first directive with a particular
+    /// name as an `i64`. See [`get_parsed`] for the meaning of the result.
     ///
     /// ```
     /// # use scfg::*;
-    /// let mut scfg = Scfg::new();
-    /// scfg.add("foo");
-    /// assert!(scfg.contains("foo"));
-    /// assert!(!scfg.contains("bar"));
+    /// let doc: Scfg = "port 6697\n".parse().unwrap();
+    /// assert_eq!(doc.get_i64("port"), Ok(Some(Some(6697))));
     /// ```
-    pub fn contains<Q>(&self, name: &Q) -> bool
+    ///
+    /// # Errors
+    /// Returns an error if the parameter is not an integer in range.
+    ///
+    /// [`get_parsed`]: Scfg::get_parsed
+    pub fn get_i64<Q>(&self, name: &Q) -> Result<Option<Option<i64>>, ParseIntError>
     where
         String: Borrow<Q>,
         Q: Ord + Eq + Hash + ?Sized,
     {
-        self.directives.contains_key(name)
+        self.get_parsed(name)
     }
 
-    /// Adds a new name returning the new (empty) directive.
+    /// Retrieves the first parameter of the first directive with a particular
+    /// name as a `bool`, which must be either `true` or `false`. See
+    /// [`get_parsed`] for the meaning of the result.
+    ///
     /// ```
     /// # use scfg::*;
-    /// let mut scfg = Scfg::new();
-    /// let dir = scfg.add("dir1");
-    /// assert_eq!(*dir, Directive::default());
+    /// let doc: Scfg = "tls true\n".parse().unwrap();
+    /// assert_eq!(doc.get_bool("tls"), Ok(Some(Some(true))));
     /// ```
     ///
-    /// # Note
-    /// This does not validate that `name` is a legal scfg word. It is possible to create
-    /// unparsable documents should `name` contain control characters or newlines.
-    pub fn add(&mut self, name: impl Into<String>) -> &mut Directive {
-        self.add_directive(name, Directive::default())
-    }
-
-    fn add_directive(&mut self, name: impl Into<String>, directive: Directive) -> &mut Directive {
-        let entry = self.directives.entry(name.into()).or_insert_with(Vec::new);
-        entry.push(directive);
-        entry.last_mut().unwrap()
-    }
-
-    /// Removes all directives with the supplied name, returning them.
-    pub fn remove<Q>(&mut self, name: &Q) -> Option<Vec<Directive>>
+    /// # Errors
+    /// Returns an error if the parameter is neither `true` nor `false`.
+    ///
+    /// [`get_parsed`]: Scfg::get_parsed
+    pub fn get_bool<Q>(&self, name: &Q) -> Result<Option<Option<bool>>, ParseBoolError>
     where
         String: Borrow<Q>,
         Q: Ord + Eq + Hash + ?Sized,
     {
-        self.directives.remove(name)
+        self.get_parsed(name)
     }
 
-    /// Removes all directives with the supplied name, returning them, and their
-    /// key.
-    pub fn remove_entry<Q>(&mut self, name: &Q) -> Option<(String, Vec<Directive>)>
+    /// Retrieves a directive by following a path of names through nested
+    /// blocks.
+    ///
+    /// Each name is looked up in the child block of the first directive
+    /// matched by the previous name. Returns `None` if `path` is empty, or if
+    /// any name is missing.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "train {\n\tmodel E5\n}\n".parse().unwrap();
+    /// let model = doc.get_path(&["train", "model"]).unwrap();
+    /// assert_eq!(model.params(), ["E5"]);
+    /// assert!(doc.get_path(&["train", "weight"]).is_none());
+    /// ```
+    pub fn get_path<Q>(&self, path: &[Q]) -> Option<&Directive>
     where
-        String: Borrow<Q>,
-        Q: Ord + Eq + Hash + ?Sized,
+        Q: AsRef<str>,
     {
-        self.directives.remove_entry(name)
+        let (last, init) = path.split_last()?;
+        let mut block = self;
+        for name in init {
+            block = block.get(name.as_ref())?.child()?;
+        }
+        block.get(last.as_ref())
     }
 
-    /// Writes the document to the specified writer. If efficiency is a concern,
-    /// it may be best to wrap the writer in a [`BufWriter`] first. This will
-    /// not write any comments that the document had if it was parsed first.
+    /// Retrieves a directive by following a path of names like [`get_path`],
+    /// or `default` if any name is missing.
     ///
-    /// [`BufWriter`]: std::io::BufWriter
-    pub fn write<W>(&self, writer: &mut W) -> io::Result<()>
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "server {\n\tport 6697\n}\n".parse().unwrap();
+    /// let default: Directive = "port 6667".parse().unwrap();
+    /// let port = doc.get_path_or_default(&["server", "port"], &default);
+    /// assert_eq!(port.params(), ["6697"]);
+    /// let host = doc.get_path_or_default(&["server", "host"], &default);
+    /// assert_eq!(host, &default);
+    /// ```
+    ///
+    /// [`get_path`]: Scfg::get_path
+    pub fn get_path_or_default<'a, Q>(&'a self, path: &[Q], default: &'a Directive) -> &'a Directive
     where
-        W: io::Write,
+        Q: AsRef<str>,
     {
-        self.write_with_indent(0, writer)
+        self.get_path(path).unwrap_or(default)
     }
 
-    fn write_with_indent<W>(&self, indent: usize, wtr: &mut W) -> io::Result<()>
+    /// Retrieves every directive matched by a path of names through nested
+    /// blocks.
+    ///
+    /// Unlike [`get_path`], each name is looked up in the child blocks of all
+    /// the directives matched by the previous name. The directives are
+    /// returned in document order, depth first. Returns an empty vector if
+    /// `path` is empty or nothing matches.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "model E5 {\n\tspeed 320\n}\nmodel E7 {\n\tspeed 275\n}\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// let speeds: Vec<_> = doc
+    ///     .get_all_path(&["model", "speed"])
+    ///     .into_iter()
+    ///     .map(|speed| speed.params())
+    ///     .collect();
+    /// assert_eq!(speeds, [["320"], ["275"]]);
+    /// ```
+    ///
+    /// [`get_path`]: Scfg::get_path
+    pub fn get_all_path<Q>(&self, path: &[Q]) -> Vec<&Directive>
     where
-        W: io::Write,
+        Q: AsRef<str>,
     {
-        let mut prefix = "";
-        for (name, directives) in &self.directives {
-            for directive in directives {
-                wtr.write_all(prefix.as_ref())?;
-                prefix = "";
-                for _ in 0..indent {
-                    write!(wtr, "\t")?;
-                }
-                write!(wtr, "{}", shell_words::quote(&name))?;
-                for param in &directive.params {
-                    write!(wtr, " {}", shell_words::quote(&param))?;
-                }
-
-                if let Some(ref child) = directive.child {
-                    wtr.write_all(b" {\n")?;
-                    child.write_with_indent(indent + 1, wtr)?;
-                    for _ in 0..indent {
-                        wtr.write_all(b"\t")?;
-                    }
-                    wtr.write_all(b"}")?;
-                    prefix = "\n";
-                }
-                wtr.write_all(b"\n")?;
-            }
+        let (first, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+        let directives = self.get_all(first.as_ref()).unwrap_or_default();
+        if rest.is_empty() {
+            return directives.iter().collect();
         }
-
-        Ok(())
+        directives
+            .iter()
+            .filter_map(Directive::child)
+            .flat_map(|child| child.get_all_path(rest))
+            .collect()
     }
-}
 
-impl FromStr for Scfg {
-    type Err = ParseError;
-    fn from_str(src: &str) -> Result<Self, Self::Err> {
-        let r = std::io::Cursor::new(src.as_bytes());
-        parser::document(r)
+    /// Retrieves the all directives with a particular name.
+    pub fn get_all<Q>(&self, name: &Q) -> Option<&[Directive]>
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        self.directives.get(name).map(|ds| ds.as_ref())
     }
-}
 
-impl<K: Into<String>> std::iter::FromIterator<(K, Directive)> for Scfg {
-    fn from_iter<T>(it: T) -> Self
+    /// Retrieves a mutable reference to all directives with a particular name.
+    ///
+    /// The returned guard dereferences to the document's own storage, not a
+    /// copy: any change made through it, or through the directives it
+    /// contains, is reflected by every later read of the document, including
+    /// [`write`]. If the vector is left empty, the name is removed from the
+    /// document when the guard is dropped, as if by [`remove`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "listen 0.0.0.0:6697\n".parse().unwrap();
+    /// doc.get_all_mut("listen").unwrap()[0].append_param("tls");
+    /// let mut out = Vec::new();
+    /// doc.write(&mut out).unwrap();
+    /// assert_eq!(out, b"listen 0.0.0.0:6697 tls\n");
+    ///
+    /// doc.get_all_mut("listen").unwrap().clear();
+    /// assert!(!doc.contains("listen"));
+    /// ```
+    ///
+    /// [`remove`]: Scfg::remove
+    /// [`write`]: Scfg::write
+    pub fn get_all_mut<Q>(&mut self, name: &Q) -> Option<DirectivesMut<'_>>
     where
-        T: IntoIterator<Item = (K, Directive)>,
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
     {
-        let mut scfg = Self::default();
-
-        for (name, directive) in it {
-            let name = name.into();
-            scfg.directives
-                .entry(name)
-                .or_insert_with(Vec::new)
-                .push(directive);
+        let (name, _) = self.directives.get_key_value(name)?;
+        match self.directives.entry(name.clone()) {
+            Entry::Occupied(entry) => Some(DirectivesMut { entry: Some(entry) }),
+            Entry::Vacant(_) => unreachable!(),
         }
-
-        scfg
     }
-}
+
+    /// Does the document contain a directive with `name`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut scfg = Scfg::new();
+    /// scfg.add("foo");
+    /// assert!(scfg.contains("foo"));
+    /// assert!(!scfg.contains("bar"));
+    /// ```
+    pub fn contains<Q>(&self, name: &Q) -> bool
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        self.directives.contains_key(name)
+    }
+
+    /// Does the document contain a directive with any of the supplied names.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut scfg = Scfg::new();
+    /// scfg.add("socket");
+    /// assert!(scfg.has_any(&["listen", "socket", "inet"]));
+    /// assert!(!scfg.has_any(&["listen", "inet"]));
+    /// ```
+    pub fn has_any<I>(&self, names: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        names.into_iter().any(|name| self.contains(name.as_ref()))
+    }
+
+    /// Does the document contain a directive for every one of the supplied
+    /// names. This is trivially true if `names` is empty.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut scfg = Scfg::new();
+    /// scfg.add("certificate");
+    /// scfg.add("key");
+    /// assert!(scfg.has_all(&["certificate", "key"]));
+    /// assert!(!scfg.has_all(&["certificate", "key", "ca"]));
+    /// ```
+    pub fn has_all<I>(&self, names: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        names.into_iter().all(|name| self.contains(name.as_ref()))
+    }
+
+    /// Adds a new name returning the new (empty) directive.
+    /// ```
+    /// # use scfg::*;
+    /// let mut scfg = Scfg::new();
+    /// let dir = scfg.add("dir1");
+    /// assert_eq!(*dir, Directive::default());
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that `name` is a legal scfg word. It is possible to create
+    /// unparsable documents should `name` contain control characters or newlines. See
+    /// [`try_add`](Scfg::try_add) for a checked alternative.
+    pub fn add(&mut self, name: impl Into<String>) -> &mut Directive {
+        self.add_directive(name, Directive::default())
+    }
+
+    /// Adds a new name returning the new (empty) directive, like [`add`], but
+    /// checks that `name` can be written first.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut scfg = Scfg::new();
+    /// scfg.try_add("max-speed").unwrap().try_append_param("320km/h").unwrap();
+    /// assert!(scfg.try_add("max\nspeed").is_err());
+    /// assert!(scfg.try_add("").is_err());
+    ///
+    /// let mut out = Vec::new();
+    /// scfg.write(&mut out).unwrap();
+    /// assert_eq!(out, b"max-speed 320km/h\n");
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error, and adds nothing, if `name` is empty or is not a valid
+    /// word, see [`is_valid_word`].
+    ///
+    /// [`add`]: Scfg::add
+    pub fn try_add(&mut self, name: impl Into<String>) -> Result<&mut Directive, InvalidWord> {
+        let name = name.into();
+        word::check_name(&name)?;
+        Ok(self.add(name))
+    }
+
+    /// Adds a new directive with the supplied parameters and a child block,
+    /// which is populated by `body`. Returns the new directive.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut scfg = Scfg::new();
+    /// scfg.add_block("train", ["Shinkansen"], |train| {
+    ///     train.add_block("model", ["E5"], |e5| {
+    ///         e5.add("max-speed").append_param("320km/h");
+    ///         e5.add("weight").append_param("453.5t");
+    ///         e5.add("lines-served")
+    ///             .append_param("Tōhoku")
+    ///             .append_param("Hokkaido");
+    ///     });
+    ///     train.add_block("model", ["E7"], |e7| {
+    ///         e7.add("max-speed").append_param("275km/h");
+    ///         e7.add("weight").append_param("540t");
+    ///         e7.add("lines-served")
+    ///             .append_param("Hokuriku")
+    ///             .append_param("Jōetsu");
+    ///     });
+    /// });
+    ///
+    /// let doc: Scfg = r#"train "Shinkansen" {
+    ///     model "E5" {
+    ///         max-speed 320km/h
+    ///         weight 453.5t
+    ///         lines-served "Tōhoku" "Hokkaido"
+    ///     }
+    ///     model "E7" {
+    ///         max-speed 275km/h
+    ///         weight 540t
+    ///         lines-served "Hokuriku" "Jōetsu"
+    ///     }
+    /// }"#
+    /// .parse()
+    /// .unwrap();
+    /// assert_eq!(scfg, doc);
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that `name` and `params` are legal scfg words.
+    pub fn add_block<I, F>(&mut self, name: impl Into<String>, params: I, body: F) -> &mut Directive
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+        F: FnOnce(&mut Scfg),
+    {
+        let mut child = Scfg::new();
+        body(&mut child);
+        let directive = Directive {
+            params: params.into_iter().map(Into::into).collect(),
+            child: Some(child),
+            line: None,
+        };
+        self.add_directive(name, directive)
+    }
+
+    fn add_directive(&mut self, name: impl Into<String>, directive: Directive) -> &mut Directive {
+        let entry = self.directives.entry(name.into()).or_default();
+        entry.push(directive);
+        entry.last_mut().unwrap()
+    }
+
+    /// Replaces the directives at the end of a path of names through nested
+    /// blocks with `directive`, returning the replaced directives, if any.
+    ///
+    /// Like [`get_path`], each name but the last is looked up in the child
+    /// block of the first directive matched by the previous name. If a name is
+    /// missing, a directive without parameters is created for it, and if a
+    /// matched directive has no child block, an empty one is created.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "server {\n\tport 80\n}\n".parse().unwrap();
+    /// let mut port = Directive::new();
+    /// port.append_param("8080");
+    /// let old = doc.replace_path(&["server", "port"], port).unwrap();
+    /// assert_eq!(old[0].params(), ["80"]);
+    /// assert_eq!(doc.get_path(&["server", "port"]).unwrap().params(), ["8080"]);
+    /// ```
+    ///
+    /// An empty path names no directive, so the document is then unchanged and
+    /// `None` is returned.
+    ///
+    /// [`get_path`]: Scfg::get_path
+    pub fn replace_path<Q>(&mut self, path: &[Q], directive: Directive) -> Option<Vec<Directive>>
+    where
+        Q: AsRef<str>,
+    {
+        let (last, init) = path.split_last()?;
+        let mut block = self;
+        for name in init {
+            let name = name.as_ref();
+            if block.get(name).is_none() {
+                block.add(name);
+            }
+            block = block.directives.get_mut(name).unwrap()[0].get_or_create_child();
+        }
+        block
+            .directives
+            .insert(last.as_ref().to_owned(), vec![directive])
+    }
+
+    /// Removes all directives with the supplied name, returning them.
+    pub fn remove<Q>(&mut self, name: &Q) -> Option<Vec<Directive>>
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        self.directives.remove(name)
+    }
+
+    /// Removes the last directive with the supplied name, returning it. The
+    /// name is removed from the document along with its last directive.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "listen 80\nlisten 443\n".parse().unwrap();
+    /// assert_eq!(doc.pop_directive("listen").unwrap().params(), ["443"]);
+    /// assert_eq!(doc.get_all("listen").unwrap().len(), 1);
+    /// ```
+    pub fn pop_directive<Q>(&mut self, name: &Q) -> Option<Directive>
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        self.get_all_mut(name)?.pop()
+    }
+
+    /// Removes the directive at `index` among those with the supplied name,
+    /// returning it. The other directives keep their order, and the name is
+    /// removed from the document along with its last directive.
+    ///
+    /// Returns `None` if the name is not found or `index` is out of range.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "listen 80\nlisten 443\nlisten 8080\n".parse().unwrap();
+    /// assert_eq!(doc.remove_at("listen", 1).unwrap().params(), ["443"]);
+    /// assert_eq!(doc, "listen 80\nlisten 8080\n".parse().unwrap());
+    /// ```
+    pub fn remove_at<Q>(&mut self, name: &Q, index: usize) -> Option<Directive>
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        let mut directives = self.get_all_mut(name)?;
+        if index < directives.len() {
+            Some(directives.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Inserts `directive` at `index` among the directives with the supplied
+    /// name, shifting the later ones. Returns the inserted directive.
+    ///
+    /// An `index` past the end of the group is clamped to its length, so the
+    /// directive is appended, as with [`add`]. If the name is not found, it is
+    /// added to the document with `directive` as its only directive.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "rule allow\nrule deny\n".parse().unwrap();
+    /// doc.insert_at("rule", 1, "rule log".parse().unwrap());
+    /// assert_eq!(doc, "rule allow\nrule log\nrule deny\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that `name` and the directive's parameters are
+    /// legal scfg words.
+    ///
+    /// [`add`]: Scfg::add
+    pub fn insert_at(
+        &mut self,
+        name: impl Into<String>,
+        index: usize,
+        directive: Directive,
+    ) -> &mut Directive {
+        let directives = self.directives.entry(name.into()).or_default();
+        let index = index.min(directives.len());
+        directives.insert(index, directive);
+        &mut directives[index]
+    }
+
+    /// Swaps the directives at `i` and `j` among those with the supplied name.
+    /// Returns `false`, leaving the document unchanged, if the name is not
+    /// found or either index is out of range.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "rule allow\nrule deny\n".parse().unwrap();
+    /// assert!(doc.swap("rule", 0, 1));
+    /// assert_eq!(doc, "rule deny\nrule allow\n".parse().unwrap());
+    /// ```
+    pub fn swap<Q>(&mut self, name: &Q, i: usize, j: usize) -> bool
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        match self.directives.get_mut(name) {
+            Some(directives) if i < directives.len() && j < directives.len() => {
+                directives.swap(i, j);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sorts the directives with the supplied name, see [`Directive`]'s
+    /// implementation of [`Ord`]. The sort is stable.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "allow 10.0.0.0/8\nallow 10.0.0.0/16\n".parse().unwrap();
+    /// doc.sort_directives("allow");
+    /// assert_eq!(doc, "allow 10.0.0.0/16\nallow 10.0.0.0/8\n".parse().unwrap());
+    /// ```
+    pub fn sort_directives<Q>(&mut self, name: &Q)
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        self.sort_directives_by(name, Directive::cmp);
+    }
+
+    /// Sorts the directives with the supplied name with `compare`. The sort is
+    /// stable, so directives comparing equal keep their order.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "route a 20\nroute b 5\n".parse().unwrap();
+    /// doc.sort_directives_by("route", |a, b| {
+    ///     let metric = |d: &Directive| d.params()[1].parse::<u32>().unwrap();
+    ///     metric(a).cmp(&metric(b))
+    /// });
+    /// assert_eq!(doc, "route b 5\nroute a 20\n".parse().unwrap());
+    /// ```
+    pub fn sort_directives_by<Q, F>(&mut self, name: &Q, compare: F)
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+        F: FnMut(&Directive, &Directive) -> Ordering,
+    {
+        if let Some(directives) = self.directives.get_mut(name) {
+            directives.sort_by(compare);
+        }
+    }
+
+    /// Sorts the directives with the supplied name like [`sort_directives`],
+    /// at every level of nesting. Child blocks are sorted before the
+    /// directives holding them, so that those are compared by their sorted
+    /// children.
+    ///
+    /// [`sort_directives`]: Scfg::sort_directives
+    pub fn sort_directives_recursive<Q>(&mut self, name: &Q)
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        for directive in self.directives.values_mut().flatten() {
+            if let Some(ref mut child) = directive.child {
+                child.sort_directives_recursive(name);
+            }
+        }
+        self.sort_directives(name);
+    }
+
+    /// Removes all directives with the supplied name, returning them, and their
+    /// key.
+    pub fn remove_entry<Q>(&mut self, name: &Q) -> Option<(String, Vec<Directive>)>
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq + Hash + ?Sized,
+    {
+        self.directives.remove_entry(name)
+    }
+
+    /// Releases memory the document no longer needs, at every level of
+    /// nesting: the excess capacity of directive and parameter vectors is
+    /// freed, for example after removing directives through [`get_all_mut`],
+    /// and any name left without directives is removed.
+    ///
+    /// This never changes what the document contains or how it is written,
+    /// and is only worth calling on long lived documents after heavy editing.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "listen 80\nlisten 443\n".parse().unwrap();
+    /// doc.get_all_mut("listen").unwrap().truncate(1);
+    /// doc.optimize_memory();
+    /// assert_eq!(doc.get_all("listen").unwrap().len(), 1);
+    /// ```
+    ///
+    /// [`get_all_mut`]: Scfg::get_all_mut
+    pub fn optimize_memory(&mut self) {
+        self.directives
+            .retain(|_, directives| !directives.is_empty());
+        for directives in self.directives.values_mut() {
+            directives.shrink_to_fit();
+            for directive in directives {
+                directive.params.shrink_to_fit();
+                if let Some(ref mut child) = directive.child {
+                    child.optimize_memory();
+                }
+            }
+        }
+    }
+
+    /// Renames every directive in the document, at every level of nesting, to
+    /// the name returned by `f` for its current name.
+    ///
+    /// If several names in the same block are mapped to the same new name, the
+    /// directives are merged into a single group, in the order [`write`] would
+    /// have written them before the rename.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "a 1\nb 2 {\n\ta 3\n}\n".parse().unwrap();
+    /// doc.map_names_recursive(&mut |name| name.to_uppercase());
+    /// assert_eq!(doc, "A 1\nB 2 {\n\tA 3\n}\n".parse().unwrap());
+    /// ```
+    ///
+    /// [`write`]: Scfg::write
+    pub fn map_names_recursive<F>(&mut self, f: &mut F)
+    where
+        F: FnMut(&str) -> String,
+    {
+        for (name, mut directives) in std::mem::take(&mut self.directives) {
+            let name = f(&name);
+            for directive in &mut directives {
+                if let Some(ref mut child) = directive.child {
+                    child.map_names_recursive(f);
+                }
+            }
+            self.directives.entry(name).or_default().extend(directives);
+        }
+    }
+
+    /// Renames every directive in the document, at every level of nesting, to
+    /// the name returned by `f` for its current name.
+    ///
+    /// This is [`map_names_recursive`] taking the closure by value. Within a
+    /// block, directives whose new names collide are merged into one group:
+    /// the groups are appended in the order of their old names, as [`write`]
+    /// would list them, and each group keeps its own order. Blocks are never
+    /// merged with each other.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "Listen 80\nlisten 443\n".parse().unwrap();
+    /// doc.map_names(|name| name.to_lowercase());
+    /// assert_eq!(doc.get_all("listen").unwrap().len(), 2);
+    /// ```
+    ///
+    /// [`map_names_recursive`]: Scfg::map_names_recursive
+    /// [`write`]: Scfg::write
+    pub fn map_names(&mut self, mut f: impl FnMut(&str) -> String) {
+        self.map_names_recursive(&mut f);
+    }
+
+    /// Consumes the document, passing each top-level directive and its name
+    /// to `f`, and builds a new document from the pairs it returns. Directives
+    /// for which `f` returns `None` are dropped.
+    ///
+    /// Directives are moved into `f` rather than cloned, and each group of
+    /// directives sharing a name is released once it has been processed, so
+    /// the transformation never holds two full copies of the document. Child
+    /// blocks are passed along with their directive, and `f` may transform
+    /// them in turn.
+    ///
+    /// Directives are visited in the order [`write`] would write them: by
+    /// name, then in document order within a name. A returned directive is
+    /// appended to the group of its returned name, after those already
+    /// returned for that name.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "debug true\nport 80\nport 443\n".parse().unwrap();
+    /// let doc = doc.transform(|name, directive| match name.as_str() {
+    ///     "debug" => None,
+    ///     "port" => Some(("listen".to_owned(), directive)),
+    ///     _ => Some((name, directive)),
+    /// });
+    /// assert_eq!(doc, "listen 80\nlisten 443\n".parse().unwrap());
+    /// ```
+    ///
+    /// [`write`]: Scfg::write
+    pub fn transform<F>(self, mut f: F) -> Scfg
+    where
+        F: FnMut(String, Directive) -> Option<(String, Directive)>,
+    {
+        let mut transformed = Scfg::new();
+        for (name, directives) in self.directives {
+            for directive in directives {
+                if let Some((name, directive)) = f(name.clone(), directive) {
+                    transformed.add_directive(name, directive);
+                }
+            }
+        }
+        transformed
+    }
+
+    /// Consumes the document, splitting its top-level directives into those
+    /// for which `predicate` returns `true` and the others.
+    ///
+    /// Directives are moved rather than cloned, along with their child blocks,
+    /// and keep their relative order within each part. Appending the second
+    /// part to the first gives back a document equal to this one.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "listen :80\nplugin-auth {\n\ttoken x\n}\n".parse().unwrap();
+    /// let (plugins, core) = doc.partition(|name, _| name.starts_with("plugin-"));
+    /// assert_eq!(core, "listen :80\n".parse().unwrap());
+    /// assert_eq!(plugins, "plugin-auth {\n\ttoken x\n}\n".parse().unwrap());
+    /// ```
+    pub fn partition<F>(self, predicate: F) -> (Scfg, Scfg)
+    where
+        F: Fn(&str, &Directive) -> bool,
+    {
+        let mut matching = Scfg::new();
+        let mut rest = Scfg::new();
+        for (name, directives) in self.directives {
+            let (yes, no): (Vec<_>, Vec<_>) =
+                directives.into_iter().partition(|d| predicate(&name, d));
+            match (yes.is_empty(), no.is_empty()) {
+                (false, false) => {
+                    matching.directives.insert(name.clone(), yes);
+                    rest.directives.insert(name, no);
+                }
+                (false, true) => {
+                    matching.directives.insert(name, yes);
+                }
+                (true, false) => {
+                    rest.directives.insert(name, no);
+                }
+                (true, true) => {}
+            }
+        }
+        (matching, rest)
+    }
+
+    /// Consumes the document, splitting its top-level directives into those
+    /// named in `names` and the others, see [`partition`].
+    ///
+    /// [`partition`]: Scfg::partition
+    pub fn partition_names(self, names: &[&str]) -> (Scfg, Scfg) {
+        self.partition(|name, _| names.contains(&name))
+    }
+
+    /// Renames the directives listed in `renames`, at every level of nesting.
+    ///
+    /// `renames` maps old names to new names, and can be a
+    /// `HashMap<&str, &str>` or a slice or array of `(old, new)` pairs. Each
+    /// name is looked up once, so renames are not chained, and names that are
+    /// not listed are left unchanged. Directives renamed to the same name are
+    /// merged as in [`map_names_recursive`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "max-speed 320km/h\nweight 453.5t\n".parse().unwrap();
+    /// doc.apply_rename_map(&[("max-speed", "maxSpeed")]);
+    /// assert_eq!(doc, "maxSpeed 320km/h\nweight 453.5t\n".parse().unwrap());
+    /// ```
+    ///
+    /// [`map_names_recursive`]: Scfg::map_names_recursive
+    pub fn apply_rename_map<M>(&mut self, renames: &M)
+    where
+        M: RenameMap + ?Sized,
+    {
+        self.map_names_recursive(&mut |name| renames.renamed(name).unwrap_or(name).to_owned());
+    }
+
+    /// Removes the child block of every directive whose block holds no
+    /// directives, at every level of nesting.
+    ///
+    /// This undoes [`Directive::get_or_create_child`] for blocks that ended up
+    /// empty. Directives are never removed, so a block holding only directives
+    /// whose blocks were removed is kept.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "a {\n}\nb {\n\tc {\n\t}\n}\n".parse().unwrap();
+    /// doc.remove_all_empty_children();
+    /// assert_eq!(doc, "a\nb {\n\tc\n}\n".parse().unwrap());
+    /// ```
+    pub fn remove_all_empty_children(&mut self) {
+        for directive in self.directives.values_mut().flatten() {
+            if let Some(ref mut child) = directive.child {
+                child.remove_all_empty_children();
+                if child.directives.values().all(Vec::is_empty) {
+                    directive.child = None;
+                }
+            }
+        }
+    }
+
+    /// Keeps only the directives for which `predicate` returns `true`, at
+    /// every level of nesting, and removes the names left without directives.
+    ///
+    /// `predicate` is given the names of the directives leading to the
+    /// directive, including its own name, and the directive. Directives are
+    /// considered before their child blocks, so the directives of a removed
+    /// block are never considered. With `prune_empty`, the child blocks left
+    /// empty by the removals are removed too, like in
+    /// [`remove_all_empty_children`], but blocks which were already empty are
+    /// kept.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "debug\nexperimental {\n}\nlisten :80 {\n\tdebug\n}\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// doc.retain_recursive(true, |path, _| {
+    ///     path[0] != "experimental" && path.last() != Some(&"debug")
+    /// });
+    /// assert_eq!(doc, "listen :80\n".parse().unwrap());
+    /// ```
+    ///
+    /// [`remove_all_empty_children`]: Scfg::remove_all_empty_children
+    pub fn retain_recursive<F>(&mut self, prune_empty: bool, mut predicate: F)
+    where
+        F: FnMut(&[&str], &Directive) -> bool,
+    {
+        self.retain_at(&[], prune_empty, &mut predicate);
+    }
+
+    /// Retains the directives of the block at `path`, see [`retain_recursive`].
+    ///
+    /// [`retain_recursive`]: Scfg::retain_recursive
+    fn retain_at<F>(&mut self, path: &[&str], prune_empty: bool, predicate: &mut F)
+    where
+        F: FnMut(&[&str], &Directive) -> bool,
+    {
+        for (name, directives) in self.directives.iter_mut() {
+            let mut path = path.to_vec();
+            path.push(name);
+            directives.retain(|directive| predicate(&path, directive));
+            for directive in directives.iter_mut() {
+                if let Some(ref mut child) = directive.child {
+                    let was_empty = child.directives.values().all(Vec::is_empty);
+                    child.retain_at(&path, prune_empty, predicate);
+                    if prune_empty && !was_empty && child.directives.is_empty() {
+                        directive.child = None;
+                    }
+                }
+            }
+        }
+        self.directives
+            .retain(|_, directives| !directives.is_empty());
+    }
+
+    /// Replaces every parameter equal to `from` with `to`, at every level of
+    /// nesting, and returns the number of parameters replaced. Directive names
+    /// are left unchanged.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "upstream {\n\tserver old.example.com\n}\n".parse().unwrap();
+    /// assert_eq!(doc.replace_param_values("old.example.com", "new.example.com"), 1);
+    /// assert_eq!(
+    ///     doc.get_path(&["upstream", "server"]).unwrap().params(),
+    ///     ["new.example.com"]
+    /// );
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that `to` is a legal scfg word.
+    pub fn replace_param_values(&mut self, from: &str, to: &str) -> usize {
+        self.replace_param_values_with(|_, param| (param == from).then(|| to.to_owned()))
+    }
+
+    /// Replaces parameters with the value returned by `f`, at every level of
+    /// nesting, and returns the number of parameters replaced.
+    ///
+    /// `f` is called with the name of the directive and each of its
+    /// parameters, in the order [`write`] would write them, and returns the
+    /// replacement, or `None` to keep the parameter. Directive names are left
+    /// unchanged.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "certificate /etc/old/cert.pem\nroot /etc/old\n".parse().unwrap();
+    /// let replaced = doc.replace_param_values_with(|name, param| match name {
+    ///     "certificate" => Some(param.replace("/old/", "/new/")),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(replaced, 1);
+    /// assert_eq!(doc, "certificate /etc/new/cert.pem\nroot /etc/old\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that the replacements are legal scfg words.
+    ///
+    /// [`write`]: Scfg::write
+    pub fn replace_param_values_with<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&str, &str) -> Option<String>,
+    {
+        self.replace_params_recursive(&mut f)
+    }
+
+    fn replace_params_recursive<F>(&mut self, f: &mut F) -> usize
+    where
+        F: FnMut(&str, &str) -> Option<String>,
+    {
+        let mut replaced = 0;
+        for (name, directives) in &mut self.directives {
+            for directive in directives {
+                for param in &mut directive.params {
+                    if let Some(replacement) = f(name, param) {
+                        *param = replacement;
+                        replaced += 1;
+                    }
+                }
+                if let Some(ref mut child) = directive.child {
+                    replaced += child.replace_params_recursive(f);
+                }
+            }
+        }
+        replaced
+    }
+
+    /// Returns the total number of parameters of all directives in the
+    /// document, at every level of nesting.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "train Shinkansen {\n\tlines-served Tōhoku Hokkaido\n}\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(doc.count_total_params(), 3);
+    /// ```
+    pub fn count_total_params(&self) -> usize {
+        self.fold(0, |count, _, directive, _| count + directive.params.len())
+    }
+
+    /// Returns the maximum nesting depth of blocks in the document: 0 if no
+    /// directive has a block, 1 if some do but their blocks have no blocks of
+    /// their own, and so on. Empty blocks count.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "train {\n\tmodel E5 {\n\t}\n}\n".parse().unwrap();
+    /// assert_eq!(doc.depth(), 2);
+    /// ```
+    pub fn depth(&self) -> usize {
+        self.fold(0, |max, _, directive, depth| match directive.child {
+            Some(_) => max.max(depth + 1),
+            None => max,
+        })
+    }
+
+    /// Returns the number of distinct names of the top-level directives.
+    pub fn name_count(&self) -> usize {
+        self.directives.values().filter(|d| !d.is_empty()).count()
+    }
+
+    /// Returns the number of top-level directives, counting every directive
+    /// of each name.
+    pub fn total_directive_count(&self) -> usize {
+        self.directives.values().map(Vec::len).sum()
+    }
+
+    /// Returns a single line summary of the document, for logging: its
+    /// [`name_count`], [`total_directive_count`] and [`depth`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "listen :80\nlisten :443\nserver {\n}\n".parse().unwrap();
+    /// assert_eq!(doc.summarize(), "Scfg { 2 names, 3 directives, depth 1 }");
+    /// ```
+    ///
+    /// [`name_count`]: Scfg::name_count
+    /// [`total_directive_count`]: Scfg::total_directive_count
+    /// [`depth`]: Scfg::depth
+    pub fn summarize(&self) -> String {
+        let plural = |n| if n == 1 { "" } else { "s" };
+        let (names, directives) = (self.name_count(), self.total_directive_count());
+        format!(
+            "Scfg {{ {} name{}, {} directive{}, depth {} }}",
+            names,
+            plural(names),
+            directives,
+            plural(directives),
+            self.depth()
+        )
+    }
+
+    /// Returns the distinct names of the directives of the document, at every
+    /// level of nesting.
+    pub fn collect_all_names(&self) -> HashSet<String> {
+        self.fold(HashSet::new(), |mut names, name, _, _| {
+            if !names.contains(name) {
+                names.insert(name.to_owned());
+            }
+            names
+        })
+    }
+
+    /// Counts the directives of the document with each name, at every level
+    /// of nesting.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "a {\n\ta\n\tb\n}\n".parse().unwrap();
+    /// let counts = doc.collect_all_names_with_count();
+    /// assert_eq!(counts["a"], 2);
+    /// assert_eq!(counts["b"], 1);
+    /// ```
+    pub fn collect_all_names_with_count(&self) -> HashMap<String, usize> {
+        self.fold(HashMap::new(), |mut counts, name, _, _| {
+            match counts.get_mut(name) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(name.to_owned(), 1);
+                }
+            }
+            counts
+        })
+    }
+
+    /// Flattens the leaf directives (those without a child block) of the
+    /// document into environment variable style `(name, value)` pairs.
+    ///
+    /// The variable name is the path of directive names leading to the leaf,
+    /// starting with `prefix` if it is not empty, joined with `_`. It is
+    /// uppercased, and any character that is not an ASCII letter or digit is
+    /// replaced with `_`. The value is the directive's parameters joined with
+    /// spaces. The parameters of block directives are not included, so
+    /// repeated blocks produce repeated names; pairs are returned in the order
+    /// [`write`] would write the directives.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "train {\n\tmax-speed 320km/h\n}\n".parse().unwrap();
+    /// assert_eq!(
+    ///     doc.to_env_vars("app"),
+    ///     [("APP_TRAIN_MAX_SPEED".to_owned(), "320km/h".to_owned())]
+    /// );
+    /// ```
+    ///
+    /// [`write`]: Scfg::write
+    pub fn to_env_vars(&self, prefix: &str) -> Vec<(String, String)> {
+        let mut vars = Vec::new();
+        let mut path = Vec::new();
+        if !prefix.is_empty() {
+            path.push(prefix);
+        }
+        self.collect_env_vars(&mut path, &mut vars);
+        vars
+    }
+
+    fn collect_env_vars<'a>(&'a self, path: &mut Vec<&'a str>, vars: &mut Vec<(String, String)>) {
+        for (name, directives) in &self.directives {
+            path.push(name);
+            for directive in directives {
+                match directive.child {
+                    Some(ref child) => child.collect_env_vars(path, vars),
+                    None => {
+                        let key = path
+                            .join("_")
+                            .chars()
+                            .map(|c| {
+                                if c.is_ascii_alphanumeric() {
+                                    c.to_ascii_uppercase()
+                                } else {
+                                    '_'
+                                }
+                            })
+                            .collect();
+                        vars.push((key, directive.params.join(" ")));
+                    }
+                }
+            }
+            path.pop();
+        }
+    }
+
+    /// Writes the document to the specified writer. If efficiency is a concern,
+    /// it may be best to wrap the writer in a [`BufWriter`] first. This will
+    /// not write any comments that the document had if it was parsed first.
+    ///
+    /// Names and parameters are quoted with [`quote_word`].
+    ///
+    /// # Errors
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if a name or
+    /// parameter cannot be written, see [`is_valid_word`], or any error
+    /// returned by `writer`.
+    ///
+    /// [`BufWriter`]: std::io::BufWriter
+    pub fn write<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.write_with_options(writer, &WriteOptions::default())
+    }
+
+    /// Writes the document to the specified writer like [`write`], with a
+    /// blank line between the groups of directives sharing a name, at every
+    /// level of nesting.
+    ///
+    /// Groups are sorted by name, or, if the `preserve_order` feature is
+    /// enabled, ordered by the first appearance of each name. In both cases,
+    /// directives that were interleaved in the source document are written
+    /// next to each other.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "a 1\nb 2\na 3\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_grouped(&mut out).unwrap();
+    /// assert_eq!(out, b"a 1\na 3\n\nb 2\n");
+    /// ```
+    ///
+    /// # Errors
+    /// Returns the same errors as [`write`].
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_grouped<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let options = WriteOptions {
+            separate_groups: true,
+            ..Default::default()
+        };
+        self.write_with_options(writer, &options)
+    }
+
+    /// Writes the document to the specified writer with every line commented
+    /// out, by prefixing it with `# `.
+    ///
+    /// The output parses to an empty document. Stripping the `# ` prefix from
+    /// every line gives back the output of [`write`].
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_with_comment_prefix<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let mut buf = Vec::new();
+        self.write(&mut buf)?;
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            writer.write_all(b"# ")?;
+            writer.write_all(line)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a canonical representation of the document, such that two
+    /// documents are equal if and only if their canonical forms are identical.
+    ///
+    /// The canonical form is written like [`write`] would, except that names
+    /// are always sorted, there are no blank lines, and every name and
+    /// parameter is enclosed in double quotes. It parses back to a document
+    /// equal to this one, unless a name or parameter contains a newline.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "b 'x y'\na {\n\tc\n}\n".parse().unwrap();
+    /// assert_eq!(doc.canonical_form(), "\"a\" {\n\t\"c\"\n}\n\"b\" \"x y\"\n");
+    /// ```
+    ///
+    /// [`write`]: Scfg::write
+    pub fn canonical_form(&self) -> String {
+        let mut out = String::new();
+        self.write_canonical(0, &mut out);
+        out
+    }
+
+    fn write_canonical(&self, indent: usize, out: &mut String) {
+        fn push_quoted(out: &mut String, word: &str) {
+            out.push('"');
+            for c in word.chars() {
+                if c == '"' || c == '\\' {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push('"');
+        }
+
+        let mut names: Vec<_> = self.directives.iter().collect();
+        names.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        for (name, directives) in names {
+            for directive in directives {
+                for _ in 0..indent {
+                    out.push('\t');
+                }
+                push_quoted(out, name);
+                for param in &directive.params {
+                    out.push(' ');
+                    push_quoted(out, param);
+                }
+                if let Some(ref child) = directive.child {
+                    out.push_str(" {\n");
+                    child.write_canonical(indent + 1, out);
+                    for _ in 0..indent {
+                        out.push('\t');
+                    }
+                    out.push('}');
+                }
+                out.push('\n');
+            }
+        }
+    }
+
+    /// Returns a copy of the document with its names sorted at every level of
+    /// nesting, and without the line numbers recorded by the parser.
+    ///
+    /// The copy is equal to the document, but is written the same way
+    /// regardless of the `preserve_order` feature, and regardless of how the
+    /// document was built.
+    pub fn normalize(&self) -> Scfg {
+        let mut directives: Vec<_> = self
+            .directives
+            .iter()
+            .map(|(name, directives)| {
+                let directives = directives
+                    .iter()
+                    .map(|directive| Directive {
+                        params: directive.params.clone(),
+                        child: directive.child.as_ref().map(Scfg::normalize),
+                        line: None,
+                    })
+                    .collect::<Vec<_>>();
+                (name.clone(), directives)
+            })
+            .collect();
+        directives.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        Scfg {
+            directives: directives.into_iter().collect(),
+        }
+    }
+
+    /// Writes the document and parses the output back, returning the parsed
+    /// document.
+    ///
+    /// The result is equal to the document, with the line numbers of its
+    /// directives in the output of [`write`]. This normalizes a document built
+    /// in code as if it had been read from a file, and checks that it can be
+    /// written and read back: parsing always succeeds for a document whose
+    /// names and parameters are valid words, see [`is_valid_word`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc = Scfg::new();
+    /// doc.add("model").append_param("E5");
+    /// let reparsed = doc.reparse().unwrap();
+    /// assert_eq!(reparsed, doc);
+    /// assert_eq!(reparsed.get("model").unwrap().line(), Some(1));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the document can't be written, or if its output
+    /// can't be parsed.
+    ///
+    /// [`write`]: Scfg::write
+    pub fn reparse(&self) -> Result<Scfg, ParseError> {
+        let mut out = Vec::new();
+        self.write(&mut out).map_err(ParseError::unwritable)?;
+        parser::document(out.as_slice())
+    }
+
+    /// Writes the document to the specified writer like [`write`], with the
+    /// output tweaked by `options`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "flags verbose color ascii\n".parse().unwrap();
+    /// let options = WriteOptions {
+    ///     sort_params: true,
+    ///     ..Default::default()
+    /// };
+    /// let mut out = Vec::new();
+    /// doc.write_with_options(&mut out, &options).unwrap();
+    /// assert_eq!(out, b"flags ascii color verbose\n");
+    /// ```
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_with_options<W>(&self, writer: &mut W, options: &WriteOptions) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.write_ordered(writer, options, None)
+    }
+
+    /// Writes the document to the specified writer like [`write`], with the
+    /// directives of each name group sorted by `key_fn`, at every level of
+    /// nesting, without changing the document.
+    ///
+    /// The sort is stable, so directives with equal keys are written in
+    /// document order. Groups themselves are written in the usual order.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "server 10.0.0.2\nserver 10.0.0.1\n".parse().unwrap();
+    /// let mut out = Vec::new();
+    /// doc.write_with_directive_sort(|d| d.params().first().cloned(), &mut out)
+    ///     .unwrap();
+    /// assert_eq!(out, b"server 10.0.0.1\nserver 10.0.0.2\n");
+    /// ```
+    ///
+    /// [`write`]: Scfg::write
+    pub fn write_with_directive_sort<F, K, W>(&self, key_fn: F, writer: &mut W) -> io::Result<()>
+    where
+        F: Fn(&Directive) -> K,
+        K: Ord,
+        W: io::Write,
+    {
+        let order = |a: &Directive, b: &Directive| key_fn(a).cmp(&key_fn(b));
+        self.write_ordered(writer, &WriteOptions::default(), Some(&order))
+    }
+
+    /// Writes the document with `options`, sorting the directives of each
+    /// group by `order`, if any.
+    fn write_ordered<W>(
+        &self,
+        writer: &mut W,
+        options: &WriteOptions,
+        order: Option<&DirectiveOrder<'_>>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::debug_span!(
+                "write",
+                bytes = tracing::field::Empty,
+                lines = tracing::field::Empty,
+            )
+            .entered();
+            let mut writer = instrument::Counting::new(writer);
+            let result = self.write_with_indent(&mut Vec::new(), &mut writer, options, order);
+            span.record("bytes", writer.bytes())
+                .record("lines", writer.lines());
+            result
+        }
+        #[cfg(not(feature = "tracing"))]
+        self.write_with_indent(&mut Vec::new(), writer, options, order)
+    }
+
+    /// Writes the block at `path`, the names of the directives leading to it.
+    fn write_with_indent<'a, W>(
+        &'a self,
+        path: &mut Vec<&'a str>,
+        wtr: &mut W,
+        options: &WriteOptions,
+        order: Option<&DirectiveOrder<'_>>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let indent = path.len();
+        let mut prefix = "";
+        for (name, directives) in &self.directives {
+            path.push(name);
+            let separate = options.separate_groups && !directives.is_empty();
+            // only allocate when the group is to be sorted
+            let (directives, sorted) = match order {
+                Some(order) => {
+                    let mut sorted: Vec<_> = directives.iter().collect();
+                    sorted.sort_by(|a, b| order(a, b));
+                    (&[][..], sorted)
+                }
+                None => (&directives[..], Vec::new()),
+            };
+            for directive in directives.iter().chain(sorted) {
+                wtr.write_all(prefix.as_ref())?;
+                prefix = "";
+                if let Some(ref comment_for) = options.comment_for {
+                    if let Some(comment) = (comment_for.0)(path, directive) {
+                        for line in comment.lines() {
+                            for _ in 0..indent {
+                                write!(wtr, "\t")?;
+                            }
+                            match line {
+                                "" => writeln!(wtr, "#")?,
+                                line => writeln!(wtr, "# {}", line)?,
+                            }
+                        }
+                    }
+                }
+                let mut params: Vec<_> = directive.params.iter().collect();
+                if options.sort_params {
+                    params.sort_unstable();
+                }
+                let mut words = Vec::with_capacity(params.len() + 1);
+                words.push(word::quote_with(name, options)?);
+                for param in params {
+                    words.push(word::quote_with(param, options)?);
+                }
+                let compact = options.compact_empty_blocks
+                    && matches!(directive.child, Some(ref child) if child.directives.values().all(Vec::is_empty));
+                let brace = match directive.child {
+                    Some(_) if compact => " {}",
+                    Some(_) => " {",
+                    None => "",
+                };
+                write_wrapped(wtr, indent, &words, brace, options.max_width)?;
+
+                if let Some(ref child) = directive.child {
+                    if compact {
+                        wtr.write_all(b"\n")?;
+                        prefix = "\n";
+                        continue;
+                    }
+                    wtr.write_all(b"\n")?;
+                    #[cfg(feature = "tracing")]
+                    let _span =
+                        tracing::debug_span!("block", name = %name, depth = indent + 1).entered();
+                    child.write_with_indent(path, wtr, options, order)?;
+                    for _ in 0..indent {
+                        wtr.write_all(b"\t")?;
+                    }
+                    wtr.write_all(b"}")?;
+                    prefix = "\n";
+                }
+                wtr.write_all(b"\n")?;
+            }
+            if separate {
+                prefix = "\n";
+            }
+            path.pop();
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of columns of a tab, for [`WriteOptions::max_width`].
+const TAB_WIDTH: usize = 8;
+
+/// Writes the words of a directive, indented by `indent` tabs and followed by
+/// `brace`, wrapped onto continuation lines to fit in `max_width` columns, if
+/// any, see [`WriteOptions::max_width`].
+fn write_wrapped<W: io::Write>(
+    wtr: &mut W,
+    indent: usize,
+    words: &[Cow<'_, str>],
+    brace: &str,
+    max_width: Option<usize>,
+) -> io::Result<()> {
+    let width = |word: &str| word.chars().count();
+    let line_width: usize = words.iter().map(|word| width(word) + 1).sum::<usize>() - 1;
+    let max_width = match max_width {
+        Some(max_width) if TAB_WIDTH * indent + line_width + brace.len() > max_width => max_width,
+        _ => {
+            wtr.write_all("\t".repeat(indent).as_bytes())?;
+            wtr.write_all(words.join(" ").as_bytes())?;
+            return wtr.write_all(brace.as_bytes());
+        }
+    };
+    wtr.write_all("\t".repeat(indent).as_bytes())?;
+    let mut column = TAB_WIDTH * indent;
+    for (i, word) in words.iter().enumerate() {
+        // what must still fit on the line after the word
+        let rest = if i + 1 == words.len() { brace.len() } else { 2 };
+        if i > 0 && column + 1 + width(word) + rest > max_width {
+            wtr.write_all(b" \\\n")?;
+            wtr.write_all("\t".repeat(indent + 1).as_bytes())?;
+            column = TAB_WIDTH * (indent + 1);
+        } else if i > 0 {
+            wtr.write_all(b" ")?;
+            column += 1;
+        }
+        wtr.write_all(word.as_bytes())?;
+        column += width(word);
+    }
+    wtr.write_all(brace.as_bytes())
+}
+
+impl Hash for Scfg {
+    #[cfg(not(feature = "preserve_order"))]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.directives.hash(state);
+    }
+
+    #[cfg(feature = "preserve_order")]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted_entries().hash(state);
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl Scfg {
+    /// Returns the groups of directives sorted by name.
+    fn sorted_entries(&self) -> Vec<(&String, &Vec<Directive>)> {
+        let mut entries = self.directives.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+}
+
+/// Documents are ordered by their directives, compared name by name in sorted
+/// order, regardless of the `preserve_order` feature, consistently with
+/// equality.
+impl Ord for Scfg {
+    #[cfg(not(feature = "preserve_order"))]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.directives.cmp(&other.directives)
+    }
+
+    #[cfg(feature = "preserve_order")]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted_entries().cmp(&other.sorted_entries())
+    }
+}
+
+impl PartialOrd for Scfg {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Scfg {
+    type Err = ParseError;
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let r = std::io::Cursor::new(src.as_bytes());
+        parser::document(r)
+    }
+}
+
+impl<K: Into<String>> std::iter::FromIterator<(K, Directive)> for Scfg {
+    fn from_iter<T>(it: T) -> Self
+    where
+        T: IntoIterator<Item = (K, Directive)>,
+    {
+        let mut scfg = Self::default();
+
+        for (name, directive) in it {
+            let name = name.into();
+            scfg.directives
+                .entry(name)
+                .or_insert_with(Vec::new)
+                .push(directive);
+        }
+
+        scfg
+    }
+}
+
+/// How [`Scfg::apply_overlay`] merges the directives of an overlay with the
+/// directives of the same name in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The overlay's directives replace the document's.
+    Replace,
+    /// The overlay's directives are added after the document's.
+    Append,
+    /// Each overlay directive is merged into the first document directive with
+    /// the same parameters: their child blocks are merged recursively, and a
+    /// child block is added if the document directive has none. Overlay
+    /// directives without a match are added after the document's.
+    DeepMerge,
+}
+
+/// A mapping from old to new directive names, for [`Scfg::apply_rename_map`].
+pub trait RenameMap {
+    /// Returns the new name for `name`, or `None` if it is not renamed.
+    fn renamed(&self, name: &str) -> Option<&str>;
+}
+
+impl<S: BuildHasher> RenameMap for HashMap<&str, &str, S> {
+    fn renamed(&self, name: &str) -> Option<&str> {
+        self.get(name).copied()
+    }
+}
+
+impl RenameMap for [(&str, &str)] {
+    fn renamed(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(old, _)| *old == name)
+            .map(|(_, new)| *new)
+    }
+}
+
+impl<const N: usize> RenameMap for [(&str, &str); N] {
+    fn renamed(&self, name: &str) -> Option<&str> {
+        self[..].renamed(name)
+    }
+}
+
+/// Options for [`Scfg::write_with_options`].
+///
+/// The default options write documents the same way as [`Scfg::write`]. New
+/// options may be added, so this should be created with
+/// `..Default::default()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Writes the parameters of every directive sorted, without changing the
+    /// document. Off by default, since the order of parameters is usually
+    /// significant.
+    pub sort_params: bool,
+    /// Writes empty blocks as `{}` on the line of their directive, instead of
+    /// an opening and a closing brace on separate lines. Both forms are read
+    /// back as an empty block.
+    pub compact_empty_blocks: bool,
+    /// Writes a blank line between the groups of directives sharing a name,
+    /// at every level of nesting, as [`Scfg::write_grouped`] does. Off by
+    /// default.
+    pub separate_groups: bool,
+    /// Computes a comment to write before each directive, from the names of
+    /// the directives leading to it, including its own name, and the
+    /// directive. Each line of the comment is written as a `#` line, indented
+    /// like the directive. `None` by default.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "listen :6697\n".parse().unwrap();
+    /// let options = WriteOptions {
+    ///     comment_for: Some(CommentFn::new(|path, _| Some(format!("{} generated", path[0])))),
+    ///     ..Default::default()
+    /// };
+    /// let mut out = Vec::new();
+    /// doc.write_with_options(&mut out, &options).unwrap();
+    /// assert_eq!(out, b"# listen generated\nlisten :6697\n");
+    /// ```
+    pub comment_for: Option<CommentFn>,
+    /// Writes the words holding characters outside of this range in double
+    /// quotes, with those characters as `\u{X}` escapes, which are read back
+    /// with [`ParseOptions::unicode_escapes`]. Newlines and NUL are escaped
+    /// regardless of the range, so every word can be written. `None` by
+    /// default, in which case no escapes are written.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc = Scfg::new();
+    /// doc.add("sep").append_param("\t");
+    /// let options = WriteOptions {
+    ///     unicode_escapes: Some(' '..='~'),
+    ///     ..Default::default()
+    /// };
+    /// let mut out = Vec::new();
+    /// doc.write_with_options(&mut out, &options).unwrap();
+    /// assert_eq!(out, b"sep \"\\u{9}\"\n");
+    /// ```
+    pub unicode_escapes: Option<RangeInclusive<char>>,
+    /// Wraps the directives whose line would be wider than this many columns
+    /// onto continuation lines, ending each line but the last with a
+    /// backslash. Continuation lines are indented one level past their
+    /// directive, and hold at least one word, so words wider than the limit
+    /// still exceed it. Widths count characters, and tabs as 8 columns.
+    /// Comments are not wrapped. `None` by default.
+    ///
+    /// Wrapping relies on line continuations, an extension of scfg, so the
+    /// output must be read back with [`ParseOptions::line_continuations`].
+    /// The opening brace of a block always stays on the last line of its
+    /// directive, as if it were another word.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "lines Tōhoku Hokkaido Jōetsu\n".parse().unwrap();
+    /// let options = WriteOptions {
+    ///     max_width: Some(24),
+    ///     ..Default::default()
+    /// };
+    /// let mut out = Vec::new();
+    /// doc.write_with_options(&mut out, &options).unwrap();
+    /// assert_eq!(out, "lines Tōhoku Hokkaido \\\n\tJōetsu\n".as_bytes());
+    /// ```
+    pub max_width: Option<usize>,
+}
+
+/// A comparison of the directives of a group, see
+/// [`Scfg::write_with_directive_sort`].
+type DirectiveOrder<'a> = dyn Fn(&Directive, &Directive) -> std::cmp::Ordering + 'a;
+
+/// A function computing comments, see [`WriteOptions::comment_for`].
+///
+/// Comment functions are compared by identity: clones of a `CommentFn` are
+/// equal, but two functions created separately are not.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct CommentFn(Arc<dyn Fn(&[&str], &Directive) -> Option<String> + Send + Sync>);
+
+impl CommentFn {
+    /// Wraps `f`, which returns the comment to write before a directive, if
+    /// any.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&[&str], &Directive) -> Option<String> + Send + Sync + 'static,
+    {
+        CommentFn(Arc::new(f))
+    }
+}
+
+impl fmt::Debug for CommentFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CommentFn")
+    }
+}
+
+impl PartialEq for CommentFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CommentFn {}
 
 /// A single scfg directive, containing any number of parameters, and possibly
 /// one child block.
-#[derive(Debug, Default, PartialEq, Eq, Clone)]
+///
+/// Directives read by the parser remember the line they were read from, see
+/// [`line`](Directive::line). The line is not considered by equality or
+/// hashing.
+#[derive(Default, Clone)]
 pub struct Directive {
     params: Vec<String>,
     child: Option<Scfg>,
+    line: Option<usize>,
+}
+
+/// Formats the directive's parameters on a single line, separated by spaces and
+/// quoted like [`Scfg::write`] would. The child block, if any, is not written.
+///
+/// ```
+/// # use scfg::*;
+/// let mut directive = Directive::new();
+/// directive.append_param("a").append_param("b c");
+/// assert_eq!(directive.to_string(), "a 'b c'");
+/// ```
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(&word::quote(param))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a single directive, such as `max-speed 320km/h`.
+///
+/// The source must hold exactly one top-level directive, along with any
+/// comments and blank lines. The directive may have a child block, opened on
+/// its line and closed on a following line. Since a [`Directive`] does not hold
+/// its name, the name is read but discarded; parse an [`Scfg`] to keep it.
+///
+/// ```
+/// # use scfg::*;
+/// let directive: Directive = "lines-served Tōhoku Hokkaido".parse().unwrap();
+/// assert_eq!(directive.params(), ["Tōhoku", "Hokkaido"]);
+/// assert!("a\nb\n".parse::<Directive>().is_err());
+/// ```
+impl FromStr for Directive {
+    type Err = ParseError;
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let r = std::io::Cursor::new(src.as_bytes());
+        parser::directive(r)
+    }
+}
+
+impl PartialEq for Directive {
+    fn eq(&self, other: &Self) -> bool {
+        self.params == other.params && self.child == other.child
+    }
+}
+
+impl Eq for Directive {}
+
+/// Directives are ordered by their parameters, compared lexicographically,
+/// then by their child blocks, directives without a child coming first. Like
+/// equality, this ignores lines.
+impl Ord for Directive {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.params
+            .cmp(&other.params)
+            .then_with(|| self.child.cmp(&other.child))
+    }
+}
+
+impl PartialOrd for Directive {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Directive {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.params.hash(state);
+        self.child.hash(state);
+    }
+}
+
+impl Directive {
+    /// Creates a new empty directive.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get this directive's parameters
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// Get a mutable reference to this directive's parameters.
+    ///
+    /// Like [`Scfg::get_all_mut`], this gives access to the directive's own
+    /// storage, so changes are seen by every later read, including writes.
+    ///
+    /// # Note
+    /// This does not validate that the parameters are legal scfg words.
+    pub fn params_mut(&mut self) -> &mut Vec<String> {
+        &mut self.params
+    }
+
+    /// Returns the parameter at `index` parsed as a `T`, or `Ok(None)` if
+    /// there is no parameter at `index`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let listen: Directive = "listen 6697".parse().unwrap();
+    /// assert_eq!(listen.param_as::<u16>(0), Ok(Some(6697)));
+    /// assert_eq!(listen.param_as::<u16>(1), Ok(None));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns the error of [`FromStr::from_str`] if the parameter cannot be
+    /// parsed as a `T`.
+    pub fn param_as<T: FromStr>(&self, index: usize) -> Result<Option<T>, T::Err> {
+        self.params
+            .get(index)
+            .map(|param| param.parse())
+            .transpose()
+    }
+
+    /// Returns the line number of this directive's name in the source it was
+    /// parsed from, or `None` if it wasn't created by the parser.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "# comment\ndomain example.com\n".parse().unwrap();
+    /// assert_eq!(doc.get("domain").unwrap().line(), Some(2));
+    /// assert_eq!(Directive::new().line(), None);
+    /// ```
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Compares the parameter at `index` to a value that may or may not be
+    /// quoted.
+    ///
+    /// Parameters are stored with their quotes and escapes removed, so
+    /// `quoted_or_plain` is first tokenized the same way the parser tokenizes a
+    /// line: surrounding whitespace is ignored, quotes are removed, and
+    /// backslash escapes are resolved. If that produces exactly one word, it is
+    /// compared to the parameter. Otherwise, for example if the value contains
+    /// unquoted whitespace or an unterminated quote, `quoted_or_plain` is
+    /// compared verbatim. Returns `false` if there is no parameter at `index`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut directive = Directive::new();
+    /// directive.append_param("param 1");
+    /// assert!(directive.param_eq_unquoted(0, "\"param 1\""));
+    /// assert!(directive.param_eq_unquoted(0, "'param 1'"));
+    /// assert!(directive.param_eq_unquoted(0, "param\\ 1"));
+    /// assert!(directive.param_eq_unquoted(0, "param 1"));
+    /// assert!(!directive.param_eq_unquoted(1, "param 1"));
+    /// ```
+    pub fn param_eq_unquoted(&self, index: usize, quoted_or_plain: &str) -> bool {
+        let param = match self.params.get(index) {
+            Some(param) => param,
+            None => return false,
+        };
+        match shell_words::split(quoted_or_plain.trim()) {
+            Ok(words) if words.len() == 1 => *param == words[0],
+            _ => param == quoted_or_plain,
+        }
+    }
+
+    /// Appends the supplied parameter. Returns `&mut self` to support method
+    /// chaining.
+    ///
+    /// # Note
+    /// This does not validate that `param` is a legal scfg word. It is possible to create
+    /// unparsable documents should `param` contain control characters or newlines. See
+    /// [`try_append_param`](Directive::try_append_param) for a checked alternative.
+    pub fn append_param(&mut self, param: impl Into<String>) -> &mut Self {
+        self.params.push(param.into());
+        self
+    }
+
+    /// Appends the supplied parameters. Returns `&mut self` to support method
+    /// chaining.
+    ///
+    /// Room for the parameters is reserved up front from the lower bound of
+    /// the iterator's [`size_hint`], so appending many parameters from an
+    /// iterator of known length reallocates at most once.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut allow = Directive::new();
+    /// allow.append_params((1..=3).map(|i| format!("10.0.0.{}", i)));
+    /// assert_eq!(allow.params(), ["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that `params` are legal scfg words. See
+    /// [`try_append_params`](Directive::try_append_params) for a checked
+    /// alternative.
+    ///
+    /// [`size_hint`]: Iterator::size_hint
+    pub fn append_params<I>(&mut self, params: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let params = params.into_iter();
+        self.params.reserve(params.size_hint().0);
+        for param in params {
+            self.params.push(param.into());
+        }
+        self
+    }
+
+    /// Appends the supplied parameter, like [`append_param`], but checks that
+    /// `param` can be written first. Returns `&mut self` to support method
+    /// chaining.
+    ///
+    /// # Errors
+    /// Returns an error, and appends nothing, if `param` is not a valid word,
+    /// see [`is_valid_word`].
+    ///
+    /// [`append_param`]: Directive::append_param
+    pub fn try_append_param(&mut self, param: impl Into<String>) -> Result<&mut Self, InvalidWord> {
+        let param = param.into();
+        word::check(&param)?;
+        Ok(self.append_param(param))
+    }
+
+    /// Appends the supplied parameters, checking that each can be written
+    /// first. Returns `&mut self` to support method chaining.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut lines = Directive::new();
+    /// lines.try_append_params(["Tōhoku", "Hokkaido"]).unwrap();
+    /// assert!(lines.try_append_params(["Jōetsu", "a\nb"]).is_err());
+    /// assert_eq!(lines.params(), ["Tōhoku", "Hokkaido"]);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error for the first parameter which is not a valid word, see
+    /// [`is_valid_word`]. In that case none of the parameters are appended.
+    pub fn try_append_params<I>(&mut self, params: I) -> Result<&mut Self, InvalidWord>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let params: Vec<String> = params.into_iter().map(Into::into).collect();
+        for param in &params {
+            word::check(param)?;
+        }
+        Ok(self.append_params(params))
+    }
+
+    /// Appends the supplied parameter, unless an equal parameter is already
+    /// present. Returns whether the parameter was added.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut listen = Directive::new();
+    /// listen.append_param(":443");
+    /// assert!(listen.insert_param_unique("tls"));
+    /// assert!(!listen.insert_param_unique("tls"));
+    /// assert_eq!(listen.params(), [":443", "tls"]);
+    /// ```
+    ///
+    /// # Note
+    /// This does not validate that `param` is a legal scfg word.
+    pub fn insert_param_unique(&mut self, param: impl Into<String>) -> bool {
+        let param = param.into();
+        if self.params.contains(&param) {
+            return false;
+        }
+        self.params.push(param);
+        true
+    }
+
+    /// Removes every parameter equal to `value`, keeping the others in order.
+    /// Returns whether any parameter was removed.
+    pub fn remove_param_value(&mut self, value: &str) -> bool {
+        let len = self.params.len();
+        self.params.retain(|param| param != value);
+        self.params.len() != len
+    }
+
+    /// Clears all parameters from this directive.
+    pub fn clear_params(&mut self) {
+        self.params.clear();
+    }
+
+    /// Get this directive's child, if there is one.
+    pub fn child(&self) -> Option<&Scfg> {
+        self.child.as_ref()
+    }
+
+    /// Takes this directive's child, leaving it with `None`.
+    pub fn take_child(&mut self) -> Option<Scfg> {
+        self.child.take()
+    }
+
+    /// Returns the child, optionally creating it if it does not exist.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut directive = Directive::new();
+    /// assert!(directive.child().is_none());
+    /// directive.get_or_create_child();
+    /// assert!(directive.child().is_some());
+    /// ```
+    pub fn get_or_create_child(&mut self) -> &mut Scfg {
+        self.child.get_or_insert_with(Scfg::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+
+    static SHINKANSEN: &str = r#"train "Shinkansen" {
+    model "E5" {
+        max-speed 320km/h
+        weight 453.5t
+
+        lines-served "Tōhoku" "Hokkaido"
+    }
+
+    model "E7" {
+        max-speed 275km/h
+        weight 540t
+
+        lines-served "Hokuriku" "Jōetsu"
+    }
+}"#;
+
+    #[test]
+    fn flat() -> Result {
+        let src = r#"dir1 param1 param2 param3
+dir2
+dir3 param1
+
+# comment
+dir4 "param 1" 'param 2'
+"#;
+        let cfg = Scfg::from_str(src)?;
+        // this tests the fromiter impl
+        // builder type api is generally a little cleaner
+        let exp = vec![
+            (
+                "dir1",
+                Directive {
+                    params: vec!["param1".into(), "param2".into(), "param3".into()],
+                    child: None,
+                    line: None,
+                },
+            ),
+            (
+                "dir2",
+                Directive {
+                    params: vec![],
+                    child: None,
+                    line: None,
+                },
+            ),
+            (
+                "dir3",
+                Directive {
+                    params: vec!["param1".into()],
+                    child: None,
+                    line: None,
+                },
+            ),
+            (
+                "dir4",
+                Directive {
+                    params: vec!["param 1".into(), "param 2".into()],
+                    child: None,
+                    line: None,
+                },
+            ),
+        ]
+        .into_iter()
+        .collect::<Scfg>();
+        assert_eq!(cfg, exp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn simple_blocks() -> Result {
+        let src = r#"block1 {
+    dir1 param1 param2
+    dir2 param1
+}
+
+block2 {
+}
+
+block3 {
+    # comment
+}
+
+block4 param1 "param2" {
+    dir1
+}"#;
+        let cfg = Scfg::from_str(src)?;
+        let mut exp = Scfg::new();
+        let block1 = exp.add("block1");
+        let block = block1.get_or_create_child();
+        block
+            .add("dir1")
+            .append_param("param1")
+            .append_param("param2");
+        block.add("dir2").append_param("param1");
+        exp.add("block2").get_or_create_child();
+        exp.add("block3").get_or_create_child();
+        exp.add("block4")
+            .append_param("param1")
+            .append_param("param2")
+            .get_or_create_child()
+            .add("dir1");
+
+        assert_eq!(cfg, exp);
+        Ok(())
+    }
+
+    #[test]
+    fn nested() -> Result {
+        let src = r#"block1 {
+    block2 {
+        dir1 param1
+    }
+
+    block3 {
+    }
+}
+
+block4 {
+    block5 {
+        block6 param1 {
+            dir1
+        }
+    }
+
+    dir1
+}"#;
+        let cfg = Scfg::from_str(src)?;
+        let mut exp = Scfg::new();
+        let block1 = exp.add("block1").get_or_create_child();
+        block1
+            .add("block2")
+            .get_or_create_child()
+            .add("dir1")
+            .append_param("param1");
+        block1.add("block3").get_or_create_child();
+        let block4 = exp.add("block4").get_or_create_child();
+        block4
+            .add("block5")
+            .get_or_create_child()
+            .add("block6")
+            .append_param("param1")
+            .get_or_create_child()
+            .add("dir1");
+        block4.add("dir1");
+
+        assert_eq!(cfg, exp);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write() -> Result {
+        let src = r#"dir1 param1 param2 param3
+dir2
+dir3 param1
+
+# comment
+dir4 "param 1" 'param 2'
+"#;
+        let doc = Scfg::from_str(src)?;
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        let exp = r#"dir1 param1 param2 param3
+dir2
+dir3 param1
+dir4 'param 1' 'param 2'
+"#;
+        assert_eq!(std::str::from_utf8(&out)?, exp);
+        Ok(())
+    }
+
+    #[test]
+    fn write_block() -> Result {
+        let src = r#"block1 {
+	dir1 param1 param2
+	dir2 param1
+}
+
+block2 {
+}
+
+block3 {
+	# comment
+}
+
+block4 param1 "param2" {
+	dir1
+}"#;
+        let doc = Scfg::from_str(src)?;
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        let exp = r#"block1 {
+	dir1 param1 param2
+	dir2 param1
+}
+
+block2 {
+}
+
+block3 {
+}
+
+block4 param1 param2 {
+	dir1
+}
+"#;
+        assert_eq!(std::str::from_utf8(&out)?, exp);
+        Ok(())
+    }
+
+    #[test]
+    fn write_grouped() -> Result {
+        let src = r#"a 1
+b 2
+a 3
+c {
+	d 4
+	e 5
+}
+"#;
+        let doc = Scfg::from_str(src)?;
+        let mut out = Vec::new();
+        doc.write_grouped(&mut out)?;
+        let exp = r#"a 1
+a 3
+
+b 2
+
+c {
+	d 4
+
+	e 5
+}
+"#;
+        assert_eq!(std::str::from_utf8(&out)?, exp);
+        Ok(())
+    }
+
+    #[test]
+    fn has_any_has_all() -> Result {
+        let doc = Scfg::from_str("listen 0.0.0.0:6697\ninet 127.0.0.1\n")?;
+
+        // all present
+        assert!(doc.has_any(&["listen", "inet"]));
+        assert!(doc.has_all(&["listen", "inet"]));
+
+        // partially present
+        assert!(doc.has_any(["listen", "socket"].iter()));
+        assert!(!doc.has_all(vec!["listen", "socket"]));
+
+        // absent
+        assert!(!doc.has_any(&["socket", "unix"]));
+        assert!(!doc.has_all(&["socket", "unix"]));
+
+        // no names
+        assert!(!doc.has_any(Vec::<String>::new()));
+        assert!(doc.has_all(Vec::<String>::new()));
+        Ok(())
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn hash() -> Result {
+        let a = Scfg::from_str("a 1\nb 2 {\n\tc 3\n\td 4\n}\n")?;
+        let b = Scfg::from_str("b 2 {\n\td 4\n\tc 3\n}\na 1\n")?;
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_eq!(hash_of(a.get("b").unwrap()), hash_of(b.get("b").unwrap()));
+
+        // the order of directives sharing a name is significant
+        let c = Scfg::from_str("a 1\na 2\n")?;
+        let d = Scfg::from_str("a 2\na 1\n")?;
+        assert_ne!(c, d);
+        assert_ne!(hash_of(&c), hash_of(&d));
+
+        let e = Scfg::from_str("a 1\nb 2 {\n\tc 3\n}\n")?;
+        assert_ne!(a, e);
+        assert_ne!(hash_of(&a), hash_of(&e));
+        Ok(())
+    }
+
+    #[test]
+    fn param_eq_unquoted() -> Result {
+        let doc = Scfg::from_str(r#"dir "a b" 'c"d' e\ f "" plain"#)?;
+        let dir = doc.get("dir").unwrap();
+        assert!(dir.param_eq_unquoted(0, r#""a b""#));
+        assert!(dir.param_eq_unquoted(0, "'a b'"));
+        assert!(dir.param_eq_unquoted(0, "a b"));
+        assert!(dir.param_eq_unquoted(1, r#""c\"d""#));
+        assert!(dir.param_eq_unquoted(1, r#"'c"d'"#));
+        assert!(dir.param_eq_unquoted(2, r#"e\ f"#));
+        assert!(dir.param_eq_unquoted(2, r#""e f""#));
+        assert!(dir.param_eq_unquoted(3, "''"));
+        assert!(dir.param_eq_unquoted(4, "plain"));
+        assert!(dir.param_eq_unquoted(4, " plain "));
+        assert!(!dir.param_eq_unquoted(4, "'plain "));
+        assert!(!dir.param_eq_unquoted(0, "a  b"));
+        assert!(!dir.param_eq_unquoted(5, "plain"));
+        Ok(())
+    }
+
+    #[test]
+    fn write_with_comment_prefix() -> Result {
+        let src = r#"block1 {
+	dir2 "param 2"
+}
+
+dir1 param1
+dir3
+"#;
+        let doc = Scfg::from_str(src)?;
+        let mut out = Vec::new();
+        doc.write_with_comment_prefix(&mut out)?;
+        let out = String::from_utf8(out)?;
+        assert_eq!(
+            out,
+            "# block1 {\n# \tdir2 'param 2'\n# }\n# \n# dir1 param1\n# dir3\n"
+        );
+        assert_eq!(Scfg::from_str(&out)?, Scfg::new());
+
+        let stripped = out
+            .lines()
+            .map(|line| line.strip_prefix("# ").unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(Scfg::from_str(&stripped)?, doc);
+        Ok(())
+    }
+
+    #[test]
+    fn to_env_vars() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        let mut vars = doc.to_env_vars("RAIL");
+        vars.sort();
+        let exp = [
+            ("RAIL_TRAIN_MODEL_LINES_SERVED", "Hokuriku Jōetsu"),
+            ("RAIL_TRAIN_MODEL_LINES_SERVED", "Tōhoku Hokkaido"),
+            ("RAIL_TRAIN_MODEL_MAX_SPEED", "275km/h"),
+            ("RAIL_TRAIN_MODEL_MAX_SPEED", "320km/h"),
+            ("RAIL_TRAIN_MODEL_WEIGHT", "453.5t"),
+            ("RAIL_TRAIN_MODEL_WEIGHT", "540t"),
+        ];
+        assert_eq!(vars.len(), exp.len());
+        for ((key, value), (exp_key, exp_value)) in vars.iter().zip(exp.iter()) {
+            assert_eq!(key, exp_key);
+            assert_eq!(value, exp_value);
+        }
+
+        let unprefixed = Scfg::from_str("log.level debug\n")?.to_env_vars("");
+        assert_eq!(unprefixed, [("LOG_LEVEL".to_owned(), "debug".to_owned())]);
+        Ok(())
+    }
+
+    #[test]
+    fn get_path() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        assert_eq!(doc.get_path(&["train"]).unwrap().params(), ["Shinkansen"]);
+        let speed = doc.get_path(&["train", "model", "max-speed"]).unwrap();
+        assert_eq!(speed.params(), ["320km/h"]);
+        let path = vec![String::from("train"), String::from("model")];
+        assert_eq!(doc.get_path(&path).unwrap().params(), ["E5"]);
+        assert!(doc.get_path::<&str>(&[]).is_none());
+        assert!(doc.get_path(&["model"]).is_none());
+        assert!(doc
+            .get_path(&["train", "model", "max-speed", "x"])
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn get_path_or_default() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        let default = Directive::from_str("max-speed 100km/h")?;
+        let speed = doc.get_path_or_default(&["train", "model", "max-speed"], &default);
+        assert_eq!(speed.params(), ["320km/h"]);
+        assert!(std::ptr::eq(
+            speed,
+            doc.get_path(&["train", "model", "max-speed"]).unwrap()
+        ));
+        let missing = doc.get_path_or_default(&["bus"], &default);
+        assert!(std::ptr::eq(missing, &default));
+        let partial = doc.get_path_or_default(&["train", "depot"], &default);
+        assert!(std::ptr::eq(partial, &default));
+        Ok(())
+    }
+
+    #[test]
+    fn get_all_path() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        let speeds: Vec<_> = doc
+            .get_all_path(&["train", "model", "max-speed"])
+            .into_iter()
+            .map(Directive::params)
+            .collect();
+        assert_eq!(speeds, [["320km/h"], ["275km/h"]]);
+        assert_eq!(doc.get_all_path(&["train"]).len(), 1);
+        assert!(doc.get_all_path::<&str>(&[]).is_empty());
+        assert!(doc.get_all_path(&["train", "weight"]).is_empty());
+        assert!(doc
+            .get_all_path(&["train", "model", "weight", "x"])
+            .is_empty());
+        Ok(())
+    }
+
+    /// A reader which sleeps before each read.
+    struct SlowReader {
+        inner: io::Cursor<&'static str>,
+        delay: Duration,
+    }
+
+    impl io::Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(self.delay);
+            self.inner.read(buf)
+        }
+    }
+
+    #[test]
+    fn parse_with_timeout() -> Result {
+        let slow = |delay| {
+            io::BufReader::new(SlowReader {
+                inner: io::Cursor::new(SHINKANSEN),
+                delay: Duration::from_millis(delay),
+            })
+        };
+        let doc = Scfg::parse_with_timeout(slow(0), Duration::from_secs(10))?;
+        assert_eq!(doc, SHINKANSEN.parse()?);
+
+        let err = Scfg::parse_with_timeout(slow(500), Duration::from_millis(50)).unwrap_err();
+        assert!(err.is_timeout());
+        assert_eq!(err.to_string(), "parsing error: timed out after 50ms");
+
+        let err =
+            Scfg::parse_with_timeout(io::Cursor::new("}\n"), Duration::from_secs(10)).unwrap_err();
+        assert!(!err.is_timeout());
+
+        struct PanicReader;
+        impl io::Read for PanicReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                panic!("reader panicked")
+            }
+        }
+        let payload = panic::catch_unwind(|| {
+            Scfg::parse_with_timeout(io::BufReader::new(PanicReader), Duration::from_secs(10))
+        })
+        .unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"reader panicked"));
+        Ok(())
+    }
+
+    #[test]
+    fn extend_from_str() -> Result {
+        let mut doc = Scfg::from_str("server {\n\tlisten :80\n}\nlog info\n")?;
+        doc.extend_from_str("timeout 30s\nserver {\n\tlisten :443\n}\n")?;
+        doc.extend_from_reader(io::Cursor::new("log debug\nauth token\n"))?;
+
+        let names: Vec<_> = doc.directives.keys().map(String::as_str).collect();
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(names, ["auth", "log", "server", "timeout"]);
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(names, ["server", "log", "timeout", "auth"]);
+        let logs: Vec<_> = doc.get_all("log").unwrap().iter().collect();
+        assert_eq!(logs[0].params(), ["info"]);
+        assert_eq!(logs[1].params(), ["debug"]);
+        assert_eq!(logs[1].line(), Some(1));
+        let servers = doc.get_all_path(&["server", "listen"]);
+        assert_eq!(servers[0].params(), [":80"]);
+        assert_eq!(servers[1].params(), [":443"]);
+
+        let before = doc.clone();
+        let err = doc
+            .extend_from_str("log trace\nextra {\n\tvalue 1\n")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parsing error at line 4: io: unexpected end of file"
+        );
+        assert_eq!(doc, before);
+        assert!(doc.extend_from_str("}\n").is_err());
+        assert_eq!(doc, before);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_overlay() -> Result {
+        let mut doc: Scfg = r#"listen :80
+log info
+server main {
+    root /srv
+    index index.html
+    location /api {
+        proxy :8080
+    }
+}
+"#
+        .parse()?;
+        let overlay: Scfg = r#"listen :443
+log debug
+server main {
+    root /var/www
+    index default.html
+    location /api {
+        timeout 30s
+    }
+    location /static
+}
+server extra
+"#
+        .parse()?;
+        doc.apply_overlay(overlay, |name| match name {
+            "listen" | "index" => MergeStrategy::Append,
+            "server" | "location" => MergeStrategy::DeepMerge,
+            _ => MergeStrategy::Replace,
+        });
+        let expected: Scfg = r#"listen :80
+listen :443
+log debug
+server main {
+    root /var/www
+    index index.html
+    index default.html
+    location /api {
+        proxy :8080
+        timeout 30s
+    }
+    location /static
+}
+server extra
+"#
+        .parse()?;
+        assert_eq!(doc, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn collect_all_names() -> Result {
+        let doc: Scfg = SHINKANSEN.parse()?;
+        let expected = ["train", "model", "max-speed", "weight", "lines-served"];
+        let expected: HashSet<String> = expected.iter().map(|&name| name.to_owned()).collect();
+        assert_eq!(doc.collect_all_names(), expected);
+
+        let counts = doc.collect_all_names_with_count();
+        assert_eq!(counts["model"], 2);
+        assert_eq!(counts["train"], 1);
+        assert_eq!(counts["weight"], 2);
+        assert_eq!(counts.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn param_set() {
+        let mut listen = Directive::new();
+        listen
+            .append_param("tls")
+            .append_param(":443")
+            .append_param("tls");
+        assert!(!listen.insert_param_unique("tls"));
+        assert!(listen.insert_param_unique("http2"));
+        assert!(!listen.insert_param_unique("http2"));
+        assert_eq!(listen.params(), ["tls", ":443", "tls", "http2"]);
+
+        assert!(listen.remove_param_value("tls"));
+        assert_eq!(listen.params(), [":443", "http2"]);
+        assert!(!listen.remove_param_value("tls"));
+        assert_eq!(listen.params(), [":443", "http2"]);
+    }
+
+    #[test]
+    fn replace_path() -> Result {
+        let mut doc: Scfg = "train Shinkansen\n".parse()?;
+        let mut speed = Directive::new();
+        speed.append_param("320km/h");
+        let old = doc.replace_path(&["train", "model", "max-speed"], speed);
+        assert_eq!(old, None);
+        let expected: Scfg =
+            "train Shinkansen {\n\tmodel {\n\t\tmax-speed 320km/h\n\t}\n}\n".parse()?;
+        assert_eq!(doc, expected);
+
+        let mut doc: Scfg = "a {\n\tb 1\n\tb 2\n}\na {\n\tb 3\n}\n".parse()?;
+        let old = doc.replace_path(&["a", "b"], Directive::new()).unwrap();
+        assert_eq!(old.len(), 2);
+        assert_eq!(doc, "a {\n\tb\n}\na {\n\tb 3\n}\n".parse()?);
+
+        let before = doc.clone();
+        let empty: &[&str] = &[];
+        assert_eq!(doc.replace_path(empty, Directive::new()), None);
+        assert_eq!(doc, before);
+        Ok(())
+    }
+
+    #[test]
+    fn map_names_recursive() -> Result {
+        let mut doc = Scfg::from_str(SHINKANSEN)?;
+        doc.map_names_recursive(&mut |name| {
+            if name == "max-speed" {
+                "maxSpeed".to_owned()
+            } else {
+                name.to_owned()
+            }
+        });
+        assert!(doc.get_path(&["train", "model", "max-speed"]).is_none());
+        let speed = doc.get_path(&["train", "model", "maxSpeed"]).unwrap();
+        assert_eq!(speed.params(), ["320km/h"]);
+        let models = doc.get_path(&["train"]).unwrap().child().unwrap();
+        let e7 = models.get_all("model").unwrap()[1].child().unwrap();
+        assert_eq!(e7.get("maxSpeed").unwrap().params(), ["275km/h"]);
+        assert!(!e7.contains("max-speed"));
+        Ok(())
+    }
+
+    #[test]
+    fn map_names_recursive_merges() -> Result {
+        let mut doc = Scfg::from_str("a 1\nb 2\na 3\nc {\n\ta 4\n\tb 5\n}\n")?;
+        doc.map_names_recursive(&mut |name| match name {
+            "a" | "b" => "x".to_owned(),
+            _ => name.to_owned(),
+        });
+        let exp = Scfg::from_str("x 1\nx 3\nx 2\nc {\n\tx 4\n\tx 5\n}\n")?;
+        assert_eq!(doc, exp);
+        Ok(())
+    }
+
+    #[test]
+    fn transform() -> Result {
+        let doc = Scfg::from_str("a 1\nb 2 {\n\tc 3\n}\nb 4\nd 5\n")?;
+        assert_eq!(doc.clone().transform(|name, d| Some((name, d))), doc);
+        assert_eq!(doc.clone().transform(|_, _| None), Scfg::new());
+
+        let dropped = doc.clone().transform(|name, d| {
+            if d.child().is_some() {
+                None
+            } else {
+                Some((name, d))
+            }
+        });
+        assert_eq!(dropped, Scfg::from_str("a 1\nb 4\nd 5\n")?);
+
+        let renamed = doc.transform(|name, d| match name.as_str() {
+            "a" | "d" => Some(("b".to_owned(), d)),
+            _ => Some((name, d)),
+        });
+        let params: Vec<_> = renamed
+            .get_all("b")
+            .unwrap()
+            .iter()
+            .map(Directive::params)
+            .collect();
+        assert_eq!(params, [["1"], ["2"], ["4"], ["5"]]);
+        assert!(!renamed.contains("a") && !renamed.contains("d"));
+        Ok(())
+    }
+
+    #[test]
+    fn map_names() -> Result {
+        let src = "Train Shinkansen {\n\tMODEL E5\n\tModel E7 {\n\t\tMax-Speed 275km/h\n\t}\n\tmodel N700\n}\ntrain Thalys\n";
+        let mut doc = Scfg::from_str(src)?;
+        doc.map_names(|name| name.to_lowercase());
+        let trains: Vec<_> = doc
+            .get_all("train")
+            .unwrap()
+            .iter()
+            .map(Directive::params)
+            .collect();
+        assert_eq!(trains, [["Shinkansen"], ["Thalys"]]);
+        let models: Vec<_> = doc
+            .get_all_path(&["train", "model"])
+            .into_iter()
+            .map(Directive::params)
+            .collect();
+        // "MODEL" < "Model" < "model"
+        assert_eq!(models, [["E5"], ["E7"], ["N700"]]);
+        let speed = doc.get_all_path(&["train", "model", "max-speed"]);
+        assert_eq!(speed[0].params(), ["275km/h"]);
+        assert_eq!(doc.collect_all_names().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_rename_map() -> Result {
+        let mut renames = HashMap::new();
+        renames.insert("max-speed", "maxSpeed");
+        renames.insert("lines-served", "lines");
+        let mut doc = Scfg::from_str(SHINKANSEN)?;
+        doc.apply_rename_map(&renames);
+        for model in doc.get_all_path(&["train", "model"]) {
+            let model = model.child().unwrap();
+            assert!(model.contains("maxSpeed") && model.contains("lines"));
+            assert!(!model.contains("max-speed") && !model.contains("lines-served"));
+            assert!(model.contains("weight"));
+        }
+        let lines = doc.get_all_path(&["train", "model", "lines"]);
+        assert_eq!(lines[1].params(), ["Hokuriku", "Jōetsu"]);
+
+        let mut sliced = Scfg::from_str(SHINKANSEN)?;
+        let pairs = [("max-speed", "maxSpeed"), ("lines-served", "lines")];
+        sliced.apply_rename_map(&pairs[..]);
+        assert_eq!(sliced, doc);
+
+        let mut merged = Scfg::from_str("a 1\nb 2\nc 3\n")?;
+        merged.apply_rename_map(&[("a", "x"), ("b", "x"), ("x", "y")]);
+        assert_eq!(merged, Scfg::from_str("x 1\nx 2\nc 3\n")?);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_all_empty_children() -> Result {
+        let mut doc = Scfg::new();
+        let train = doc.add("train").get_or_create_child();
+        for (i, model) in ["E2", "E5", "E6", "E7"].iter().enumerate() {
+            let child = train
+                .add("model")
+                .append_param(*model)
+                .get_or_create_child();
+            if i % 2 == 0 {
+                child.add("max-speed").append_param("275km/h");
+            }
+        }
+        let depot = doc.add("depot").get_or_create_child();
+        depot.add("tracks").get_or_create_child();
+
+        let before = doc.clone();
+        doc.remove_all_empty_children();
+        let models = doc.get_all_path(&["train", "model"]);
+        let before_models = before.get_all_path(&["train", "model"]);
+        for (model, before) in models.iter().zip(before_models) {
+            if model.params() == ["E2"] || model.params() == ["E6"] {
+                assert_eq!(model.child(), before.child());
+                assert!(model.child().unwrap().contains("max-speed"));
+            } else {
+                assert_eq!(model.child(), None);
+            }
+        }
+        let tracks = doc.get_path(&["depot", "tracks"]).unwrap();
+        assert_eq!(tracks.child(), None);
+        assert!(doc.get("depot").unwrap().child().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn retain_recursive() -> Result {
+        let src = "cache {\n}\ndebug true\nexperimental {\n\tfeature x\n}\nlisten :80 {\n\tdebug\n\ttimeout 5\n\ttls {\n\t\tcert a.pem\n\t\tdebug verbose\n\t}\n}\nlogging {\n\tdebug\n}\n";
+        let doc = Scfg::from_str(src)?;
+        assert_eq!(doc.total_directive_count(), 5);
+        assert_eq!(doc.depth(), 2);
+
+        let mut seen = Vec::new();
+        let mut pruned = doc.clone();
+        pruned.retain_recursive(true, |path, _| {
+            seen.push(path.join("."));
+            path[0] != "experimental" && path.last() != Some(&"debug")
+        });
+        seen.sort();
+        let exp = [
+            "cache",
+            "debug",
+            "experimental",
+            "listen",
+            "listen.debug",
+            "listen.timeout",
+            "listen.tls",
+            "listen.tls.cert",
+            "listen.tls.debug",
+            "logging",
+            "logging.debug",
+        ];
+        assert_eq!(seen, exp);
+        let mut out = Vec::new();
+        pruned.write(&mut out)?;
+        let exp =
+            "cache {\n}\n\nlisten :80 {\n\ttimeout 5\n\ttls {\n\t\tcert a.pem\n\t}\n}\n\nlogging\n";
+        assert_eq!(String::from_utf8(out)?, exp);
+        assert_eq!(pruned.total_directive_count(), 3);
+        assert_eq!(pruned.name_count(), 3);
+        assert_eq!(pruned.count_total_params(), 3);
+        assert_eq!(pruned.depth(), 2);
+
+        let mut kept = doc;
+        kept.retain_recursive(false, |path, directive| {
+            path.len() < 2 || directive.params() != ["verbose"]
+        });
+        let mut out = Vec::new();
+        kept.write(&mut out)?;
+        assert!(!String::from_utf8(out)?.contains("verbose"));
+        kept.retain_recursive(false, |path, _| path.last() != Some(&"debug"));
+        assert_eq!(kept.get("logging").unwrap().child(), Some(&Scfg::new()));
+        assert_eq!(kept.summarize(), "Scfg { 4 names, 4 directives, depth 2 }");
+        Ok(())
+    }
+
+    #[test]
+    fn count_total_params() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        // train 1, model 1 + max-speed 1 + weight 1 + lines-served 2, twice
+        assert_eq!(doc.count_total_params(), 11);
+        let folded = doc.fold(0, |count, _, directive, _| count + directive.params().len());
+        assert_eq!(folded, doc.count_total_params());
+        assert_eq!(Scfg::new().count_total_params(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_value_map() -> Result {
+        let mut ports = HashMap::new();
+        ports.insert(String::from("http"), 80u16);
+        ports.insert(String::from("https"), 443);
+        let doc = Scfg::try_from_value_map(ports);
+        assert_eq!(doc, Scfg::from_str("http 80\nhttps 443\n")?);
+        assert_eq!(doc.get_i64("https"), Ok(Some(Some(443))));
+
+        let mut flags = HashMap::new();
+        flags.insert("tls", true);
+        flags.insert("compression", false);
+        let doc = Scfg::try_from_value_map(flags);
+        assert_eq!(doc, Scfg::from_str("compression false\ntls true\n")?);
+
+        let doc = Scfg::try_from_value_map(vec![("retry", 1), ("retry", 2)]);
+        assert_eq!(doc.get_all("retry").unwrap().len(), 2);
+        assert_eq!(
+            Scfg::try_from_value_map(HashMap::<String, u8>::new()),
+            Scfg::new()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn summarize() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        assert_eq!((doc.name_count(), doc.total_directive_count()), (1, 1));
+        assert_eq!(doc.summarize(), "Scfg { 1 name, 1 directive, depth 2 }");
+        assert_eq!(
+            Scfg::new().summarize(),
+            "Scfg { 0 names, 0 directives, depth 0 }"
+        );
+        let doc = Scfg::from_str("a\nb 1\nb 2\nc {\n\td\n}\nb 3\n")?;
+        assert_eq!(doc.summarize(), "Scfg { 3 names, 5 directives, depth 1 }");
+        Ok(())
+    }
+
+    #[test]
+    fn depth() -> Result {
+        assert_eq!(Scfg::from_str(SHINKANSEN)?.depth(), 2);
+        assert_eq!(Scfg::new().depth(), 0);
+        assert_eq!(Scfg::from_str("a\nb c\n")?.depth(), 0);
+        assert_eq!(Scfg::from_str("a {}\nb\n")?.depth(), 1);
+        assert_eq!(
+            Scfg::from_str("a {\n\tb {\n\t\tc {}\n\t}\n}\nd {\n}\n")?.depth(),
+            3
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn add_block() {
+        let mut doc = Scfg::new();
+        let block = doc.add_block("block", vec![String::from("a b")], |child| {
+            child.add("dir");
+        });
+        block.append_param("c");
+        let empty = doc.add_block("empty", Vec::<String>::new(), |_| {});
+        assert!(empty.params().is_empty());
+        assert_eq!(empty.child(), Some(&Scfg::new()));
+
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        let exp = "block 'a b' c {\n\tdir\n}\n\nempty {\n}\n";
+        assert_eq!(String::from_utf8(out).unwrap(), exp);
+    }
+
+    #[test]
+    fn replace_param_values() -> Result {
+        let src = r#"upstream old.example.com {
+    server old.example.com:80 old.example.com
+    backup {
+        server old.example.com
+    }
+}
+server {
+    certificate /etc/ssl/old.example.com.pem
+    key /etc/ssl/old.example.com.key
+    old.example.com old.example.com
+}
+"#;
+        let mut doc: Scfg = src.parse()?;
+        assert_eq!(
+            doc.replace_param_values("old.example.com", "new.example.com"),
+            4
+        );
+        let exp = r#"upstream new.example.com {
+    server old.example.com:80 new.example.com
+    backup {
+        server new.example.com
+    }
+}
+server {
+    certificate /etc/ssl/old.example.com.pem
+    key /etc/ssl/old.example.com.key
+    old.example.com new.example.com
+}
+"#;
+        assert_eq!(doc, exp.parse()?);
+        assert_eq!(doc.replace_param_values("old.example.com", "x"), 0);
+
+        let replaced = doc.replace_param_values_with(|name, param| match name {
+            "certificate" => Some(param.replace("old.", "new.")),
+            _ => None,
+        });
+        assert_eq!(replaced, 1);
+        assert_eq!(
+            doc.get_path(&["server", "certificate"]).unwrap().params(),
+            ["/etc/ssl/new.example.com.pem"]
+        );
+        assert_eq!(
+            doc.get_path(&["server", "key"]).unwrap().params(),
+            ["/etc/ssl/old.example.com.key"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sort_directives() -> Result {
+        let src =
+            "allow 192.168.0.0/16\nallow 10.0.0.0/8 {\n}\nallow 10.0.0.0/8\nallow 172.16.0.0/12\n";
+        let mut doc: Scfg = src.parse()?;
+        doc.sort_directives("allow");
+        doc.sort_directives("missing");
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "allow 10.0.0.0/8\nallow 10.0.0.0/8 {\n}\n\nallow 172.16.0.0/12\nallow 192.168.0.0/16\n"
+        );
+
+        let src = "route a 20\nroute b 5\nroute c 100\nroute d 5\n";
+        let mut doc: Scfg = src.parse()?;
+        doc.sort_directives_by("route", |a, b| {
+            let metric = |d: &Directive| d.params()[1].parse::<u32>().unwrap();
+            metric(a).cmp(&metric(b))
+        });
+        let routes: Vec<_> = doc
+            .get_all("route")
+            .unwrap()
+            .iter()
+            .map(|d| d.params()[0].as_str())
+            .collect();
+        assert_eq!(routes, ["b", "d", "a", "c"]);
+
+        let src = "acl {\n\tallow b\n\tallow a\n}\nallow z {\n\tallow y\n\tallow x\n}\nallow z {\n\tallow w\n}\n";
+        let mut doc: Scfg = src.parse()?;
+        doc.sort_directives_recursive("allow");
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "acl {\n\tallow a\n\tallow b\n}\n\nallow z {\n\tallow w\n}\n\nallow z {\n\tallow x\n\tallow y\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn partition() -> Result {
+        let doc: Scfg = SHINKANSEN.parse()?;
+        let (e5, rest) = doc
+            .clone()
+            .partition(|name, directive| name == "model" && directive.params() == ["E5"]);
+        assert!(e5.get("train").is_none());
+        assert!(rest.get("model").is_none());
+        assert_eq!(e5.total_directive_count(), 0);
+
+        let train = doc.get("train").unwrap().child().unwrap().clone();
+        let (e5, rest) = train
+            .clone()
+            .partition(|name, directive| name == "model" && directive.params() == ["E5"]);
+        let e5 = e5.get("model").unwrap();
+        assert_eq!(e5.line(), Some(2));
+        assert_eq!(
+            e5.child().unwrap().get("weight").unwrap().params(),
+            ["453.5t"]
+        );
+        assert_eq!(rest.get_all("model").unwrap().len(), 1);
+
+        let src = "listen :80\nlisten :443\nplugin a {\n\tx 1\n}\nplugin b\nlog info\n";
+        let doc: Scfg = src.parse()?;
+        let (plugins, mut core) = doc
+            .clone()
+            .partition(|name, directive| name == "plugin" || directive.params() == [":443"]);
+        assert_eq!(core, "listen :80\nlog info\n".parse()?);
+        assert_eq!(
+            plugins,
+            "listen :443\nplugin a {\n\tx 1\n}\nplugin b\n".parse()?
+        );
+        core.append(plugins);
+        assert_eq!(core.normalize(), doc.normalize());
+
+        let (listen, rest) = doc.clone().partition_names(&["listen", "log"]);
+        assert_eq!(listen, "listen :80\nlisten :443\nlog info\n".parse()?);
+        assert_eq!(rest.collect_all_names().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn reparse() -> Result {
+        let doc: Scfg = SHINKANSEN.parse()?;
+        let reparsed = doc.reparse()?;
+        assert_eq!(reparsed, doc.normalize());
+        assert_eq!(reparsed.canonical_form(), doc.canonical_form());
+
+        // empty names and parameters are quoted, so they survive, but the
+        // directives get the lines they are written at.
+        let mut doc = Scfg::new();
+        doc.add("").append_param("x");
+        doc.add("").get_or_create_child().add("").append_param("");
+        let reparsed = doc.reparse()?;
+        assert_eq!(reparsed, doc);
+        let lines: Vec<_> = reparsed
+            .get_all("")
+            .unwrap()
+            .iter()
+            .map(Directive::line)
+            .collect();
+        assert_eq!(lines, [Some(1), Some(2)]);
+        assert_eq!(doc.get("").unwrap().line(), None);
+
+        let mut doc = Scfg::new();
+        doc.add("a\nb");
+        assert!(doc.reparse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_form() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        let mut built = Scfg::new();
+        built.add_block("train", ["Shinkansen"], |train| {
+            train.add_block("model", ["E5"], |e5| {
+                e5.add("lines-served")
+                    .append_param("Tōhoku")
+                    .append_param("Hokkaido");
+                e5.add("weight").append_param("453.5t");
+                e5.add("max-speed").append_param("320km/h");
+            });
+            train.add_block("model", ["E7"], |e7| {
+                e7.add("weight").append_param("540t");
+                e7.add("lines-served")
+                    .append_param("Hokuriku")
+                    .append_param("Jōetsu");
+                e7.add("max-speed").append_param("275km/h");
+            });
+        });
+        assert_eq!(doc.canonical_form(), built.canonical_form());
+
+        let exp = r#""train" "Shinkansen" {
+	"model" "E5" {
+		"lines-served" "Tōhoku" "Hokkaido"
+		"max-speed" "320km/h"
+		"weight" "453.5t"
+	}
+	"model" "E7" {
+		"lines-served" "Hokuriku" "Jōetsu"
+		"max-speed" "275km/h"
+		"weight" "540t"
+	}
+}
+"#;
+        assert_eq!(doc.canonical_form(), exp);
+        assert_eq!(Scfg::from_str(exp)?, doc.normalize());
+
+        let mut other = built.clone();
+        other.add("train");
+        assert_ne!(other.canonical_form(), built.canonical_form());
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_form_quoting() -> Result {
+        let mut doc = Scfg::new();
+        doc.add("")
+            .append_param("\"quoted\"")
+            .append_param("back\\slash")
+            .append_param("$HOME {")
+            .append_param("#");
+        let canonical = doc.canonical_form();
+        assert_eq!(
+            canonical,
+            "\"\" \"\\\"quoted\\\"\" \"back\\\\slash\" \"$HOME {\" \"#\"\n"
+        );
+        assert_eq!(Scfg::from_str(&canonical)?, doc.normalize());
+        Ok(())
+    }
+
+    #[test]
+    fn normalize() -> Result {
+        let src = "b 1\na {\n\td\n\tc\n}\n";
+        let doc = Scfg::from_str(src)?;
+        let normalized = doc.normalize();
+        assert_eq!(normalized, doc);
+        assert_eq!(doc.get("b").unwrap().line(), Some(1));
+        assert_eq!(normalized.get("b").unwrap().line(), None);
+
+        let mut out = Vec::new();
+        normalized.write(&mut out)?;
+        assert_eq!(String::from_utf8(out)?, "a {\n\tc\n\td\n}\n\nb 1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn mutate_then_write() -> Result {
+        let src = r#"listen 0.0.0.0:6697 {
+	certificate cert.pem
+}
+
+listen 127.0.0.1:6667
+"#;
+        let mut doc = Scfg::from_str(src)?;
+        let mut listen = doc.get_all_mut("listen").unwrap();
+        listen[0].append_param("tls");
+        listen[1].params_mut()[0] = "[::1]:6667".to_owned();
+        listen[0]
+            .get_or_create_child()
+            .get_all_mut("certificate")
+            .unwrap()[0]
+            .params_mut()
+            .push("chain.pem".to_owned());
+        listen.push(Directive::new());
+        drop(listen);
+
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        let exp = r#"listen 0.0.0.0:6697 tls {
+	certificate cert.pem chain.pem
 }
 
-impl Directive {
-    /// Creates a new empty directive.
-    pub fn new() -> Self {
-        Default::default()
+listen '[::1]:6667'
+listen
+"#;
+        assert_eq!(String::from_utf8(out)?, exp);
+        Ok(())
     }
 
-    /// Get this directive's parameters
-    pub fn params(&self) -> &[String] {
-        &self.params
-    }
+    #[test]
+    fn parse_multiple_files() -> Result {
+        let dir = tempfile::tempdir()?;
+        let system = dir.path().join("system.scfg");
+        let user = dir.path().join("user.scfg");
+        let local = dir.path().join("local.scfg");
+        let missing = dir.path().join("missing.scfg");
+        std::fs::write(&system, "listen 0.0.0.0:6697\n")?;
+        std::fs::write(&user, "domain example.com\n")?;
+        std::fs::write(&local, "listen 127.0.0.1:6667\nlog debug\n")?;
 
-    /// Appends the supplied parameter. Returns `&mut self` to support method
-    /// chaining.
-    ///
-    /// # Note
-    /// This does not validate that `param` is a legal scfg word. It is possible to create
-    /// unparsable documents should `param` contain control characters or newlines.
-    pub fn append_param(&mut self, param: impl Into<String>) -> &mut Self {
-        self.params.push(param.into());
-        self
-    }
+        let paths = [&system, &missing, &user, &local];
+        let doc = Scfg::parse_multiple_files(paths, false)?;
+        let exp = "domain example.com\nlisten 0.0.0.0:6697\nlisten 127.0.0.1:6667\nlog debug\n";
+        assert_eq!(doc, Scfg::from_str(exp)?);
 
-    /// Clears all parameters from this directive.
-    pub fn clear_params(&mut self) {
-        self.params.clear();
+        let err = Scfg::parse_multiple_files(paths, true).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with(&format!("{}: parsing error: io: ", missing.display())));
+
+        std::fs::write(&user, "domain example.com\n}\n")?;
+        let err = Scfg::parse_multiple_files(paths, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: parsing error at line 2: unexpected '}}'",
+                user.display()
+            )
+        );
+        Ok(())
     }
 
-    /// Get this directive's child, if there is one.
-    pub fn child(&self) -> Option<&Scfg> {
-        self.child.as_ref()
+    #[test]
+    fn from_file() -> Result {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.scfg");
+        std::fs::write(&path, "listen 0.0.0.0:6697\n")?;
+        assert_eq!(
+            Scfg::from_file(&path)?,
+            Scfg::from_str("listen 0.0.0.0:6697\n")?
+        );
+
+        std::fs::write(&path, "listen 0.0.0.0:6697 {\n}\n}\n")?;
+        let err = Scfg::from_file(&path).unwrap_err();
+        assert_eq!(err.file_path(), Some(path.as_path()));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: parsing error at line 3: unexpected '}}'",
+                path.display()
+            )
+        );
+
+        let missing = dir.path().join("missing.scfg");
+        let err = Scfg::from_file(&missing).unwrap_err();
+        assert_eq!(err.file_path(), Some(missing.as_path()));
+        assert!(std::error::Error::source(&err).is_some());
+
+        let err = Scfg::from_str("}").unwrap_err();
+        assert_eq!(err.file_path(), None);
+        Ok(())
     }
 
-    /// Takes this directive's child, leaving it with `None`.
-    pub fn take_child(&mut self) -> Option<Scfg> {
-        self.child.take()
+    #[test]
+    fn write_sort_params() -> Result {
+        let src = "flags verbose color ascii {\n\tlisten b a\n}\n";
+        let doc = Scfg::from_str(src)?;
+        let options = WriteOptions {
+            sort_params: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &options)?;
+        let exp = "flags ascii color verbose {\n\tlisten a b\n}\n";
+        assert_eq!(String::from_utf8(out)?, exp);
+        assert_eq!(doc.get("flags").unwrap().params()[0], "verbose");
+
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &WriteOptions::default())?;
+        assert_eq!(String::from_utf8(out)?, src);
+        Ok(())
     }
 
-    /// Returns the child, optionally creating it if it does not exist.
-    ///
-    /// ```
-    /// # use scfg::*;
-    /// let mut directive = Directive::new();
-    /// assert!(directive.child().is_none());
-    /// directive.get_or_create_child();
-    /// assert!(directive.child().is_some());
-    /// ```
-    pub fn get_or_create_child(&mut self) -> &mut Scfg {
-        self.child.get_or_insert_with(Scfg::new)
+    #[test]
+    fn write_compact_empty_blocks() -> Result {
+        let src = "a {\n}\n\nb x {\n\tc {\n\t}\n}\n\nd\n";
+        let doc = Scfg::from_str(src)?;
+        let options = WriteOptions {
+            compact_empty_blocks: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &options)?;
+        let exp = "a {}\n\nb x {\n\tc {}\n}\n\nd\n";
+        assert_eq!(String::from_utf8(out)?, exp);
+        assert_eq!(Scfg::from_str(exp)?, doc);
+        assert_eq!(doc.get("a").unwrap().child(), Some(&Scfg::new()));
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn write_max_width() -> Result {
+        let src = "a {\n\tb x\n\tservers alpha.example.com beta.example.com gamma.example.com delta.example.com epsilon.example.com {\n\t\tweight 1\n\t}\n}\n";
+        let doc = Scfg::from_str(src)?;
+        let options = WriteOptions {
+            max_width: Some(60),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &options)?;
+        let exp = "a {\n\tb x\n\tservers alpha.example.com beta.example.com \\\n\t\tgamma.example.com delta.example.com \\\n\t\tepsilon.example.com {\n\t\tweight 1\n\t}\n}\n";
+        assert_eq!(String::from_utf8(out.clone())?, exp);
+        let parse_options = ParseOptions {
+            line_continuations: true,
+            ..Default::default()
+        };
+        let parsed = Scfg::parse_with_options(out.as_slice(), &parse_options)?;
+        assert_eq!(parsed, doc);
+        assert_eq!(parsed.get_path(&["a", "servers"]).unwrap().line(), Some(3));
+        assert_eq!(
+            parsed.get_path(&["a", "servers", "weight"]).unwrap().line(),
+            Some(6)
+        );
+        assert_ne!(Scfg::from_str(exp)?, doc);
 
-    type Result = std::result::Result<(), Box<dyn std::error::Error>>;
+        let options = WriteOptions {
+            max_width: Some(4),
+            compact_empty_blocks: true,
+            ..Default::default()
+        };
+        let mut doc = Scfg::new();
+        doc.add("long-name").append_param("x").get_or_create_child();
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &options)?;
+        assert_eq!(String::from_utf8(out)?, "long-name \\\n\tx {}\n");
 
-    #[test]
-    fn flat() -> Result {
-        let src = r#"dir1 param1 param2 param3
-dir2
-dir3 param1
+        let mut out = Vec::new();
+        let options = WriteOptions {
+            max_width: Some(80),
+            ..Default::default()
+        };
+        Scfg::from_str(SHINKANSEN)?.write_with_options(&mut out, &options)?;
+        assert_eq!(
+            Scfg::from_str(std::str::from_utf8(&out)?)?,
+            Scfg::from_str(SHINKANSEN)?
+        );
+        Ok(())
+    }
 
-# comment
-dir4 "param 1" 'param 2'
-"#;
-        let cfg = Scfg::from_str(src)?;
-        // this tests the fromiter impl
-        // builder type api is generally a little cleaner
-        let exp = vec![
-            (
-                "dir1",
-                Directive {
-                    params: vec!["param1".into(), "param2".into(), "param3".into()],
-                    child: None,
-                },
-            ),
-            (
-                "dir2",
-                Directive {
-                    params: vec![],
-                    child: None,
-                },
-            ),
-            (
-                "dir3",
-                Directive {
-                    params: vec!["param1".into()],
-                    child: None,
-                },
-            ),
-            (
-                "dir4",
-                Directive {
-                    params: vec!["param 1".into(), "param 2".into()],
-                    child: None,
-                },
-            ),
-        ]
-        .into_iter()
-        .collect::<Scfg>();
-        assert_eq!(cfg, exp);
+    #[test]
+    fn write_with_directive_sort() -> Result {
+        let src = SHINKANSEN
+            .replacen("model \"E5\"", "model \"E9\"", 1)
+            .replacen("\"Hokkaido\"", "\"Hokkaido\"\nlines-served Akita", 1);
+        let doc = Scfg::from_str(&src)?;
+        let first_param = |d: &Directive| d.params().first().cloned();
+        let mut out = Vec::new();
+        doc.write_with_directive_sort(first_param, &mut out)?;
+        let sorted = Scfg::from_str(std::str::from_utf8(&out)?)?;
 
+        let models: Vec<_> = sorted.get_all_path(&["train", "model"]);
+        assert_eq!(models[0].params(), ["E7"]);
+        assert_eq!(models[1].params(), ["E9"]);
+        let lines: Vec<_> = models[1]
+            .child()
+            .unwrap()
+            .get_all("lines-served")
+            .unwrap()
+            .iter()
+            .map(Directive::params)
+            .collect();
+        assert_eq!(lines, [&["Akita"][..], &["Tōhoku", "Hokkaido"][..]]);
+        let original: Vec<_> = doc.get_all_path(&["train", "model"]);
+        assert_eq!(original[0].params(), ["E9"]);
         Ok(())
     }
 
     #[test]
-    fn simple_blocks() -> Result {
-        let src = r#"block1 {
-    dir1 param1 param2
-    dir2 param1
-}
+    fn write_comment_for() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        let options = WriteOptions {
+            comment_for: Some(CommentFn::new(|path, directive| match path {
+                ["train"] => Some("generated\n\ndo not edit".to_owned()),
+                [.., "max-speed"] => Some(format!("{} in km/h", directive.params()[0])),
+                _ => None,
+            })),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &options)?;
+        let out = String::from_utf8(out)?;
+        assert!(out.starts_with("# generated\n#\n# do not edit\ntrain Shinkansen {\n"));
+        assert!(out.contains("\t\t# 320km/h in km/h\n\t\tmax-speed 320km/h\n"));
+        assert!(out.contains("\t\t# 275km/h in km/h\n\t\tmax-speed 275km/h\n"));
+        assert_eq!(out.matches('#').count(), 5);
+        assert_eq!(Scfg::from_str(&out)?, doc);
 
-block2 {
-}
+        assert_eq!(options.clone(), options);
+        assert_ne!(
+            options,
+            WriteOptions {
+                comment_for: Some(CommentFn::new(|_, _| None)),
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
 
-block3 {
-    # comment
-}
+    #[test]
+    fn write_invalid_word() {
+        let mut doc = Scfg::new();
+        doc.add("motd").append_param("hello\nworld");
+        let err = doc.write(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "a word cannot contain '\\n' (found at byte 5)"
+        );
+    }
 
-block4 param1 "param2" {
-    dir1
-}"#;
-        let cfg = Scfg::from_str(src)?;
-        let mut exp = Scfg::new();
-        let block1 = exp.add("block1");
-        let block = block1.get_or_create_child();
-        block
-            .add("dir1")
-            .append_param("param1")
-            .append_param("param2");
-        block.add("dir2").append_param("param1");
-        exp.add("block2").get_or_create_child();
-        exp.add("block3").get_or_create_child();
-        exp.add("block4")
-            .append_param("param1")
-            .append_param("param2")
-            .get_or_create_child()
-            .add("dir1");
+    #[test]
+    fn checked_builders() -> Result {
+        let mut doc = Scfg::new();
+        doc.try_add("train")?
+            .try_append_param("Shinkansen")?
+            .try_append_params(vec!["Tōhoku", "東北"])?
+            .try_append_param("")?;
+        assert_eq!(
+            doc.get("train").unwrap().params(),
+            ["Shinkansen", "Tōhoku", "東北", ""]
+        );
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        assert_eq!(std::str::from_utf8(&out)?.parse::<Scfg>()?, doc);
 
-        assert_eq!(cfg, exp);
+        let err = doc.try_add("max\nspeed").unwrap_err();
+        assert_eq!((err.character(), err.position()), (Some('\n'), Some(3)));
+        assert_eq!(doc.try_add("").unwrap_err().character(), None);
+        assert!(doc.try_add("nul\0").is_err());
+        assert_eq!(doc.collect_all_names().len(), 1);
+
+        let train = &mut doc.get_all_mut("train").unwrap()[0];
+        let err = train.try_append_param("ō\0").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a word cannot contain '\\0' (found at byte 2)"
+        );
+        let err = train.try_append_params(["Jōetsu", "a\nb"]).unwrap_err();
+        assert_eq!(err.position(), Some(1));
+        assert_eq!(train.params().len(), 4);
         Ok(())
     }
 
     #[test]
-    fn nested() -> Result {
-        let src = r#"block1 {
-    block2 {
-        dir1 param1
-    }
+    fn append_params_reserves() {
+        let mut allow = Directive::new();
+        allow.append_params((0..1000).map(|i| i.to_string()));
+        assert_eq!(allow.params().len(), 1000);
+        assert_eq!(allow.params_mut().capacity(), 1000);
 
-    block3 {
+        // iterators without a lower bound still append everything.
+        allow.append_params((0..10).filter(|i| i % 2 == 0).map(|i| i.to_string()));
+        assert_eq!(allow.params()[1000..], ["0", "2", "4", "6", "8"]);
     }
-}
 
-block4 {
-    block5 {
-        block6 param1 {
-            dir1
-        }
+    #[test]
+    fn typed_getters() -> Result {
+        let doc = Scfg::from_str("flag\nport 6697 tls\nspeed fast\ntls true\n")?;
+        assert_eq!(doc.get_i64("port"), Ok(Some(Some(6697))));
+        assert_eq!(doc.get_i64("flag"), Ok(Some(None)));
+        assert_eq!(doc.get_i64("missing"), Ok(None));
+        assert!(doc.get_i64("speed").is_err());
+
+        assert_eq!(doc.get_bool("tls"), Ok(Some(Some(true))));
+        assert_eq!(doc.get_bool("flag"), Ok(Some(None)));
+        assert_eq!(doc.get_bool("missing"), Ok(None));
+        assert!(doc.get_bool("port").is_err());
+
+        assert_eq!(
+            doc.get_parsed::<String, _>("speed")?,
+            Some(Some("fast".into()))
+        );
+        assert_eq!(doc.get_parsed::<u8, _>("flag")?, Some(None));
+        assert_eq!(doc.get_parsed::<u8, _>("missing")?, None);
+        assert!(doc.get_parsed::<u8, _>("port").is_err());
+
+        let port = doc.get("port").unwrap();
+        assert_eq!(port.param_as::<u16>(0), Ok(Some(6697)));
+        assert_eq!(port.param_as::<u16>(2), Ok(None));
+        assert!(port.param_as::<u16>(1).is_err());
+        Ok(())
     }
 
-    dir1
-}"#;
-        let cfg = Scfg::from_str(src)?;
-        let mut exp = Scfg::new();
-        let block1 = exp.add("block1").get_or_create_child();
-        block1
-            .add("block2")
-            .get_or_create_child()
-            .add("dir1")
-            .append_param("param1");
-        block1.add("block3").get_or_create_child();
-        let block4 = exp.add("block4").get_or_create_child();
-        block4
-            .add("block5")
-            .get_or_create_child()
-            .add("block6")
-            .append_param("param1")
-            .get_or_create_child()
-            .add("dir1");
-        block4.add("dir1");
+    #[test]
+    fn optimize_memory() -> Result {
+        fn has_empty_entries(doc: &Scfg) -> bool {
+            doc.directives.values().any(|directives| {
+                directives.is_empty()
+                    || directives
+                        .iter()
+                        .filter_map(Directive::child)
+                        .any(has_empty_entries)
+            })
+        }
 
-        assert_eq!(cfg, exp);
+        let mut doc = Scfg::from_str(SHINKANSEN)?;
+        let mut trains = doc.get_all_mut("train").unwrap();
+        let train = trains[0].get_or_create_child();
+        for model in &mut train.get_all_mut("model").unwrap() {
+            let model = model.get_or_create_child();
+            model.remove("lines-served");
+            // get_all_mut cannot leave a name without directives, so build
+            // one directly.
+            model.directives.get_mut("weight").unwrap().clear();
+        }
+        drop(trains);
+        let write = |doc: &Scfg| -> std::result::Result<Vec<u8>, io::Error> {
+            let mut out = Vec::new();
+            doc.write(&mut out)?;
+            Ok(out)
+        };
+        let before = write(&doc)?;
+        assert!(has_empty_entries(&doc));
 
+        doc.optimize_memory();
+        assert!(!has_empty_entries(&doc));
+        assert_eq!(write(&doc)?, before);
+        let speeds = doc.get_all_path(&["train", "model", "max-speed"]);
+        assert_eq!(speeds.len(), 2);
+        assert_eq!(speeds[0].params(), ["320km/h"]);
+        let model = doc.get_path(&["train", "model"]).unwrap().child().unwrap();
+        assert_eq!(model.get_all("max-speed").unwrap().len(), 1);
+        assert_eq!(model.directives.len(), 1);
         Ok(())
     }
 
     #[test]
-    fn write() -> Result {
-        let src = r#"dir1 param1 param2 param3
-dir2
-dir3 param1
+    fn get_all_mut_removes_empty() -> Result {
+        let mut doc = Scfg::from_str("a 1\nb 2\nb 3\nc {\n\td 4\n}\n")?;
+        doc.get_all_mut("b").unwrap().clear();
+        doc.get_all_mut("c").unwrap()[0]
+            .get_or_create_child()
+            .get_all_mut("d")
+            .unwrap()
+            .retain(|d| d.params() != ["4"]);
+        let mut a = doc.get_all_mut("a").unwrap();
+        a.pop();
+        a.push(Directive::from_str("a 5")?);
+        drop(a);
 
-# comment
-dir4 "param 1" 'param 2'
-"#;
-        let doc = Scfg::from_str(src)?;
+        assert!(!doc.contains("b"));
+        assert_eq!(doc.get("b"), None);
+        assert_eq!(doc.get_all("b"), None);
+        assert!(doc.get_all_mut("b").is_none());
+        let names: Vec<_> = doc.directives.keys().collect();
+        assert_eq!(names, ["a", "c"]);
+        assert_eq!(doc.get_path(&["c", "d"]), None);
+        assert_eq!(doc, Scfg::from_str("a 5\nc {\n}\n")?);
         let mut out = Vec::new();
         doc.write(&mut out)?;
-        let exp = r#"dir1 param1 param2 param3
-dir2
-dir3 param1
-dir4 'param 1' 'param 2'
-"#;
-        assert_eq!(std::str::from_utf8(&out)?, exp);
+        assert_eq!(out, b"a 5\nc {\n}\n");
         Ok(())
     }
 
     #[test]
-    fn write_block() -> Result {
-        let src = r#"block1 {
-	dir1 param1 param2
-	dir2 param1
-}
+    fn positional() -> Result {
+        let params = |doc: &Scfg| -> Vec<String> {
+            doc.get_all("rule")
+                .unwrap_or_default()
+                .iter()
+                .map(|d| d.params().join(" "))
+                .collect()
+        };
+        let mut doc = Scfg::from_str("rule a\nrule b\nrule c\nzone x\n")?;
+        doc.insert_at("rule", 1, Directive::from_str("rule log")?)
+            .append_param("all");
+        assert_eq!(params(&doc), ["a", "log all", "b", "c"]);
+        doc.insert_at("rule", 0, Directive::from_str("rule first")?);
+        doc.insert_at("rule", 99, Directive::from_str("rule last")?);
+        assert_eq!(params(&doc), ["first", "a", "log all", "b", "c", "last"]);
+        let mut out = Vec::new();
+        doc.write(&mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "rule first\nrule a\nrule log all\nrule b\nrule c\nrule last\nzone x\n"
+        );
 
-block2 {
-}
+        assert!(doc.swap("rule", 0, 5));
+        assert!(doc.swap("rule", 2, 2));
+        assert!(!doc.swap("rule", 0, 6));
+        assert!(!doc.swap("missing", 0, 0));
+        assert_eq!(params(&doc), ["last", "a", "log all", "b", "c", "first"]);
 
-block3 {
-	# comment
-}
+        assert_eq!(doc.remove_at("rule", 6), None);
+        assert_eq!(doc.remove_at("missing", 0), None);
+        assert_eq!(doc.remove_at("rule", 5).unwrap().params(), ["first"]);
+        assert_eq!(doc.remove_at("rule", 0).unwrap().params(), ["last"]);
+        assert_eq!(doc.remove_at("rule", 1).unwrap().params(), ["log", "all"]);
+        assert_eq!(params(&doc), ["a", "b", "c"]);
 
-block4 param1 "param2" {
-	dir1
-}"#;
-        let doc = Scfg::from_str(src)?;
+        assert!(doc.remove_at("zone", 0).is_some());
+        assert!(!doc.contains("zone"));
+        assert_eq!(doc.get_all("zone"), None);
+        doc.insert_at("zone", 3, Directive::from_str("zone y")?);
+        assert_eq!(doc.get_all("zone").unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn pop_directive() -> Result {
+        let mut doc = Scfg::from_str("model E5\nmodel E6\nmodel E7\nline Tōhoku\n")?;
+        let last = doc.pop_directive("model").unwrap();
+        assert_eq!(last.params(), ["E7"]);
+        let models: Vec<_> = doc
+            .get_all("model")
+            .unwrap()
+            .iter()
+            .map(Directive::params)
+            .collect();
+        assert_eq!(models, [["E5"], ["E6"]]);
+
+        assert_eq!(doc.pop_directive("line").unwrap().params(), ["Tōhoku"]);
+        assert!(!doc.contains("line"));
+        assert_eq!(doc.pop_directive("line"), None);
+        assert_eq!(doc.pop_directive("weight"), None);
+
+        let mut doc = Scfg::from_str("a 1\nb 2\nc 3\nd 4\n")?;
+        doc.pop_directive("a");
+        let names: Vec<_> = doc.directives.keys().map(String::as_str).collect();
+        assert_eq!(names, ["b", "c", "d"]);
         let mut out = Vec::new();
         doc.write(&mut out)?;
-        let exp = r#"block1 {
-	dir1 param1 param2
-	dir2 param1
-}
+        assert_eq!(String::from_utf8(out)?, "b 2\nc 3\nd 4\n");
+        Ok(())
+    }
 
-block2 {
-}
+    #[test]
+    fn subtree() -> Result {
+        let doc = Scfg::from_str(SHINKANSEN)?;
+        let train = doc.subtree("train").unwrap();
+        assert_eq!(&train, doc.get("train").unwrap().child().unwrap());
+        let models = train.get_all("model").unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].params(), ["E5"]);
 
-block3 {
-}
+        let mut out = Vec::new();
+        train.write(&mut out)?;
+        assert_eq!(Scfg::from_str(std::str::from_utf8(&out)?)?, train);
 
-block4 param1 param2 {
-	dir1
-}
-"#;
-        assert_eq!(std::str::from_utf8(&out)?, exp);
+        let doc = Scfg::from_str("leaf value\n")?;
+        assert_eq!(doc.subtree("leaf"), None);
+        assert_eq!(doc.subtree("missing"), None);
         Ok(())
     }
+
+    #[test]
+    fn display_directive() {
+        let mut directive = Directive::new();
+        assert_eq!(directive.to_string(), "");
+        directive.append_param("plain");
+        assert_eq!(directive.to_string(), "plain");
+        directive.clear_params();
+        directive.append_param("needs quotes");
+        assert_eq!(directive.to_string(), "'needs quotes'");
+        directive.append_param("").append_param("it's");
+        directive.get_or_create_child().add("hidden");
+        assert_eq!(directive.to_string(), "'needs quotes' '' 'it'\\''s'");
+    }
 }
@@ -0,0 +1,171 @@
+/// Builds an [`Scfg`] document inline, with a syntax close to that of scfg
+/// files.
+///
+/// Each directive is a name followed by its parameters, and is terminated by
+/// either a `;` or a child block delimited by `{` and `}`. The terminator may be
+/// omitted for the last directive of a block.
+///
+/// Names may be identifiers, which are used as written, string literals, for
+/// names which aren't valid identifiers (like `"max-speed"`), or any expression
+/// in parentheses. Parameters may be literals, variables, or any expression in
+/// parentheses, and must implement `Into<String>`. Repeated directives are added
+/// in the order they appear.
+///
+/// ```
+/// # use scfg::*;
+/// static SCFG_DOC: &str = r#"train "Shinkansen" {
+///     model "E5" {
+///         max-speed 320km/h
+///         weight 453.5t
+///
+///         lines-served "Tōhoku" "Hokkaido"
+///     }
+///
+///     model "E7" {
+///         max-speed 275km/h
+///         weight 540t
+///
+///         lines-served "Hokuriku" "Jōetsu"
+///     }
+/// }"#;
+///
+/// let e7_speed = 275;
+/// let doc = scfg! {
+///     train "Shinkansen" {
+///         model "E5" {
+///             "max-speed" "320km/h";
+///             weight "453.5t";
+///             "lines-served" "Tōhoku" "Hokkaido";
+///         }
+///         model "E7" {
+///             "max-speed" (format!("{}km/h", e7_speed));
+///             weight "540t";
+///             "lines-served" "Hokuriku" "Jōetsu";
+///         }
+///     }
+/// };
+///
+/// assert_eq!(doc, SCFG_DOC.parse::<Scfg>().unwrap());
+/// ```
+///
+/// [`Scfg`]: crate::Scfg
+#[macro_export]
+macro_rules! scfg {
+    ($($body:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut doc = $crate::Scfg::new();
+        $crate::__scfg_block!(doc; $($body)*);
+        doc
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scfg_block {
+    ($block:ident;) => {};
+    ($block:ident; $name:tt $($rest:tt)*) => {
+        #[allow(unused_variables)]
+        let directive = $block.add($crate::__scfg_name!($name));
+        $crate::__scfg_directive!($block; directive; $($rest)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scfg_name {
+    ($name:ident) => {
+        stringify!($name)
+    };
+    ($name:literal) => {
+        $name
+    };
+    (($name:expr)) => {
+        $name
+    };
+    ({ $($body:tt)* }) => {
+        compile_error!("expected a directive name, found a block")
+    };
+    ($other:tt) => {
+        compile_error!(concat!(
+            "expected a directive name, found `",
+            stringify!($other),
+            "`"
+        ))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scfg_directive {
+    ($block:ident; $directive:ident;) => {};
+    ($block:ident; $directive:ident; ; $($rest:tt)*) => {
+        $crate::__scfg_block!($block; $($rest)*);
+    };
+    ($block:ident; $directive:ident; { $($body:tt)* } $($rest:tt)*) => {
+        {
+            #[allow(unused_variables)]
+            let child = $directive.get_or_create_child();
+            $crate::__scfg_block!(child; $($body)*);
+        }
+        $crate::__scfg_block!($block; $($rest)*);
+    };
+    ($block:ident; $directive:ident; - $($rest:tt)*) => {
+        compile_error!(
+            "expected a parameter, `;` or a block, found `-` (names and parameters containing `-` must be quoted)"
+        );
+    };
+    ($block:ident; $directive:ident; $param:literal $($rest:tt)*) => {
+        $directive.append_param($param);
+        $crate::__scfg_directive!($block; $directive; $($rest)*);
+    };
+    ($block:ident; $directive:ident; $param:ident $($rest:tt)*) => {
+        $directive.append_param($param);
+        $crate::__scfg_directive!($block; $directive; $($rest)*);
+    };
+    ($block:ident; $directive:ident; ($param:expr) $($rest:tt)*) => {
+        $directive.append_param($param);
+        $crate::__scfg_directive!($block; $directive; $($rest)*);
+    };
+    ($block:ident; $directive:ident; $other:tt $($rest:tt)*) => {
+        compile_error!(concat!(
+            "expected a parameter, `;` or a block, found `",
+            stringify!($other),
+            "` (names and parameters that aren't identifiers must be quoted)"
+        ));
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(scfg! {}, Scfg::new());
+    }
+
+    #[test]
+    fn repeated_and_nested() {
+        let name = String::from("dir3");
+        let param = "param 2";
+        let doc = scfg! {
+            dir1 "param1" param;
+            dir1;
+            block1 {
+                block2 "x" {}
+                (name) ("a".to_owned() + "b")
+            }
+            dir2
+        };
+        let mut exp = Scfg::new();
+        exp.add("dir1")
+            .append_param("param1")
+            .append_param("param 2");
+        exp.add("dir1");
+        let block1 = exp.add("block1").get_or_create_child();
+        block1.add("block2").append_param("x").get_or_create_child();
+        block1.add("dir3").append_param("ab");
+        exp.add("dir2");
+        assert_eq!(doc, exp);
+    }
+}
@@ -0,0 +1,609 @@
+//! Parameters restricted to a closed set of values, and parameters holding
+//! lists of values or `key=value` options.
+use crate::{suggest::did_you_mean, Directive, Map};
+use std::{convert::Infallible, error::Error, fmt, str::FromStr};
+
+/// The error returned by [`Directive::param_enum`] and its variants for a
+/// parameter which is missing or not one of the allowed values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumParamError {
+    /// The index of the parameter.
+    pub index: usize,
+    /// The value of the parameter, or `None` if the directive has no
+    /// parameter at `index`.
+    pub value: Option<String>,
+    /// The allowed values.
+    pub allowed: Vec<String>,
+    /// The allowed value closest to `value`, if it is close enough to be a
+    /// plausible typo.
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for EnumParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(ref value) => write!(f, "invalid parameter {} {:?}", self.index, value)?,
+            None => write!(f, "missing parameter {}", self.index)?,
+        }
+        write!(f, ", expected one of ")?;
+        for (i, value) in self.allowed.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}", value)?;
+        }
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, ", did you mean {:?}?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for EnumParamError {}
+
+/// The error returned by [`Directive::param_list`] and
+/// [`Directive::param_list_parsed`], where `E` is the error of parsing an
+/// element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError<E = Infallible> {
+    /// The directive has no parameter at `index`.
+    Missing {
+        /// The index of the parameter.
+        index: usize,
+    },
+    /// An element other than the trailing ones is empty, as in `"a,,b"`.
+    EmptyElement {
+        /// The index of the parameter.
+        index: usize,
+        /// The index of the element in the list.
+        element: usize,
+    },
+    /// An element could not be parsed.
+    Parse {
+        /// The index of the parameter.
+        index: usize,
+        /// The index of the element in the list.
+        element: usize,
+        /// The error of parsing the element.
+        error: E,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ParamError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ParamError::Missing { index } => write!(f, "missing parameter {}", index),
+            ParamError::EmptyElement { index, element } => {
+                write!(f, "parameter {}: element {} is empty", index, element)
+            }
+            ParamError::Parse {
+                index,
+                element,
+                ref error,
+            } => write!(f, "parameter {}: element {}: {}", index, element, error),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for ParamError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ParamError::Parse { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// How [`Directive::params_kv`] handles a key given by several parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// The first value of the key is kept, and later ones are ignored.
+    KeepFirst,
+    /// A repeated key is an error.
+    Error,
+}
+
+/// The error returned by [`Directive::params_kv`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvError {
+    /// The parameter at `index` does not contain `=`.
+    MissingEquals {
+        /// The index of the parameter.
+        index: usize,
+        /// The parameter.
+        param: String,
+    },
+    /// The parameter at `index` repeats a key, with [`DuplicateKeys::Error`].
+    DuplicateKey {
+        /// The index of the parameter.
+        index: usize,
+        /// The repeated key.
+        key: String,
+    },
+}
+
+impl fmt::Display for KvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            KvError::MissingEquals { index, ref param } => {
+                write!(f, "parameter {} {:?} is not a key=value pair", index, param)
+            }
+            KvError::DuplicateKey { index, ref key } => {
+                write!(f, "parameter {}: duplicate key {:?}", index, key)
+            }
+        }
+    }
+}
+
+impl Error for KvError {}
+
+/// The `key=value` options of a directive, returned by
+/// [`Directive::params_kv`].
+///
+/// Keys are iterated in the same order as the names of a [`Scfg`]: sorted, or
+/// in the order of the parameters with the `preserve_order` feature.
+///
+/// [`Scfg`]: crate::Scfg
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KvParams {
+    options: Map<String, String>,
+}
+
+impl KvParams {
+    /// Returns the value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// Returns the value of `key` parsed as a `T`, like
+    /// [`Directive::param_as`].
+    ///
+    /// # Errors
+    /// Returns the error of parsing the value.
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Result<Option<T>, T::Err> {
+        self.get(key).map(str::parse).transpose()
+    }
+
+    /// Does `key` have a value.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.options.contains_key(key)
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    /// Returns `true` if there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// Iterates over the keys and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.options
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+impl Directive {
+    /// Returns the parameter at `index` if it is one of the `allowed` values.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let levels = ["debug", "info", "warn"];
+    /// let level: Directive = "log-level info".parse().unwrap();
+    /// assert_eq!(level.param_enum(0, &levels), Ok("info"));
+    ///
+    /// let level: Directive = "log-level infro".parse().unwrap();
+    /// assert_eq!(
+    ///     level.param_enum(0, &levels).unwrap_err().to_string(),
+    ///     r#"invalid parameter 0 "infro", expected one of "debug", "info", "warn", did you mean "info"?"#
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is no parameter at `index`, or if it is not
+    /// one of the `allowed` values. The error suggests the closest allowed
+    /// value, if any is close enough.
+    pub fn param_enum<'a>(
+        &self,
+        index: usize,
+        allowed: &[&'a str],
+    ) -> Result<&'a str, EnumParamError> {
+        let found = self.find_enum(index, allowed, false)?;
+        Ok(allowed[found])
+    }
+
+    /// Returns the allowed value matching the parameter at `index`, ignoring
+    /// case, like [`param_enum`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let level: Directive = "log-level INFO".parse().unwrap();
+    /// assert_eq!(level.param_enum_ignore_case(0, &["debug", "info"]), Ok("info"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is no parameter at `index`, or if it is not
+    /// one of the `allowed` values, ignoring case.
+    ///
+    /// [`param_enum`]: Directive::param_enum
+    pub fn param_enum_ignore_case<'a>(
+        &self,
+        index: usize,
+        allowed: &[&'a str],
+    ) -> Result<&'a str, EnumParamError> {
+        let found = self.find_enum(index, allowed, true)?;
+        Ok(allowed[found])
+    }
+
+    /// Returns the value paired with the parameter at `index` in `variants`,
+    /// matching names like [`param_enum`]. This maps parameters to an enum of
+    /// the caller.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// #[derive(Debug, Clone, PartialEq)]
+    /// enum Level {
+    ///     Debug,
+    ///     Info,
+    /// }
+    /// let variants = [("debug", Level::Debug), ("info", Level::Info)];
+    /// let level: Directive = "log-level debug".parse().unwrap();
+    /// assert_eq!(level.param_enum_as(0, &variants), Ok(Level::Debug));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is no parameter at `index`, or if it is not
+    /// one of the names in `variants`.
+    ///
+    /// [`param_enum`]: Directive::param_enum
+    pub fn param_enum_as<T: Clone>(
+        &self,
+        index: usize,
+        variants: &[(&str, T)],
+    ) -> Result<T, EnumParamError> {
+        let names: Vec<_> = variants.iter().map(|&(name, _)| name).collect();
+        let found = self.find_enum(index, &names, false)?;
+        Ok(variants[found].1.clone())
+    }
+
+    /// Splits the parameter at `index` into a list of elements separated by
+    /// `sep`, for lists written as a single parameter.
+    ///
+    /// Whitespace around each element is trimmed, and empty trailing elements
+    /// are skipped, so `"a, b,"` is `["a", "b"]`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let admins: Directive = r#"admins "alice, bob,carol""#.parse().unwrap();
+    /// assert_eq!(admins.param_list(0, ','), Ok(vec!["alice", "bob", "carol"]));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is no parameter at `index`, or if an element
+    /// other than the trailing ones is empty.
+    pub fn param_list(&self, index: usize, sep: char) -> Result<Vec<&str>, ParamError> {
+        let param = self
+            .params
+            .get(index)
+            .ok_or(ParamError::Missing { index })?;
+        let mut list: Vec<_> = param.split(sep).map(str::trim).collect();
+        while list.last() == Some(&"") {
+            list.pop();
+        }
+        match list.iter().position(|element| element.is_empty()) {
+            Some(element) => Err(ParamError::EmptyElement { index, element }),
+            None => Ok(list),
+        }
+    }
+
+    /// Splits the parameter at `index` like [`param_list`], parsing each
+    /// element as a `T`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let ports: Directive = "ports 80,443".parse().unwrap();
+    /// assert_eq!(ports.param_list_parsed::<u16>(0, ','), Ok(vec![80, 443]));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error like [`param_list`], or the error of the first element
+    /// which cannot be parsed, along with its index in the list.
+    ///
+    /// [`param_list`]: Directive::param_list
+    pub fn param_list_parsed<T: FromStr>(
+        &self,
+        index: usize,
+        sep: char,
+    ) -> Result<Vec<T>, ParamError<T::Err>> {
+        let list = self.param_list(index, sep).map_err(|err| match err {
+            ParamError::Missing { index } => ParamError::Missing { index },
+            ParamError::EmptyElement { index, element } => {
+                ParamError::EmptyElement { index, element }
+            }
+            ParamError::Parse { error, .. } => match error {},
+        })?;
+        list.into_iter()
+            .enumerate()
+            .map(|(element, value)| {
+                value.parse().map_err(|error| ParamError::Parse {
+                    index,
+                    element,
+                    error,
+                })
+            })
+            .collect()
+    }
+
+    /// Parses the parameters from `start` onward as `key=value` options, as in
+    /// `upstream backend1 weight=5 max-conns=100`.
+    ///
+    /// The key is everything before the first `=`, so values may contain `=`
+    /// or be empty. `duplicates` tells how a key given twice is handled.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let upstream: Directive = "upstream backend1 weight=5 max-conns=100".parse().unwrap();
+    /// let options = upstream.params_kv(1, DuplicateKeys::Error).unwrap();
+    /// assert_eq!(options.get("max-conns"), Some("100"));
+    /// assert_eq!(options.get_parsed::<u32>("weight"), Ok(Some(5)));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a parameter does not contain `=`, or if a key is
+    /// repeated with [`DuplicateKeys::Error`].
+    pub fn params_kv(&self, start: usize, duplicates: DuplicateKeys) -> Result<KvParams, KvError> {
+        let mut options = Map::new();
+        for (index, param) in self.params.iter().enumerate().skip(start) {
+            let (key, value) = match param.find('=') {
+                Some(i) => (&param[..i], &param[i + 1..]),
+                None => {
+                    return Err(KvError::MissingEquals {
+                        index,
+                        param: param.clone(),
+                    })
+                }
+            };
+            if options.contains_key(key) {
+                match duplicates {
+                    DuplicateKeys::KeepFirst => continue,
+                    DuplicateKeys::Error => {
+                        return Err(KvError::DuplicateKey {
+                            index,
+                            key: key.to_owned(),
+                        })
+                    }
+                }
+            }
+            options.insert(key.to_owned(), value.to_owned());
+        }
+        Ok(KvParams { options })
+    }
+
+    /// Returns the index in `allowed` of the parameter at `index`.
+    fn find_enum(
+        &self,
+        index: usize,
+        allowed: &[&str],
+        ignore_case: bool,
+    ) -> Result<usize, EnumParamError> {
+        let value = self.params.get(index);
+        let matches = |candidate: &str, value: &str| {
+            if ignore_case {
+                candidate.to_lowercase() == value.to_lowercase()
+            } else {
+                candidate == value
+            }
+        };
+        if let Some(found) = value.and_then(|value| allowed.iter().position(|a| matches(a, value)))
+        {
+            return Ok(found);
+        }
+        let suggestion = value.and_then(|value| {
+            if ignore_case {
+                let lowercase: Vec<_> = allowed.iter().map(|a| a.to_lowercase()).collect();
+                let suggestion =
+                    did_you_mean(&value.to_lowercase(), lowercase.iter().map(String::as_str))?;
+                let found = lowercase.iter().position(|a| a == suggestion)?;
+                Some(allowed[found].to_owned())
+            } else {
+                did_you_mean(value, allowed.iter().copied()).map(str::to_owned)
+            }
+        });
+        Err(EnumParamError {
+            index,
+            value: value.cloned(),
+            allowed: allowed.iter().map(|&a| a.to_owned()).collect(),
+            suggestion,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+
+    fn directive(src: &str) -> Directive {
+        src.parse().unwrap()
+    }
+
+    #[test]
+    fn exact() {
+        let level = directive("log-level warn info");
+        assert_eq!(level.param_enum(0, &LEVELS), Ok("warn"));
+        assert_eq!(level.param_enum(1, &LEVELS), Ok("info"));
+        let err = directive("log-level Warn")
+            .param_enum(0, &LEVELS)
+            .unwrap_err();
+        assert_eq!(err.value.as_deref(), Some("Warn"));
+        assert_eq!(err.suggestion.as_deref(), Some("warn"));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let level = directive("log-level WaRn");
+        assert_eq!(level.param_enum_ignore_case(0, &LEVELS), Ok("warn"));
+        let err = directive("log-level EROR")
+            .param_enum_ignore_case(0, &LEVELS)
+            .unwrap_err();
+        assert_eq!(err.suggestion.as_deref(), Some("error"));
+    }
+
+    #[test]
+    fn suggestions() {
+        let err = directive("log-level infro")
+            .param_enum(0, &LEVELS)
+            .unwrap_err();
+        assert_eq!(err.suggestion.as_deref(), Some("info"));
+        assert_eq!(
+            err.to_string(),
+            r#"invalid parameter 0 "infro", expected one of "debug", "info", "warn", "error", did you mean "info"?"#
+        );
+
+        let err = directive("log-level verbose")
+            .param_enum(0, &LEVELS)
+            .unwrap_err();
+        assert_eq!(err.suggestion, None);
+        assert_eq!(
+            err.to_string(),
+            r#"invalid parameter 0 "verbose", expected one of "debug", "info", "warn", "error""#
+        );
+
+        let err = directive("log-level").param_enum(0, &LEVELS).unwrap_err();
+        assert_eq!((err.value, err.suggestion), (None, None));
+    }
+
+    #[test]
+    fn list() {
+        let admins = directive(r#"admins "alice, bob ,carol,, " plain"#);
+        assert_eq!(admins.param_list(0, ','), Ok(vec!["alice", "bob", "carol"]));
+        assert_eq!(admins.param_list(1, ','), Ok(vec!["plain"]));
+        assert_eq!(
+            admins.param_list(2, ','),
+            Err(ParamError::Missing { index: 2 })
+        );
+        assert_eq!(directive(r#"admins """#).param_list(0, ','), Ok(vec![]));
+        assert_eq!(
+            directive("admins a:b").param_list(0, ':'),
+            Ok(vec!["a", "b"])
+        );
+
+        let err = directive("admins a,,b").param_list(0, ',').unwrap_err();
+        assert_eq!(
+            err,
+            ParamError::EmptyElement {
+                index: 0,
+                element: 1
+            }
+        );
+        assert_eq!(err.to_string(), "parameter 0: element 1 is empty");
+        assert_eq!(
+            directive(r#"admins ", a""#).param_list(0, ','),
+            Err(ParamError::EmptyElement {
+                index: 0,
+                element: 0
+            })
+        );
+    }
+
+    #[test]
+    fn list_parsed() {
+        let ports = directive(r#"ports "80, 443, 8080,""#);
+        assert_eq!(
+            ports.param_list_parsed::<u16>(0, ','),
+            Ok(vec![80, 443, 8080])
+        );
+
+        let err = directive("ports 80,http,443")
+            .param_list_parsed::<u16>(0, ',')
+            .unwrap_err();
+        match err {
+            ParamError::Parse { index, element, .. } => assert_eq!((index, element), (0, 1)),
+            _ => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(
+            err.to_string(),
+            "parameter 0: element 1: invalid digit found in string"
+        );
+        assert!(err.source().is_some());
+        assert_eq!(
+            directive("ports 80,,443").param_list_parsed::<u16>(0, ','),
+            Err(ParamError::EmptyElement {
+                index: 0,
+                element: 1
+            })
+        );
+    }
+
+    #[test]
+    fn kv() {
+        let upstream = directive(r#"upstream backend1 weight=5 backup= url="a=b?c=d""#);
+        let options = upstream.params_kv(1, DuplicateKeys::Error).unwrap();
+        assert_eq!(options.len(), 3);
+        assert_eq!(options.get("weight"), Some("5"));
+        assert_eq!(options.get("backup"), Some(""));
+        assert_eq!(options.get("url"), Some("a=b?c=d"));
+        assert_eq!(options.get("max-conns"), None);
+        assert_eq!(options.get_parsed::<u32>("weight"), Ok(Some(5)));
+        assert_eq!(options.get_parsed::<u32>("max-conns"), Ok(None));
+        assert!(options.get_parsed::<u32>("backup").is_err());
+        assert!(upstream
+            .params_kv(4, DuplicateKeys::Error)
+            .unwrap()
+            .is_empty());
+
+        let err = upstream.params_kv(0, DuplicateKeys::Error).unwrap_err();
+        assert_eq!(
+            err,
+            KvError::MissingEquals {
+                index: 0,
+                param: "backend1".to_owned()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            r#"parameter 0 "backend1" is not a key=value pair"#
+        );
+    }
+
+    #[test]
+    fn kv_duplicates() {
+        let upstream = directive("upstream weight=5 max-conns=100 weight=1");
+        let options = upstream.params_kv(0, DuplicateKeys::KeepFirst).unwrap();
+        assert_eq!(options.get("weight"), Some("5"));
+        assert_eq!(options.len(), 2);
+        let err = upstream.params_kv(0, DuplicateKeys::Error).unwrap_err();
+        assert_eq!(
+            err,
+            KvError::DuplicateKey {
+                index: 2,
+                key: "weight".to_owned()
+            }
+        );
+        assert_eq!(err.to_string(), r#"parameter 2: duplicate key "weight""#);
+    }
+
+    #[test]
+    fn typed() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Level {
+            Debug,
+            Info,
+        }
+        let variants = [("debug", Level::Debug), ("info", Level::Info)];
+        assert_eq!(
+            directive("log-level info").param_enum_as(0, &variants),
+            Ok(Level::Info)
+        );
+        let err = directive("log-level debgu")
+            .param_enum_as(0, &variants)
+            .unwrap_err();
+        assert_eq!(err.allowed, ["debug", "info"]);
+        assert_eq!(err.suggestion.as_deref(), Some("debug"));
+    }
+}
@@ -1,11 +1,20 @@
 use crate::Directive;
 use crate::Scfg;
+use std::borrow::Cow;
 use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug)]
 enum ErrorKind {
     UnexpectedClosingBrace,
+    MisplacedOpeningBrace,
+    InvalidCharacter(char),
+    NoDirective,
+    ExtraDirective,
+    Timeout(Duration),
+    InvalidEscape(String),
     Io(io::Error),
     ShellWords(shell_words::ParseError),
 }
@@ -14,13 +23,82 @@ enum ErrorKind {
 pub struct Error {
     kind: ErrorKind,
     lineno: usize,
+    path: Option<PathBuf>,
+}
+
+impl Error {
+    fn new(kind: ErrorKind, lineno: usize) -> Self {
+        Error {
+            kind,
+            lineno,
+            path: None,
+        }
+    }
+
+    /// An error for a file which couldn't be read at all.
+    pub(crate) fn open(err: io::Error) -> Self {
+        Error::new(ErrorKind::Io(err), 0)
+    }
+
+    /// An error for a document which couldn't be written to be parsed again.
+    pub(crate) fn unwritable(err: io::Error) -> Self {
+        Error::new(ErrorKind::Io(err), 0)
+    }
+
+    /// An error for a document which wasn't fully read before `timeout`.
+    pub(crate) fn timeout(timeout: Duration) -> Self {
+        Error::new(ErrorKind::Timeout(timeout), 0)
+    }
+
+    /// Annotates the error with the path of the file being parsed, which is
+    /// shown before the message.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let err = "}".parse::<Scfg>().unwrap_err().with_file_path("app.scfg");
+    /// assert_eq!(err.file_path(), Some("app.scfg".as_ref()));
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "app.scfg: parsing error at line 1: unexpected '}'"
+    /// );
+    /// ```
+    pub fn with_file_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Returns the path of the file the error occurred in, if known.
+    pub fn file_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns whether parsing was abandoned because the document wasn't read
+    /// in time, see [`Scfg::parse_with_timeout`].
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.kind, ErrorKind::Timeout(_))
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "parsing error at line {}: ", self.lineno)?;
+        if let Some(ref path) = self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        if self.lineno == 0 {
+            write!(f, "parsing error: ")?;
+        } else {
+            write!(f, "parsing error at line {}: ", self.lineno)?;
+        }
         match &self.kind {
             ErrorKind::UnexpectedClosingBrace => write!(f, "unexpected '}}'"),
+            ErrorKind::MisplacedOpeningBrace => {
+                write!(f, "unexpected '{{', a block must follow a directive name")
+            }
+            ErrorKind::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            ErrorKind::NoDirective => write!(f, "expected a directive"),
+            ErrorKind::ExtraDirective => write!(f, "expected a single directive"),
+            ErrorKind::Timeout(timeout) => write!(f, "timed out after {:?}", timeout),
+            ErrorKind::InvalidEscape(escape) => write!(f, "invalid unicode escape {:?}", escape),
             ErrorKind::Io(err) => write!(f, "io: {}", err),
             ErrorKind::ShellWords(err) => write!(f, "{}", err),
         }
@@ -37,46 +115,224 @@ impl std::error::Error for Error {
     }
 }
 
-pub fn document(mut r: impl io::BufRead) -> Result<Scfg, Error> {
+/// Options of [`Scfg::parse_with_options`].
+///
+/// The default options parse documents the same way as [`FromStr`]. New
+/// options may be added, so this should be created with
+/// `..Default::default()`.
+///
+/// [`FromStr`]: std::str::FromStr
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Decodes `\uXXXX` and `\u{X}` escapes, of one to six hexadecimal
+    /// digits, inside double-quoted words, like `"tab:\u0009"`. This is an
+    /// extension of scfg, off by default, in which case backslashes in
+    /// double-quoted words only escape `"`, `\`, `$` and `` ` ``. Escapes of
+    /// surrogates, of NUL and of values above `10FFFF` are errors. See
+    /// [`WriteOptions::unicode_escapes`] for the writer's counterpart.
+    ///
+    /// [`WriteOptions::unicode_escapes`]: crate::WriteOptions::unicode_escapes
+    pub unicode_escapes: bool,
+    /// Joins the lines ending with a backslash, which is not itself escaped,
+    /// to the next line, the backslash being replaced by a space, like in
+    /// shell scripts. The directive is on the line of its first word. This is
+    /// an extension of scfg, off by default, written by
+    /// [`WriteOptions::max_width`]. Comment lines are never continued.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let options = ParseOptions {
+    ///     line_continuations: true,
+    ///     ..Default::default()
+    /// };
+    /// let doc = Scfg::parse_with_options("lines Tōhoku \\\n\tHokkaido\n".as_bytes(), &options)
+    ///     .unwrap();
+    /// assert_eq!(doc.get("lines").unwrap().params(), ["Tōhoku", "Hokkaido"]);
+    /// ```
+    ///
+    /// [`WriteOptions::max_width`]: crate::WriteOptions::max_width
+    pub line_continuations: bool,
+}
+
+/// Where [`Scfg::from_block_reader`] stopped reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoppedOn {
+    /// The end of the input.
+    Eof,
+    /// A line holding only `}`, at the given line number.
+    ClosingBrace(usize),
+}
+
+/// A problem found in a document which does not prevent parsing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    kind: WarningKind,
+    lineno: usize,
+}
+
+/// The kind of a [`Warning`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// A word holds a control character, such as a tab inside quotes, which
+    /// other scfg parsers may split on or reject. This is reported for the characters
+    /// for which [`char::is_control`] is true: the C0 controls, including tab
+    /// and carriage return, DEL and the C1 controls.
+    ControlCharacter {
+        /// The index of the word in its line: 0 for the directive name, and
+        /// `i + 1` for parameter `i`.
+        word: usize,
+        /// The first control character of the word.
+        character: char,
+    },
+}
+
+impl Warning {
+    /// Returns the kind of problem.
+    pub fn kind(&self) -> &WarningKind {
+        &self.kind
+    }
+
+    /// Returns the line of the problem, counting from 1.
+    pub fn line(&self) -> usize {
+        self.lineno
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.lineno, self.kind)
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            WarningKind::ControlCharacter { word, character } => {
+                match word {
+                    0 => write!(f, "directive name")?,
+                    i => write!(f, "parameter {}", i - 1)?,
+                }
+                write!(
+                    f,
+                    " contains the control character {:?}, which other parsers may not accept",
+                    character
+                )
+            }
+        }
+    }
+}
+
+pub fn block(mut r: impl io::BufRead) -> Result<(Scfg, StoppedOn), Error> {
+    let mut lineno = 0;
+    let options = ParseOptions::default();
+    let (block, closing_brace) = read_block(&mut r, &mut lineno, 0, &options, &mut Vec::new())?;
+    let stopped_on = if closing_brace {
+        StoppedOn::ClosingBrace(lineno)
+    } else {
+        StoppedOn::Eof
+    };
+    Ok((block, stopped_on))
+}
+
+pub fn document(r: impl io::BufRead) -> Result<Scfg, Error> {
+    document_with_warnings(r, &ParseOptions::default(), &mut Vec::new())
+}
+
+/// Reads a document with `options`, adding the problems found along the way
+/// to `warnings`.
+pub fn document_with_warnings(
+    mut r: impl io::BufRead,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Scfg, Error> {
+    #[cfg(feature = "tracing")]
+    let mut r = crate::instrument::Counting::new(&mut r);
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "document",
+        bytes = tracing::field::Empty,
+        lines = tracing::field::Empty,
+    )
+    .entered();
+
     let mut lineno = 0;
-    let (block, closing_brace) = read_block(&mut r, &mut lineno)?;
+    let result = read_block(&mut r, &mut lineno, 0, options, warnings);
+    #[cfg(feature = "tracing")]
+    span.record("bytes", r.bytes()).record("lines", r.lines());
+    let (block, closing_brace) = result?;
     if closing_brace {
-        return Err(Error {
-            kind: ErrorKind::UnexpectedClosingBrace,
-            lineno,
-        });
+        return Err(Error::new(ErrorKind::UnexpectedClosingBrace, lineno));
     }
     Ok(block)
 }
 
+/// Reads a document holding exactly one top-level directive.
+pub fn directive(r: impl io::BufRead) -> Result<Directive, Error> {
+    let doc = document(r)?;
+    let mut directives: Vec<_> = doc.directives.into_values().flatten().collect();
+    directives.sort_by_key(|directive| directive.line);
+    if directives.len() > 1 {
+        let lineno = directives[1].line.unwrap_or(0);
+        return Err(Error::new(ErrorKind::ExtraDirective, lineno));
+    }
+    directives
+        .pop()
+        .ok_or_else(|| Error::new(ErrorKind::NoDirective, 0))
+}
+
 /// Reads a block.
 ///
 /// Returns `(block, closing_brace)` where `closing_brace` is true if parsing stopped on '}', and
 /// false if parsing stopped on EOF.
 ///
 /// `lineno` must be set the line number of the first line of the block minus one, and is set to
-/// the line number of the closing bracket or EOF.
-fn read_block<R: io::BufRead>(r: &mut R, lineno: &mut usize) -> Result<(Scfg, bool), Error> {
+/// the line number of the closing bracket or EOF. `depth` is the number of blocks enclosing the
+/// block, 0 for a document. Problems which do not prevent parsing are added to `warnings`.
+#[cfg_attr(not(feature = "tracing"), allow(clippy::only_used_in_recursion))]
+fn read_block<R: io::BufRead>(
+    r: &mut R,
+    lineno: &mut usize,
+    depth: usize,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<(Scfg, bool), Error> {
     let mut block = Scfg::new();
     let mut line = String::new();
 
     loop {
         *lineno += 1;
         line.clear();
-        let n = r.read_line(&mut line).map_err(|err| Error {
-            kind: ErrorKind::Io(err),
-            lineno: *lineno,
-        })?;
+        let n = r
+            .read_line(&mut line)
+            .map_err(|err| Error::new(ErrorKind::Io(err), *lineno))?;
         if n == 0 {
             // reached EOF.
             return Ok((block, false));
         }
+        #[cfg(feature = "tracing")]
+        if n > crate::instrument::LONG_LINE {
+            tracing::debug!(line = *lineno, bytes = n, "long line");
+        }
+        let directive_lineno = *lineno;
+        if options.line_continuations {
+            read_continuations(r, lineno, &mut line)?;
+        }
+        if line.contains('\0') {
+            // binary input, which would produce unwritable words.
+            return Err(Error::new(ErrorKind::InvalidCharacter('\0'), *lineno));
+        }
         let line = line.trim();
+        let line = if options.unicode_escapes {
+            decode_unicode_escapes(line)
+                .map_err(|escape| Error::new(ErrorKind::InvalidEscape(escape), *lineno))?
+        } else {
+            Cow::Borrowed(line)
+        };
+        let line = line.as_ref();
 
-        let mut words = shell_words::split(&line).map_err(|err| Error {
-            kind: ErrorKind::ShellWords(err),
-            lineno: *lineno,
-        })?;
+        let mut words = shell_words::split(line)
+            .map_err(|err| Error::new(ErrorKind::ShellWords(err), *lineno))?;
         if words.is_empty() {
             // line is either empty or a comment.
             continue;
@@ -88,26 +344,59 @@ fn read_block<R: io::BufRead>(r: &mut R, lineno: &mut usize) -> Result<(Scfg, bo
             return Ok((block, true));
         }
 
+        if line.starts_with('{') && line != "{" {
+            // `{ foo` would otherwise be a directive named `{`.
+            return Err(Error::new(ErrorKind::MisplacedOpeningBrace, *lineno));
+        }
+
+        for (word, chars) in words.iter().enumerate() {
+            if let Some(character) = chars.chars().find(|c| c.is_control()) {
+                let kind = WarningKind::ControlCharacter { word, character };
+                #[cfg(feature = "tracing")]
+                tracing::debug!(line = *lineno, "{}", kind);
+                warnings.push(Warning {
+                    kind,
+                    lineno: *lineno,
+                });
+            }
+        }
+
         let has_child = words.last().unwrap() == "{" && last_byte == b'{'; // avoid matching `"{"`
-        let (name, directive) = if has_child {
+        let has_empty_child = words.len() > 1 && is_empty_block(line, &words);
+        let (name, directive) = if has_child || has_empty_child {
             words.pop(); // remove brace
             let name = if words.is_empty() {
                 String::new()
             } else {
                 words.remove(0)
             };
-            let (child, closing_brace) = read_block(r, lineno)?;
-            if !closing_brace {
-                return Err(Error {
-                    kind: ErrorKind::Io(io::ErrorKind::UnexpectedEof.into()),
-                    lineno: *lineno,
-                });
-            }
+            let child = if has_empty_child {
+                Scfg::new()
+            } else {
+                #[cfg(feature = "tracing")]
+                let _span = {
+                    let depth = depth + 1;
+                    if depth == crate::instrument::DEEP_NESTING + 1 {
+                        tracing::debug!(line = directive_lineno, depth, "deep nesting");
+                    }
+                    tracing::debug_span!("block", name = %name, depth, line = directive_lineno)
+                        .entered()
+                };
+                let (child, closing_brace) = read_block(r, lineno, depth + 1, options, warnings)?;
+                if !closing_brace {
+                    return Err(Error::new(
+                        ErrorKind::Io(io::ErrorKind::UnexpectedEof.into()),
+                        *lineno,
+                    ));
+                }
+                child
+            };
             (
                 name,
                 Directive {
                     params: words,
                     child: Some(child),
+                    line: Some(directive_lineno),
                 },
             )
         } else {
@@ -117,6 +406,7 @@ fn read_block<R: io::BufRead>(r: &mut R, lineno: &mut usize) -> Result<(Scfg, bo
                 Directive {
                     params: words,
                     child: None,
+                    line: Some(directive_lineno),
                 },
             )
         };
@@ -124,6 +414,115 @@ fn read_block<R: io::BufRead>(r: &mut R, lineno: &mut usize) -> Result<(Scfg, bo
     }
 }
 
+/// Appends the lines continuing `line` to it, see
+/// [`ParseOptions::line_continuations`]. `lineno` is set to the line number of
+/// the last line read.
+fn read_continuations<R: io::BufRead>(
+    r: &mut R,
+    lineno: &mut usize,
+    line: &mut String,
+) -> Result<(), Error> {
+    loop {
+        let end = line.trim_end().len();
+        let backslashes = line[..end]
+            .bytes()
+            .rev()
+            .take_while(|&b| b == b'\\')
+            .count();
+        if backslashes % 2 == 0 || line.trim_start().starts_with('#') {
+            return Ok(());
+        }
+        line.truncate(end - 1);
+        line.push(' ');
+        *lineno += 1;
+        let n = r
+            .read_line(line)
+            .map_err(|err| Error::new(ErrorKind::Io(err), *lineno))?;
+        if n == 0 {
+            // the last line is continued by EOF.
+            *lineno -= 1;
+            return Ok(());
+        }
+    }
+}
+
+/// Decodes the unicode escapes inside the double-quoted words of `line`, see
+/// [`ParseOptions::unicode_escapes`]. Decoded quotes and backslashes are
+/// escaped again, for the tokenizer. Returns the invalid escape on error.
+fn decode_unicode_escapes(line: &str) -> Result<Cow<'_, str>, String> {
+    if !line.contains("\\u") {
+        return Ok(Cow::Borrowed(line));
+    }
+    let mut out = String::with_capacity(line.len());
+    let mut quote = None;
+    let mut word_start = true;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let mut len = c.len_utf8();
+        match (quote, c) {
+            (None, '#') if word_start => {
+                // the rest of the line is a comment.
+                out.push_str(rest);
+                break;
+            }
+            (Some('"'), '\\') if rest[1..].starts_with('u') => {
+                let (decoded, n) = unicode_escape(&rest[2..]).ok_or_else(|| {
+                    let digits = rest[2..]
+                        .chars()
+                        .take_while(|&c| c.is_ascii_hexdigit() || c == '{' || c == '}');
+                    format!("\\u{}", digits.collect::<String>())
+                })?;
+                if decoded == '"' || decoded == '\\' {
+                    out.push('\\');
+                }
+                out.push(decoded);
+                rest = &rest[2 + n..];
+                word_start = false;
+                continue;
+            }
+            (Some(q), c) if q == c => quote = None,
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                // the next character is escaped.
+                len += rest[1..].chars().next().map_or(0, char::len_utf8);
+            }
+            _ => {}
+        }
+        out.push_str(&rest[..len]);
+        rest = &rest[len..];
+        word_start = quote.is_none() && c.is_whitespace();
+    }
+    Ok(Cow::Owned(out))
+}
+
+/// Decodes the escape at the start of `rest`, which follows `\u`, returning
+/// the character and the length of the escape without `\u`.
+fn unicode_escape(rest: &str) -> Option<(char, usize)> {
+    let (digits, len) = match rest.strip_prefix('{') {
+        Some(braced) => {
+            let end = braced.find('}')?;
+            (&braced[..end], end + 2)
+        }
+        None => (rest.get(..4)?, 4),
+    };
+    if digits.is_empty() || digits.len() > 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let c = u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .filter(|&c| c != '\0')?;
+    Some((c, len))
+}
+
+/// Returns true if the line ends with a separate, unquoted `{}`, an empty block
+/// on the same line as its directive.
+fn is_empty_block(line: &str, words: &[String]) -> bool {
+    words.last().map(String::as_str) == Some("{}")
+        && matches!(line.strip_suffix("{}"), Some(rest) if rest.ends_with(char::is_whitespace))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -186,4 +585,213 @@ listen 127.0.0.1:6667
         assert!(matches!(err.kind, ErrorKind::ShellWords(_)));
         assert_eq!(err.lineno, 5);
     }
+
+    #[test]
+    fn nul_byte() {
+        let src = "domain example.com\nlisten 0.0.0.0:6697\0\n";
+
+        let err = Scfg::from_str(src).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidCharacter('\0')));
+        assert_eq!(err.lineno, 2);
+        assert_eq!(
+            err.to_string(),
+            "parsing error at line 2: invalid character '\\0'"
+        );
+    }
+
+    #[test]
+    fn misplaced_opening_brace() {
+        for src in [
+            "domain example.com\n{ foo\n",
+            "domain example.com\n{foo {\n}\n",
+        ] {
+            let err = Scfg::from_str(src).unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::MisplacedOpeningBrace));
+            assert_eq!(err.lineno, 2);
+            assert_eq!(
+                err.to_string(),
+                "parsing error at line 2: unexpected '{', a block must follow a directive name"
+            );
+        }
+
+        // a quoted brace is an ordinary word
+        let doc = Scfg::from_str("\"{\" foo\n").unwrap();
+        assert_eq!(doc.get("{").unwrap().params(), ["foo"]);
+    }
+
+    #[test]
+    fn empty_block_on_one_line() {
+        let doc = Scfg::from_str("a {}\nb x  {}\nc \"{}\"\nd ''{}\n").unwrap();
+        assert_eq!(doc.get("a").unwrap().child(), Some(&Scfg::new()));
+        let b = doc.get("b").unwrap();
+        assert_eq!(b.params(), ["x"]);
+        assert_eq!(b.child(), Some(&Scfg::new()));
+        assert_eq!(doc.get("c").unwrap().params(), ["{}"]);
+        assert_eq!(doc.get("d").unwrap().params(), ["{}"]);
+    }
+
+    #[test]
+    fn single_directive() {
+        let directive: Directive = "max-speed 320km/h".parse().unwrap();
+        assert_eq!(directive.params(), ["320km/h"]);
+        assert_eq!(directive.line(), Some(1));
+        assert_eq!(directive.child(), None);
+
+        let src = "# E5\nmodel \"E5\" \"Hayabusa\" {\n\tmax-speed 320km/h\n}\n";
+        let directive: Directive = src.parse().unwrap();
+        assert_eq!(directive.params(), ["E5", "Hayabusa"]);
+        assert_eq!(directive.line(), Some(2));
+        let child = directive.child().unwrap();
+        assert_eq!(child.get("max-speed").unwrap().params(), ["320km/h"]);
+
+        let err = "b\n\na\n".parse::<Directive>().unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::ExtraDirective));
+        assert_eq!(
+            err.to_string(),
+            "parsing error at line 3: expected a single directive"
+        );
+        let err = "# nothing\n".parse::<Directive>().unwrap_err();
+        assert_eq!(err.to_string(), "parsing error: expected a directive");
+    }
+
+    #[test]
+    fn control_characters() {
+        let src = "a \"b\tc\"\n'x\u{7f}' {\n\td \"e\" '\u{85}'\n}\nf\tg\n";
+        let mut warnings = Vec::new();
+        let doc = document_with_warnings(
+            io::Cursor::new(src),
+            &ParseOptions::default(),
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(doc.get("a").unwrap().params(), ["b\tc"]);
+        assert_eq!(doc.get("f").unwrap().params(), ["g"]);
+        let found: Vec<_> = warnings
+            .iter()
+            .map(|warning| (warning.line(), warning.kind().clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    1,
+                    WarningKind::ControlCharacter {
+                        word: 1,
+                        character: '\t'
+                    }
+                ),
+                (
+                    2,
+                    WarningKind::ControlCharacter {
+                        word: 0,
+                        character: '\u{7f}'
+                    }
+                ),
+                (
+                    3,
+                    WarningKind::ControlCharacter {
+                        word: 2,
+                        character: '\u{85}'
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 1: parameter 0 contains the control character '\\t', which other parsers may not accept"
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            "line 2: directive name contains the control character '\\u{7f}', which other parsers may not accept"
+        );
+    }
+
+    #[test]
+    fn block_reader() {
+        let (block, stopped_on) = Scfg::from_block_reader("a 1\nb {\n\tc\n}\n".as_bytes()).unwrap();
+        assert_eq!(stopped_on, StoppedOn::Eof);
+        assert_eq!(block.get("b").unwrap().line(), Some(2));
+
+        let (block, stopped_on) = Scfg::from_block_reader("\n  a 1\n  }\n}\n".as_bytes()).unwrap();
+        assert_eq!(stopped_on, StoppedOn::ClosingBrace(3));
+        assert_eq!(block.get("a").unwrap().line(), Some(2));
+
+        let err = Scfg::from_block_reader("a {\n".as_bytes()).unwrap_err();
+        assert_eq!(err.lineno, 2);
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let extended = ParseOptions {
+            unicode_escapes: true,
+            ..Default::default()
+        };
+        let parse =
+            |src: &str, options: &ParseOptions| Scfg::parse_with_options(src.as_bytes(), options);
+
+        let src = "sep \"\\u0009\" \"\\u{1F685}x\" '\\u0041' \\u0041 \"\\u0022\\\\u0041\" # \"\\uD800\"\n";
+        let doc = parse(src, &extended).unwrap();
+        assert_eq!(
+            doc.get("sep").unwrap().params(),
+            ["\t", "\u{1F685}x", "\\u0041", "u0041", "\"\\u0041"]
+        );
+        let doc = parse(src, &ParseOptions::default()).unwrap();
+        assert_eq!(doc, Scfg::from_str(src).unwrap());
+        assert_eq!(
+            doc.get("sep").unwrap().params(),
+            [
+                "\\u0009",
+                "\\u{1F685}x",
+                "\\u0041",
+                "u0041",
+                "\\u0022\\u0041"
+            ]
+        );
+
+        for escape in [
+            "\\uD800",
+            "\\u{DFFF}",
+            "\\u{110000}",
+            "\\u{0}",
+            "\\u00G1",
+            "\\u{}",
+        ] {
+            let src = format!("a {{\n\tb \"{}\"\n}}\n", escape);
+            let err = parse(&src, &extended).unwrap_err();
+            assert!(
+                matches!(err.kind, ErrorKind::InvalidEscape(_)),
+                "{}",
+                escape
+            );
+            assert_eq!(err.lineno, 2);
+            assert!(parse(&src, &ParseOptions::default()).is_ok());
+        }
+        assert_eq!(
+            parse("b \"\\uD800\"\n", &extended).unwrap_err().to_string(),
+            "parsing error at line 1: invalid unicode escape \"\\\\uD800\""
+        );
+    }
+
+    #[test]
+    fn line_continuations() {
+        let options = ParseOptions {
+            line_continuations: true,
+            ..Default::default()
+        };
+        let parse = |src: &str| Scfg::parse_with_options(src.as_bytes(), &options);
+
+        let src = "a b \\\n  c\\\n\td {\n\t# e \\\n\tf 'g \\\\' \\\\\n}\nh \\";
+        let doc = parse(src).unwrap();
+        let a = doc.get("a").unwrap();
+        assert_eq!(a.params(), ["b", "c", "d"]);
+        assert_eq!(a.line(), Some(1));
+        let f = a.child().unwrap().get("f").unwrap();
+        assert_eq!(f.params(), ["g \\\\", "\\"]);
+        assert_eq!(f.line(), Some(5));
+        assert_eq!(doc.get("h").unwrap().line(), Some(7));
+
+        assert_ne!(Scfg::from_str(src).unwrap(), doc);
+        let err = parse("a {\n\tb \\\n\tc\n").unwrap_err();
+        assert_eq!(err.lineno, 4);
+    }
 }
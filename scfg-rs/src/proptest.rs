@@ -0,0 +1,109 @@
+//! [proptest] strategies for generating scfg documents.
+//!
+//! These are available with the `proptest` feature, and are intended for
+//! property based testing of code consuming scfg documents. Every generated
+//! document can be written with [`Scfg::write`] and parsed back to an equal
+//! document.
+//!
+//! [proptest]: https://docs.rs/proptest
+use crate::{Directive, Scfg};
+use ::proptest::{collection::vec, option, prelude::*};
+
+/// The maximum number of parameters of directives generated by [`arb_scfg`].
+const MAX_PARAMS: usize = 4;
+
+/// Generates a single word, usable as either a directive name or parameter.
+///
+/// Words may be empty, and may contain characters that need quoting when
+/// written. Shrinks toward the empty word.
+pub fn arb_word() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9_.:/=$#'\" \\\\ōé{}-]{0,12}"
+}
+
+/// Generates a directive with up to `max_params` parameters and no child
+/// block. Shrinks toward a directive without parameters.
+pub fn arb_directive(max_params: usize) -> impl Strategy<Value = Directive> {
+    vec(arb_word(), 0..=max_params).prop_map(|params| Directive {
+        params,
+        child: None,
+        line: None,
+    })
+}
+
+/// Generates a document with at most `max_directives` directives per block,
+/// nested at most `max_depth` blocks deep. Shrinks toward the empty document.
+pub fn arb_scfg(max_depth: usize, max_directives: usize) -> BoxedStrategy<Scfg> {
+    let child = if max_depth == 0 {
+        Just(None).boxed()
+    } else {
+        option::of(arb_scfg(max_depth - 1, max_directives)).boxed()
+    };
+    vec(
+        (arb_word(), arb_directive(MAX_PARAMS), child),
+        0..=max_directives,
+    )
+    .prop_map(|entries| {
+        entries
+            .into_iter()
+            .map(|(name, mut directive, child)| {
+                directive.child = child;
+                (name, directive)
+            })
+            .collect()
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    proptest! {
+        /// Writing any generated document and parsing the output must give
+        /// back the original document. This guards the writer's quoting
+        /// against the parser's tokenization.
+        #[test]
+        fn write_parse_roundtrip(doc in arb_scfg(3, 4)) {
+            let mut out = Vec::new();
+            doc.write(&mut out).unwrap();
+            let src = String::from_utf8(out).unwrap();
+            prop_assert_eq!(Scfg::from_str(&src).unwrap(), doc);
+        }
+
+        /// Documents which [`Scfg::validate`] accepts, and only those, can be
+        /// written, and must round-trip, even when built from words which may
+        /// be invalid.
+        #[test]
+        fn validate_clean_roundtrip(doc in arb_unchecked_scfg()) {
+            let mut out = Vec::new();
+            let written = doc.write(&mut out);
+            prop_assert_eq!(doc.validate().is_ok(), written.is_ok());
+            if written.is_ok() {
+                let src = String::from_utf8(out).unwrap();
+                prop_assert_eq!(Scfg::from_str(&src).unwrap(), doc);
+            }
+        }
+    }
+
+    /// Generates a document two blocks deep from words which are sometimes
+    /// invalid.
+    fn arb_unchecked_scfg() -> impl Strategy<Value = Scfg> {
+        let word = || prop_oneof![4 => arb_word(), 1 => "[a\n\0]{0,3}"];
+        let block = vec((word(), vec(word(), 0..3)), 0..4);
+        vec((word(), vec(word(), 0..3), option::of(block)), 0..4).prop_map(|entries| {
+            let mut doc = Scfg::new();
+            for (name, params, block) in entries {
+                let directive = doc.add(name);
+                directive.params = params;
+                if let Some(block) = block {
+                    let child = directive.get_or_create_child();
+                    for (name, params) in block {
+                        child.add(name).params = params;
+                    }
+                }
+            }
+            doc
+        })
+    }
+}
@@ -0,0 +1,360 @@
+//! Reusable blocks, an extension to scfg expanded on request.
+use crate::{Directive, Scfg};
+use std::{collections::HashMap, fmt};
+
+/// Options of [`Scfg::expand_references_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceOptions {
+    /// The name of definition directives, `@define` by default.
+    pub define_marker: String,
+    /// The name of reference directives, `@use` by default.
+    pub use_marker: String,
+}
+
+impl Default for ReferenceOptions {
+    fn default() -> Self {
+        ReferenceOptions {
+            define_marker: "@define".to_owned(),
+            use_marker: "@use".to_owned(),
+        }
+    }
+}
+
+/// An error returned by [`Scfg::expand_references`] for a malformed
+/// definition or reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceError {
+    /// A definition is not at the top level, does not have exactly one
+    /// parameter, or has no block.
+    InvalidDefinition {
+        /// The line of the definition, if known.
+        line: Option<usize>,
+    },
+    /// Two definitions share a name.
+    DuplicateDefinition {
+        /// The line of the second definition, if known.
+        line: Option<usize>,
+        /// The name of the definitions.
+        name: String,
+    },
+    /// A reference does not have exactly one parameter, or has a block.
+    InvalidReference {
+        /// The line of the reference, if known.
+        line: Option<usize>,
+    },
+    /// A reference names no definition.
+    UnknownReference {
+        /// The line of the reference, if known.
+        line: Option<usize>,
+        /// The name referenced.
+        name: String,
+    },
+    /// A reference is to a definition which is being expanded, directly or
+    /// through other definitions.
+    Cycle {
+        /// The line of the reference closing the cycle, if known.
+        line: Option<usize>,
+        /// The name referenced.
+        name: String,
+    },
+}
+
+impl fmt::Display for ReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = match self {
+            ReferenceError::InvalidDefinition { line }
+            | ReferenceError::DuplicateDefinition { line, .. }
+            | ReferenceError::InvalidReference { line }
+            | ReferenceError::UnknownReference { line, .. }
+            | ReferenceError::Cycle { line, .. } => *line,
+        };
+        if let Some(line) = line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self {
+            ReferenceError::InvalidDefinition { .. } => write!(
+                f,
+                "a definition must be a top-level block with a single parameter"
+            ),
+            ReferenceError::DuplicateDefinition { name, .. } => {
+                write!(f, "{:?} is already defined", name)
+            }
+            ReferenceError::InvalidReference { .. } => {
+                write!(f, "a reference must have a single parameter and no block")
+            }
+            ReferenceError::UnknownReference { name, .. } => {
+                write!(f, "reference to undefined {:?}", name)
+            }
+            ReferenceError::Cycle { name, .. } => {
+                write!(f, "reference to {:?} is cyclic", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReferenceError {}
+
+impl Scfg {
+    /// Expands the references of the document with the default
+    /// [`ReferenceOptions`].
+    ///
+    /// This is an extension of scfg: definitions and references are ordinary
+    /// directives until this is called, and parsing never expands them.
+    ///
+    /// A definition is a top-level block named `@define`, whose single
+    /// parameter is its name. A reference is a directive named `@use`, whose
+    /// single parameter is the name of a definition, and may appear in any
+    /// block, including definitions:
+    ///
+    /// ```text
+    /// @define tls-common {
+    ///     protocols tlsv1.2 tlsv1.3
+    /// }
+    ///
+    /// listen :443 {
+    ///     @use tls-common
+    ///     certificate cert.pem
+    /// }
+    /// ```
+    ///
+    /// Each reference is replaced by a copy of the directives of its
+    /// definition, appended to the block holding the reference after its own
+    /// directives of the same names, since a document keeps the directives
+    /// sharing a name together. The definitions are then removed.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc: Scfg = "@define tls {\n\tprotocols tlsv1.3\n}\nlisten :443 {\n\t@use tls\n}\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// doc.expand_references().unwrap();
+    /// assert_eq!(doc, "listen :443 {\n\tprotocols tlsv1.3\n}\n".parse().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a definition or reference is malformed, if a
+    /// reference names no definition, or if definitions reference each other
+    /// in a cycle. The document may then be partially expanded.
+    pub fn expand_references(&mut self) -> Result<(), ReferenceError> {
+        self.expand_references_with(&ReferenceOptions::default())
+    }
+
+    /// Expands the references of the document like [`expand_references`],
+    /// with `options`.
+    ///
+    /// # Errors
+    /// See [`expand_references`].
+    ///
+    /// [`expand_references`]: Scfg::expand_references
+    pub fn expand_references_with(
+        &mut self,
+        options: &ReferenceOptions,
+    ) -> Result<(), ReferenceError> {
+        let mut expander = Expander {
+            options,
+            definitions: HashMap::new(),
+            expanded: HashMap::new(),
+            stack: Vec::new(),
+        };
+        for definition in self
+            .remove(options.define_marker.as_str())
+            .unwrap_or_default()
+        {
+            let line = definition.line;
+            let (name, block) = match (definition.params.as_slice(), definition.child) {
+                ([name], Some(block)) => (name.clone(), block),
+                _ => return Err(ReferenceError::InvalidDefinition { line }),
+            };
+            if expander.definitions.contains_key(&name) {
+                return Err(ReferenceError::DuplicateDefinition { line, name });
+            }
+            expander.definitions.insert(name, block);
+        }
+        expander.expand(self)
+    }
+}
+
+/// The state of an expansion.
+struct Expander<'a> {
+    options: &'a ReferenceOptions,
+    /// The definitions, as written.
+    definitions: HashMap<String, Scfg>,
+    /// The definitions expanded so far.
+    expanded: HashMap<String, Scfg>,
+    /// The names of the definitions being expanded.
+    stack: Vec<String>,
+}
+
+impl Expander<'_> {
+    /// Expands the references in `block`, at every level of nesting.
+    fn expand(&mut self, block: &mut Scfg) -> Result<(), ReferenceError> {
+        if let Some(definition) = block.get(self.options.define_marker.as_str()) {
+            return Err(ReferenceError::InvalidDefinition {
+                line: definition.line,
+            });
+        }
+        let references = block
+            .remove(self.options.use_marker.as_str())
+            .unwrap_or_default();
+        for directive in block.directives.values_mut().flatten() {
+            if let Some(ref mut child) = directive.child {
+                self.expand(child)?;
+            }
+        }
+        for reference in references {
+            let expanded = self.resolve(&reference)?;
+            block.append(expanded);
+        }
+        Ok(())
+    }
+
+    /// Returns the expanded definition `reference` names.
+    fn resolve(&mut self, reference: &Directive) -> Result<Scfg, ReferenceError> {
+        let line = reference.line;
+        let name = match (reference.params.as_slice(), &reference.child) {
+            ([name], None) => name,
+            _ => return Err(ReferenceError::InvalidReference { line }),
+        };
+        if let Some(expanded) = self.expanded.get(name) {
+            return Ok(expanded.clone());
+        }
+        if self.stack.contains(name) {
+            let name = name.clone();
+            return Err(ReferenceError::Cycle { line, name });
+        }
+        let mut block = match self.definitions.get(name) {
+            Some(block) => block.clone(),
+            None => {
+                let name = name.clone();
+                return Err(ReferenceError::UnknownReference { line, name });
+            }
+        };
+        self.stack.push(name.clone());
+        self.expand(&mut block)?;
+        self.stack.pop();
+        self.expanded.insert(name.clone(), block.clone());
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn expand(src: &str) -> Result<Scfg, ReferenceError> {
+        let mut doc: Scfg = src.parse().unwrap();
+        doc.expand_references()?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn references() {
+        let src = r#"@define tls-common {
+    protocols tlsv1.2 tlsv1.3
+    @use ciphers
+}
+@define ciphers {
+    ciphers {
+        prefer-server true
+    }
+}
+listen :443 {
+    @use tls-common
+    certificate a.pem
+}
+listen :8443 {
+    tls {
+        @use tls-common
+    }
+}
+"#;
+        let doc = expand(src).unwrap();
+        let exp = r#"listen :443 {
+    certificate a.pem
+    ciphers {
+        prefer-server true
+    }
+    protocols tlsv1.2 tlsv1.3
+}
+listen :8443 {
+    tls {
+        ciphers {
+            prefer-server true
+        }
+        protocols tlsv1.2 tlsv1.3
+    }
+}
+"#;
+        assert_eq!(doc, exp.parse().unwrap());
+        assert!(!doc.collect_all_names().iter().any(|n| n.starts_with('@')));
+
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        let written: Scfg = String::from_utf8(out).unwrap().parse().unwrap();
+        assert_eq!(written, doc);
+    }
+
+    #[test]
+    fn options() {
+        let options = ReferenceOptions {
+            define_marker: "%block".to_owned(),
+            use_marker: "%include".to_owned(),
+        };
+        let mut doc: Scfg = "%block a {\n\tx 1\n}\ny {\n\t%include a\n}\n@use a\n"
+            .parse()
+            .unwrap();
+        doc.expand_references_with(&options).unwrap();
+        assert_eq!(doc, "@use a\ny {\n\tx 1\n}\n".parse().unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src| expand(src).unwrap_err();
+        let missing = err("@define a {\n\tx 1\n}\nb {\n\t@use c\n}\n");
+        assert_eq!(
+            missing,
+            ReferenceError::UnknownReference {
+                line: Some(5),
+                name: "c".to_owned()
+            }
+        );
+        assert_eq!(missing.to_string(), r#"line 5: reference to undefined "c""#);
+
+        let cycle = err("@define a {\n\t@use b\n}\n@define b {\n\t@use a\n}\nc {\n\t@use a\n}\n");
+        assert_eq!(
+            cycle,
+            ReferenceError::Cycle {
+                line: Some(5),
+                name: "a".to_owned()
+            }
+        );
+        assert_eq!(cycle.to_string(), r#"line 5: reference to "a" is cyclic"#);
+        assert_eq!(
+            err("@define a {\n\t@use a\n}\n@use a\n"),
+            ReferenceError::Cycle {
+                line: Some(2),
+                name: "a".to_owned()
+            }
+        );
+
+        assert_eq!(
+            err("@define a\n"),
+            ReferenceError::InvalidDefinition { line: Some(1) }
+        );
+        assert_eq!(
+            err("x {\n\t@define a {\n\t}\n}\n"),
+            ReferenceError::InvalidDefinition { line: Some(2) }
+        );
+        assert_eq!(
+            err("@define a {\n}\n@define a {\n}\n"),
+            ReferenceError::DuplicateDefinition {
+                line: Some(3),
+                name: "a".to_owned()
+            }
+        );
+        assert_eq!(
+            err("@define a {\n}\n@use a b\n"),
+            ReferenceError::InvalidReference { line: Some(3) }
+        );
+    }
+}
@@ -0,0 +1,2373 @@
+use crate::{suggest::did_you_mean, Directive, Scfg};
+use std::fmt::{self, Write as _};
+
+/// A description of the directives allowed in a block, used to validate
+/// documents with [`Schema::validate`].
+///
+/// Schemas are built by chaining calls to [`directive`], which describes a
+/// directive with a [`DirectiveSchema`]. Directives which are not described by
+/// the schema are ignored, unless [`deny_unknown`] is called.
+///
+/// ```
+/// # use scfg::*;
+/// let tls = Schema::new()
+///     .directive("certificate", |d| d.min(1).max(1).params(1))
+///     .directive("key", |d| d.min(1).max(1).params(1));
+/// let schema = Schema::new()
+///     .directive("domain", |d| d.min(1).max(1).params(1).no_child())
+///     .directive("listen", |d| d.min(1).params(1).optional_child(tls))
+///     .deny_unknown();
+///
+/// let doc: Scfg = r#"domain example.com
+/// listen 0.0.0.0:6697 {
+///     certificate cert.pem
+/// }
+/// "#
+/// .parse()
+/// .unwrap();
+/// let errors = schema.validate(&doc).unwrap_err();
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(
+///     errors[0].to_string(),
+///     "line 2: listen.key: must appear at least 1 time, found 0"
+/// );
+/// ```
+///
+/// [`directive`]: Schema::directive
+/// [`deny_unknown`]: Schema::deny_unknown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    directives: Vec<(String, DirectiveSchema)>,
+    constraints: Vec<Constraint>,
+    deny_unknown: bool,
+}
+
+/// A rule spanning several directives of a block.
+#[derive(Debug, Clone, PartialEq)]
+enum Constraint {
+    UniqueByParam(String, usize),
+    Requires(String, String),
+    Conflicts(String, String),
+}
+
+/// The largest parameter index a [`ParamRule`] applies to, see
+/// [`DirectiveSchema::param`].
+pub const MAX_PARAM_INDEX: usize = 255;
+
+/// The rules for a directive in a [`Schema`].
+///
+/// By default, a directive may appear any number of times, with any number of
+/// parameters, and with or without a child block, which is not validated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectiveSchema {
+    min: usize,
+    max: Option<usize>,
+    min_params: usize,
+    max_params: Option<usize>,
+    param_rules: Vec<ParamRule>,
+    child: ChildRule,
+    doc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ChildRule {
+    Any,
+    Forbidden,
+    Required(Schema),
+    Optional(Schema),
+}
+
+// not derived, since `#[default]` on a variant needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for ChildRule {
+    fn default() -> Self {
+        ChildRule::Any
+    }
+}
+
+/// A constraint on the value of a parameter, see [`DirectiveSchema::param`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ParamRule {
+    /// Any value.
+    Any,
+    /// A decimal integer, within the bounds if any. Both bounds are
+    /// inclusive.
+    Integer { min: Option<i64>, max: Option<i64> },
+    /// One of the listed values.
+    Enum(Vec<String>),
+    /// A value matched by the regular expression. The expression may match
+    /// any part of the value unless it is anchored. Available with the `regex`
+    /// feature.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+    /// A duration: one or more numbers, each followed by a unit among `ms`,
+    /// `s`, `m`, `h` and `d`, like `1h30m`.
+    Duration,
+    /// A size in bytes: a number, optionally followed by a unit among `B`,
+    /// `K`, `KB`, `KiB`, `M`, `MB`, `MiB`, `G`, `GB`, `GiB`, `T`, `TB` and
+    /// `TiB`, ignoring case.
+    Size,
+}
+
+// not derived, since `#[default]` on a variant needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for ParamRule {
+    fn default() -> Self {
+        ParamRule::Any
+    }
+}
+
+/// Completion data for a directive allowed in a block, as returned by
+/// [`Schema::directives_at`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// The name of the directive.
+    pub name: String,
+    /// The description of the directive, see [`DirectiveSchema::doc`].
+    pub doc: Option<String>,
+    /// The minimum number of parameters.
+    pub min_params: usize,
+    /// The maximum number of parameters, if any.
+    pub max_params: Option<usize>,
+    /// The rules for the parameters, by index. Parameters past the end are
+    /// unconstrained. [`ParamRule::Enum`] rules list the values to suggest.
+    pub params: Vec<ParamRule>,
+    /// Whether the directive may have a block.
+    pub block_allowed: bool,
+    /// Whether the directive must have a block.
+    pub block_required: bool,
+}
+
+/// A violation of a [`Schema`] found by [`Schema::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// The names of the directives leading to the offending directive,
+    /// including its own name.
+    pub path: Vec<String>,
+    /// The line of the offending directive, if known. For missing directives,
+    /// this is the line of the directive holding the block they are missing
+    /// from.
+    pub line: Option<usize>,
+    /// The rule which was broken.
+    pub rule: Rule,
+}
+
+/// A rule of a [`Schema`], as broken by a [`ValidationError`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Rule {
+    /// The directive is not described by a schema which denies unknown
+    /// directives.
+    UnknownDirective {
+        /// The closest name described by the schema, if it is close enough to
+        /// be a plausible typo.
+        suggestion: Option<String>,
+    },
+    /// The directive appears fewer times than required.
+    TooFew { min: usize, found: usize },
+    /// The directive appears more times than allowed. This is reported on the
+    /// first directive in excess.
+    TooMany { max: usize, found: usize },
+    /// The directive has the wrong number of parameters.
+    Params {
+        min: usize,
+        max: Option<usize>,
+        found: usize,
+    },
+    /// The directive has no child block, but one is required.
+    MissingChild,
+    /// The directive has a child block, but none is allowed.
+    UnexpectedChild,
+    /// The parameter at `index` (starting at 0) doesn't satisfy its
+    /// [`ParamRule`].
+    InvalidParam {
+        index: usize,
+        value: String,
+        expected: ParamRule,
+        /// The closest accepted value, for [`ParamRule::Enum`] rules.
+        suggestion: Option<String>,
+    },
+    /// Several directives have the same `value` for the parameter at
+    /// `index`, which must be unique. `lines` has the lines of all of them.
+    Duplicate {
+        index: usize,
+        value: String,
+        lines: Vec<usize>,
+    },
+    /// The directive is present, but `other` is not. `lines` has the lines of
+    /// all the directives.
+    Requires { other: String, lines: Vec<usize> },
+    /// Both the directive and `other` are present. `lines` has the lines of
+    /// all of them.
+    Conflicts { other: String, lines: Vec<usize> },
+}
+
+impl Schema {
+    /// Creates a schema which allows no directives, except unknown ones.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Describes the directive `name`, with rules set by `f` on a default
+    /// [`DirectiveSchema`]. Describing a directive again replaces its previous
+    /// rules.
+    pub fn directive<F>(mut self, name: impl Into<String>, f: F) -> Self
+    where
+        F: FnOnce(DirectiveSchema) -> DirectiveSchema,
+    {
+        let name = name.into();
+        let rules = f(DirectiveSchema::default());
+        match self.directives.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = rules,
+            None => self.directives.push((name, rules)),
+        }
+        self
+    }
+
+    /// Reports directives which are not described by this schema as
+    /// [`Rule::UnknownDirective`] errors, instead of ignoring them.
+    ///
+    /// This only applies to the block validated by this schema, not to the
+    /// child blocks validated by nested schemas.
+    pub fn deny_unknown(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+
+    /// Requires the directives `name` in the block to have different values
+    /// for the parameter at `index`. Directives without that parameter are
+    /// ignored.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let schema = Schema::new().unique_by_param("listen", 0);
+    /// let doc = "listen :80\nlisten :443\nlisten :80\n".parse().unwrap();
+    /// let errors = schema.validate(&doc).unwrap_err();
+    /// assert_eq!(
+    ///     errors[0].to_string(),
+    ///     "line 1: listen: duplicate parameter 0 \":80\" (lines 1, 3)"
+    /// );
+    /// ```
+    pub fn unique_by_param(mut self, name: impl Into<String>, index: usize) -> Self {
+        self.constraints
+            .push(Constraint::UniqueByParam(name.into(), index));
+        self
+    }
+
+    /// Requires the directive `other` to be present in the block if `name` is.
+    pub fn requires(mut self, name: impl Into<String>, other: impl Into<String>) -> Self {
+        self.constraints
+            .push(Constraint::Requires(name.into(), other.into()));
+        self
+    }
+
+    /// Forbids the directives `name` and `other` from both being present in
+    /// the block.
+    pub fn conflicts(mut self, name: impl Into<String>, other: impl Into<String>) -> Self {
+        self.constraints
+            .push(Constraint::Conflicts(name.into(), other.into()));
+        self
+    }
+
+    /// Validates `doc` against this schema.
+    ///
+    /// All violations are returned, in the order the offending directives
+    /// appear in the document, followed by the missing directives in the
+    /// order they were described, and by the violated constraints in the
+    /// order they were added.
+    pub fn validate(&self, doc: &Scfg) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.validate_block(doc, &mut Vec::new(), None, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&DirectiveSchema> {
+        self.directives
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, rules)| rules)
+    }
+
+    fn validate_block(
+        &self,
+        doc: &Scfg,
+        path: &mut Vec<String>,
+        line: Option<usize>,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (name, directives) in &doc.directives {
+            path.push(name.clone());
+            match self.get(name) {
+                Some(rules) => rules.validate_group(directives, path, errors),
+                None if self.deny_unknown => {
+                    let names = self.directives.iter().map(|(name, _)| name.as_str());
+                    let suggestion = did_you_mean(name, names).map(str::to_owned);
+                    for directive in directives {
+                        errors.push(ValidationError {
+                            path: path.clone(),
+                            line: directive.line,
+                            rule: Rule::UnknownDirective {
+                                suggestion: suggestion.clone(),
+                            },
+                        });
+                    }
+                }
+                None => {}
+            }
+            path.pop();
+        }
+
+        for (name, rules) in &self.directives {
+            let found = doc.get_all(name).map_or(0, |group| group.len());
+            if found < rules.min {
+                path.push(name.clone());
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    line,
+                    rule: Rule::TooFew {
+                        min: rules.min,
+                        found,
+                    },
+                });
+                path.pop();
+            }
+        }
+
+        for constraint in &self.constraints {
+            constraint.validate(doc, path, errors);
+        }
+    }
+}
+
+impl Scfg {
+    /// Validates the block at `path` against `schema`, ignoring the rest of
+    /// the document.
+    ///
+    /// The block is the child of the directive found with [`get_path`]. A
+    /// directive without a block, or a missing directive, is validated as an
+    /// empty block, so that the directives required by `schema` are reported
+    /// missing. An empty `path` validates the whole document, like
+    /// [`Schema::validate`].
+    ///
+    /// The paths of the returned errors start with `path`, and missing
+    /// directives are reported at the line of the directive at `path`.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "server {\n\thost example.com\n}\n".parse().unwrap();
+    /// let schema = Schema::new().directive("port", |d| d.min(1).params(1));
+    /// let errors = doc.verify_schema_at_path(&["server"], &schema).unwrap_err();
+    /// assert_eq!(
+    ///     errors[0].to_string(),
+    ///     "line 1: server.port: must appear at least 1 time, found 0"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns every violation of `schema`, in the order described by
+    /// [`Schema::validate`].
+    ///
+    /// [`get_path`]: Scfg::get_path
+    pub fn verify_schema_at_path<Q>(
+        &self,
+        path: &[Q],
+        schema: &Schema,
+    ) -> Result<(), Vec<ValidationError>>
+    where
+        Q: AsRef<str>,
+    {
+        if path.is_empty() {
+            return schema.validate(self);
+        }
+        let directive = self.get_path(path);
+        let empty = Scfg::new();
+        let block = directive.and_then(Directive::child).unwrap_or(&empty);
+        let line = directive.and_then(Directive::line);
+        let mut path = path.iter().map(|name| name.as_ref().to_owned()).collect();
+        let mut errors = Vec::new();
+        schema.validate_block(block, &mut path, line, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Returns the known lines of `directives`.
+fn lines(directives: &[&Directive]) -> Vec<usize> {
+    let mut lines: Vec<_> = directives.iter().filter_map(|d| d.line).collect();
+    lines.sort_unstable();
+    lines
+}
+
+impl Constraint {
+    fn from_scfg(directive: &Directive) -> Result<Self, SchemaError> {
+        let constraint = match directive.params.as_slice() {
+            [kind, name, index] if kind == "unique" => index
+                .parse()
+                .ok()
+                .map(|index| Constraint::UniqueByParam(name.clone(), index)),
+            [kind, a, b] if kind == "requires" => Some(Constraint::Requires(a.clone(), b.clone())),
+            [kind, a, b] if kind == "conflicts" => {
+                Some(Constraint::Conflicts(a.clone(), b.clone()))
+            }
+            _ => None,
+        };
+        constraint.ok_or_else(|| {
+            SchemaError::new(
+                directive,
+                "expected `unique <name> <index>`, `requires <name> <other>` or `conflicts <name> <other>`",
+            )
+        })
+    }
+
+    fn validate(&self, doc: &Scfg, path: &mut Vec<String>, errors: &mut Vec<ValidationError>) {
+        let group = |name: &str| -> Vec<&Directive> {
+            doc.get_all(name).unwrap_or_default().iter().collect()
+        };
+        let (name, rule, first) = match self {
+            Constraint::UniqueByParam(name, index) => {
+                let mut values: Vec<(&String, Vec<&Directive>)> = Vec::new();
+                for directive in group(name) {
+                    let value = match directive.params.get(*index) {
+                        Some(value) => value,
+                        None => continue,
+                    };
+                    match values.iter_mut().find(|(v, _)| *v == value) {
+                        Some((_, directives)) => directives.push(directive),
+                        None => values.push((value, vec![directive])),
+                    }
+                }
+                for (value, directives) in values {
+                    if directives.len() < 2 {
+                        continue;
+                    }
+                    path.push(name.clone());
+                    errors.push(ValidationError {
+                        path: path.clone(),
+                        line: directives[0].line,
+                        rule: Rule::Duplicate {
+                            index: *index,
+                            value: value.clone(),
+                            lines: lines(&directives),
+                        },
+                    });
+                    path.pop();
+                }
+                return;
+            }
+            Constraint::Requires(name, other) => {
+                let directives = group(name);
+                if directives.is_empty() || doc.contains(other.as_str()) {
+                    return;
+                }
+                let rule = Rule::Requires {
+                    other: other.clone(),
+                    lines: lines(&directives),
+                };
+                (name, rule, directives[0])
+            }
+            Constraint::Conflicts(name, other) => {
+                let directives = group(name);
+                let others = group(other);
+                if directives.is_empty() || others.is_empty() {
+                    return;
+                }
+                let all: Vec<_> = directives.iter().chain(&others).copied().collect();
+                let rule = Rule::Conflicts {
+                    other: other.clone(),
+                    lines: lines(&all),
+                };
+                (name, rule, directives[0])
+            }
+        };
+        path.push(name.clone());
+        errors.push(ValidationError {
+            path: path.clone(),
+            line: first.line,
+            rule,
+        });
+        path.pop();
+    }
+}
+
+impl DirectiveSchema {
+    /// Requires the directive to appear at least `min` times.
+    pub fn min(mut self, min: usize) -> Self {
+        self.min = min;
+        self
+    }
+
+    /// Allows the directive to appear at most `max` times.
+    pub fn max(mut self, max: usize) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Requires the directive to have exactly `n` parameters.
+    pub fn params(self, n: usize) -> Self {
+        self.min_params(n).max_params(n)
+    }
+
+    /// Requires the directive to have at least `min` parameters.
+    pub fn min_params(mut self, min: usize) -> Self {
+        self.min_params = min;
+        self
+    }
+
+    /// Allows the directive to have at most `max` parameters.
+    pub fn max_params(mut self, max: usize) -> Self {
+        self.max_params = Some(max);
+        self
+    }
+
+    /// Requires the directive to have a child block, validated by `schema`.
+    pub fn child(mut self, schema: Schema) -> Self {
+        self.child = ChildRule::Required(schema);
+        self
+    }
+
+    /// Allows the directive to have a child block, validated by `schema`.
+    pub fn optional_child(mut self, schema: Schema) -> Self {
+        self.child = ChildRule::Optional(schema);
+        self
+    }
+
+    /// Forbids the directive from having a child block.
+    pub fn no_child(mut self) -> Self {
+        self.child = ChildRule::Forbidden;
+        self
+    }
+
+    /// Attaches a description of the directive, used by
+    /// [`Schema::to_markdown`].
+    pub fn doc(mut self, doc: impl Into<String>) -> Self {
+        self.doc = Some(doc.into());
+        self
+    }
+
+    /// Constrains the value of the parameter at `index`, starting at 0, and at
+    /// most [`MAX_PARAM_INDEX`]. This doesn't require the parameter to be
+    /// present, see [`params`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let schema = Schema::new().directive("log", |d| {
+    ///     let levels = vec!["debug".to_owned(), "info".to_owned(), "error".to_owned()];
+    ///     d.params(1).param(0, ParamRule::Enum(levels))
+    /// });
+    /// let errors = schema.validate(&"log inof\n".parse().unwrap()).unwrap_err();
+    /// assert_eq!(
+    ///     errors[0].to_string(),
+    ///     "line 1: log: invalid parameter 0 \"inof\", expected one of \"debug\", \"info\", \"error\", did you mean \"info\"?"
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `index` is above [`MAX_PARAM_INDEX`].
+    ///
+    /// [`params`]: DirectiveSchema::params
+    pub fn param(mut self, index: usize, rule: ParamRule) -> Self {
+        assert!(
+            index <= MAX_PARAM_INDEX,
+            "parameter index {} is above {}",
+            index,
+            MAX_PARAM_INDEX
+        );
+        if self.param_rules.len() <= index {
+            self.param_rules.resize(index + 1, ParamRule::Any);
+        }
+        self.param_rules[index] = rule;
+        self
+    }
+
+    fn validate_group(
+        &self,
+        directives: &[Directive],
+        path: &mut Vec<String>,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if let Some(max) = self.max {
+            if let Some(excess) = directives.get(max) {
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    line: excess.line,
+                    rule: Rule::TooMany {
+                        max,
+                        found: directives.len(),
+                    },
+                });
+            }
+        }
+
+        for directive in directives {
+            let found = directive.params.len();
+            if found < self.min_params || matches!(self.max_params, Some(max) if found > max) {
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    line: directive.line,
+                    rule: Rule::Params {
+                        min: self.min_params,
+                        max: self.max_params,
+                        found,
+                    },
+                });
+            }
+
+            for (index, (rule, value)) in self.param_rules.iter().zip(&directive.params).enumerate()
+            {
+                if rule.accepts(value) {
+                    continue;
+                }
+                let suggestion = match rule {
+                    ParamRule::Enum(values) => {
+                        did_you_mean(value, values.iter().map(String::as_str)).map(str::to_owned)
+                    }
+                    _ => None,
+                };
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    line: directive.line,
+                    rule: Rule::InvalidParam {
+                        index,
+                        value: value.clone(),
+                        expected: rule.clone(),
+                        suggestion,
+                    },
+                });
+            }
+
+            let rule = match (&self.child, &directive.child) {
+                (ChildRule::Required(schema), Some(child))
+                | (ChildRule::Optional(schema), Some(child)) => {
+                    schema.validate_block(child, path, directive.line, errors);
+                    continue;
+                }
+                (ChildRule::Required(_), None) => Rule::MissingChild,
+                (ChildRule::Forbidden, Some(_)) => Rule::UnexpectedChild,
+                _ => continue,
+            };
+            errors.push(ValidationError {
+                path: path.clone(),
+                line: directive.line,
+                rule,
+            });
+        }
+    }
+}
+
+impl ParamRule {
+    /// Returns whether `value` satisfies this rule.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            ParamRule::Any => true,
+            ParamRule::Integer { min, max } => match value.parse::<i64>() {
+                Ok(n) => min.unwrap_or(i64::MIN) <= n && n <= max.unwrap_or(i64::MAX),
+                Err(_) => false,
+            },
+            ParamRule::Enum(values) => values.iter().any(|v| v == value),
+            #[cfg(feature = "regex")]
+            ParamRule::Regex(regex) => regex.is_match(value),
+            ParamRule::Duration => is_duration(value),
+            ParamRule::Size => is_size(value),
+        }
+    }
+}
+
+impl PartialEq for ParamRule {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ParamRule::Any, ParamRule::Any)
+            | (ParamRule::Duration, ParamRule::Duration)
+            | (ParamRule::Size, ParamRule::Size) => true,
+            (
+                ParamRule::Integer { min, max },
+                ParamRule::Integer {
+                    min: other_min,
+                    max: other_max,
+                },
+            ) => min == other_min && max == other_max,
+            (ParamRule::Enum(values), ParamRule::Enum(other)) => values == other,
+            #[cfg(feature = "regex")]
+            (ParamRule::Regex(regex), ParamRule::Regex(other)) => regex.as_str() == other.as_str(),
+            _ => false,
+        }
+    }
+}
+
+/// Splits `s` after its leading ASCII digits.
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn is_duration(mut value: &str) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    while !value.is_empty() {
+        let (digits, rest) = split_digits(value);
+        if digits.is_empty() {
+            return false;
+        }
+        // `ms` must be tried before `m`
+        match ["ms", "s", "m", "h", "d"]
+            .iter()
+            .find_map(|unit| rest.strip_prefix(unit))
+        {
+            Some(rest) => value = rest,
+            None => return false,
+        }
+    }
+    true
+}
+
+fn is_size(value: &str) -> bool {
+    const UNITS: &[&str] = &[
+        "", "b", "k", "kb", "kib", "m", "mb", "mib", "g", "gb", "gib", "t", "tb", "tib",
+    ];
+    let (digits, unit) = split_digits(value);
+    !digits.is_empty() && UNITS.contains(&unit.to_ascii_lowercase().as_str())
+}
+
+impl fmt::Display for ParamRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamRule::Any => write!(f, "any value"),
+            ParamRule::Integer { min, max } => match (min, max) {
+                (Some(min), Some(max)) => write!(f, "an integer from {} to {}", min, max),
+                (Some(min), None) => write!(f, "an integer of at least {}", min),
+                (None, Some(max)) => write!(f, "an integer of at most {}", max),
+                (None, None) => write!(f, "an integer"),
+            },
+            ParamRule::Enum(values) => {
+                write!(f, "one of ")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", value)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "regex")]
+            ParamRule::Regex(regex) => write!(f, "a match for `{}`", regex),
+            ParamRule::Duration => write!(f, "a duration like `1h30m`"),
+            ParamRule::Size => write!(f, "a size like `512MiB`"),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "{}: {}", self.path.join("."), self.rule)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+fn times(n: usize) -> &'static str {
+    if n == 1 {
+        "time"
+    } else {
+        "times"
+    }
+}
+
+fn write_lines(f: &mut fmt::Formatter<'_>, lines: &[usize]) -> fmt::Result {
+    match lines {
+        [] => Ok(()),
+        [line] => write!(f, " (line {})", line),
+        _ => {
+            let lines: Vec<_> = lines.iter().map(usize::to_string).collect();
+            write!(f, " (lines {})", lines.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Rule::UnknownDirective { ref suggestion } => {
+                write!(f, "unknown directive")?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean {:?}?", suggestion)?;
+                }
+                Ok(())
+            }
+            Rule::TooFew { min, found } => write!(
+                f,
+                "must appear at least {} {}, found {}",
+                min,
+                times(min),
+                found
+            ),
+            Rule::TooMany { max, found } => write!(
+                f,
+                "must appear at most {} {}, found {}",
+                max,
+                times(max),
+                found
+            ),
+            Rule::Params { min, max, found } => {
+                match max {
+                    Some(max) if max == min => write!(f, "expected {} parameters", min)?,
+                    Some(max) => write!(f, "expected {} to {} parameters", min, max)?,
+                    None => write!(f, "expected at least {} parameters", min)?,
+                }
+                write!(f, ", found {}", found)
+            }
+            Rule::MissingChild => write!(f, "expected a block"),
+            Rule::UnexpectedChild => write!(f, "unexpected block"),
+            Rule::InvalidParam {
+                index,
+                ref value,
+                ref expected,
+                ref suggestion,
+            } => {
+                write!(
+                    f,
+                    "invalid parameter {} {:?}, expected {}",
+                    index, value, expected
+                )?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean {:?}?", suggestion)?;
+                }
+                Ok(())
+            }
+            Rule::Duplicate {
+                index,
+                ref value,
+                ref lines,
+            } => {
+                write!(f, "duplicate parameter {} {:?}", index, value)?;
+                write_lines(f, lines)
+            }
+            Rule::Requires {
+                ref other,
+                ref lines,
+            } => {
+                write!(f, "requires `{}`", other)?;
+                write_lines(f, lines)
+            }
+            Rule::Conflicts {
+                ref other,
+                ref lines,
+            } => {
+                write!(f, "conflicts with `{}`", other)?;
+                write_lines(f, lines)
+            }
+        }
+    }
+}
+
+/// An error returned by [`Schema::from_scfg`] for a malformed schema
+/// document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    line: Option<usize>,
+    message: String,
+}
+
+impl SchemaError {
+    fn new(directive: &Directive, message: impl Into<String>) -> Self {
+        SchemaError {
+            line: directive.line,
+            message: message.into(),
+        }
+    }
+
+    /// The line of the offending directive in the schema document, if known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "schema error at line {}: {}", line, self.message),
+            None => write!(f, "schema error: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl Schema {
+    /// Loads a schema described by an scfg document.
+    ///
+    /// The document may contain these directives:
+    /// - `directive <name> { ... }` describes the directive `name`, in the
+    ///   order they appear. Its block may contain:
+    ///   - `doc <text>`, for [`DirectiveSchema::doc`].
+    ///   - `min <n>` and `max <n>`, for [`DirectiveSchema::min`] and
+    ///     [`DirectiveSchema::max`].
+    ///   - `params <n>`, `params <min>..<max>` or `params <min>..`, for the
+    ///     allowed number of parameters. Ranges are inclusive.
+    ///   - `param <index> <kind> [args...]`, for [`DirectiveSchema::param`],
+    ///     where the kind and its arguments are one of `any`, `integer`,
+    ///     `integer <min>..<max>` (either bound may be omitted), `enum
+    ///     <values...>`, `regex <pattern>`, `duration` and `size`. This may be
+    ///     repeated for different indices.
+    ///   - `child { ... }`, `optional-child { ... }` or `no-child`, where the
+    ///     blocks are schema documents themselves.
+    /// - `constraint unique <name> <index>`, `constraint requires <name>
+    ///   <other>` and `constraint conflicts <name> <other>`, for
+    ///   [`Schema::unique_by_param`], [`Schema::requires`] and
+    ///   [`Schema::conflicts`]. These may be repeated.
+    /// - `unknown deny` or `unknown ignore`, for [`Schema::deny_unknown`].
+    ///
+    /// Unknown keywords, missing or extra parameters, invalid numbers and
+    /// repeated keywords are errors.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let src = r#"
+    /// unknown deny
+    /// directive listen {
+    ///     min 1
+    ///     params 1..2
+    ///     optional-child {
+    ///         directive certificate {
+    ///             min 1
+    ///             max 1
+    ///             params 1
+    ///         }
+    ///     }
+    /// }
+    /// "#;
+    /// let schema = Schema::from_scfg(&src.parse().unwrap()).unwrap();
+    /// let built = Schema::new()
+    ///     .directive("listen", |d| {
+    ///         let tls = Schema::new().directive("certificate", |d| d.min(1).max(1).params(1));
+    ///         d.min(1).min_params(1).max_params(2).optional_child(tls)
+    ///     })
+    ///     .deny_unknown();
+    /// assert_eq!(schema, built);
+    /// ```
+    pub fn from_scfg(doc: &Scfg) -> Result<Schema, SchemaError> {
+        let mut schema = Schema::new();
+        for (name, directives) in &doc.directives {
+            match name.as_str() {
+                "directive" => {}
+                "constraint" => {
+                    for directive in directives {
+                        schema.constraints.push(Constraint::from_scfg(directive)?);
+                    }
+                }
+                "unknown" => {
+                    let directive = single(directives, name)?;
+                    schema.deny_unknown = match word_param(directive, name)? {
+                        "deny" => true,
+                        "ignore" => false,
+                        value => {
+                            let message =
+                                format!("`unknown` must be `deny` or `ignore`, found `{}`", value);
+                            return Err(SchemaError::new(directive, message));
+                        }
+                    };
+                }
+                _ => {
+                    let message = format!("unknown keyword `{}`", name);
+                    return Err(SchemaError::new(&directives[0], message));
+                }
+            }
+        }
+
+        for directive in doc.get_all("directive").unwrap_or_default() {
+            let name = word_param(directive, "directive")?;
+            if schema.get(name).is_some() {
+                let message = format!("directive `{}` is described more than once", name);
+                return Err(SchemaError::new(directive, message));
+            }
+            let rules = match directive.child {
+                Some(ref block) => DirectiveSchema::from_scfg(block)?,
+                None => {
+                    let message = format!("directive `{}` must have a block", name);
+                    return Err(SchemaError::new(directive, message));
+                }
+            };
+            schema.directives.push((name.to_owned(), rules));
+        }
+        Ok(schema)
+    }
+
+    /// Describes this schema as an scfg document, which can be loaded back
+    /// with [`Schema::from_scfg`].
+    pub fn to_scfg(&self) -> Scfg {
+        let mut doc = Scfg::new();
+        if self.deny_unknown {
+            doc.add("unknown").append_param("deny");
+        }
+        for (name, rules) in &self.directives {
+            doc.add_block("directive", [name.as_str()], |block| rules.to_scfg(block));
+        }
+        for constraint in &self.constraints {
+            let (kind, a, b) = match constraint {
+                Constraint::UniqueByParam(name, index) => ("unique", name, index.to_string()),
+                Constraint::Requires(a, b) => ("requires", a, b.clone()),
+                Constraint::Conflicts(a, b) => ("conflicts", a, b.clone()),
+            };
+            doc.add("constraint")
+                .append_param(kind)
+                .append_param(a)
+                .append_param(b);
+        }
+        doc
+    }
+}
+
+impl DirectiveSchema {
+    fn from_scfg(block: &Scfg) -> Result<Self, SchemaError> {
+        let mut rules = DirectiveSchema::default();
+        let mut child_keyword: Option<&str> = None;
+        for (name, directives) in &block.directives {
+            if name == "param" {
+                for directive in directives {
+                    let (index, rule) = param_rule(directive)?;
+                    if matches!(rules.param_rules.get(index), Some(r) if *r != ParamRule::Any) {
+                        let message = format!("parameter {} is constrained more than once", index);
+                        return Err(SchemaError::new(directive, message));
+                    }
+                    rules = rules.param(index, rule);
+                }
+                continue;
+            }
+            let directive = single(directives, name)?;
+            match name.as_str() {
+                "doc" => rules.doc = Some(word_param(directive, name)?.to_owned()),
+                "min" => rules.min = number_param(directive, name)?,
+                "max" => rules.max = Some(number_param(directive, name)?),
+                "params" => {
+                    let range = word_param(directive, name)?;
+                    let (min, max) = parse_range(range).ok_or_else(|| {
+                        let message = format!("invalid parameter count `{}`", range);
+                        SchemaError::new(directive, message)
+                    })?;
+                    rules.min_params = min;
+                    rules.max_params = max;
+                }
+                "child" | "optional-child" | "no-child" => {
+                    if let Some(other) = child_keyword {
+                        let message = format!("`{}` conflicts with `{}`", name, other);
+                        return Err(SchemaError::new(directive, message));
+                    }
+                    child_keyword = Some(name);
+                    rules.child = if name == "no-child" {
+                        no_params(directive, name)?;
+                        if directive.child.is_some() {
+                            return Err(SchemaError::new(directive, "`no-child` takes no block"));
+                        }
+                        ChildRule::Forbidden
+                    } else {
+                        no_params(directive, name)?;
+                        let schema = match directive.child {
+                            Some(ref child) => Schema::from_scfg(child)?,
+                            None => {
+                                let message = format!("`{}` must have a block", name);
+                                return Err(SchemaError::new(directive, message));
+                            }
+                        };
+                        if name == "child" {
+                            ChildRule::Required(schema)
+                        } else {
+                            ChildRule::Optional(schema)
+                        }
+                    };
+                }
+                _ => {
+                    let message = format!("unknown keyword `{}`", name);
+                    return Err(SchemaError::new(directive, message));
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    fn to_scfg(&self, block: &mut Scfg) {
+        if let Some(ref doc) = self.doc {
+            block.add("doc").append_param(doc);
+        }
+        if self.min != 0 {
+            block.add("min").append_param(self.min.to_string());
+        }
+        if let Some(max) = self.max {
+            block.add("max").append_param(max.to_string());
+        }
+        let params = match self.max_params {
+            Some(max) if max == self.min_params => Some(max.to_string()),
+            Some(max) => Some(format!("{}..{}", self.min_params, max)),
+            None if self.min_params == 0 => None,
+            None => Some(format!("{}..", self.min_params)),
+        };
+        if let Some(params) = params {
+            block.add("params").append_param(params);
+        }
+        for (index, rule) in self.param_rules.iter().enumerate() {
+            let mut args = match rule {
+                ParamRule::Any => continue,
+                ParamRule::Integer { min, max } => {
+                    let range = match (min, max) {
+                        (None, None) => None,
+                        (min, max) => Some(format!(
+                            "{}..{}",
+                            min.map(|n| n.to_string()).unwrap_or_default(),
+                            max.map(|n| n.to_string()).unwrap_or_default()
+                        )),
+                    };
+                    vec!["integer".to_owned()]
+                        .into_iter()
+                        .chain(range)
+                        .collect()
+                }
+                ParamRule::Enum(values) => {
+                    let mut args = vec!["enum".to_owned()];
+                    args.extend(values.iter().cloned());
+                    args
+                }
+                #[cfg(feature = "regex")]
+                ParamRule::Regex(regex) => vec!["regex".to_owned(), regex.as_str().to_owned()],
+                ParamRule::Duration => vec!["duration".to_owned()],
+                ParamRule::Size => vec!["size".to_owned()],
+            };
+            args.insert(0, index.to_string());
+            block.add("param").params = args;
+        }
+        match self.child {
+            ChildRule::Any => {}
+            ChildRule::Forbidden => {
+                block.add("no-child");
+            }
+            ChildRule::Required(ref schema) => {
+                block.add("child").child = Some(schema.to_scfg());
+            }
+            ChildRule::Optional(ref schema) => {
+                block.add("optional-child").child = Some(schema.to_scfg());
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// Renders reference documentation for this schema as Markdown.
+    ///
+    /// Each directive gets a section headed by its path, with its description
+    /// from [`DirectiveSchema::doc`] and a list of its rules, followed by the
+    /// sections of the directives of its block, one heading level deeper.
+    /// Directives are listed in the order they were described, so the output
+    /// only depends on the schema.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let schema = Schema::new().directive("domain", |d| {
+    ///     d.min(1).max(1).params(1).no_child().doc("The domain name of the server.")
+    /// });
+    /// assert_eq!(
+    ///     schema.to_markdown(),
+    ///     "# `domain`\n\
+    ///      \n\
+    ///      The domain name of the server.\n\
+    ///      \n\
+    ///      - Occurrences: exactly 1 time\n\
+    ///      - Parameters: exactly 1\n\
+    ///      - Block: not allowed\n"
+    /// );
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        self.write_markdown(&mut out, &mut Vec::new())
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_markdown(&self, out: &mut String, path: &mut Vec<String>) -> fmt::Result {
+        if !self.constraints.is_empty() || self.deny_unknown {
+            let prefix: String = path.iter().map(|name| format!("{}.", name)).collect();
+            for constraint in &self.constraints {
+                writeln!(out, "{}", constraint.describe(&prefix))?;
+            }
+            if self.deny_unknown && path.is_empty() {
+                writeln!(out, "Other directives are not allowed.")?;
+            } else if self.deny_unknown {
+                let block = path.join(".");
+                writeln!(out, "Other directives are not allowed in `{}`.", block)?;
+            }
+            writeln!(out)?;
+        }
+        for (i, (name, rules)) in self.directives.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            path.push(name.clone());
+            let level = "#".repeat(path.len().min(6));
+            writeln!(out, "{} `{}`", level, path.join("."))?;
+            writeln!(out)?;
+            if let Some(ref doc) = rules.doc {
+                writeln!(out, "{}", doc)?;
+                writeln!(out)?;
+            }
+            writeln!(out, "- Occurrences: {}", occurrences(rules.min, rules.max))?;
+            writeln!(
+                out,
+                "- Parameters: {}",
+                param_count(rules.min_params, rules.max_params)
+            )?;
+            for (index, rule) in rules.param_rules.iter().enumerate() {
+                if *rule != ParamRule::Any {
+                    writeln!(out, "- Parameter {}: {}", index, rule)?;
+                }
+            }
+            let block = match rules.child {
+                ChildRule::Any => "any, not validated",
+                ChildRule::Forbidden => "not allowed",
+                ChildRule::Required(_) => "required",
+                ChildRule::Optional(_) => "optional",
+            };
+            writeln!(out, "- Block: {}", block)?;
+            if let ChildRule::Required(ref schema) | ChildRule::Optional(ref schema) = rules.child {
+                writeln!(out)?;
+                schema.write_markdown(out, path)?;
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+
+    /// Synthesizes an example document for this schema, with placeholder
+    /// parameters.
+    ///
+    /// Each directive appears as many times as it must, and at least once,
+    /// with as many parameters as it must have, and with its block if it may
+    /// have one. When two directives conflict, only the one which is required,
+    /// or else the first one, appears. The placeholders satisfy the
+    /// [`ParamRule`]s and uniqueness constraints, so the example validates
+    /// against the schema, except for [`ParamRule::Regex`] parameters, whose
+    /// placeholder is the pattern itself.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let schema = Schema::new().directive("listen", |d| {
+    ///     d.min(2).params(1).param(0, ParamRule::Integer { min: Some(1024), max: None })
+    /// });
+    /// let example = schema.to_scfg_example();
+    /// assert_eq!(example, "listen 1024\nlisten 1025\n".parse().unwrap());
+    /// assert!(schema.validate(&example).is_ok());
+    /// ```
+    pub fn to_scfg_example(&self) -> Scfg {
+        let mut doc = Scfg::new();
+        for (name, rules) in &self.directives {
+            if self.excluded_from_example(name) {
+                continue;
+            }
+            let count = rules.min.max(1).min(rules.max.unwrap_or(usize::MAX));
+            for k in 0..count {
+                let directive = doc.add(name.as_str());
+                for index in 0..rules.min_params {
+                    let rule = rules.param_rules.get(index).unwrap_or(&ParamRule::Any);
+                    directive.append_param(rule.placeholder(k));
+                }
+                if let ChildRule::Required(ref schema) | ChildRule::Optional(ref schema) =
+                    rules.child
+                {
+                    directive.child = Some(schema.to_scfg_example());
+                }
+            }
+        }
+        doc
+    }
+
+    /// Returns whether the directive `name` is left out of the example because
+    /// it conflicts with another directive.
+    fn excluded_from_example(&self, name: &str) -> bool {
+        self.constraints.iter().any(|constraint| match constraint {
+            Constraint::Conflicts(a, b) => {
+                if matches!(self.get(b), Some(rules) if rules.min > 0) {
+                    a == name
+                } else {
+                    b == name
+                }
+            }
+            _ => false,
+        })
+    }
+}
+
+impl Schema {
+    /// Returns completion data for the directives allowed in the block at
+    /// `path`, in the order they were described.
+    ///
+    /// `path` names the directives leading to the block, and is empty for the
+    /// top level. Returns `None` if a name in `path` is not described, or if
+    /// its block is not validated by a nested schema.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let tls = Schema::new().directive("certificate", |d| d.params(1));
+    /// let schema = Schema::new().directive("listen", |d| d.optional_child(tls));
+    /// let names: Vec<_> = schema
+    ///     .directives_at(&["listen"])
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|completion| completion.name)
+    ///     .collect();
+    /// assert_eq!(names, ["certificate"]);
+    /// assert_eq!(schema.directives_at(&["certificate"]), None);
+    /// ```
+    pub fn directives_at<Q>(&self, path: &[Q]) -> Option<Vec<Completion>>
+    where
+        Q: AsRef<str>,
+    {
+        let mut schema = self;
+        for name in path {
+            schema = match schema.get(name.as_ref())?.child {
+                ChildRule::Required(ref child) | ChildRule::Optional(ref child) => child,
+                _ => return None,
+            };
+        }
+        Some(schema.completions())
+    }
+
+    fn completions(&self) -> Vec<Completion> {
+        self.directives
+            .iter()
+            .map(|(name, rules)| Completion {
+                name: name.clone(),
+                doc: rules.doc.clone(),
+                min_params: rules.min_params,
+                max_params: rules.max_params,
+                params: rules.param_rules.clone(),
+                block_allowed: rules.child != ChildRule::Forbidden,
+                block_required: matches!(rules.child, ChildRule::Required(_)),
+            })
+            .collect()
+    }
+
+    /// Exports completion data for every block of this schema as JSON, for
+    /// editors. Available with the `json` feature.
+    ///
+    /// The result is an object with a `contexts` array, holding an object per
+    /// block validated by the schema, in depth-first order. Each has a `path`
+    /// array, as accepted by [`Schema::directives_at`], and a `directives`
+    /// array with an object per [`Completion`]. Parameter rules become
+    /// objects with an `index`, the `expected` value as text, and the
+    /// suggested `values`, which are empty except for [`ParamRule::Enum`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let schema = Schema::new().directive("log", |d| {
+    ///     d.params(1).param(0, ParamRule::Enum(vec!["debug".to_owned(), "info".to_owned()]))
+    /// });
+    /// let json = schema.to_completion_json();
+    /// let log = &json["contexts"][0]["directives"][0];
+    /// assert_eq!(log["name"], "log");
+    /// assert_eq!(log["params"][0]["values"], serde_json::json!(["debug", "info"]));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_completion_json(&self) -> serde_json::Value {
+        let mut contexts = Vec::new();
+        self.completion_contexts(&mut Vec::new(), &mut contexts);
+        serde_json::json!({ "contexts": contexts })
+    }
+
+    #[cfg(feature = "json")]
+    fn completion_contexts(&self, path: &mut Vec<String>, contexts: &mut Vec<serde_json::Value>) {
+        use serde_json::json;
+
+        let directives: Vec<_> = self
+            .completions()
+            .into_iter()
+            .map(|completion| {
+                let params: Vec<_> = completion
+                    .params
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, rule)| **rule != ParamRule::Any)
+                    .map(|(index, rule)| {
+                        let values = match rule {
+                            ParamRule::Enum(values) => values.clone(),
+                            _ => Vec::new(),
+                        };
+                        json!({
+                            "index": index,
+                            "expected": rule.to_string(),
+                            "values": values,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "name": completion.name,
+                    "doc": completion.doc,
+                    "min_params": completion.min_params,
+                    "max_params": completion.max_params,
+                    "params": params,
+                    "block_allowed": completion.block_allowed,
+                    "block_required": completion.block_required,
+                })
+            })
+            .collect();
+        contexts.push(json!({ "path": path, "directives": directives }));
+
+        for (name, rules) in &self.directives {
+            if let ChildRule::Required(ref child) | ChildRule::Optional(ref child) = rules.child {
+                path.push(name.clone());
+                child.completion_contexts(path, contexts);
+                path.pop();
+            }
+        }
+    }
+}
+
+impl ParamRule {
+    /// Returns a value satisfying this rule, different for each `k` when
+    /// possible.
+    fn placeholder(&self, k: usize) -> String {
+        match self {
+            ParamRule::Any if k == 0 => "value".to_owned(),
+            ParamRule::Any => format!("value{}", k + 1),
+            ParamRule::Integer { min, max } => {
+                let low = min.unwrap_or_else(|| max.unwrap_or(0).min(0));
+                let n = low.saturating_add(k as i64);
+                max.map_or(n, |max| n.min(max)).to_string()
+            }
+            ParamRule::Enum(values) if values.is_empty() => String::new(),
+            ParamRule::Enum(values) => values[k % values.len()].clone(),
+            #[cfg(feature = "regex")]
+            ParamRule::Regex(regex) => regex.as_str().to_owned(),
+            ParamRule::Duration => format!("{}s", 30 * (k + 1)),
+            ParamRule::Size => format!("{}MiB", 512 * (k + 1)),
+        }
+    }
+}
+
+fn occurrences(min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (0, None) => "any number of times".to_owned(),
+        (min, Some(max)) if min == max => format!("exactly {} {}", max, times(max)),
+        (0, Some(max)) => format!("at most {} {}", max, times(max)),
+        (min, None) => format!("at least {} {}", min, times(min)),
+        (min, Some(max)) => format!("{} to {} times", min, max),
+    }
+}
+
+fn param_count(min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (0, None) => "any number".to_owned(),
+        (0, Some(0)) => "none".to_owned(),
+        (min, Some(max)) if min == max => format!("exactly {}", max),
+        (0, Some(max)) => format!("at most {}", max),
+        (min, None) => format!("at least {}", min),
+        (min, Some(max)) => format!("{} to {}", min, max),
+    }
+}
+
+impl Constraint {
+    /// Describes this constraint, with `prefix` prepended to the names.
+    fn describe(&self, prefix: &str) -> String {
+        match self {
+            Constraint::UniqueByParam(name, index) => {
+                format!(
+                    "Parameter {} of `{}{}` must be unique.",
+                    index, prefix, name
+                )
+            }
+            Constraint::Requires(a, b) => {
+                format!("`{}{}` requires `{}{}`.", prefix, a, prefix, b)
+            }
+            Constraint::Conflicts(a, b) => {
+                format!("`{}{}` conflicts with `{}{}`.", prefix, a, prefix, b)
+            }
+        }
+    }
+}
+
+/// Parses a `param <index> <kind> [args...]` directive.
+fn param_rule(directive: &Directive) -> Result<(usize, ParamRule), SchemaError> {
+    let error = |message: String| Err(SchemaError::new(directive, message));
+    let (index, kind, args) = match directive.params.as_slice() {
+        [index, kind, args @ ..] => (index, kind.as_str(), args),
+        params => {
+            let message = format!(
+                "`param` takes an index and a kind, found {} parameters",
+                params.len()
+            );
+            return error(message);
+        }
+    };
+    let index = match index.parse() {
+        Ok(index) => index,
+        Err(_) => return error(format!("`param` takes an index, found `{}`", index)),
+    };
+    if index > MAX_PARAM_INDEX {
+        return error(format!(
+            "parameter index {} is above {}",
+            index, MAX_PARAM_INDEX
+        ));
+    }
+    let rule = match (kind, args) {
+        ("any", []) => ParamRule::Any,
+        ("duration", []) => ParamRule::Duration,
+        ("size", []) => ParamRule::Size,
+        ("integer", []) => ParamRule::Integer {
+            min: None,
+            max: None,
+        },
+        ("integer", [range]) => match parse_integer_range(range) {
+            Some((min, max)) => ParamRule::Integer { min, max },
+            None => return error(format!("invalid integer range `{}`", range)),
+        },
+        ("enum", values) if !values.is_empty() => ParamRule::Enum(values.to_vec()),
+        #[cfg(feature = "regex")]
+        ("regex", [pattern]) => match regex::Regex::new(pattern) {
+            Ok(regex) => ParamRule::Regex(regex),
+            Err(err) => return error(format!("invalid regex: {}", err)),
+        },
+        #[cfg(not(feature = "regex"))]
+        ("regex", _) => return error("`regex` rules require the `regex` feature".to_owned()),
+        #[cfg(feature = "regex")]
+        ("regex", _) => return error("wrong number of arguments for `regex`".to_owned()),
+        ("any", _) | ("duration", _) | ("size", _) | ("integer", _) | ("enum", _) => {
+            return error(format!("wrong number of arguments for `{}`", kind));
+        }
+        _ => return error(format!("unknown parameter kind `{}`", kind)),
+    };
+    Ok((index, rule))
+}
+
+/// Parses `min..max`, `min..` or `..max` into inclusive integer bounds.
+fn parse_integer_range(range: &str) -> Option<(Option<i64>, Option<i64>)> {
+    let (min, max) = range.split_once("..")?;
+    let bound = |s: &str| -> Result<Option<i64>, std::num::ParseIntError> {
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            s.parse().map(Some)
+        }
+    };
+    let (min, max) = (bound(min).ok()?, bound(max).ok()?);
+    match (min, max) {
+        (Some(min), Some(max)) if min > max => None,
+        (None, None) => None,
+        bounds => Some(bounds),
+    }
+}
+
+/// Returns the only directive of a group, for keywords that may not be
+/// repeated.
+fn single<'a>(directives: &'a [Directive], name: &str) -> Result<&'a Directive, SchemaError> {
+    match directives {
+        [directive] => Ok(directive),
+        _ => {
+            let message = format!("`{}` may only appear once", name);
+            Err(SchemaError::new(&directives[1], message))
+        }
+    }
+}
+
+fn no_params(directive: &Directive, name: &str) -> Result<(), SchemaError> {
+    if directive.params.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "`{}` takes no parameters, found {}",
+        name,
+        directive.params.len()
+    );
+    Err(SchemaError::new(directive, message))
+}
+
+fn word_param<'a>(directive: &'a Directive, name: &str) -> Result<&'a str, SchemaError> {
+    match directive.params.as_slice() {
+        [param] => Ok(param),
+        params => {
+            let message = format!("`{}` takes 1 parameter, found {}", name, params.len());
+            Err(SchemaError::new(directive, message))
+        }
+    }
+}
+
+fn number_param(directive: &Directive, name: &str) -> Result<usize, SchemaError> {
+    let param = word_param(directive, name)?;
+    param.parse().map_err(|_| {
+        let message = format!("`{}` takes a number, found `{}`", name, param);
+        SchemaError::new(directive, message)
+    })
+}
+
+/// Parses `n`, `min..max` or `min..` into inclusive bounds.
+fn parse_range(range: &str) -> Option<(usize, Option<usize>)> {
+    match range.split_once("..") {
+        None => {
+            let n = range.parse().ok()?;
+            Some((n, Some(n)))
+        }
+        Some((min, "")) => Some((min.parse().ok()?, None)),
+        Some((min, max)) => {
+            let (min, max) = (min.parse().ok()?, max.parse().ok()?);
+            if min > max {
+                return None;
+            }
+            Some((min, Some(max)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema() -> Schema {
+        let tls = Schema::new()
+            .directive("certificate", |d| d.min(1).max(1).params(1))
+            .directive("key", |d| d.min(1).max(1).params(1));
+        Schema::new()
+            .directive("domain", |d| d.min(1).max(1).params(1).no_child())
+            .directive("listen", |d| d.min(1).min_params(1).max_params(2))
+            .directive("tls", |d| d.max(1).params(0).child(tls))
+    }
+
+    fn errors(schema: &Schema, src: &str) -> Vec<(String, Option<usize>, Rule)> {
+        let doc = src.parse::<Scfg>().unwrap();
+        match schema.validate(&doc) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|err| (err.path.join("."), err.line, err.rule))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn verify_schema_at_path() {
+        let src = r#"server {
+    host example.com
+}
+train Shinkansen {
+    model E5 {
+        max-speed 320km/h
+    }
+    model E7 {
+    }
+}
+"#;
+        let doc = src.parse::<Scfg>().unwrap();
+        let model = Schema::new().directive("max-speed", |d| d.min(1).max(1).params(1));
+        let train = Schema::new()
+            .directive("model", |d| d.min(1).params(1).child(model))
+            .deny_unknown();
+        let found = |path: &[&str], schema: &Schema| match doc.verify_schema_at_path(path, schema) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|err| (err.path.join("."), err.line, err.rule))
+                .collect(),
+        };
+        assert_eq!(
+            found(&["train"], &train),
+            [(
+                "train.model.max-speed".to_owned(),
+                Some(8),
+                Rule::TooFew { min: 1, found: 0 }
+            )]
+        );
+
+        let server = Schema::new()
+            .directive("host", |d| d.params(1))
+            .directive("port", |d| d.min(1).params(1));
+        assert_eq!(
+            found(&["server"], &server),
+            [(
+                "server.port".to_owned(),
+                Some(1),
+                Rule::TooFew { min: 1, found: 0 }
+            )]
+        );
+        assert_eq!(
+            found(&["train"], &server),
+            [(
+                "train.port".to_owned(),
+                Some(4),
+                Rule::TooFew { min: 1, found: 0 }
+            )]
+        );
+        assert_eq!(
+            found(&["train", "model"], &Schema::new().deny_unknown()),
+            [(
+                "train.model.max-speed".to_owned(),
+                Some(6),
+                Rule::UnknownDirective { suggestion: None }
+            )]
+        );
+        assert_eq!(
+            found(&["missing"], &server),
+            [(
+                "missing.port".to_owned(),
+                None,
+                Rule::TooFew { min: 1, found: 0 }
+            )]
+        );
+        assert_eq!(found(&[], &Schema::new()), []);
+    }
+
+    #[test]
+    fn clean_document() {
+        let src = r#"domain example.com
+listen 0.0.0.0:6697 tls
+listen 127.0.0.1:6667
+tls {
+    certificate cert.pem
+    key key.pem
+    unknown directives are ignored
+}
+unknown
+"#;
+        assert_eq!(errors(&schema(), src), []);
+    }
+
+    #[test]
+    fn cardinality() {
+        let src = "domain a\ndomain b\ndomain c\ntls {\n\tkey k\n}\n";
+        assert_eq!(
+            errors(&schema(), src),
+            [
+                (
+                    "domain".to_owned(),
+                    Some(2),
+                    Rule::TooMany { max: 1, found: 3 }
+                ),
+                (
+                    "tls.certificate".to_owned(),
+                    Some(4),
+                    Rule::TooFew { min: 1, found: 0 }
+                ),
+                ("listen".to_owned(), None, Rule::TooFew { min: 1, found: 0 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn params() {
+        let src = "domain\nlisten 1 2 3\nlisten 1 2\n";
+        assert_eq!(
+            errors(&schema(), src),
+            [
+                (
+                    "domain".to_owned(),
+                    Some(1),
+                    Rule::Params {
+                        min: 1,
+                        max: Some(1),
+                        found: 0
+                    }
+                ),
+                (
+                    "listen".to_owned(),
+                    Some(2),
+                    Rule::Params {
+                        min: 1,
+                        max: Some(2),
+                        found: 3
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn children() {
+        let src = "domain a {\n}\nlisten a\ntls\n";
+        assert_eq!(
+            errors(&schema(), src),
+            [
+                ("domain".to_owned(), Some(1), Rule::UnexpectedChild),
+                ("tls".to_owned(), Some(4), Rule::MissingChild),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown() {
+        let schema = schema().deny_unknown();
+        let src = "domain a\nlisten a\nlisten-addr a\ntls {\n\tcertificate c\n\tkey k\n\tca c\n}\n";
+        assert_eq!(
+            errors(&schema, src),
+            [(
+                "listen-addr".to_owned(),
+                Some(3),
+                Rule::UnknownDirective { suggestion: None }
+            )]
+        );
+    }
+
+    #[test]
+    fn unknown_suggestions() {
+        let tls = Schema::new()
+            .deny_unknown()
+            .directive("certificate", |d| d.min(1))
+            .directive("key", |d| d.min(1));
+        let schema = Schema::new()
+            .deny_unknown()
+            .directive("domain", |d| d.min(1))
+            .directive("listen", |d| d.min(1))
+            .directive("tls", |d| d.child(tls));
+        let src = "domian a\nlisten a\ntls {\n\tcertifcate c\n\tkey k\n\tproxy p\n}\n";
+        let unknown = |rule| Rule::UnknownDirective { suggestion: rule };
+        assert_eq!(
+            errors(&schema, src),
+            [
+                (
+                    "domian".to_owned(),
+                    Some(1),
+                    unknown(Some("domain".to_owned()))
+                ),
+                (
+                    "tls.certifcate".to_owned(),
+                    Some(4),
+                    unknown(Some("certificate".to_owned()))
+                ),
+                ("tls.proxy".to_owned(), Some(6), unknown(None)),
+                (
+                    "tls.certificate".to_owned(),
+                    Some(3),
+                    Rule::TooFew { min: 1, found: 0 }
+                ),
+                ("domain".to_owned(), None, Rule::TooFew { min: 1, found: 0 }),
+            ]
+        );
+        let doc: Scfg = "domian a\n".parse().unwrap();
+        let errors = schema.validate(&doc).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "line 1: domian: unknown directive, did you mean \"domain\"?"
+        );
+    }
+
+    #[test]
+    fn display() {
+        let errors = schema().validate(&"listen\n".parse().unwrap()).unwrap_err();
+        let messages: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 1: listen: expected 1 to 2 parameters, found 0",
+                "domain: must appear at least 1 time, found 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn meta_round_trip() {
+        let schema = schema().deny_unknown().directive("any", |d| d);
+        let meta = schema.to_scfg();
+        let mut out = Vec::new();
+        meta.write(&mut out).unwrap();
+        let src = String::from_utf8(out).unwrap();
+        let loaded = Schema::from_scfg(&src.parse().unwrap()).unwrap();
+        assert_eq!(loaded, schema);
+
+        let docs = [
+            "domain a\nlisten a\n",
+            "domain a\ndomain b\nlisten\ntls\n",
+            "domain a {\n}\nlisten a b c\ntls {\n\tkey k\n}\nother\n",
+        ];
+        for src in docs.iter() {
+            let doc = src.parse().unwrap();
+            assert_eq!(loaded.validate(&doc), schema.validate(&doc), "{}", src);
+        }
+    }
+
+    #[test]
+    fn meta_errors() {
+        let cases = [
+            (
+                "directive a {\n\tminimum 1\n}\n",
+                2,
+                "unknown keyword `minimum`",
+            ),
+            ("directives a {\n}\n", 1, "unknown keyword `directives`"),
+            (
+                "directive a {\n\tparams 1 2\n}\n",
+                2,
+                "`params` takes 1 parameter, found 2",
+            ),
+            (
+                "directive a {\n\tparams 2..1\n}\n",
+                2,
+                "invalid parameter count `2..1`",
+            ),
+            (
+                "directive a {\n\tmin one\n}\n",
+                2,
+                "`min` takes a number, found `one`",
+            ),
+            (
+                "directive a {\n\tmax 1\n\tmax 2\n}\n",
+                3,
+                "`max` may only appear once",
+            ),
+            ("directive a\n", 1, "directive `a` must have a block"),
+            (
+                "directive a {\n}\ndirective a {\n}\n",
+                3,
+                "directive `a` is described more than once",
+            ),
+            (
+                "directive a {\n\tchild {\n\t}\n\tno-child\n}\n",
+                4,
+                "`no-child` conflicts with `child`",
+            ),
+            (
+                "directive a {\n\tchild\n}\n",
+                2,
+                "`child` must have a block",
+            ),
+            (
+                "directive a {\n\tchild {\n\t\tdirective b {\n\t\t\tmin x\n\t\t}\n\t}\n}\n",
+                4,
+                "`min` takes a number, found `x`",
+            ),
+            (
+                "unknown allow\n",
+                1,
+                "`unknown` must be `deny` or `ignore`, found `allow`",
+            ),
+        ];
+        for (src, line, message) in cases.iter() {
+            let err = Schema::from_scfg(&src.parse().unwrap()).unwrap_err();
+            assert_eq!(err.line(), Some(*line), "{}", src);
+            assert_eq!(err.message, *message, "{}", src);
+        }
+    }
+
+    fn param_errors(rule: ParamRule, values: &[&str]) -> Vec<String> {
+        let schema = Schema::new().directive("value", |d| d.param(1, rule));
+        let mut doc = Scfg::new();
+        for value in values {
+            doc.add("value").append_param("x").append_param(*value);
+        }
+        doc.add("value")
+            .append_param("missing values aren't checked");
+        match schema.validate(&doc) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|err| err.rule.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn param_rules() {
+        let rule = ParamRule::Integer {
+            min: Some(1),
+            max: Some(65535),
+        };
+        assert!(param_errors(rule.clone(), &["1", "6697", "65535"]).is_empty());
+        assert_eq!(
+            param_errors(rule, &["0", "65536", "port", "1.5"]),
+            [
+                "invalid parameter 1 \"0\", expected an integer from 1 to 65535",
+                "invalid parameter 1 \"65536\", expected an integer from 1 to 65535",
+                "invalid parameter 1 \"port\", expected an integer from 1 to 65535",
+                "invalid parameter 1 \"1.5\", expected an integer from 1 to 65535",
+            ]
+        );
+        let rule = ParamRule::Integer {
+            min: None,
+            max: Some(-1),
+        };
+        assert!(param_errors(rule.clone(), &["-1", "-100"]).is_empty());
+        assert_eq!(
+            param_errors(rule, &["0"]),
+            ["invalid parameter 1 \"0\", expected an integer of at most -1"]
+        );
+
+        let rule = ParamRule::Duration;
+        assert!(param_errors(rule.clone(), &["30s", "1h30m", "250ms", "2d", "1m1ms"]).is_empty());
+        assert_eq!(
+            param_errors(rule, &["", "30", "s", "1h30", "1.5s", "3w"]).len(),
+            6
+        );
+
+        let rule = ParamRule::Size;
+        assert!(
+            param_errors(rule.clone(), &["512", "1B", "4k", "512MiB", "2gb", "1TiB"]).is_empty()
+        );
+        assert_eq!(
+            param_errors(rule, &["", "MiB", "1.5G", "1PiB", "-1"]).len(),
+            5
+        );
+
+        assert!(param_errors(ParamRule::Any, &["", "anything"]).is_empty());
+    }
+
+    #[test]
+    fn enum_suggestions() {
+        let levels = ["debug", "info", "warning", "error"];
+        let rule = ParamRule::Enum(levels.iter().map(|&l| l.to_owned()).collect());
+        assert!(param_errors(rule.clone(), &levels).is_empty());
+
+        let schema = Schema::new().directive("log", |d| d.param(0, rule));
+        let doc = "log wraning\nlog verbose\n".parse().unwrap();
+        let errors = schema.validate(&doc).unwrap_err();
+        let suggestions: Vec<_> = errors
+            .iter()
+            .map(|err| match err.rule {
+                Rule::InvalidParam { ref suggestion, .. } => suggestion.as_deref(),
+                _ => panic!("unexpected rule {:?}", err.rule),
+            })
+            .collect();
+        assert_eq!(suggestions, [Some("warning"), None]);
+        assert_eq!(
+            errors[0].to_string(),
+            "line 1: log: invalid parameter 0 \"wraning\", expected one of \"debug\", \"info\", \"warning\", \"error\", did you mean \"warning\"?"
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "line 2: log: invalid parameter 0 \"verbose\", expected one of \"debug\", \"info\", \"warning\", \"error\""
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_rule() {
+        let rule = ParamRule::Regex(regex::Regex::new("^[a-z]+$").unwrap());
+        assert!(param_errors(rule.clone(), &["example", "com"]).is_empty());
+        assert_eq!(
+            param_errors(rule, &["example.com"]),
+            ["invalid parameter 1 \"example.com\", expected a match for `^[a-z]+$`"]
+        );
+    }
+
+    #[test]
+    fn meta_param_rules() {
+        let schema = Schema::new().directive("listen", |d| {
+            d.param(0, ParamRule::Any)
+                .param(
+                    1,
+                    ParamRule::Integer {
+                        min: Some(1),
+                        max: None,
+                    },
+                )
+                .param(2, ParamRule::Enum(vec!["tcp".to_owned(), "udp".to_owned()]))
+                .param(3, ParamRule::Duration)
+                .param(4, ParamRule::Size)
+                .param(
+                    5,
+                    ParamRule::Integer {
+                        min: None,
+                        max: None,
+                    },
+                )
+        });
+        let meta = schema.to_scfg();
+        assert_eq!(Schema::from_scfg(&meta).unwrap(), schema);
+
+        let src = "directive listen {\n\tparam 1 integer 1..\n\tparam 2 enum tcp udp\n\tparam 3 duration\n\tparam 4 size\n\tparam 5 integer\n}\n";
+        assert_eq!(Schema::from_scfg(&src.parse().unwrap()).unwrap(), schema);
+
+        let cases = [
+            (
+                "param 0\n",
+                "`param` takes an index and a kind, found 1 parameters",
+            ),
+            ("param first any\n", "`param` takes an index, found `first`"),
+            (
+                "param 18446744073709551615 any\n",
+                "parameter index 18446744073709551615 is above 255",
+            ),
+            (
+                "param 100000000000 integer\n",
+                "parameter index 100000000000 is above 255",
+            ),
+            ("param 0 float\n", "unknown parameter kind `float`"),
+            ("param 0 enum\n", "wrong number of arguments for `enum`"),
+            ("param 0 size 1\n", "wrong number of arguments for `size`"),
+            ("param 0 integer 2..1\n", "invalid integer range `2..1`"),
+            ("param 0 integer ..\n", "invalid integer range `..`"),
+            (
+                "param 0 size\nparam 0 duration\n",
+                "parameter 0 is constrained more than once",
+            ),
+        ];
+        for (body, message) in cases.iter() {
+            let src = format!("directive a {{\n{}}}\n", body);
+            let err = Schema::from_scfg(&src.parse().unwrap()).unwrap_err();
+            assert_eq!(err.message, *message, "{}", src);
+        }
+    }
+
+    #[test]
+    fn constraints() {
+        let tls = Schema::new()
+            .requires("certificate", "key")
+            .requires("key", "certificate")
+            .conflicts("password", "password-file");
+        let schema = Schema::new()
+            .directive("tls", |d| d.child(tls))
+            .unique_by_param("listen", 0);
+        let src = r#"listen 0.0.0.0:6697
+listen 127.0.0.1:6667
+listen 0.0.0.0:6697
+listen
+listen
+tls {
+    key key.pem
+    password hunter2
+    password-file /etc/password
+    password hunter3
+}
+"#;
+        let errors = errors(&schema, src);
+        assert_eq!(
+            errors,
+            [
+                (
+                    "tls.key".to_owned(),
+                    Some(7),
+                    Rule::Requires {
+                        other: "certificate".to_owned(),
+                        lines: vec![7]
+                    }
+                ),
+                (
+                    "tls.password".to_owned(),
+                    Some(8),
+                    Rule::Conflicts {
+                        other: "password-file".to_owned(),
+                        lines: vec![8, 9, 10]
+                    }
+                ),
+                (
+                    "listen".to_owned(),
+                    Some(1),
+                    Rule::Duplicate {
+                        index: 0,
+                        value: "0.0.0.0:6697".to_owned(),
+                        lines: vec![1, 3]
+                    }
+                ),
+            ]
+        );
+
+        let clean = "listen a\nlisten b\ntls {\n\tcertificate c\n\tkey k\n\tpassword p\n}\n";
+        assert_eq!(self::errors(&schema, clean), []);
+    }
+
+    #[test]
+    fn constraints_display() {
+        let schema = Schema::new()
+            .unique_by_param("listen", 0)
+            .conflicts("password", "password-file");
+        let doc = "listen a\nlisten a\npassword p\npassword-file f\n"
+            .parse()
+            .unwrap();
+        let messages: Vec<_> = schema
+            .validate(&doc)
+            .unwrap_err()
+            .iter()
+            .map(|err| err.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "line 1: listen: duplicate parameter 0 \"a\" (lines 1, 2)",
+                "line 3: password: conflicts with `password-file` (lines 3, 4)",
+            ]
+        );
+
+        let meta = schema.to_scfg();
+        assert_eq!(Schema::from_scfg(&meta).unwrap(), schema);
+        let src = "constraint unique listen 0\nconstraint conflicts password password-file\n";
+        assert_eq!(Schema::from_scfg(&src.parse().unwrap()).unwrap(), schema);
+        let src = "constraint unique listen\n";
+        let err = Schema::from_scfg(&src.parse().unwrap()).unwrap_err();
+        assert_eq!(err.line(), Some(1));
+    }
+
+    fn documented_schema() -> Schema {
+        let tls = Schema::new()
+            .directive("certificate", |d| {
+                d.min(1)
+                    .max(1)
+                    .params(1)
+                    .doc("Path to the PEM certificate chain.")
+            })
+            .directive("key", |d| {
+                d.min(1)
+                    .max(1)
+                    .params(1)
+                    .doc("Path to the PEM private key.")
+            })
+            .directive("password", |d| d.max(1).params(1))
+            .directive("password-file", |d| d.max(1).params(1))
+            .conflicts("password", "password-file")
+            .deny_unknown();
+        Schema::new()
+            .directive("listen", |d| {
+                d.min(1)
+                    .min_params(1)
+                    .max_params(2)
+                    .optional_child(tls)
+                    .doc("An address to listen on.")
+            })
+            .directive("log", |d| {
+                let levels = vec!["debug".to_owned(), "info".to_owned(), "error".to_owned()];
+                d.max(1)
+                    .params(1)
+                    .param(0, ParamRule::Enum(levels))
+                    .no_child()
+            })
+            .directive("workers", |d| {
+                let count = ParamRule::Integer {
+                    min: Some(1),
+                    max: Some(64),
+                };
+                d.max(1).params(1).param(0, count)
+            })
+            .directive("timeout", |d| {
+                d.params(2)
+                    .param(0, ParamRule::Duration)
+                    .param(1, ParamRule::Size)
+            })
+            .unique_by_param("listen", 0)
+    }
+
+    #[test]
+    fn markdown() {
+        let markdown = documented_schema().to_markdown();
+        assert_eq!(markdown, include_str!("../tests/data/schema.md"));
+    }
+
+    #[test]
+    #[ignore]
+    fn regenerate_markdown() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/schema.md");
+        std::fs::write(path, documented_schema().to_markdown()).unwrap();
+    }
+
+    #[test]
+    fn example() {
+        let schema = documented_schema();
+        let example = schema.to_scfg_example();
+        assert_eq!(schema.validate(&example), Ok(()));
+        let listen = example.get("listen").unwrap();
+        assert_eq!(listen.params(), ["value"]);
+        let tls = listen.child().unwrap();
+        assert!(tls.contains("password") && !tls.contains("password-file"));
+        assert_eq!(example.get("workers").unwrap().params(), ["1"]);
+        assert_eq!(example.get("timeout").unwrap().params(), ["30s", "512MiB"]);
+
+        let meta = schema.to_scfg();
+        assert_eq!(Schema::from_scfg(&meta).unwrap(), schema);
+    }
+
+    #[test]
+    fn directives_at() {
+        let schema = documented_schema();
+        let names = |path: &[&str]| {
+            schema
+                .directives_at(path)
+                .map(|completions| completions.into_iter().map(|c| c.name).collect::<Vec<_>>())
+        };
+        assert_eq!(names(&[]).unwrap(), ["listen", "log", "workers", "timeout"]);
+        assert_eq!(
+            names(&["listen"]).unwrap(),
+            ["certificate", "key", "password", "password-file"]
+        );
+        assert_eq!(names(&["log"]), None);
+        assert_eq!(names(&["listen", "key"]), None);
+        assert_eq!(names(&["missing"]), None);
+
+        let log = &schema.directives_at::<&str>(&[]).unwrap()[1];
+        assert_eq!(
+            *log,
+            Completion {
+                name: "log".to_owned(),
+                doc: None,
+                min_params: 1,
+                max_params: Some(1),
+                params: vec![ParamRule::Enum(vec![
+                    "debug".to_owned(),
+                    "info".to_owned(),
+                    "error".to_owned()
+                ])],
+                block_allowed: false,
+                block_required: false,
+            }
+        );
+        let certificate = &schema.directives_at(&["listen"]).unwrap()[0];
+        assert_eq!(
+            certificate.doc.as_deref(),
+            Some("Path to the PEM certificate chain.")
+        );
+        assert!(certificate.block_allowed && !certificate.block_required);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn completion_json() {
+        let tls = Schema::new().directive("certificate", |d| d.params(1).doc("A PEM file."));
+        let schema = Schema::new()
+            .directive("listen", |d| d.min_params(1).child(tls))
+            .directive("log", |d| {
+                let levels = vec!["debug".to_owned(), "info".to_owned()];
+                d.max_params(1).param(0, ParamRule::Enum(levels)).no_child()
+            });
+        let expected = serde_json::json!({
+            "contexts": [
+                {
+                    "path": [],
+                    "directives": [
+                        {
+                            "name": "listen",
+                            "doc": null,
+                            "min_params": 1,
+                            "max_params": null,
+                            "params": [],
+                            "block_allowed": true,
+                            "block_required": true,
+                        },
+                        {
+                            "name": "log",
+                            "doc": null,
+                            "min_params": 0,
+                            "max_params": 1,
+                            "params": [
+                                {
+                                    "index": 0,
+                                    "expected": "one of \"debug\", \"info\"",
+                                    "values": ["debug", "info"],
+                                },
+                            ],
+                            "block_allowed": false,
+                            "block_required": false,
+                        },
+                    ],
+                },
+                {
+                    "path": ["listen"],
+                    "directives": [
+                        {
+                            "name": "certificate",
+                            "doc": "A PEM file.",
+                            "min_params": 1,
+                            "max_params": 1,
+                            "params": [],
+                            "block_allowed": true,
+                            "block_required": false,
+                        },
+                    ],
+                },
+            ],
+        });
+        assert_eq!(schema.to_completion_json(), expected);
+    }
+}
@@ -0,0 +1,220 @@
+//! Selection of directives with queries similar to CSS selectors.
+use crate::{Directive, Scfg};
+use std::fmt;
+
+/// The error returned by [`Scfg::select`] for a malformed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    message: &'static str,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid query: {}", self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/// One name of a query, with the combinator relating it to the previous one.
+#[derive(Debug)]
+struct Step<'q> {
+    combinator: Combinator,
+    name: &'q str,
+}
+
+impl Step<'_> {
+    fn matches(&self, name: &str) -> bool {
+        self.name == "*" || self.name == name
+    }
+}
+
+fn parse(query: &str) -> Result<Vec<Step<'_>>, QueryError> {
+    let error = |message| Err(QueryError { message });
+    let mut steps = Vec::new();
+    let mut combinator = None;
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('>') {
+            if steps.is_empty() {
+                return error("expected a name before '>'");
+            }
+            if combinator == Some(Combinator::Child) {
+                return error("expected a name between two '>'");
+            }
+            combinator = Some(Combinator::Child);
+            rest = after.trim_start();
+            continue;
+        }
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '>')
+            .unwrap_or(rest.len());
+        steps.push(Step {
+            combinator: combinator.unwrap_or(Combinator::Descendant),
+            name: &rest[..end],
+        });
+        combinator = None;
+        rest = rest[end..].trim_start();
+    }
+    if steps.is_empty() {
+        return error("expected a name");
+    }
+    if combinator.is_some() {
+        return error("expected a name after '>'");
+    }
+    Ok(steps)
+}
+
+/// Returns whether the last of `names`, preceded by the names of its
+/// ancestors, is matched by `steps`.
+fn matches(steps: &[Step<'_>], names: &[&str]) -> bool {
+    let (step, init) = match (steps.split_last(), names.split_last()) {
+        (Some((step, steps)), Some((name, _))) if step.matches(name) => (step, steps),
+        _ => return false,
+    };
+    if init.is_empty() {
+        return true;
+    }
+    let ancestors = &names[..names.len() - 1];
+    match step.combinator {
+        Combinator::Child => matches(init, ancestors),
+        Combinator::Descendant => (1..=ancestors.len())
+            .rev()
+            .any(|end| matches(init, &ancestors[..end])),
+    }
+}
+
+impl Scfg {
+    /// Selects directives with a query similar to a CSS selector.
+    ///
+    /// A query is a list of directive names, or `*` to match any name,
+    /// separated by combinators:
+    /// - whitespace, as in `train max-speed`, selects the directives matching
+    ///   the right-hand side which are nested, at any depth, in the block of a
+    ///   directive matching the left-hand side;
+    /// - `>`, as in `train > model`, selects the directives matching the
+    ///   right-hand side which are directly in the block of a directive
+    ///   matching the left-hand side.
+    ///
+    /// The first name may match at any depth. The selected directives are
+    /// returned once each, depth first, in the order [`write`] would write
+    /// them.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "train Shinkansen {\n\tmodel E5 {\n\t\tmax-speed 320km/h\n\t}\n}\n"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(doc.select("train > model").unwrap()[0].params(), ["E5"]);
+    /// assert_eq!(doc.select("train max-speed").unwrap()[0].params(), ["320km/h"]);
+    /// assert!(doc.select("train > max-speed").unwrap().is_empty());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if `query` is empty, or if a `>` is not surrounded by
+    /// names.
+    ///
+    /// [`write`]: Scfg::write
+    pub fn select(&self, query: &str) -> Result<Vec<&Directive>, QueryError> {
+        let steps = parse(query)?;
+        let mut selected = Vec::new();
+        self.select_into(&steps, &mut Vec::new(), &mut selected);
+        Ok(selected)
+    }
+
+    fn select_into<'a>(
+        &'a self,
+        steps: &[Step<'_>],
+        names: &mut Vec<&'a str>,
+        selected: &mut Vec<&'a Directive>,
+    ) {
+        for (name, directives) in &self.directives {
+            names.push(name);
+            for directive in directives {
+                if matches(steps, names) {
+                    selected.push(directive);
+                }
+                if let Some(ref child) = directive.child {
+                    child.select_into(steps, names, selected);
+                }
+            }
+            names.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // names selected together are in sorted order, so that the results do not
+    // depend on `preserve_order`.
+    static SRC: &str = r#"model Thalys
+train Shinkansen {
+    model E5 {
+        max-speed 320km/h
+        car 1 {
+            seats 29
+        }
+    }
+    model E7 {
+        max-speed 275km/h
+    }
+    yard {
+        model E2
+    }
+}
+"#;
+
+    fn select(query: &str) -> Vec<Vec<String>> {
+        let doc: Scfg = SRC.parse().unwrap();
+        doc.select(query)
+            .unwrap()
+            .into_iter()
+            .map(|directive| directive.params().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn combinators() {
+        assert_eq!(select("train > model"), [["E5"], ["E7"]]);
+        assert_eq!(select("train model"), [["E5"], ["E7"], ["E2"]]);
+        assert_eq!(select("model"), [["Thalys"], ["E5"], ["E7"], ["E2"]]);
+        assert_eq!(select("train > * > model"), [["E2"]]);
+        assert_eq!(
+            select("train>model   max-speed"),
+            [["320km/h"], ["275km/h"]]
+        );
+        assert_eq!(select("model seats"), [["29"]]);
+        assert_eq!(select("train > model > seats"), Vec::<Vec<String>>::new());
+        assert_eq!(select("train * seats"), [["29"]]);
+        assert!(select("car model").is_empty());
+    }
+
+    #[test]
+    fn descendant_matches_once() {
+        let doc: Scfg = "a {\n\ta {\n\t\tb x\n\t}\n}\n".parse().unwrap();
+        assert_eq!(doc.select("a b").unwrap().len(), 1);
+        assert_eq!(doc.select("a a").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn malformed() {
+        let doc = Scfg::new();
+        let err = |query| doc.select(query).unwrap_err().to_string();
+        assert_eq!(err(""), "invalid query: expected a name");
+        assert_eq!(err("  "), "invalid query: expected a name");
+        assert_eq!(err("> a"), "invalid query: expected a name before '>'");
+        assert_eq!(err("a >"), "invalid query: expected a name after '>'");
+        assert_eq!(
+            err("a > > b"),
+            "invalid query: expected a name between two '>'"
+        );
+    }
+}
@@ -0,0 +1,938 @@
+//! Serializing Rust values into documents laid out the way [`de::from_scfg`]
+//! reads them, for [`de::merge_from_scfg`].
+//!
+//! A value serializes as the directives of its name in the enclosing block:
+//!
+//! - A scalar is a directive with a single parameter.
+//! - `None` is no directive at all, and `Some` is its content.
+//! - A sequence has a directive per element, or is a directive without
+//!   parameters if it is empty.
+//! - A struct is a directive without parameters holding its fields in a block.
+//! - A map has a directive per entry, whose first parameter is the key, or is
+//!   a directive with an empty block if it is empty.
+//! - A tuple is a directive holding its elements as parameters.
+//! - An enum variant is its content, with the variant name as the first
+//!   parameter.
+//!
+//! The elements of a sequence, and the values of a map, are a single directive
+//! each: a sequence is then a directive holding its elements as parameters,
+//! and a map a directive holding its entries in a block, keyed by name. Values
+//! held as parameters must be scalars, tuples or enum variants of those.
+//!
+//! Values which would read back differently are errors, like `None` anywhere
+//! but in a field, or a sequence of a single empty element, like `vec![()]`,
+//! which would read back empty.
+//!
+//! [`de::from_scfg`]: crate::de::from_scfg
+//! [`de::merge_from_scfg`]: crate::de::merge_from_scfg
+use crate::{de::Error, Directive, Scfg};
+use serde::ser::{self, Impossible, Serialize};
+
+/// Serializes `value`, which must serialize as a struct, into a document.
+pub(crate) fn to_scfg<T>(value: &T) -> Result<Scfg, Error>
+where
+    T: Serialize + ?Sized,
+{
+    match value.serialize(GroupSerializer::GROUP)?.as_mut_slice() {
+        [Directive {
+            params,
+            child: Some(block),
+            ..
+        }] if params.is_empty() => Ok(std::mem::take(block)),
+        _ => Err(ser::Error::custom("expected a struct")),
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        <Error as serde::de::Error>::custom(msg)
+    }
+}
+
+/// Returns a group made of a directive with the single parameter `param`.
+fn scalar(param: String) -> Vec<Directive> {
+    vec![Directive {
+        params: vec![param],
+        ..Directive::default()
+    }]
+}
+
+macro_rules! serialize_scalars {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Error> {
+                self.serialize_str(&v.to_string())
+            }
+        )*
+    };
+}
+
+/// Serializes a value into the directives sharing a name or, if `single`, into
+/// a single directive, like an element of a sequence or the value of a map
+/// entry.
+#[derive(Clone, Copy)]
+struct GroupSerializer {
+    single: bool,
+}
+
+impl GroupSerializer {
+    const GROUP: Self = GroupSerializer { single: false };
+    const SINGLE: Self = GroupSerializer { single: true };
+}
+
+impl ser::Serializer for GroupSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = TupleSerializer;
+    type SerializeTupleStruct = TupleSerializer;
+    type SerializeTupleVariant = TupleSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    serialize_scalars! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(scalar(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Error> {
+        Err(ser::Error::custom("bytes are not supported"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        if self.single {
+            // a single directive is always `Some`.
+            return Err(ser::Error::custom(
+                "`None` is only supported as the value of a field",
+            ));
+        }
+        Ok(Vec::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(vec![Directive::default()])
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        let mut group = value.serialize(GroupSerializer::SINGLE)?;
+        group[0].params.insert(0, variant.to_owned());
+        Ok(group)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        if self.single {
+            return Ok(SeqSerializer::Params(TupleSerializer {
+                params: Vec::with_capacity(len.unwrap_or(0)),
+            }));
+        }
+        Ok(SeqSerializer::Group(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        Ok(TupleSerializer {
+            params: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        let mut params = Vec::with_capacity(len + 1);
+        params.push(variant.to_owned());
+        Ok(TupleSerializer { params })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(MapSerializer {
+            single: self.single,
+            directives: Vec::new(),
+            block: Scfg::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Ok(StructSerializer {
+            block: Scfg::new(),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(StructSerializer {
+            block: Scfg::new(),
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Collects a directive per element or, for a single directive, the elements
+/// as its parameters.
+enum SeqSerializer {
+    Group(Vec<Directive>),
+    Params(TupleSerializer),
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        match self {
+            SeqSerializer::Group(directives) => {
+                directives.extend(value.serialize(GroupSerializer::SINGLE)?);
+                Ok(())
+            }
+            SeqSerializer::Params(params) => params.push(value),
+        }
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        let directives = match self {
+            SeqSerializer::Group(directives) => directives,
+            SeqSerializer::Params(params) => return params.finish(),
+        };
+        match directives.as_slice() {
+            // no directive at all would be a missing field.
+            [] => Ok(vec![Directive::default()]),
+            [element] if element.params.is_empty() && element.child.is_none() => Err(
+                ser::Error::custom("a sequence of a single empty element reads back as empty"),
+            ),
+            _ => Ok(directives),
+        }
+    }
+}
+
+/// Collects the elements of a tuple, or of a sequence written as a single
+/// directive, as the parameters of a directive.
+struct TupleSerializer {
+    params: Vec<String>,
+}
+
+impl TupleSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(ElementSerializer {
+            params: &mut self.params,
+        })
+    }
+
+    fn finish(self) -> Result<Vec<Directive>, Error> {
+        Ok(vec![Directive {
+            params: self.params,
+            ..Directive::default()
+        }])
+    }
+}
+
+impl ser::SerializeTuple for TupleSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for TupleSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for TupleSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+/// Collects a directive per entry, keyed by its first parameter, or, for a
+/// single directive, the entries of its child block, keyed by their name.
+struct MapSerializer {
+    single: bool,
+    directives: Vec<Directive>,
+    block: Scfg,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(ParamSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("value serialized before key");
+        if self.single {
+            let group = value.serialize(GroupSerializer::GROUP)?;
+            if group.is_empty() {
+                return Err(ser::Error::custom(
+                    "`None` is only supported as the value of a field",
+                ));
+            }
+            self.block.directives.insert(key, group);
+            return Ok(());
+        }
+        let mut group = value.serialize(GroupSerializer::SINGLE)?;
+        group[0].params.insert(0, key);
+        self.directives.append(&mut group);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        if self.single || self.directives.is_empty() {
+            // no directive at all would be a missing field.
+            return Ok(vec![Directive {
+                child: Some(self.block),
+                ..Directive::default()
+            }]);
+        }
+        Ok(self.directives)
+    }
+}
+
+/// Collects the fields of a struct into a block.
+struct StructSerializer {
+    block: Scfg,
+    variant: Option<&'static str>,
+}
+
+impl StructSerializer {
+    fn field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        let group = value.serialize(GroupSerializer::GROUP)?;
+        if !group.is_empty() {
+            self.block
+                .directives
+                .entry(key.to_owned())
+                .or_default()
+                .extend(group);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Directive>, Error> {
+        Ok(vec![Directive {
+            params: self.variant.iter().map(|&v| v.to_owned()).collect(),
+            child: Some(self.block),
+            line: None,
+        }])
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Vec<Directive>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        self.finish()
+    }
+}
+
+/// Serializes an element of a tuple, or of a sequence written as a single
+/// directive, into the parameters it takes: one for a scalar, one per element
+/// for a tuple, and the variant name followed by its content for an enum.
+struct ElementSerializer<'a> {
+    params: &'a mut Vec<String>,
+}
+
+impl ElementSerializer<'_> {
+    fn unsupported() -> Error {
+        ser::Error::custom("expected a scalar, a tuple or an enum variant of those")
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(ElementSerializer {
+            params: self.params,
+        })
+    }
+}
+
+impl ser::Serializer for ElementSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    serialize_scalars! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.params.push(v.to_owned());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.params.push(variant.to_owned());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        // it would take the parameters of the elements following it.
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.params.push(variant.to_owned());
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Self::unsupported())
+    }
+}
+
+impl ser::SerializeTuple for ElementSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for ElementSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for ElementSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serializes a scalar into a single parameter.
+struct ParamSerializer;
+
+impl ParamSerializer {
+    fn unsupported() -> Error {
+        ser::Error::custom("expected a scalar")
+    }
+}
+
+impl ser::Serializer for ParamSerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    serialize_scalars! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_owned())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Self::unsupported())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::de::from_scfg;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Storage {
+        Memory,
+        Disk(String),
+        S3 { bucket: String },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Config {
+        port: u16,
+        ratio: f64,
+        debug: bool,
+        domain: Option<String>,
+        listen: Vec<String>,
+        empty: Vec<String>,
+        range: (u8, u8),
+        storage: Vec<Storage>,
+        models: BTreeMap<String, Model>,
+        none: BTreeMap<String, u8>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Model {
+        max_speed: u32,
+    }
+
+    #[test]
+    fn round_trip() {
+        let config = Config {
+            port: 6697,
+            ratio: 0.5,
+            debug: true,
+            domain: None,
+            listen: vec!["a".to_owned(), "b c".to_owned()],
+            empty: Vec::new(),
+            range: (1, 2),
+            storage: vec![
+                Storage::Memory,
+                Storage::Disk("/var/lib".to_owned()),
+                Storage::S3 {
+                    bucket: "b".to_owned(),
+                },
+            ],
+            models: [("E5", 320), ("E7", 275)]
+                .iter()
+                .map(|&(name, max_speed)| (name.to_owned(), Model { max_speed }))
+                .collect(),
+            none: BTreeMap::new(),
+        };
+        let doc = to_scfg(&config).unwrap();
+        let src = r#"port 6697
+ratio 0.5
+debug true
+listen a
+listen "b c"
+empty
+range 1 2
+storage Memory
+storage Disk /var/lib
+storage S3 {
+    bucket b
+}
+models E5 {
+    max_speed 320
+}
+models E7 {
+    max_speed 275
+}
+none {
+}
+"#;
+        assert_eq!(doc, src.parse().unwrap());
+        assert_eq!(from_scfg::<Config>(&doc).unwrap(), config);
+
+        assert!(to_scfg(&42).is_err());
+        assert!(to_scfg(&(1, vec![2])).is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Nested {
+        routes: Vec<(String, u16)>,
+        route: Vec<(String, u16)>,
+        tags: BTreeMap<String, Vec<String>>,
+        pairs: BTreeMap<String, Vec<(String, u8)>>,
+        matrix: Vec<Vec<u8>>,
+        row: Vec<Vec<u8>>,
+        storage: Vec<Storage>,
+        limits: BTreeMap<String, BTreeMap<String, u8>>,
+    }
+
+    #[test]
+    fn nested_round_trip() {
+        let strings = |values: &[&str]| values.iter().map(|&v| v.to_owned()).collect::<Vec<_>>();
+        let mut tags = BTreeMap::new();
+        tags.insert("k".to_owned(), strings(&["a", "b"]));
+        tags.insert("one".to_owned(), strings(&["c"]));
+        tags.insert("none".to_owned(), Vec::new());
+        let mut pairs = BTreeMap::new();
+        pairs.insert("p".to_owned(), vec![("a".to_owned(), 1)]);
+        pairs.insert(
+            "q".to_owned(),
+            vec![("b".to_owned(), 2), ("c".to_owned(), 3)],
+        );
+        let mut conns = BTreeMap::new();
+        conns.insert("conns".to_owned(), 10);
+        let mut limits = BTreeMap::new();
+        limits.insert("a".to_owned(), conns);
+        let value = Nested {
+            routes: vec![("a".to_owned(), 1), ("b".to_owned(), 2)],
+            route: vec![("a".to_owned(), 1)],
+            tags,
+            pairs,
+            matrix: vec![vec![1, 2], vec![3]],
+            row: vec![vec![1, 2]],
+            storage: vec![Storage::Disk("/var/lib".to_owned())],
+            limits,
+        };
+        let doc = to_scfg(&value).unwrap();
+        let src = r#"routes a 1
+routes b 2
+route a 1
+tags k a b
+tags none
+tags one c
+pairs p a 1
+pairs q b 2 c 3
+matrix 1 2
+matrix 3
+row 1 2
+storage Disk /var/lib
+limits a {
+    conns 10
+}
+"#;
+        assert_eq!(doc, src.parse().unwrap());
+        assert_eq!(from_scfg::<Nested>(&doc).unwrap(), value);
+    }
+
+    #[test]
+    fn ambiguous_layouts() {
+        #[derive(Serialize)]
+        struct Values<T> {
+            values: T,
+        }
+
+        fn to_scfg_values<T: Serialize>(values: T) -> Result<Scfg, Error> {
+            to_scfg(&Values { values })
+        }
+
+        // read back as an empty sequence
+        assert!(to_scfg_values(vec![()]).is_err());
+        assert!(to_scfg_values(vec![Vec::<u8>::new()]).is_err());
+        // read back as `Some`, or lost
+        assert!(to_scfg_values(vec![Some(1), None]).is_err());
+        let mut map = BTreeMap::new();
+        map.insert("k", None::<u8>);
+        assert!(to_scfg_values(map).is_err());
+        // the inner sequence would take the parameters of the following ones
+        let mut map = BTreeMap::new();
+        map.insert("k", vec![vec![1]]);
+        assert!(to_scfg_values(map).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! "Did you mean" suggestions for misspelled names and values.
+
+/// Returns the candidate closest to `value`, if it is close enough to be a
+/// plausible typo.
+///
+/// Closeness is measured with the optimal string alignment distance, which
+/// counts insertions, deletions, substitutions and transpositions of adjacent
+/// characters. A candidate is only suggested if its distance is at most a third
+/// of the length of `value` (and at least 1), and it is not `value` itself. Ties
+/// are broken in favor of the first candidate.
+pub(crate) fn did_you_mean<'a, I>(value: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let threshold = std::cmp::max(1, value.chars().count() / 3);
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != value)
+        .map(|candidate| (distance(value, candidate), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Computes the optimal string alignment distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows i - 2, i - 1 and i of the distance matrix
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("certifcate", "certificate"), 1);
+        assert_eq!(distance("ca", "ac"), 1);
+        assert_eq!(distance("Tōhoku", "Tohoku"), 1);
+    }
+
+    #[test]
+    fn suggestions() {
+        let names = ["certificate", "key", "listen"];
+        let suggest = |value| did_you_mean(value, names.iter().copied());
+        assert_eq!(suggest("certifcate"), Some("certificate"));
+        assert_eq!(suggest("lsiten"), Some("listen"));
+        assert_eq!(suggest("kye"), Some("key"));
+        assert_eq!(suggest("key"), None);
+        assert_eq!(suggest("domain"), None);
+        assert_eq!(suggest("x"), None);
+    }
+}
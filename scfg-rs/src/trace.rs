@@ -0,0 +1,717 @@
+//! Deriving schemas from types, by tracing their deserialization.
+//!
+//! The tracer is a deserializer which records the shape of the requested
+//! values, and answers with placeholder values. To tell whether a field is
+//! optional, the type is traced again without that field, and to tell whether
+//! a struct denies unknown fields, it is traced again with an extra field.
+use crate::{de::Error, DirectiveSchema, ParamRule, Schema};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Structs nested deeper than this are assumed to be recursive.
+const MAX_DEPTH: usize = 32;
+
+/// The name of the extra field added to detect denied unknown fields. It
+/// can't be a directive name, since documents can't contain NUL.
+const UNKNOWN_FIELD: &str = "\0unknown";
+
+/// Placeholders for strings, tried in order until the type accepts one.
+const STRINGS: &[&str] = &["", "0", "0.0.0.0", "0.0.0.0:0", "x"];
+
+/// Placeholders for integers, tried in order until the type accepts one.
+const INTEGERS: &[u8] = &[0, 1];
+
+impl Schema {
+    /// Derives a schema from a type deserialized with [`de::from_scfg`].
+    /// Available with the `serde` feature.
+    ///
+    /// The schema describes the fields of `T`, which must be a struct, and of
+    /// its nested structs. Fields are required unless they can be missing,
+    /// like `Option` fields and fields with `#[serde(default)]`, and may only
+    /// appear once, except for sequences and maps. Scalar fields take a
+    /// single parameter, constrained to the values of integer types, `bool`s
+    /// and unit enums. Structs denying unknown fields deny unknown
+    /// directives.
+    ///
+    /// Validating a document with the schema gives an error per problem,
+    /// while deserialization stops at the first one.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// #[derive(serde::Deserialize)]
+    /// #[serde(deny_unknown_fields)]
+    /// struct Config {
+    ///     port: u16,
+    ///     domain: Option<String>,
+    /// }
+    ///
+    /// let schema = Schema::for_type::<Config>().unwrap();
+    /// let errors = schema.validate(&"host a\nport 99999\n".parse().unwrap()).unwrap_err();
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].to_string(), "line 1: host: unknown directive");
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if `T` is not a struct, is recursive, or fails to
+    /// deserialize from placeholder values.
+    ///
+    /// [`de::from_scfg`]: crate::de::from_scfg
+    pub fn for_type<T>() -> Result<Schema, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let mut tracer = TypeTracer::<T>::new();
+        let mut root = match tracer.trace(&Probe::default())? {
+            Shape::Struct(root) => root,
+            _ => return Err(de::Error::custom("can only derive a schema for a struct")),
+        };
+        tracer.probe(&mut root, &mut Vec::new());
+        Ok(root.to_schema())
+    }
+}
+
+/// The shape of a value, as requested from the tracer.
+#[derive(Debug)]
+enum Shape {
+    /// Not requested yet, or requested with `deserialize_any`.
+    Unknown,
+    Bool,
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    /// A float, a character or a string.
+    Scalar,
+    Unit,
+    Enum(Vec<&'static str>),
+    Option(Box<Shape>),
+    Seq(Box<Shape>),
+    Tuple(Vec<Shape>),
+    /// A map, with the shape of its values.
+    Map(Box<Shape>),
+    Struct(Struct),
+}
+
+// not derived, since `#[default]` on a variant needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for Shape {
+    fn default() -> Self {
+        Shape::Unknown
+    }
+}
+
+#[derive(Debug, Default)]
+struct Struct {
+    fields: Vec<Field>,
+    deny_unknown: bool,
+}
+
+#[derive(Debug)]
+struct Field {
+    name: &'static str,
+    shape: Shape,
+    optional: bool,
+}
+
+/// A change to a tracing run.
+#[derive(Default)]
+struct Probe {
+    /// The path of a field to leave out.
+    omit: Option<Vec<&'static str>>,
+    /// The path of a struct to add an unknown field to.
+    unknown_at: Option<Vec<&'static str>>,
+}
+
+/// The state shared by a tracing run.
+struct Context<'a> {
+    probe: &'a Probe,
+    /// The index of the placeholder to use for the scalars at a path.
+    choices: &'a HashMap<Vec<&'static str>, usize>,
+    /// The path of the first scalar which rejected its placeholder, and the
+    /// number of placeholders for it.
+    rejected: RefCell<Option<(Vec<&'static str>, usize)>>,
+}
+
+impl Context<'_> {
+    fn choice(&self, path: &[&'static str]) -> usize {
+        self.choices.get(path).copied().unwrap_or(0)
+    }
+
+    fn reject(&self, path: &[&'static str], count: usize) {
+        let mut rejected = self.rejected.borrow_mut();
+        if rejected.is_none() {
+            *rejected = Some((path.to_vec(), count));
+        }
+    }
+}
+
+struct TypeTracer<T> {
+    choices: HashMap<Vec<&'static str>, usize>,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> TypeTracer<T> {
+    fn new() -> Self {
+        TypeTracer {
+            choices: HashMap::new(),
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Traces `T`, trying other placeholders for scalars which reject theirs.
+    fn trace(&mut self, probe: &Probe) -> Result<Shape, Error> {
+        loop {
+            let context = Context {
+                probe,
+                choices: &self.choices,
+                rejected: RefCell::new(None),
+            };
+            let mut shape = Shape::Unknown;
+            let tracer = Tracer {
+                shape: &mut shape,
+                path: &mut Vec::new(),
+                context: &context,
+            };
+            let err = match T::deserialize(tracer) {
+                Ok(_) => return Ok(shape),
+                Err(err) => err,
+            };
+            match context.rejected.into_inner() {
+                Some((path, count)) => {
+                    let choice = self.choices.entry(path).or_insert(0);
+                    *choice += 1;
+                    if *choice == count {
+                        return Err(err);
+                    }
+                }
+                None => return Err(err),
+            }
+        }
+    }
+
+    /// Finds the optional fields and the structs denying unknown fields in
+    /// `s`, found at `path`, and in its nested structs.
+    fn probe(&mut self, s: &mut Struct, path: &mut Vec<&'static str>) {
+        let probe = Probe {
+            unknown_at: Some(path.clone()),
+            ..Default::default()
+        };
+        s.deny_unknown = self.trace(&probe).is_err();
+        for field in &mut s.fields {
+            path.push(field.name);
+            let probe = Probe {
+                omit: Some(path.clone()),
+                ..Default::default()
+            };
+            field.optional = self.trace(&probe).is_ok();
+            field
+                .shape
+                .for_each_struct(&mut |nested| self.probe(nested, path));
+            path.pop();
+        }
+    }
+}
+
+impl Shape {
+    /// Calls `f` on the structs directly nested in this shape, not counting
+    /// the fields of structs.
+    fn for_each_struct(&mut self, f: &mut dyn FnMut(&mut Struct)) {
+        match self {
+            Shape::Struct(s) => f(s),
+            Shape::Option(inner) | Shape::Seq(inner) | Shape::Map(inner) => {
+                inner.for_each_struct(f)
+            }
+            Shape::Tuple(shapes) => {
+                for shape in shapes {
+                    shape.for_each_struct(f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the rule for a parameter of this shape, if it is a scalar.
+    fn param_rule(&self) -> Option<ParamRule> {
+        match self {
+            Shape::Bool => Some(ParamRule::Enum(vec!["true".to_owned(), "false".to_owned()])),
+            Shape::Integer { min, max } => Some(ParamRule::Integer {
+                min: *min,
+                max: *max,
+            }),
+            Shape::Scalar => Some(ParamRule::Any),
+            Shape::Enum(variants) => Some(ParamRule::Enum(
+                variants.iter().map(|&v| v.to_owned()).collect(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Struct {
+    fn to_schema(&self) -> Schema {
+        let mut schema = Schema::new();
+        for field in &self.fields {
+            schema = schema.directive(field.name, |d| field.rules(d));
+        }
+        if self.deny_unknown {
+            schema = schema.deny_unknown();
+        }
+        schema
+    }
+
+    fn child_rules(&self, d: DirectiveSchema) -> DirectiveSchema {
+        if self.fields.iter().all(|field| field.optional) {
+            d.optional_child(self.to_schema())
+        } else {
+            d.child(self.to_schema())
+        }
+    }
+}
+
+impl Field {
+    fn rules(&self, d: DirectiveSchema) -> DirectiveSchema {
+        let shape = match self.shape {
+            Shape::Option(ref inner) => &**inner,
+            ref shape => shape,
+        };
+        let optional = self.optional || matches!(self.shape, Shape::Option(_));
+        let d = if optional { d } else { d.min(1) };
+        match shape {
+            Shape::Seq(inner) => match **inner {
+                Shape::Struct(ref s) => s.child_rules(d.params(0)),
+                ref inner if inner.param_rule().is_some() => d.no_child(),
+                _ => d,
+            },
+            Shape::Map(value) => match **value {
+                Shape::Struct(ref s) => s.child_rules(d.params(1)),
+                ref value if value.param_rule().is_some() => {
+                    with_param(d.params(2), 1, value).no_child()
+                }
+                _ => d.min_params(1),
+            },
+            shape => single_rules(shape, d.max(1)),
+        }
+    }
+}
+
+/// Returns the rules for a directive which may only appear once.
+fn single_rules(shape: &Shape, d: DirectiveSchema) -> DirectiveSchema {
+    match shape {
+        Shape::Bool => with_param(d.max_params(1), 0, shape).no_child(),
+        Shape::Enum(_) => with_param(d.min_params(1), 0, shape),
+        Shape::Integer { .. } | Shape::Scalar => with_param(d.params(1), 0, shape).no_child(),
+        Shape::Unit => d.params(0).no_child(),
+        Shape::Tuple(shapes) => shapes
+            .iter()
+            .enumerate()
+            .fold(d.params(shapes.len()).no_child(), |d, (index, shape)| {
+                with_param(d, index, shape)
+            }),
+        Shape::Struct(s) => s.child_rules(d.params(0)),
+        _ => d,
+    }
+}
+
+/// Constrains the parameter at `index` to values of `shape`, if it is a
+/// scalar with restricted values.
+fn with_param(d: DirectiveSchema, index: usize, shape: &Shape) -> DirectiveSchema {
+    match shape.param_rule() {
+        Some(ParamRule::Any) | None => d,
+        Some(rule) => d.param(index, rule),
+    }
+}
+
+/// A deserializer recording the shape of the requested value in `shape`.
+struct Tracer<'a> {
+    shape: &'a mut Shape,
+    /// The names of the fields leading to the value.
+    path: &'a mut Vec<&'static str>,
+    context: &'a Context<'a>,
+}
+
+impl<'a> Tracer<'a> {
+    fn nested<'b>(&'b mut self, shape: &'b mut Shape) -> Tracer<'b>
+    where
+        'a: 'b,
+    {
+        Tracer {
+            shape,
+            path: &mut *self.path,
+            context: self.context,
+        }
+    }
+}
+
+macro_rules! trace_integer {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                use std::convert::TryFrom;
+
+                *self.shape = Shape::Integer {
+                    min: i64::try_from(<$ty>::MIN).ok(),
+                    max: i64::try_from(<$ty>::MAX).ok(),
+                };
+                let choice = self.context.choice(self.path);
+                let value = INTEGERS[choice.min(INTEGERS.len() - 1)];
+                let result = visitor.$visit(value as $ty);
+                if result.is_err() {
+                    self.context.reject(self.path, INTEGERS.len());
+                }
+                result
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Tracer<'a> {
+    type Error = Error;
+
+    trace_integer! {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Unknown;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Bool;
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Scalar;
+        visitor.visit_f32(0.0)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Scalar;
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Scalar;
+        visitor.visit_char('x')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Scalar;
+        let choice = self.context.choice(self.path);
+        let result = visitor.visit_str(STRINGS[choice.min(STRINGS.len() - 1)]);
+        if result.is_err() {
+            self.context.reject(self.path, STRINGS.len());
+        }
+        result
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Scalar;
+        visitor.visit_bytes(b"")
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let mut inner = Shape::Unknown;
+        let value = visitor.visit_some(self.nested(&mut inner))?;
+        *self.shape = Shape::Option(Box::new(inner));
+        Ok(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.shape = Shape::Unit;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let mut shapes = vec![Shape::Unknown];
+        let value = visitor.visit_seq(TraceSeq {
+            shapes: shapes.iter_mut(),
+            tracer: self.nested(&mut Shape::Unknown),
+        })?;
+        *self.shape = Shape::Seq(Box::new(shapes.pop().unwrap()));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let mut shapes: Vec<_> = (0..len).map(|_| Shape::Unknown).collect();
+        let value = visitor.visit_seq(TraceSeq {
+            shapes: shapes.iter_mut(),
+            tracer: self.nested(&mut Shape::Unknown),
+        })?;
+        *self.shape = Shape::Tuple(shapes);
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let mut value_shape = Shape::Unknown;
+        let value = visitor.visit_map(TraceMap {
+            remaining: 1,
+            value_shape: &mut value_shape,
+            tracer: self.nested(&mut Shape::Unknown),
+        })?;
+        *self.shape = Shape::Map(Box::new(value_shape));
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.path.len() > MAX_DEPTH {
+            return Err(de::Error::custom(
+                "cannot derive a schema for a recursive type",
+            ));
+        }
+        let probe = self.context.probe;
+        let omit = probe
+            .omit
+            .as_ref()
+            .filter(|omit| matches!(omit.split_last(), Some((_, init)) if init == &self.path[..]))
+            .map(|omit| *omit.last().unwrap());
+        let mut s = Struct {
+            fields: fields
+                .iter()
+                .filter(|&&name| Some(name) != omit)
+                .map(|&name| Field {
+                    name,
+                    shape: Shape::Unknown,
+                    optional: false,
+                })
+                .collect(),
+            deny_unknown: false,
+        };
+        let unknown = probe.unknown_at.as_deref() == Some(&self.path[..]);
+        let value = visitor.visit_map(TraceStruct {
+            fields: s.fields.iter_mut(),
+            field: None,
+            unknown,
+            tracer: self.nested(&mut Shape::Unknown),
+        })?;
+        *self.shape = Shape::Struct(s);
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.shape = Shape::Enum(variants.to_vec());
+        visitor.visit_enum(TraceEnum {
+            variant: variants.first().copied().unwrap_or_default(),
+            path: self.path,
+            context: self.context,
+        })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// Yields an element per shape.
+struct TraceSeq<'a, 'b> {
+    shapes: std::slice::IterMut<'b, Shape>,
+    tracer: Tracer<'a>,
+}
+
+impl<'de> de::SeqAccess<'de> for TraceSeq<'_, '_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.shapes.next() {
+            Some(shape) => seed.deserialize(self.tracer.nested(shape)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Yields `remaining` entries.
+struct TraceMap<'a, 'b> {
+    remaining: usize,
+    value_shape: &'b mut Shape,
+    tracer: Tracer<'a>,
+}
+
+impl<'de> de::MapAccess<'de> for TraceMap<'_, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(self.tracer.nested(&mut Shape::Unknown))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(self.tracer.nested(self.value_shape))
+    }
+}
+
+/// Yields an entry per field, and an unknown field if `unknown` is set.
+struct TraceStruct<'a, 'b> {
+    fields: std::slice::IterMut<'b, Field>,
+    field: Option<&'b mut Field>,
+    unknown: bool,
+    tracer: Tracer<'a>,
+}
+
+impl<'de> de::MapAccess<'de> for TraceStruct<'_, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let name = match self.fields.next() {
+            Some(field) => {
+                let name = field.name;
+                self.field = Some(field);
+                name
+            }
+            None if self.unknown => {
+                self.unknown = false;
+                UNKNOWN_FIELD
+            }
+            None => return Ok(None),
+        };
+        seed.deserialize(name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.field.take() {
+            Some(field) => {
+                self.tracer.path.push(field.name);
+                let value = seed.deserialize(self.tracer.nested(&mut field.shape));
+                self.tracer.path.pop();
+                value
+            }
+            None => seed.deserialize(self.tracer.nested(&mut Shape::Unknown)),
+        }
+    }
+}
+
+/// Picks `variant`, tracing its content.
+struct TraceEnum<'a> {
+    variant: &'static str,
+    path: &'a mut Vec<&'static str>,
+    context: &'a Context<'a>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for TraceEnum<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for TraceEnum<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(Tracer {
+            shape: &mut Shape::Unknown,
+            path: self.path,
+            context: self.context,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(
+            Tracer {
+                shape: &mut Shape::Unknown,
+                path: self.path,
+                context: self.context,
+            },
+            len,
+            visitor,
+        )
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_struct(
+            Tracer {
+                shape: &mut Shape::Unknown,
+                path: self.path,
+                context: self.context,
+            },
+            "",
+            fields,
+            visitor,
+        )
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{Directive, Scfg};
+
+/// A visitor over the directives of a document, see [`Scfg::accept`].
+///
+/// All methods do nothing by default, so implementors only need to override
+/// the callbacks they are interested in.
+pub trait Visitor {
+    /// Called for every directive, before its child block (if any) is visited.
+    fn visit_directive(&mut self, _name: &str, _directive: &Directive) {}
+
+    /// Called before visiting the directives of the child block of the
+    /// directive `name`.
+    fn enter_block(&mut self, _name: &str) {}
+
+    /// Called after visiting the directives of the child block of the
+    /// directive `name`.
+    fn leave_block(&mut self, _name: &str) {}
+}
+
+/// A visitor which may modify the directives of a document, see
+/// [`Scfg::accept_mut`].
+///
+/// All methods do nothing by default, so implementors only need to override
+/// the callbacks they are interested in.
+pub trait VisitorMut {
+    /// Called for every directive, before its child block (if any) is visited.
+    fn visit_directive_mut(&mut self, _name: &str, _directive: &mut Directive) {}
+
+    /// Called before visiting the directives of the child block of the
+    /// directive `name`.
+    fn enter_block(&mut self, _name: &str) {}
+
+    /// Called after visiting the directives of the child block of the
+    /// directive `name`.
+    fn leave_block(&mut self, _name: &str) {}
+}
+
+impl Scfg {
+    /// Walks the document depth first, calling back into `visitor`.
+    ///
+    /// For each directive, [`visit_directive`] is called first. Then, if the
+    /// directive has a child block, [`enter_block`] is called, the child's
+    /// directives are visited, and finally [`leave_block`] is called. Every
+    /// `enter_block` is therefore matched by a `leave_block` with the same
+    /// name, and directives are visited in the order that [`write`] would
+    /// write them.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// struct Paths(Vec<String>, Vec<String>);
+    ///
+    /// impl Visitor for Paths {
+    ///     fn visit_directive(&mut self, name: &str, _: &Directive) {
+    ///         let mut path = self.0.clone();
+    ///         path.push(name.to_owned());
+    ///         self.1.push(path.join("."));
+    ///     }
+    ///
+    ///     fn enter_block(&mut self, name: &str) {
+    ///         self.0.push(name.to_owned());
+    ///     }
+    ///
+    ///     fn leave_block(&mut self, _: &str) {
+    ///         self.0.pop();
+    ///     }
+    /// }
+    ///
+    /// let doc: Scfg = "train {\n\tmodel E5\n}\n".parse().unwrap();
+    /// let mut paths = Paths(vec![], vec![]);
+    /// doc.accept(&mut paths);
+    /// assert_eq!(paths.1, ["train", "train.model"]);
+    /// ```
+    ///
+    /// [`visit_directive`]: Visitor::visit_directive
+    /// [`enter_block`]: Visitor::enter_block
+    /// [`leave_block`]: Visitor::leave_block
+    /// [`write`]: Scfg::write
+    pub fn accept(&self, visitor: &mut impl Visitor) {
+        for (name, directives) in &self.directives {
+            for directive in directives {
+                visitor.visit_directive(name, directive);
+                if let Some(ref child) = directive.child {
+                    visitor.enter_block(name);
+                    child.accept(visitor);
+                    visitor.leave_block(name);
+                }
+            }
+        }
+    }
+
+    /// Walks the document depth first, calling back into `visitor`, which may
+    /// modify each directive.
+    ///
+    /// The call order is the same as for [`accept`]. Since
+    /// [`visit_directive_mut`] is called before the directive's child block is
+    /// visited, any changes it makes to the child (including adding or taking
+    /// it) are seen by the rest of the traversal.
+    ///
+    /// [`accept`]: Scfg::accept
+    /// [`visit_directive_mut`]: VisitorMut::visit_directive_mut
+    pub fn accept_mut(&mut self, visitor: &mut impl VisitorMut) {
+        for (name, directives) in &mut self.directives {
+            for directive in directives {
+                visitor.visit_directive_mut(name, directive);
+                if let Some(ref mut child) = directive.child {
+                    visitor.enter_block(name);
+                    child.accept_mut(visitor);
+                    visitor.leave_block(name);
+                }
+            }
+        }
+    }
+
+    /// Returns a copy of the document in which `f` was applied to every
+    /// directive, at every level of nesting. The document itself is left
+    /// unchanged.
+    ///
+    /// `f` is called with the name of each directive of the copy, in the same
+    /// order as [`VisitorMut::visit_directive_mut`] is called by
+    /// [`accept_mut`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "listen 80 {\n\ttls off\n}\n".parse().unwrap();
+    /// let prod = doc.deep_clone_and_apply(&mut |name, directive| {
+    ///     if name == "tls" {
+    ///         directive.clear_params();
+    ///         directive.append_param("on");
+    ///     }
+    /// });
+    /// assert_eq!(prod, "listen 80 {\n\ttls on\n}\n".parse().unwrap());
+    /// assert_eq!(doc.get_path(&["listen", "tls"]).unwrap().params(), ["off"]);
+    /// ```
+    ///
+    /// [`accept_mut`]: Scfg::accept_mut
+    pub fn deep_clone_and_apply<F>(&self, f: &mut F) -> Scfg
+    where
+        F: FnMut(&str, &mut Directive),
+    {
+        struct Apply<'a, F>(&'a mut F);
+
+        impl<F> VisitorMut for Apply<'_, F>
+        where
+            F: FnMut(&str, &mut Directive),
+        {
+            fn visit_directive_mut(&mut self, name: &str, directive: &mut Directive) {
+                (self.0)(name, directive);
+            }
+        }
+
+        let mut copy = self.clone();
+        copy.accept_mut(&mut Apply(f));
+        copy
+    }
+
+    /// Accumulates a value over every directive of the document, depth first.
+    ///
+    /// `f` is called with the accumulator, the name of the directive, the
+    /// directive and its depth (0 for top level directives), in the same order
+    /// as [`Visitor::visit_directive`] is called by [`accept`].
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let doc: Scfg = "train {\n\tmodel E5\n}\n".parse().unwrap();
+    /// let names = doc.fold(String::new(), |mut acc, name, _, depth| {
+    ///     acc.push_str(&format!("{}{} ", depth, name));
+    ///     acc
+    /// });
+    /// assert_eq!(names, "0train 1model ");
+    /// ```
+    ///
+    /// [`accept`]: Scfg::accept
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &str, &Directive, usize) -> B,
+    {
+        self.fold_at(init, &mut f, 0)
+    }
+
+    fn fold_at<B, F>(&self, mut acc: B, f: &mut F, depth: usize) -> B
+    where
+        F: FnMut(B, &str, &Directive, usize) -> B,
+    {
+        for (name, directives) in &self.directives {
+            for directive in directives {
+                acc = f(acc, name, directive, depth);
+                if let Some(ref child) = directive.child {
+                    acc = child.fold_at(acc, f, depth + 1);
+                }
+            }
+        }
+        acc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Visitor for Recorder {
+        fn visit_directive(&mut self, name: &str, directive: &Directive) {
+            self.0
+                .push(format!("visit {} {}", name, directive.params().join(" ")));
+        }
+
+        fn enter_block(&mut self, name: &str) {
+            self.0.push(format!("enter {}", name));
+        }
+
+        fn leave_block(&mut self, name: &str) {
+            self.0.push(format!("leave {}", name));
+        }
+    }
+
+    #[test]
+    fn call_order() {
+        let src = r#"a {
+    b 1 {
+        c
+    }
+    b 2
+}
+d
+"#;
+        let doc = src.parse::<Scfg>().unwrap();
+        let mut recorder = Recorder::default();
+        doc.accept(&mut recorder);
+        assert_eq!(
+            recorder.0,
+            [
+                "visit a ",
+                "enter a",
+                "visit b 1",
+                "enter b",
+                "visit c ",
+                "leave b",
+                "visit b 2",
+                "leave a",
+                "visit d ",
+            ]
+        );
+    }
+
+    struct Depth(usize);
+
+    impl VisitorMut for Depth {
+        fn visit_directive_mut(&mut self, _name: &str, directive: &mut Directive) {
+            directive.clear_params();
+            directive.append_param(self.0.to_string());
+        }
+
+        fn enter_block(&mut self, _name: &str) {
+            self.0 += 1;
+        }
+
+        fn leave_block(&mut self, _name: &str) {
+            self.0 -= 1;
+        }
+    }
+
+    #[test]
+    fn rewrite() {
+        let mut doc = "a x {\n\tb y {\n\t\tc z\n\t}\n}\nd\n"
+            .parse::<Scfg>()
+            .unwrap();
+        doc.accept_mut(&mut Depth(0));
+        let exp = "a 0 {\n\tb 1 {\n\t\tc 2\n\t}\n}\nd 0\n"
+            .parse::<Scfg>()
+            .unwrap();
+        assert_eq!(doc, exp);
+    }
+
+    #[test]
+    fn deep_clone_and_apply() {
+        let src = "a x {\n\tb y {\n\t\tc z\n\t}\n}\nd\n";
+        let doc = src.parse::<Scfg>().unwrap();
+        let mut names = Vec::new();
+        let copy = doc.deep_clone_and_apply(&mut |name, directive| {
+            names.push(name.to_owned());
+            let params: Vec<_> = directive
+                .params()
+                .iter()
+                .map(|p| p.to_uppercase())
+                .collect();
+            directive.clear_params();
+            for param in params {
+                directive.append_param(param);
+            }
+            directive.append_param("!");
+        });
+        assert_eq!(names, ["a", "b", "c", "d"]);
+        assert_eq!(doc, src.parse().unwrap());
+        let exp = "a X ! {\n\tb Y ! {\n\t\tc Z !\n\t}\n}\nd !\n";
+        assert_eq!(copy, exp.parse().unwrap());
+    }
+
+    #[test]
+    fn fold() {
+        let src = r#"a {
+    b 1 {
+        c
+    }
+    b 2
+}
+d
+"#;
+        let doc = src.parse::<Scfg>().unwrap();
+        let mut recorder = Recorder::default();
+        doc.accept(&mut recorder);
+        let visits: Vec<_> = recorder
+            .0
+            .into_iter()
+            .filter(|event| event.starts_with("visit"))
+            .collect();
+        let folded = doc.fold(Vec::new(), |mut acc, name, directive, depth| {
+            acc.push((
+                format!("visit {} {}", name, directive.params().join(" ")),
+                depth,
+            ));
+            acc
+        });
+        let (names, depths): (Vec<_>, Vec<_>) = folded.into_iter().unzip();
+        assert_eq!(names, visits);
+        assert_eq!(depths, [0, 1, 2, 1, 0]);
+        assert_eq!(Scfg::new().fold(42, |_, _, _, _| 0), 42);
+    }
+}
@@ -0,0 +1,410 @@
+//! Quoting of single words, as done by the writer.
+use crate::{Scfg, WriteOptions};
+use std::{borrow::Cow, fmt, io};
+
+/// The error returned by [`quote_word`] for a word which cannot be written,
+/// and by the checked builder methods such as [`Scfg::try_add`].
+///
+/// [`Scfg::try_add`]: crate::Scfg::try_add
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidWord {
+    reason: Reason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    Character { character: char, position: usize },
+    EmptyName,
+}
+
+impl InvalidWord {
+    /// Returns the first character of the word which cannot be written, or
+    /// `None` if the word is an empty directive name.
+    pub fn character(&self) -> Option<char> {
+        match self.reason {
+            Reason::Character { character, .. } => Some(character),
+            Reason::EmptyName => None,
+        }
+    }
+
+    /// Returns the byte offset of [`character`] in the word.
+    ///
+    /// [`character`]: InvalidWord::character
+    pub fn position(&self) -> Option<usize> {
+        match self.reason {
+            Reason::Character { position, .. } => Some(position),
+            Reason::EmptyName => None,
+        }
+    }
+}
+
+impl fmt::Display for InvalidWord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            Reason::Character {
+                character,
+                position,
+            } => write!(
+                f,
+                "a word cannot contain {:?} (found at byte {})",
+                character, position
+            ),
+            Reason::EmptyName => write!(f, "a directive name cannot be empty"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidWord {}
+
+impl From<InvalidWord> for io::Error {
+    fn from(err: InvalidWord) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// A directive name or parameter which cannot be written, as found by
+/// [`Scfg::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEntry {
+    /// The names of the directives leading to the offending directive,
+    /// including its own name.
+    pub path: Vec<String>,
+    /// The line of the offending directive, if known.
+    pub line: Option<usize>,
+    /// The index of the offending parameter, or `None` if the directive's
+    /// name is invalid.
+    pub param: Option<usize>,
+    /// Why the name or parameter cannot be written.
+    pub error: InvalidWord,
+}
+
+impl fmt::Display for InvalidEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "{:?}: ", self.path.join("."))?;
+        if let Some(index) = self.param {
+            write!(f, "parameter {}: ", index)?;
+        }
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for InvalidEntry {}
+
+impl Scfg {
+    /// Checks that every directive name and parameter of this document, at
+    /// any depth, can be written, so that [`write`] produces text which
+    /// parses back to an equal document.
+    ///
+    /// Names and parameters must be valid words, see [`is_valid_word`]. Unlike
+    /// [`try_add`], this accepts empty names, which are written quoted, like
+    /// the name of an anonymous block read by the parser.
+    ///
+    /// ```
+    /// # use scfg::*;
+    /// let mut doc = Scfg::new();
+    /// doc.add("motd").append_param("hello\nworld");
+    /// let errors = doc.validate().unwrap_err();
+    /// assert_eq!(errors[0].path, ["motd"]);
+    /// assert_eq!(errors[0].param, Some(0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns every invalid name and parameter, depth first, in the order
+    /// [`write`] would write them.
+    ///
+    /// [`write`]: Scfg::write
+    /// [`try_add`]: Scfg::try_add
+    pub fn validate(&self) -> Result<(), Vec<InvalidEntry>> {
+        let mut errors = Vec::new();
+        self.validate_words(&mut Vec::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_words(&self, path: &mut Vec<String>, errors: &mut Vec<InvalidEntry>) {
+        for (name, directives) in &self.directives {
+            path.push(name.clone());
+            for directive in directives {
+                let mut report = |param, error| {
+                    errors.push(InvalidEntry {
+                        path: path.clone(),
+                        line: directive.line,
+                        param,
+                        error,
+                    })
+                };
+                if let Err(error) = check(name) {
+                    report(None, error);
+                }
+                for (index, param) in directive.params.iter().enumerate() {
+                    if let Err(error) = check(param) {
+                        report(Some(index), error);
+                    }
+                }
+                if let Some(ref child) = directive.child {
+                    child.validate_words(path, errors);
+                }
+            }
+            path.pop();
+        }
+    }
+}
+
+/// Returns whether `word` can be written as a directive name or parameter.
+///
+/// Any word can be quoted, except words containing a newline, which would end
+/// the directive's line, or a NUL character, which the parser rejects.
+///
+/// ```
+/// assert!(scfg::is_valid_word("Tōhoku line"));
+/// assert!(!scfg::is_valid_word("a\nb"));
+/// ```
+pub fn is_valid_word(word: &str) -> bool {
+    !word.contains(is_invalid)
+}
+
+/// Quotes `word` the way [`Scfg::write`] does, so that parsing it gives back
+/// `word`.
+///
+/// Words are only quoted if needed, for instance if they are empty or contain
+/// whitespace, quotes, braces or `#`, in which case they are enclosed in single
+/// quotes. Otherwise `word` is returned as is.
+///
+/// ```
+/// # use std::borrow::Cow;
+/// assert_eq!(scfg::quote_word("320km/h"), Ok(Cow::Borrowed("320km/h")));
+/// assert_eq!(scfg::quote_word("a b").unwrap(), "'a b'");
+/// assert!(scfg::quote_word("a\nb").is_err());
+/// ```
+///
+/// # Errors
+/// Returns an error if `word` is not valid, see [`is_valid_word`].
+///
+/// [`Scfg::write`]: crate::Scfg::write
+pub fn quote_word(word: &str) -> Result<Cow<'_, str>, InvalidWord> {
+    check(word)?;
+    Ok(quote(word))
+}
+
+/// Checks that `word` is valid, see [`is_valid_word`].
+pub(crate) fn check(word: &str) -> Result<(), InvalidWord> {
+    match word.char_indices().find(|&(_, c)| is_invalid(c)) {
+        Some((position, character)) => Err(InvalidWord {
+            reason: Reason::Character {
+                character,
+                position,
+            },
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `name` is valid and not empty.
+pub(crate) fn check_name(name: &str) -> Result<(), InvalidWord> {
+    if name.is_empty() {
+        return Err(InvalidWord {
+            reason: Reason::EmptyName,
+        });
+    }
+    check(name)
+}
+
+fn is_invalid(c: char) -> bool {
+    c == '\n' || c == '\0'
+}
+
+/// Quotes `word` without checking that it is valid.
+pub(crate) fn quote(word: &str) -> Cow<'_, str> {
+    match shell_words::quote(word) {
+        // braces are not special to shells, but `{` opens a block at the end
+        // of a line, and `}` closes one on its own line.
+        Cow::Borrowed(word) if word.contains(['{', '}']) => format!("'{}'", word).into(),
+        quoted => quoted,
+    }
+}
+
+/// Quotes `word` the way [`Scfg::write_with_options`] does with `options`.
+pub(crate) fn quote_with<'a>(
+    word: &'a str,
+    options: &WriteOptions,
+) -> Result<Cow<'a, str>, InvalidWord> {
+    let printable = match options.unicode_escapes {
+        Some(ref printable) => printable,
+        None => return quote_word(word),
+    };
+    let escaped = |c: char| !printable.contains(&c) || is_invalid(c);
+    if !word.contains(escaped) {
+        return Ok(quote(word));
+    }
+    let mut out = String::with_capacity(word.len() + 2);
+    out.push('"');
+    for c in word.chars() {
+        if escaped(c) {
+            out.push_str(&format!("\\u{{{:x}}}", c as u32));
+        } else {
+            if c == '"' || c == '\\' {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+    }
+    out.push('"');
+    Ok(out.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        let cases = [
+            ("plain", "plain"),
+            ("320km/h", "320km/h"),
+            ("", "''"),
+            ("{", "'{'"),
+            ("}", "'}'"),
+            ("{}", "'{}'"),
+            ("#comment", "'#comment'"),
+            ("a#b", "'a#b'"),
+            ("a b", "'a b'"),
+            ("a\tb", "'a\tb'"),
+            ("say \"hi\"", "'say \"hi\"'"),
+            ("it's", "'it'\\''s'"),
+            ("back\\slash", "'back\\slash'"),
+            ("$HOME", "'$HOME'"),
+            ("Tōhoku", "Tōhoku"),
+            ("a}", "'a}'"),
+            ("{a", "'{a'"),
+        ];
+        for (word, quoted) in cases.iter() {
+            assert!(is_valid_word(word), "{:?}", word);
+            assert_eq!(quote_word(word).unwrap(), *quoted, "{:?}", word);
+            let doc: Scfg = format!("{} {}\n", quoted, quoted).parse().unwrap();
+            assert_eq!(doc.get(*word).unwrap().params(), [*word], "{:?}", word);
+            let doc: Scfg = format!("{}\n", quoted).parse().unwrap();
+            assert!(doc.contains(*word), "{:?}", word);
+        }
+        assert!(matches!(quote_word("plain"), Ok(Cow::Borrowed("plain"))));
+    }
+
+    #[test]
+    fn invalid() {
+        let cases = [
+            ("a\nb", '\n', 1),
+            ("\n", '\n', 0),
+            ("nul\0", '\0', 3),
+            ("ō\0", '\0', 2),
+        ];
+        for (word, character, position) in cases.iter() {
+            assert!(!is_valid_word(word));
+            let err = quote_word(word).unwrap_err();
+            assert_eq!(err.character(), Some(*character));
+            assert_eq!(err.position(), Some(*position));
+        }
+        assert_eq!(
+            quote_word("a\nb").unwrap_err().to_string(),
+            "a word cannot contain '\\n' (found at byte 1)"
+        );
+        let err = check_name("").unwrap_err();
+        assert_eq!(err.character(), None);
+        assert_eq!(err.to_string(), "a directive name cannot be empty");
+    }
+
+    #[test]
+    fn validate() {
+        let mut doc = Scfg::new();
+        doc.add_block("a", ["ok"], |a| {
+            a.add("b\nc");
+            a.add_block("d", ["ok", "nul\0"], |d| {
+                d.add("").append_param("x\ny");
+            });
+        });
+        doc.add("e").append_param("fine").append_param("\n");
+        doc.add("f");
+        let errors = doc.validate().unwrap_err();
+        let found: Vec<_> = errors
+            .iter()
+            .map(|e| (e.path.join("/"), e.param, e.error.character()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("a/b\nc".to_owned(), None, Some('\n')),
+                ("a/d".to_owned(), Some(1), Some('\0')),
+                ("a/d/".to_owned(), Some(0), Some('\n')),
+                ("e".to_owned(), Some(1), Some('\n')),
+            ]
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "\"a.d\": parameter 1: a word cannot contain '\\0' (found at byte 3)"
+        );
+
+        let doc: Scfg = "a {\n\tb c\n}\n".parse().unwrap();
+        assert_eq!(doc.validate(), Ok(()));
+        let mut empty = Scfg::new();
+        empty.add("").append_param("");
+        assert_eq!(empty.validate(), Ok(()));
+        let mut out = Vec::new();
+        empty.write(&mut out).unwrap();
+        assert_eq!(out, b"'' ''\n");
+        assert_eq!("'' ''\n".parse::<Scfg>().unwrap(), empty);
+        let mut doc = doc;
+        doc.get_all_mut("a").unwrap()[0]
+            .get_or_create_child()
+            .add("b")
+            .append_param("\0");
+        let errors = doc.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, None);
+        assert_eq!(
+            errors[0].to_string(),
+            "\"a.b\": parameter 0: a word cannot contain '\\0' (found at byte 0)"
+        );
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let options = WriteOptions {
+            unicode_escapes: Some(' '..='~'),
+            ..Default::default()
+        };
+        let cases = [
+            ("plain", "plain"),
+            ("a b", "'a b'"),
+            ("\t", "\"\\u{9}\""),
+            ("\u{1F685}", "\"\\u{1f685}\""),
+            ("a\"b\\\u{e9}", "\"a\\\"b\\\\\\u{e9}\""),
+            ("line1\nline2", "\"line1\\u{a}line2\""),
+        ];
+        for (word, quoted) in cases {
+            assert_eq!(quote_with(word, &options).unwrap(), quoted);
+        }
+        assert_eq!(
+            quote_with("\u{e9}", &WriteOptions::default()).unwrap(),
+            "\u{e9}"
+        );
+        assert!(quote_with("\n", &WriteOptions::default()).is_err());
+
+        let parse_options = crate::ParseOptions {
+            unicode_escapes: true,
+            ..Default::default()
+        };
+        let mut doc = Scfg::new();
+        doc.add("sep\u{1F685}")
+            .append_param("\t")
+            .append_param("")
+            .append_param("\"\\u0041\"\n\u{FEFF}");
+        let mut out = Vec::new();
+        doc.write_with_options(&mut out, &options).unwrap();
+        let parsed = Scfg::parse_with_options(out.as_slice(), &parse_options).unwrap();
+        assert_eq!(parsed, doc);
+    }
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "codegen")]
+//! Compiles the code generated from `data/codegen.scfg`, which is kept up to
+//! date by the `codegen::test::golden` unit test, and loads documents with it.
+use scfg::Scfg;
+
+mod config {
+    include!("data/codegen.rs");
+}
+
+use config::*;
+
+static SRC: &str = r#"domain example.com
+limits 16 1024
+listen 0.0.0.0:6697 {
+    tls {
+        certificate cert.pem
+        key key.pem
+    }
+}
+listen 127.0.0.1:6667 {
+}
+upstream backend.example.com 8080 {
+    maxFails 3
+    weight 0.5
+}
+workers 4
+unknown directives are ignored
+"#;
+
+#[test]
+fn load_document() {
+    let doc: Scfg = SRC.parse().unwrap();
+    let config = load(&doc).unwrap();
+    assert_eq!(config.domain, "example.com");
+    assert_eq!(config.limits, (16, 1024));
+    assert_eq!(config.log_level, None);
+    assert_eq!(config.timeout, None);
+    assert!(!config.verbose);
+    assert_eq!(config.workers, 4);
+
+    assert_eq!(config.listen.len(), 2);
+    assert_eq!(config.listen[0].params, "0.0.0.0:6697");
+    let tls = config.listen[0].tls.as_ref().unwrap();
+    assert_eq!(tls.certificate, "cert.pem");
+    assert_eq!(tls.key, "key.pem");
+    assert_eq!(config.listen[1].params, "127.0.0.1:6667");
+    assert_eq!(config.listen[1].tls, None);
+
+    assert_eq!(
+        config.upstream,
+        [ConfigUpstream {
+            params: ("backend.example.com".to_owned(), 8080),
+            backup: false,
+            max_fails: 3,
+            weight: Some(0.5),
+        }]
+    );
+}
+
+#[test]
+fn load_optional() {
+    let src = "domain example.com\nlimits 1 2\nworkers 1\nlog-level debug\ntimeout 30 s\nverbose\n";
+    let config = load(&src.parse().unwrap()).unwrap();
+    assert_eq!(config.log_level.as_deref(), Some("debug"));
+    assert_eq!(config.timeout, Some((30, "s".to_owned())));
+    assert!(config.verbose);
+    assert!(config.listen.is_empty());
+}
+
+#[test]
+fn load_errors() {
+    let cases = [
+        ("limits 1 2\nworkers 1\n", "domain", "missing directive"),
+        (
+            "domain a\ndomain b\nlimits 1 2\nworkers 1\n",
+            "domain",
+            "directive may only appear once",
+        ),
+        (
+            "domain a\nlimits 1\nworkers 1\n",
+            "limits",
+            "expected 2 parameters, found 1",
+        ),
+        (
+            "domain a\nlimits 1 2\nworkers many\n",
+            "workers",
+            "invalid parameter \"many\": invalid digit found in string",
+        ),
+        (
+            "domain a\nlimits 1 2\nworkers 1\nlisten 0.0.0.0:6697\n",
+            "listen",
+            "expected a block",
+        ),
+        (
+            "domain a\nlimits 1 2\nworkers 1\nlisten x {\n\ttls {\n\t\tkey k\n\t}\n}\n",
+            "listen.tls.certificate",
+            "missing directive",
+        ),
+    ];
+    for (src, path, message) in cases.iter() {
+        let err = load(&src.parse().unwrap()).unwrap_err();
+        assert_eq!(err.path, *path, "{}", src);
+        assert_eq!(err.message, *message, "{}", src);
+    }
+    let err = load(&Scfg::new()).unwrap_err();
+    assert_eq!(err.to_string(), "domain: missing directive");
+}
@@ -0,0 +1,249 @@
+// @generated by scfg::codegen, do not edit.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub domain: String,
+    pub limits: (u32, u32),
+    pub listen: Vec<ConfigListen>,
+    pub log_level: Option<String>,
+    pub timeout: Option<(u64, String)>,
+    pub upstream: Vec<ConfigUpstream>,
+    pub verbose: bool,
+    pub workers: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigListen {
+    pub params: String,
+    pub tls: Option<ConfigListenTls>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigListenTls {
+    pub certificate: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigUpstream {
+    pub params: (String, u16),
+    pub backup: bool,
+    pub max_fails: u8,
+    pub weight: Option<f64>,
+}
+
+impl Config {
+    fn load(doc: &::scfg::Scfg, path: &str) -> Result<Self, LoadError> {
+        Ok(Config {
+            domain: {
+                let (directive, path) = __scfg_one(doc, path, "domain")?;
+                __scfg_arity(directive, &path, 1)?;
+                __scfg_param(directive, &path, 0)?
+            },
+            limits: {
+                let (directive, path) = __scfg_one(doc, path, "limits")?;
+                __scfg_arity(directive, &path, 2)?;
+                (
+                    __scfg_param(directive, &path, 0)?,
+                    __scfg_param(directive, &path, 1)?,
+                )
+            },
+            listen: __scfg_repeated(doc, path, "listen")
+                .map(|(directive, path)| -> Result<_, LoadError> {
+                    ConfigListen::load(directive, &path)
+                })
+                .collect::<Result<_, _>>()?,
+            log_level: match __scfg_optional(doc, path, "log-level")? {
+                Some((directive, path)) => {
+                    __scfg_arity(directive, &path, 1)?;
+                    Some(__scfg_param(directive, &path, 0)?)
+                }
+                None => None,
+            },
+            timeout: match __scfg_optional(doc, path, "timeout")? {
+                Some((directive, path)) => {
+                    __scfg_arity(directive, &path, 2)?;
+                    Some((
+                        __scfg_param(directive, &path, 0)?,
+                        __scfg_param(directive, &path, 1)?,
+                    ))
+                }
+                None => None,
+            },
+            upstream: __scfg_repeated(doc, path, "upstream")
+                .map(|(directive, path)| -> Result<_, LoadError> {
+                    ConfigUpstream::load(directive, &path)
+                })
+                .collect::<Result<_, _>>()?,
+            verbose: doc.contains("verbose"),
+            workers: {
+                let (directive, path) = __scfg_one(doc, path, "workers")?;
+                __scfg_arity(directive, &path, 1)?;
+                __scfg_param(directive, &path, 0)?
+            },
+        })
+    }
+}
+
+impl ConfigListen {
+    fn load(directive: &::scfg::Directive, path: &str) -> Result<Self, LoadError> {
+        __scfg_arity(directive, path, 1)?;
+        let doc = __scfg_child(directive, path)?;
+        Ok(ConfigListen {
+            params: __scfg_param(directive, path, 0)?,
+            tls: match __scfg_optional(doc, path, "tls")? {
+                Some((directive, path)) => Some(ConfigListenTls::load(directive, &path)?),
+                None => None,
+            },
+        })
+    }
+}
+
+impl ConfigListenTls {
+    fn load(directive: &::scfg::Directive, path: &str) -> Result<Self, LoadError> {
+        __scfg_arity(directive, path, 0)?;
+        let doc = __scfg_child(directive, path)?;
+        Ok(ConfigListenTls {
+            certificate: {
+                let (directive, path) = __scfg_one(doc, path, "certificate")?;
+                __scfg_arity(directive, &path, 1)?;
+                __scfg_param(directive, &path, 0)?
+            },
+            key: {
+                let (directive, path) = __scfg_one(doc, path, "key")?;
+                __scfg_arity(directive, &path, 1)?;
+                __scfg_param(directive, &path, 0)?
+            },
+        })
+    }
+}
+
+impl ConfigUpstream {
+    fn load(directive: &::scfg::Directive, path: &str) -> Result<Self, LoadError> {
+        __scfg_arity(directive, path, 2)?;
+        let doc = __scfg_child(directive, path)?;
+        Ok(ConfigUpstream {
+            params: (
+                __scfg_param(directive, path, 0)?,
+                __scfg_param(directive, path, 1)?,
+            ),
+            backup: doc.contains("backup"),
+            max_fails: {
+                let (directive, path) = __scfg_one(doc, path, "maxFails")?;
+                __scfg_arity(directive, &path, 1)?;
+                __scfg_param(directive, &path, 0)?
+            },
+            weight: match __scfg_optional(doc, path, "weight")? {
+                Some((directive, path)) => {
+                    __scfg_arity(directive, &path, 1)?;
+                    Some(__scfg_param(directive, &path, 0)?)
+                }
+                None => None,
+            },
+        })
+    }
+}
+
+/// An error returned by [`load`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadError {
+    /// The path of the offending directive, with names separated by `.`.
+    pub path: String,
+    /// What is wrong with the directive.
+    pub message: String,
+}
+
+impl ::std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl ::std::error::Error for LoadError {}
+
+/// Loads a [`Config`] from a parsed document.
+pub fn load(doc: &::scfg::Scfg) -> Result<Config, LoadError> {
+    Config::load(doc, "")
+}
+
+fn __scfg_error(path: &str, message: impl ::std::fmt::Display) -> LoadError {
+    LoadError {
+        path: path.to_owned(),
+        message: message.to_string(),
+    }
+}
+
+fn __scfg_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn __scfg_one<'a>(
+    doc: &'a ::scfg::Scfg,
+    path: &str,
+    name: &str,
+) -> Result<(&'a ::scfg::Directive, String), LoadError> {
+    let path = __scfg_path(path, name);
+    match doc.get_all(name).unwrap_or_default() {
+        [directive] => Ok((directive, path)),
+        [] => Err(__scfg_error(&path, "missing directive")),
+        _ => Err(__scfg_error(&path, "directive may only appear once")),
+    }
+}
+
+fn __scfg_optional<'a>(
+    doc: &'a ::scfg::Scfg,
+    path: &str,
+    name: &str,
+) -> Result<Option<(&'a ::scfg::Directive, String)>, LoadError> {
+    let path = __scfg_path(path, name);
+    match doc.get_all(name).unwrap_or_default() {
+        [] => Ok(None),
+        [directive] => Ok(Some((directive, path))),
+        _ => Err(__scfg_error(&path, "directive may only appear once")),
+    }
+}
+
+fn __scfg_repeated<'a>(
+    doc: &'a ::scfg::Scfg,
+    path: &str,
+    name: &str,
+) -> impl Iterator<Item = (&'a ::scfg::Directive, String)> {
+    let path = __scfg_path(path, name);
+    doc.get_all(name)
+        .unwrap_or_default()
+        .iter()
+        .map(move |directive| (directive, path.clone()))
+}
+
+fn __scfg_child<'a>(
+    directive: &'a ::scfg::Directive,
+    path: &str,
+) -> Result<&'a ::scfg::Scfg, LoadError> {
+    directive
+        .child()
+        .ok_or_else(|| __scfg_error(path, "expected a block"))
+}
+
+fn __scfg_arity(directive: &::scfg::Directive, path: &str, n: usize) -> Result<(), LoadError> {
+    let found = directive.params().len();
+    if found != n {
+        let message = format!("expected {} parameters, found {}", n, found);
+        return Err(__scfg_error(path, message));
+    }
+    Ok(())
+}
+
+fn __scfg_param<T>(directive: &::scfg::Directive, path: &str, index: usize) -> Result<T, LoadError>
+where
+    T: ::std::str::FromStr,
+    T::Err: ::std::fmt::Display,
+{
+    let param = &directive.params()[index];
+    param
+        .parse()
+        .map_err(|err| __scfg_error(path, format!("invalid parameter {:?}: {}", param, err)))
+}
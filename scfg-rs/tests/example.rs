@@ -0,0 +1,87 @@
+#![cfg(all(feature = "macros", feature = "serde"))]
+use scfg::{de, Documented, Scfg};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Documented, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+struct Config {
+    /// The domain name of the server.
+    domain: String,
+    /// The addresses to listen on, one per directive.
+    listen: Vec<String>,
+    /// The number of worker threads.
+    ///
+    /// Defaults to the number of CPUs.
+    #[scfg(example = "4")]
+    workers: Option<u8>,
+    log_level: String,
+    /// TLS settings.
+    #[scfg(block)]
+    tls: Tls,
+    /// Extra upstream servers.
+    #[scfg(block, example = "{ ... }")]
+    upstream: Option<Tls>,
+    #[serde(skip)]
+    cache: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Documented, Debug, PartialEq)]
+struct Tls {
+    /// The certificate chain, in PEM.
+    #[serde(rename = "certificate")]
+    cert: String,
+    /// The port of the TLS listener.
+    port: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            domain: "example.com".to_owned(),
+            listen: vec!["0.0.0.0:6697".to_owned(), "127.0.0.1:6667".to_owned()],
+            workers: None,
+            log_level: "info".to_owned(),
+            tls: Tls {
+                cert: "/etc/tls/cert.pem".to_owned(),
+                port: 6697,
+            },
+            upstream: None,
+            cache: Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn golden() {
+    let example = scfg::example::<Config>().unwrap();
+    assert_eq!(example, include_str!("data/example.scfg"));
+
+    let doc: Scfg = example.parse().unwrap();
+    let config: Config = de::from_scfg(&doc).unwrap();
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn field_docs() {
+    let fields = Config::fields();
+    let names: Vec<_> = fields.iter().map(|field| field.name).collect();
+    assert_eq!(
+        names,
+        [
+            "domain",
+            "listen",
+            "workers",
+            "log-level",
+            "tls",
+            "upstream"
+        ]
+    );
+    assert_eq!(
+        fields[2].doc,
+        "The number of worker threads.\n\nDefaults to the number of CPUs."
+    );
+    assert!(fields[2].optional && !fields[4].optional);
+    assert_eq!(fields[3].doc, "");
+    assert_eq!(fields[4].block.unwrap()()[0].name, "certificate");
+    assert!(fields[0].block.is_none());
+}
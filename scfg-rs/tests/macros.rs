@@ -0,0 +1,37 @@
+#![cfg(feature = "macros")]
+
+use scfg::{include_scfg, scfg_literal, Scfg};
+
+#[test]
+fn literal() {
+    let doc = scfg_literal!(
+        r#"dir1 param1 "param 2"
+# comment
+block1 {
+    dir2
+    block2 {
+    }
+}"#
+    );
+    let mut exp = Scfg::new();
+    exp.add("dir1")
+        .append_param("param1")
+        .append_param("param 2");
+    let block1 = exp.add("block1").get_or_create_child();
+    block1.add("dir2");
+    block1.add("block2").get_or_create_child();
+    assert_eq!(doc, exp);
+    assert_eq!(scfg_literal!(""), Scfg::new());
+}
+
+#[test]
+fn include() {
+    let src = include_str!("data/train.scfg");
+    assert_eq!(include_scfg!("tests/data/train.scfg"), src.parse().unwrap());
+}
+
+#[test]
+fn compile_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
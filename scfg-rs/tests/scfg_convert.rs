@@ -0,0 +1,73 @@
+#![cfg(feature = "cli")]
+//! Runs `scfg convert`.
+use assert_cmd::Command;
+use scfg::Scfg;
+use std::error::Error;
+
+type Result = std::result::Result<(), Box<dyn Error>>;
+
+static SRC: &str = r#"# server
+domain example.com
+listen 0.0.0.0:6697 {
+    tls {
+        certificate "/etc/ssl/my cert.pem"
+    }
+}
+listen 127.0.0.1:6667
+flag
+"#;
+
+fn scfg_convert(args: &[&str], stdin: &str) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("scfg")
+        .unwrap()
+        .arg("convert")
+        .args(args)
+        .write_stdin(stdin)
+        .assert()
+}
+
+#[test]
+fn round_trip() -> Result {
+    let output = scfg_convert(&["--to", "json"], SRC).success().stderr("");
+    let json = String::from_utf8(output.get_output().stdout.clone())?;
+    assert_eq!(json.lines().count(), 1);
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    assert_eq!(value["domain"][0]["params"][0], "example.com");
+
+    let output = scfg_convert(&["--from", "json", "-"], &json).success();
+    let back = String::from_utf8(output.get_output().stdout.clone())?;
+    assert_eq!(back.parse::<Scfg>()?, SRC.parse::<Scfg>()?);
+    Ok(())
+}
+
+#[test]
+fn pretty() -> Result {
+    let output = scfg_convert(&["--to", "json", "--pretty"], "flag\n").success();
+    let json = String::from_utf8(output.get_output().stdout.clone())?;
+    assert_eq!(
+        json,
+        "{\n  \"flag\": [\n    {\n      \"params\": []\n    }\n  ]\n}\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn errors() {
+    scfg_convert(
+        &["--from", "json"],
+        r#"{"listen": [{"params": ["a"], "child": {"tls": [{"params": [443]}]}}]}"#,
+    )
+    .code(2)
+    .stdout("")
+    .stderr("scfg: <stdin>: cannot convert at /listen/0/child/tls/0/params/0: expected a string\n");
+    let output = scfg_convert(&["--from", "json"], "{").code(2);
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr);
+    assert!(stderr.starts_with("scfg: <stdin>: invalid JSON: "));
+    scfg_convert(&["--to", "json"], "a {\n")
+        .code(2)
+        .stderr("scfg: <stdin>: parsing error at line 2: io: unexpected end of file\n");
+    scfg_convert(&["--to", "yaml"], "")
+        .code(2)
+        .stderr("scfg: unsupported format \"yaml\"\n");
+    scfg_convert(&["--from", "json", "--pretty"], "{}").code(2);
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "cli")]
+//! Runs the `scfg-fmt` binary.
+use assert_cmd::Command;
+use std::{error::Error, fs};
+
+type Result = std::result::Result<(), Box<dyn Error>>;
+
+static UNFORMATTED: &str = "# server\nlisten 0.0.0.0:6697 {\n    # TLS\n    tls {\n        certificate cert.pem\n    }\n\n\n}\ndomain example.com\n";
+
+static FORMATTED: &str = "# server\nlisten 0.0.0.0:6697 {\n\t# TLS\n\ttls {\n\t\tcertificate cert.pem\n\t}\n}\ndomain example.com\n";
+
+fn scfg_fmt() -> Command {
+    Command::cargo_bin("scfg-fmt").unwrap()
+}
+
+#[test]
+fn stdin_to_stdout() {
+    scfg_fmt()
+        .write_stdin(UNFORMATTED)
+        .assert()
+        .success()
+        .stdout(FORMATTED)
+        .stderr("");
+}
+
+#[test]
+fn write_in_place() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("server.scfg");
+    fs::write(&path, UNFORMATTED)?;
+    scfg_fmt()
+        .arg("--write")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read_to_string(&path)?, FORMATTED);
+
+    scfg_fmt()
+        .arg("--write")
+        .write_stdin(FORMATTED)
+        .assert()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn check() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("server.scfg");
+    fs::write(&path, UNFORMATTED)?;
+    let name = path.display();
+    let diff = format!(
+        "--- {name}
++++ {name}
+@@ -1,10 +1,8 @@
+ # server
+ listen 0.0.0.0:6697 {{
+-    # TLS
+-    tls {{
+-        certificate cert.pem
+-    }}
+-
+-
++\t# TLS
++\ttls {{
++\t\tcertificate cert.pem
++\t}}
+ }}
+ domain example.com
+",
+        name = name
+    );
+    scfg_fmt()
+        .arg("--check")
+        .arg(&path)
+        .assert()
+        .code(1)
+        .stdout(diff)
+        .stderr("");
+    assert_eq!(fs::read_to_string(&path)?, UNFORMATTED);
+
+    scfg_fmt()
+        .arg("--check")
+        .write_stdin("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n  k")
+        .assert()
+        .code(1)
+        .stdout("--- <stdin>\n+++ <stdin>\n@@ -8,4 +8,4 @@\n h\n i\n j\n-  k\n\\ No newline at end of file\n+k\n");
+
+    scfg_fmt()
+        .arg("--check")
+        .write_stdin(FORMATTED)
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn parse_error() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("broken.scfg");
+    fs::write(&path, "listen {\n}\n}\n")?;
+    scfg_fmt()
+        .arg(&path)
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(format!(
+            "scfg-fmt: {}: parsing error at line 3: unexpected '}}'\n",
+            path.display()
+        ));
+    Ok(())
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "cli")]
+//! Runs `scfg get`.
+use assert_cmd::Command;
+use std::{error::Error, fs};
+
+type Result = std::result::Result<(), Box<dyn Error>>;
+
+static SHINKANSEN: &str = r#"train "Shinkansen" {
+    model "E5" {
+        max-speed 320km/h
+        weight 453.5t
+
+        lines-served "Tōhoku" "Hokkaido"
+    }
+
+    model "E7" {
+        max-speed 275km/h
+        weight 540t
+
+        lines-served "Hokuriku" "Jōetsu"
+        nickname "Kagayaki Hakutaka"
+    }
+}
+"#;
+
+fn scfg_get(args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("scfg")
+        .unwrap()
+        .arg("get")
+        .args(args)
+        .write_stdin(SHINKANSEN)
+        .assert()
+}
+
+#[test]
+fn hit() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("train.scfg");
+    fs::write(&path, SHINKANSEN)?;
+    Command::cargo_bin("scfg")?
+        .arg("get")
+        .arg(&path)
+        .arg("train.model.max-speed")
+        .assert()
+        .success()
+        .stdout("320km/h\n")
+        .stderr("");
+    scfg_get(&["-", "train"]).success().stdout("Shinkansen\n");
+    Ok(())
+}
+
+#[test]
+fn miss() {
+    scfg_get(&["-", "train.model.max-weight"])
+        .code(1)
+        .stdout("")
+        .stderr("");
+    scfg_get(&["--json", "-", "train.engine"])
+        .code(1)
+        .stdout("[]\n");
+}
+
+#[test]
+fn repeated() {
+    scfg_get(&["--all", "-", "train.model.max-speed"])
+        .success()
+        .stdout("320km/h\n275km/h\n");
+    scfg_get(&["--all", "-", "train.model"])
+        .success()
+        .stdout("E5\nE7\n");
+    scfg_get(&["--all", "--json", "-", "train.model.lines-served"])
+        .success()
+        .stdout(
+            r#"[{"line":6,"params":["Tōhoku","Hokkaido"]},{"line":13,"params":["Hokuriku","Jōetsu"]}]
+"#,
+        );
+}
+
+#[test]
+fn quoting() {
+    scfg_get(&["-", "train.model.nickname"])
+        .success()
+        .stdout("'Kagayaki Hakutaka'\n");
+    scfg_get(&["--raw", "-", "train.model.nickname"])
+        .success()
+        .stdout("Kagayaki Hakutaka\n");
+    scfg_get(&["--json", "-", "train.model.nickname"])
+        .success()
+        .stdout("[{\"line\":14,\"params\":[\"Kagayaki Hakutaka\"]}]\n");
+}
+
+#[test]
+fn parse_error() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("broken.scfg");
+    fs::write(&path, "train {\n}\n}\n")?;
+    Command::cargo_bin("scfg")?
+        .arg("get")
+        .arg(&path)
+        .arg("train")
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(format!(
+            "scfg: {}: parsing error at line 3: unexpected '}}'\n",
+            path.display()
+        ));
+    Command::cargo_bin("scfg")?
+        .args(["get", "-"])
+        .assert()
+        .code(2);
+    Ok(())
+}
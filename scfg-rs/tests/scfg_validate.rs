@@ -0,0 +1,87 @@
+#![cfg(feature = "cli")]
+//! Runs `scfg validate`.
+use assert_cmd::Command;
+use std::{error::Error, fs, path::Path};
+
+type Result = std::result::Result<(), Box<dyn Error>>;
+
+static SCHEMA: &str = r#"directive domain {
+    min 1
+    max 1
+    params 1
+}
+directive listen {
+    params 1
+}
+"#;
+
+fn scfg_validate(dir: &Path, src: &str, args: &[&str]) -> assert_cmd::assert::Assert {
+    let schema = dir.join("app.schema.scfg");
+    fs::write(&schema, SCHEMA).unwrap();
+    Command::cargo_bin("scfg")
+        .unwrap()
+        .current_dir(dir)
+        .arg("validate")
+        .arg("--schema")
+        .arg("app.schema.scfg")
+        .args(args)
+        .arg("-")
+        .write_stdin(src)
+        .assert()
+}
+
+#[test]
+fn passing() -> Result {
+    let dir = tempfile::tempdir()?;
+    let src = "domain example.com\nlisten 0.0.0.0:6697\n";
+    scfg_validate(dir.path(), src, &[])
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin("scfg")?
+        .args(["validate"])
+        .write_stdin("greeting \"a\tb\"\n")
+        .assert()
+        .success()
+        .stdout("<stdin>:1: warning: parameter 0 contains the control character '\\t', which other parsers may not accept\n");
+    Ok(())
+}
+
+#[test]
+fn violations() -> Result {
+    let dir = tempfile::tempdir()?;
+    let src = "listen\ndomain a.example b.example\nlisten 0.0.0.0:6697\n";
+    scfg_validate(dir.path(), src, &[])
+        .code(1)
+        .stdout(
+            "<stdin>:1: listen: expected 1 parameters, found 0
+<stdin>:2: domain: expected 1 parameters, found 2
+",
+        )
+        .stderr("");
+    scfg_validate(dir.path(), src, &["--max-errors", "1"])
+        .code(1)
+        .stdout("<stdin>:1: listen: expected 1 parameters, found 0\n<stdin>: 1 more errors\n");
+    scfg_validate(dir.path(), src, &["--quiet"])
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn syntax_error() -> Result {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("broken.scfg");
+    fs::write(&path, "domain example.com\n}\n")?;
+    Command::cargo_bin("scfg")?
+        .arg("validate")
+        .arg(&path)
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(format!(
+            "scfg: {}: parsing error at line 2: unexpected '}}'\n",
+            path.display()
+        ));
+    Ok(())
+}
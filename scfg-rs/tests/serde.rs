@@ -0,0 +1,158 @@
+#![cfg(feature = "serde")]
+use scfg::{de, ParamRule, Scfg, Schema};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    domain: String,
+    listen: Vec<String>,
+    workers: Option<u8>,
+    log_level: Level,
+    tls: Option<Tls>,
+    #[serde(default)]
+    verbose: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Debug,
+    Info,
+    Error,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Tls {
+    certificate: String,
+    key: String,
+    #[serde(default)]
+    port: u16,
+}
+
+static SRC: &str = r#"domain example.com
+listen 0.0.0.0:6697
+listen 127.0.0.1:6667
+log-level info
+
+tls {
+    certificate /etc/tls/cert.pem
+    key /etc/tls/key.pem
+    port 6697
+}
+"#;
+
+#[test]
+fn deserialize() {
+    let doc: Scfg = SRC.parse().unwrap();
+    let config: Config = de::from_scfg(&doc).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            domain: "example.com".to_owned(),
+            listen: vec!["0.0.0.0:6697".to_owned(), "127.0.0.1:6667".to_owned()],
+            workers: None,
+            log_level: Level::Info,
+            tls: Some(Tls {
+                certificate: "/etc/tls/cert.pem".to_owned(),
+                key: "/etc/tls/key.pem".to_owned(),
+                port: 6697,
+            }),
+            verbose: false,
+        }
+    );
+}
+
+#[test]
+fn derived_schema() {
+    let tls = Schema::new()
+        .directive("certificate", |d| d.min(1).max(1).params(1).no_child())
+        .directive("key", |d| d.min(1).max(1).params(1).no_child())
+        .directive("port", |d| {
+            let port = ParamRule::Integer {
+                min: Some(0),
+                max: Some(65535),
+            };
+            d.max(1).params(1).param(0, port).no_child()
+        })
+        .deny_unknown();
+    let levels = ["debug", "info", "error"].iter().map(|&l| l.to_owned());
+    let expected = Schema::new()
+        .directive("domain", |d| d.min(1).max(1).params(1).no_child())
+        .directive("listen", |d| d.min(1).no_child())
+        .directive("workers", |d| {
+            let workers = ParamRule::Integer {
+                min: Some(0),
+                max: Some(255),
+            };
+            d.max(1).params(1).param(0, workers).no_child()
+        })
+        .directive("log-level", |d| {
+            d.min(1)
+                .max(1)
+                .min_params(1)
+                .param(0, ParamRule::Enum(levels.collect()))
+        })
+        .directive("tls", |d| d.max(1).params(0).child(tls))
+        .directive("verbose", |d| {
+            let bool = ParamRule::Enum(vec!["true".to_owned(), "false".to_owned()]);
+            d.max(1).max_params(1).param(0, bool).no_child()
+        })
+        .deny_unknown();
+    assert_eq!(Schema::for_type::<Config>().unwrap(), expected);
+}
+
+#[test]
+fn validation_agrees_with_deserialization() {
+    let schema = Schema::for_type::<Config>().unwrap();
+    let docs = [
+        SRC,
+        "domain a\nlisten a\nlog-level debug\n",
+        "domain a\nlisten a b\nlog-level debug\nworkers 8\nverbose\ntls {\n\tcertificate c\n\tkey k\n}\n",
+        "domain a\nlisten a\nlog-level debug\nverbose false\n",
+        "listen a\nlog-level debug\n",
+        "domain a\ndomain b\nlisten a\nlog-level debug\n",
+        "domain a b\nlisten a\nlog-level debug\n",
+        "domain a\nlisten a\nlog-level verbose\n",
+        "domain a\nlisten a\nlog-level debug\nworkers 300\n",
+        "domain a\nlisten a\nlog-level debug\nworkers many\n",
+        "domain a\nlisten a\nlog-level debug\nverbose yes\n",
+        "domain a\nlisten a\nlog-level debug\nhost a\n",
+        "domain a\nlisten a {\n}\nlog-level debug\n",
+        "domain a\nlisten a\nlog-level debug\ntls {\n\tcertificate c\n}\n",
+        "domain a\nlisten a\nlog-level debug\ntls {\n\tcertificate c\n\tkey k\n\tca c\n}\n",
+        "domain a\nlisten a\nlog-level debug\ntls x {\n\tcertificate c\n\tkey k\n}\n",
+        "domain a\nlisten a\nlog-level debug\ntls\n",
+    ];
+    for src in docs.iter() {
+        let doc: Scfg = src.parse().unwrap();
+        let validated = schema.validate(&doc);
+        let deserialized = de::from_scfg::<Config>(&doc);
+        assert_eq!(
+            validated.is_ok(),
+            deserialized.is_ok(),
+            "{}\nvalidation: {:?}\ndeserialization: {:?}",
+            src,
+            validated,
+            deserialized
+        );
+    }
+}
+
+#[test]
+fn unsupported_types() {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Node {
+        children: Vec<Node>,
+    }
+
+    let err = Schema::for_type::<Node>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot derive a schema for a recursive type"
+    );
+    let err = Schema::for_type::<Vec<String>>().unwrap_err();
+    assert_eq!(err.to_string(), "can only derive a schema for a struct");
+}
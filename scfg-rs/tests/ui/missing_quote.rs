@@ -0,0 +1,3 @@
+fn main() {
+    let _ = scfg::scfg_literal!("domain example.com\nlisten \"0.0.0.0:6697\n");
+}
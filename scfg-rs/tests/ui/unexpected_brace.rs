@@ -0,0 +1,11 @@
+fn main() {
+    let _ = scfg::scfg_literal!(
+        r#"domain example.com
+
+listen 0.0.0.0:6697 {
+    certificate "/etc/ssl/fullchain.pem"
+}
+}
+"#
+    );
+}